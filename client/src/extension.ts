@@ -1,6 +1,16 @@
 // Copyright (c) ZeroC, Inc.
 
-import { workspace, ExtensionContext, window } from "vscode";
+import {
+  commands,
+  workspace,
+  ConfigurationTarget,
+  env,
+  ExtensionContext,
+  QuickPickItem,
+  QuickPickItemKind,
+  Uri,
+  window,
+} from "vscode";
 import {
   Executable,
   LanguageClient,
@@ -17,6 +27,31 @@ const traceOutputChannel = window.createOutputChannel("Slice");
 // The language client.
 let client: LanguageClient | undefined;
 
+// The commands which write a file, and how the user is asked where to write it.
+const exportFormats: {
+  [command: string]: {
+    fileName: string;
+    filters: { [name: string]: string[] };
+    title: string;
+  };
+} = {
+  "slice.exportSarif": {
+    fileName: "slice.sarif",
+    filters: { SARIF: ["sarif"] },
+    title: "Export Slice Diagnostics",
+  },
+  "slice.exportIndex": {
+    fileName: "slice.lsif",
+    filters: { LSIF: ["lsif"] },
+    title: "Export Slice Index",
+  },
+  "slice.exportDependencyGraph": {
+    fileName: "slice-dependencies.dot",
+    filters: { DOT: ["dot", "gv"], JSON: ["json"] },
+    title: "Export Slice Dependency Graph",
+  },
+};
+
 /**
  * Create a new instance of LanguageClient.
  * @param {ServerOptions} serverOptions - The server options.
@@ -51,18 +86,10 @@ const handleConfigurationChanges = (context: ExtensionContext) => {
         "languageServer.enabled"
       );
 
-      // Retrieve the 'slice.configurations' setting
-      const configurations = config.get<any[]>("configurations");
-
       // Send the updated configuration to the language server
       if (client) {
         client.sendNotification("workspace/didChangeConfiguration", {
-          settings: {
-            slice: {
-              configurations,
-              enableLanguageServer,
-            },
-          },
+          settings: { slice: getServerSettings() },
         });
       }
 
@@ -79,6 +106,19 @@ const handleConfigurationChanges = (context: ExtensionContext) => {
       }
     }
   });
+
+  // Added folders can have their own configuration sets, which the server doesn't know about yet.
+  workspace.onDidChangeWorkspaceFolders((event) => {
+    const addedFolders = event.added.map((folder) => folder.uri.toString());
+    const hasFolderConfigurations = getFolderConfigurations().some(({ uri }) =>
+      addedFolders.includes(uri)
+    );
+    if (client && hasFolderConfigurations) {
+      client.sendNotification("workspace/didChangeConfiguration", {
+        settings: { slice: getServerSettings() },
+      });
+    }
+  });
 };
 
 /**
@@ -155,9 +195,122 @@ export async function activate(context: ExtensionContext) {
 
     // Configure the language client options.
     const clientOptions: LanguageClientOptions = {
-      documentSelector: [{ scheme: "file", language: "slice" }],
+      documentSelector: [
+        { scheme: "file", language: "slice" },
+        { scheme: "untitled", language: "slice" },
+      ],
       synchronize: {
-        fileEvents: workspace.createFileSystemWatcher("**/.clientrc"),
+        // Let the server know when the workspace's '.sliceignore' or '.sliceproject.json' files change.
+        fileEvents: [
+          workspace.createFileSystemWatcher("**/.sliceignore"),
+          workspace.createFileSystemWatcher("**/.sliceproject.json"),
+        ],
+      },
+      middleware: {
+        executeCommand: async (command, args, next) => {
+          // Commands run from the command palette don't have any arguments, so they're run on the active document.
+          const isDocumentCommand =
+            command === "slice.compile" ||
+            command === "slice.showFileConfiguration";
+          if (isDocumentCommand && args.length === 0) {
+            const uri = window.activeTextEditor?.document.uri;
+            if (!uri) {
+              window.showWarningMessage("Open a Slice file first.");
+              return undefined;
+            }
+            args = [uri.toString()];
+          }
+
+          // The type ID is copied for the type under the cursor of the active document.
+          if (command === "slice.copyTypeId" && args.length === 0) {
+            const editor = window.activeTextEditor;
+            if (!editor) {
+              window.showWarningMessage("Open a Slice file first.");
+              return undefined;
+            }
+            const { line, character } = editor.selection.active;
+            args = [editor.document.uri.toString(), { line, character }];
+          }
+
+          // Likewise, the user is asked where to export the diagnostics (or the index) to.
+          const exportFormat = exportFormats[command];
+          if (exportFormat && args.length === 0) {
+            const folder = workspace.workspaceFolders?.[0]?.uri;
+            const uri = await window.showSaveDialog({
+              defaultUri: folder && Uri.joinPath(folder, exportFormat.fileName),
+              filters: exportFormat.filters,
+              title: exportFormat.title,
+            });
+            if (!uri) {
+              return undefined;
+            }
+            args = [uri.toString()];
+
+            // Dependency graphs can be of files or of modules.
+            if (command === "slice.exportDependencyGraph") {
+              const granularity = await window.showQuickPick(
+                ["file", "module"],
+                { title: "Show dependencies between..." }
+              );
+              if (!granularity) {
+                return undefined;
+              }
+              args.push(granularity);
+            }
+          }
+
+          // New files are created in the folder that was right-clicked in the explorer, or in a folder the user picks.
+          if (command === "slice.newFile") {
+            const newFileArgs = await pickNewFileArguments(args[0]);
+            if (!newFileArgs) {
+              return undefined;
+            }
+            args = newFileArgs;
+          }
+
+          // The user picks what to document, where to write it, and whether to include deprecated definitions.
+          if (command === "slice.generateDocs" && args.length === 0) {
+            const docsArgs = await pickGenerateDocsArguments();
+            if (!docsArgs) {
+              return undefined;
+            }
+            args = docsArgs;
+          }
+
+          const result = await next(command, args);
+          if (command === "slice.compile" && result) {
+            showCompileResult(result);
+          } else if (command === "slice.compileAll" && result) {
+            showCompileAllResult(result);
+          } else if (command === "slice.showFileConfiguration" && result) {
+            showFileConfigurationResult(result);
+          } else if (command === "slice.generateDocs" && result) {
+            window.showInformationMessage(
+              `Generated ${result.pages.length} documentation page(s) in '${result.path}'.`
+            );
+          } else if (command === "slice.newFile" && result) {
+            await window.showTextDocument(Uri.parse(result.uri));
+          } else if (command === "slice.openBuiltInSliceFiles" && result) {
+            await pickBuiltInSliceFile(result);
+          } else if (command === "slice.copyTypeId" && result) {
+            await copyTypeIdResult(result);
+          } else if (command === "slice.workspaceStats" && result) {
+            showWorkspaceStatsResult(result);
+          } else if (command === "slice.exportSarif" && result) {
+            window.showInformationMessage(
+              `Exported ${result.results} diagnostic(s) to '${result.path}'.`
+            );
+          } else if (command === "slice.exportIndex" && result) {
+            window.showInformationMessage(
+              `Exported an index of ${result.documents} file(s) to '${result.path}'.`
+            );
+          } else if (command === "slice.exportDependencyGraph" && result) {
+            window.showInformationMessage(
+              `Exported a dependency graph of ${result.nodes} node(s) and ${result.edges} edge(s) to '${result.path}'.`
+            );
+          }
+          return result;
+        },
       },
       traceOutputChannel,
       outputChannel: traceOutputChannel,
@@ -165,6 +318,20 @@ export async function activate(context: ExtensionContext) {
       initializationOptions: {
         builtInSlicePath: builtInSlicePath,
         configurations: configuration_sets,
+        folderConfigurations: getFolderConfigurations(),
+        referencedDirectories: config.get<string[]>("referencedDirectories"),
+        compileWorkspaceByDefault: config.get<boolean>(
+          "compileWorkspaceByDefault"
+        ),
+        autoDiscoverConfigurations: config.get<boolean>(
+          "autoDiscoverConfigurations"
+        ),
+        maxFileSizeKB: config.get<number>("maxFileSizeKB"),
+        diagnostics: config.get<object>("diagnostics"),
+        compilation: config.get<object>("compilation"),
+        features: config.get<object>("features"),
+        server: config.get<object>("server"),
+        cacheDirectory: context.storageUri?.fsPath,
       },
     };
 
@@ -198,6 +365,52 @@ export async function activate(context: ExtensionContext) {
         }
       }
     );
+
+    // The server asks us to edit the user's configuration, since it can't modify settings itself.
+    client.onNotification(
+      "custom/editConfiguration",
+      async (params: EditConfigurationParams) => {
+        switch (params.edit) {
+          case "AddPath":
+            await addPathToConfiguration(params.path);
+            break;
+          case "RemovePath":
+            await removePathFromConfiguration(params.path);
+            break;
+          default:
+            logMessage(`Unknown configuration edit: ${params.edit}`, "Error");
+        }
+      }
+    );
+
+    client.onNotification(
+      "custom/saveConfigurations",
+      async (params: SaveConfigurationsParams) => {
+        await saveConfigurations(params.folder_uri, params.configurations);
+      }
+    );
+
+    client.onNotification(
+      "custom/openSettings",
+      async (params: OpenSettingsParams) => {
+        await commands.executeCommand(
+          "workbench.action.openSettings",
+          params.query
+        );
+      }
+    );
+
+    // Reloading is a request instead of a server command, since other tools (and editors) use it too.
+    context.subscriptions.push(
+      commands.registerCommand("slice.reloadConfiguration", async () => {
+        const result = await client?.sendRequest<ReloadConfigurationsResult>(
+          "slice/reloadConfigurations"
+        );
+        if (result) {
+          showReloadConfigurationsResult(result);
+        }
+      })
+    );
   } catch (error) {
     logMessage(`Failed to start client: ${error}`, "Error");
     window.showErrorMessage(
@@ -216,6 +429,377 @@ export async function deactivate(): Promise<void> {
   }
 }
 
+/**
+ * Get the settings that are sent to the language server when they change.
+ * @returns {object} - The settings, in the same layout as the 'slice' settings.
+ */
+function getServerSettings() {
+  const config = workspace.getConfiguration("slice");
+  return {
+    configurations: config.get<any[]>("configurations"),
+    folderConfigurations: getFolderConfigurations(),
+    referencedDirectories: config.get<string[]>("referencedDirectories"),
+    compileWorkspaceByDefault: config.get<boolean>(
+      "compileWorkspaceByDefault"
+    ),
+    autoDiscoverConfigurations: config.get<boolean>(
+      "autoDiscoverConfigurations"
+    ),
+    maxFileSizeKB: config.get<number>("maxFileSizeKB"),
+    diagnostics: config.get<object>("diagnostics"),
+    compilation: config.get<object>("compilation"),
+    features: config.get<object>("features"),
+    server: config.get<object>("server"),
+    enableLanguageServer: config.get<boolean>("languageServer.enabled"),
+  };
+}
+
+/**
+ * Get the Slice configuration sets that were configured for specific workspace folders.
+ * These only exist in multi-root workspaces, since a single folder's settings are the workspace's settings.
+ * @returns {object[]} - The URI and configuration sets of each folder that has its own configuration sets.
+ */
+function getFolderConfigurations() {
+  if (!workspace.workspaceFile) {
+    return [];
+  }
+
+  return (workspace.workspaceFolders ?? []).flatMap((folder) => {
+    const configurations = workspace
+      .getConfiguration("slice", folder.uri)
+      .inspect<any[]>("configurations")?.workspaceFolderValue;
+    return configurations === undefined
+      ? []
+      : [{ uri: folder.uri.toString(), configurations }];
+  });
+}
+
+/**
+ * Add a path to the first Slice configuration set, creating one if necessary.
+ * @param {string} path - The path to add.
+ */
+async function addPathToConfiguration(path: string) {
+  const config = workspace.getConfiguration("slice");
+  const configurations = [...(config.get<any[]>("configurations") ?? [])];
+
+  if (configurations.length === 0) {
+    // Without any configuration sets the server compiles the workspace root, so keep it when creating the first set.
+    configurations.push({ paths: [".", path] });
+  } else {
+    const paths = configurations[0].paths ?? [];
+    configurations[0] = { ...configurations[0], paths: [...paths, path] };
+  }
+
+  logMessage(`Adding '${path}' to the Slice configuration`);
+  await config.update(
+    "configurations",
+    configurations,
+    ConfigurationTarget.Workspace
+  );
+}
+
+/**
+ * Remove a path from every Slice configuration set that contains it.
+ * @param {string} path - The path to remove.
+ */
+async function removePathFromConfiguration(path: string) {
+  // The server may have normalized the path's separators, so compare them loosely.
+  const normalize = (p: string) => p.replace(/\\/g, "/");
+
+  const config = workspace.getConfiguration("slice");
+  const configurations = (config.get<any[]>("configurations") ?? []).map(
+    (set) => ({
+      ...set,
+      paths: (set.paths ?? []).filter(
+        (p: string) => normalize(p) !== normalize(path)
+      ),
+    })
+  );
+
+  logMessage(`Removing '${path}' from the Slice configuration`);
+  await config.update(
+    "configurations",
+    configurations,
+    ConfigurationTarget.Workspace
+  );
+}
+
+/**
+ * Replace the Slice configuration sets of a workspace folder (or of the whole workspace) with the provided sets.
+ * @param {string | undefined} folderUri - The URI of the folder to update, or undefined to update the workspace.
+ * @param {any[]} configurations - The configuration sets to write.
+ */
+async function saveConfigurations(
+  folderUri: string | undefined,
+  configurations: any[]
+) {
+  const scope = folderUri ? Uri.parse(folderUri) : undefined;
+  const target = folderUri
+    ? ConfigurationTarget.WorkspaceFolder
+    : ConfigurationTarget.Workspace;
+
+  logMessage(
+    `Saving ${configurations.length} configuration set(s) to the Slice configuration`
+  );
+  await workspace
+    .getConfiguration("slice", scope)
+    .update("configurations", configurations, target);
+}
+
+/**
+ * Tell the user how many errors and warnings the sets that were compiled by the 'slice.compile' command have.
+ * @param {CompileResult} result - The result of the command.
+ */
+function showCompileResult(result: CompileResult) {
+  if (result.configurationSets.length === 0) {
+    window.showWarningMessage(
+      "The file isn't part of any Slice configuration set, so it wasn't compiled."
+    );
+    return;
+  }
+
+  const sets = result.configurationSets.map((name) => `'${name}'`).join(", ");
+  window.showInformationMessage(
+    `Compiled ${sets}: ${result.errors} error(s), ${result.warnings} warning(s).`
+  );
+}
+
+/**
+ * Report how long each set took to compile with the 'slice.compileAll' command. The totals are shown to the user, and
+ * the details of each set are written to the output channel.
+ * @param {CompileAllResult} result - The result of the command.
+ */
+function showCompileAllResult(result: CompileAllResult) {
+  let errors = 0;
+  let warnings = 0;
+  for (const set of result.configurationSets) {
+    errors += set.errors;
+    warnings += set.warnings;
+    logMessage(
+      `Compiled '${set.name}' in ${set.milliseconds} ms: ${set.files} file(s), ${set.errors} error(s), ${set.warnings} warning(s)`
+    );
+  }
+
+  const count = result.configurationSets.length;
+  window.showInformationMessage(
+    `Compiled ${count} configuration set(s) in ${result.milliseconds} ms: ${errors} error(s), ${warnings} warning(s). See the 'Slice' output for the details of each set.`
+  );
+}
+
+/**
+ * Show which configuration sets contain a file, and why, with the 'slice.showFileConfiguration' command.
+ * @param {FileConfigurationResult} result - The result of the command.
+ */
+function showFileConfigurationResult(result: FileConfigurationResult) {
+  if (result.configurationSets.length === 0) {
+    window.showWarningMessage(
+      "The file isn't part of any Slice configuration set, so it doesn't get any diagnostics."
+    );
+    return;
+  }
+
+  const items = result.configurationSets.map((set) => ({
+    label: set.name,
+    description: set.isDisabled ? "(disabled)" : undefined,
+    detail: set.isBuiltIn ? `${set.reason} (built-in Slice file)` : set.reason,
+  }));
+  window.showQuickPick(items, {
+    title: `Configuration sets containing ${Uri.parse(result.uri).fsPath}`,
+  });
+}
+
+/**
+ * Ask the user for the arguments of the 'slice.generateDocs' command: what to document (the current file, or one of the
+ * configuration sets), where to write the documentation, and whether to include deprecated definitions.
+ * @returns {Promise<any[] | undefined>} - The arguments, or undefined if the user cancelled.
+ */
+async function pickGenerateDocsArguments(): Promise<any[] | undefined> {
+  const projectInfo = await client?.sendRequest<{
+    configurationSets: { name: string; isDisabled: boolean }[];
+  }>("slice/projectInfo");
+  const items = (projectInfo?.configurationSets ?? [])
+    .filter((set) => !set.isDisabled)
+    .map((set) => ({ label: set.name, target: set.name }));
+  const document = window.activeTextEditor?.document;
+  if (document?.languageId === "slice") {
+    items.unshift({ label: "Current File", target: document.uri.toString() });
+  }
+  const target = await window.showQuickPick(items, {
+    title: "Generate documentation for...",
+  });
+  if (!target) {
+    return undefined;
+  }
+
+  const folders = await window.showOpenDialog({
+    canSelectFiles: false,
+    canSelectFolders: true,
+    defaultUri: workspace.workspaceFolders?.[0]?.uri,
+    openLabel: "Generate Here",
+    title: "Generate Slice Documentation",
+  });
+  if (!folders || folders.length === 0) {
+    return undefined;
+  }
+
+  const deprecated = await window.showQuickPick(
+    ["Skip deprecated definitions", "Include deprecated definitions"],
+    { title: "Deprecated definitions" }
+  );
+  if (!deprecated) {
+    return undefined;
+  }
+  return [
+    folders[0].toString(),
+    target.target,
+    deprecated === "Include deprecated definitions",
+  ];
+}
+
+/**
+ * Report what the configuration sets define with the 'slice.workspaceStats' command. The totals are shown to the user,
+ * and the statistics of each set are written to the output channel.
+ * @param {WorkspaceStatsResult} result - The result of the command.
+ */
+function showWorkspaceStatsResult(result: WorkspaceStatsResult) {
+  const describe = (stats: WorkspaceStats) =>
+    `${stats.files} file(s), ${stats.modules} module(s), ${stats.interfaces} interface(s), ${stats.operations} operation(s), ${stats.structs} struct(s), ${stats.classes} class(es), ${stats.exceptions} exception(s), ${stats.enums} enum(s), ${stats.customTypes} custom type(s), ${stats.typeAliases} type alias(es), ${stats.deprecated} deprecated`;
+  for (const set of result.configurationSets) {
+    const modes = Object.entries(set.filesPerMode)
+      .map(([mode, files]) => `${mode}: ${files}`)
+      .join(", ");
+    logMessage(`'${set.name}': ${describe(set)} (files per mode: ${modes})`);
+  }
+
+  window.showInformationMessage(
+    `${describe(result)}. See the 'Slice' output for the statistics of each configuration set.`
+  );
+}
+
+/**
+ * Ask the user for the arguments of the 'slice.newFile' command: the folder to create the file in (unless one was
+ * right-clicked in the explorer), and the name of the file.
+ * @param {Uri | undefined} folder - The folder that the command was run on, if any.
+ * @returns {Promise<any[] | undefined>} - The arguments, or undefined if the user cancelled.
+ */
+async function pickNewFileArguments(
+  folder: Uri | undefined
+): Promise<any[] | undefined> {
+  if (!(folder instanceof Uri)) {
+    const document = window.activeTextEditor?.document;
+    const folders = await window.showOpenDialog({
+      canSelectFiles: false,
+      canSelectFolders: true,
+      defaultUri:
+        document?.uri.scheme === "file"
+          ? Uri.joinPath(document.uri, "..")
+          : workspace.workspaceFolders?.[0]?.uri,
+      title: "Create a Slice file in...",
+    });
+    folder = folders?.[0];
+    if (!folder) {
+      return undefined;
+    }
+  }
+  const fileName = await window.showInputBox({
+    title: "Name of the new Slice file",
+    value: "NewFile.slice",
+    valueSelection: [0, "NewFile".length],
+  });
+  if (!fileName) {
+    return undefined;
+  }
+  return [folder.toString(), fileName];
+}
+
+/**
+ * Let the user pick one of the built-in Slice files listed by the 'slice.openBuiltInSliceFiles' command, and open it.
+ * Files are grouped by the package they're in.
+ * @param {BuiltInSliceFilesResult} result - The result of the command.
+ */
+async function pickBuiltInSliceFile(result: BuiltInSliceFilesResult) {
+  if (result.files.length === 0) {
+    window.showWarningMessage(
+      `There aren't any built-in Slice files in '${result.path}'.`
+    );
+    return;
+  }
+
+  const items: (QuickPickItem & { uri?: string })[] = [];
+  let package_: string | null | undefined = undefined;
+  for (const file of result.files) {
+    if (file.package !== package_) {
+      package_ = file.package;
+      items.push({
+        label: package_ ?? "",
+        kind: QuickPickItemKind.Separator,
+      });
+    }
+    items.push({
+      label: file.name,
+      description: file.relativePath,
+      uri: file.uri,
+    });
+  }
+  const item = await window.showQuickPick(items, {
+    title: "Open a built-in Slice file",
+    matchOnDescription: true,
+  });
+  if (item?.uri) {
+    await window.showTextDocument(Uri.parse(item.uri));
+  }
+}
+
+/**
+ * Copy the type ID that was resolved by the 'slice.copyTypeId' command to the clipboard. Classes with a compact ID
+ * let the user pick which of their IDs to copy.
+ * @param {CopyTypeIdResult} result - The result of the command.
+ */
+async function copyTypeIdResult(result: CopyTypeIdResult) {
+  let typeId = result.typeId;
+  if (result.compactId !== null) {
+    const items = [
+      { label: result.typeId, description: "Type ID" },
+      { label: `${result.compactId}`, description: "Compact ID" },
+    ];
+    const item = await window.showQuickPick(items, {
+      title: `Copy the ID of ${result.kind} '${result.name}'`,
+    });
+    if (!item) {
+      return;
+    }
+    typeId = item.label;
+  }
+  await env.clipboard.writeText(typeId);
+  window.setStatusBarMessage(`Copied '${typeId}' to the clipboard.`, 3000);
+}
+
+/**
+ * Tell the user which configuration sets changed when the configuration was reloaded.
+ * @param {ReloadConfigurationsResult} result - The result of the 'slice/reloadConfigurations' request.
+ */
+function showReloadConfigurationsResult(result: ReloadConfigurationsResult) {
+  const changes = [
+    ["added", result.added],
+    ["removed", result.removed],
+    ["modified", result.modified],
+  ]
+    .filter(([, names]) => names.length > 0)
+    .map(([change, names]) => `${change}: ${(names as string[]).join(", ")}`);
+  if (changes.length === 0 && !result.settingsChanged) {
+    window.showInformationMessage(
+      "Reloaded the Slice configuration: nothing changed."
+    );
+    return;
+  }
+  if (result.settingsChanged) {
+    changes.push("every set was recompiled, since a setting changed");
+  }
+  window.showInformationMessage(
+    `Reloaded the Slice configuration (${changes.join("; ")}).`
+  );
+}
+
 function logMessage(
   message: string,
   type: "Info" | "Error" | "Warning" = "Info"
@@ -228,4 +812,94 @@ function logMessage(
 interface ShowNotificationParams {
   message: string;
   message_type: "Error" | "Warning" | "Info";
+  code?: string;
+  configuration_set?: string;
+  uri?: string;
+  kind?: "configurationError" | "fileNotInSet";
+}
+
+interface EditConfigurationParams {
+  edit: "AddPath" | "RemovePath";
+  path: string;
+}
+
+interface SaveConfigurationsParams {
+  folder_uri?: string;
+  configurations: any[];
+}
+
+interface OpenSettingsParams {
+  query: string;
+}
+
+interface CompileResult {
+  configurationSets: string[];
+  errors: number;
+  warnings: number;
+}
+
+interface FileConfigurationResult {
+  uri: string;
+  configurationSets: {
+    name: string;
+    isDisabled: boolean;
+    reason: string;
+    matchedPath?: string;
+    isBuiltIn: boolean;
+  }[];
+}
+
+interface WorkspaceStats {
+  files: number;
+  modules: number;
+  interfaces: number;
+  operations: number;
+  structs: number;
+  classes: number;
+  exceptions: number;
+  enums: number;
+  customTypes: number;
+  typeAliases: number;
+  deprecated: number;
+  filesPerMode: { [mode: string]: number };
+}
+
+interface WorkspaceStatsResult extends WorkspaceStats {
+  configurationSets: (WorkspaceStats & { name: string })[];
+}
+
+interface BuiltInSliceFilesResult {
+  path: string;
+  files: {
+    name: string;
+    package: string | null;
+    relativePath: string;
+    uri: string;
+  }[];
+}
+
+interface CopyTypeIdResult {
+  kind: string;
+  name: string;
+  typeId: string;
+  compactId: number | null;
+}
+
+interface ReloadConfigurationsResult {
+  added: string[];
+  removed: string[];
+  modified: string[];
+  unchanged: number;
+  settingsChanged: boolean;
+}
+
+interface CompileAllResult {
+  milliseconds: number;
+  configurationSets: {
+    name: string;
+    milliseconds: number;
+    files: number;
+    errors: number;
+    warnings: number;
+  }[];
 }
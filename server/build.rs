@@ -0,0 +1,20 @@
+// Copyright (c) ZeroC, Inc.
+
+// Embeds the version of `slicec` that the server is built with, so that it can be reported by `--version`. Cargo
+// doesn't tell crates the versions of their dependencies, so it's read from the lock file (which Cargo always writes
+// before running build scripts).
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let lock_file = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let version = lock_file
+        .split("[[package]]")
+        .filter(|package| package.lines().any(|line| line.trim() == "name = \"slicec\""))
+        .find_map(|package| {
+            package
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("version = \"")?.strip_suffix('"'))
+        })
+        .unwrap_or("unknown");
+    println!("cargo:rustc-env=SLICEC_VERSION={version}");
+}
@@ -0,0 +1,484 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::ServerConfig;
+use crate::configuration_set::ConfigurationSet;
+use crate::notifications::{ConfigurationEdit, EditConfigurationParams, MessageType, NotificationKind};
+use crate::notifications::{OpenSettingsParams, SaveConfigurationsParams, ShowNotificationParams};
+use crate::server_state::ServerState;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::MessageActionItem;
+
+/// The actions a user can pick from when the server shows them a popup with `window/showMessageRequest`.
+///
+/// Each action is presented to the user as a button labeled with its [title](PopupAction::title). When the user clicks
+/// one, the client sends back the title of the clicked button, which [`PopupAction::from_selection`] maps back to
+/// the action it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PopupAction {
+    /// Open the Slice settings in the client.
+    OpenSettings,
+
+    /// Create the specified (missing) directory on disk.
+    CreateFolder(PathBuf),
+
+    /// Add the specified path to the user's Slice configuration.
+    AddToConfiguration(String),
+
+    /// Remove the specified path from the user's Slice configuration.
+    RemoveFromConfiguration(String),
+
+    /// Write the configuration sets which were discovered from the workspace's layout into the user's settings.
+    SaveDiscoveredConfigurations,
+
+    /// Stop notifying the user about opened files that aren't part of any configuration set.
+    SuppressUnconfiguredFileNotifications,
+}
+
+impl PopupAction {
+    /// Returns the title of the button which is shown to the user for this action.
+    pub fn title(&self) -> &'static str {
+        match self {
+            PopupAction::OpenSettings => "Open settings",
+            PopupAction::CreateFolder(_) => "Create folder",
+            PopupAction::AddToConfiguration(_) => "Add folder to configuration",
+            PopupAction::RemoveFromConfiguration(_) => "Remove from configuration",
+            PopupAction::SaveDiscoveredConfigurations => "Save to settings",
+            PopupAction::SuppressUnconfiguredFileNotifications => "Don't show again for this workspace",
+        }
+    }
+
+    /// Converts this action into the item that is sent to the client.
+    pub fn to_message_action_item(&self) -> MessageActionItem {
+        MessageActionItem {
+            title: self.title().to_owned(),
+            properties: HashMap::new(),
+        }
+    }
+
+    /// Returns the action that corresponds to the item selected by the user, if there is one.
+    ///
+    /// Only the actions which were offered to the user are considered, since the client can only send back an item
+    /// that we gave it.
+    pub fn from_selection(offered_actions: Vec<PopupAction>, selected: &MessageActionItem) -> Option<PopupAction> {
+        offered_actions
+            .into_iter()
+            .find(|action| action.title() == selected.title)
+    }
+}
+
+/// A popup that's shown to the user, along with the actions it offers them.
+#[derive(Debug)]
+pub struct Popup {
+    pub params: ShowNotificationParams,
+    pub actions: Vec<PopupAction>,
+}
+
+/// A problem with one of the paths of a configuration set, which the user has already been told about.
+///
+/// Sets are re-compiled whenever a file changes, and their paths are re-checked each time. To avoid showing the user
+/// the same popup after every edit, each problem is only reported once, until the configuration changes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathProblem {
+    pub set_name: String,
+    pub path: PathBuf,
+}
+
+/// Returns the popups which warn the user about problems with the paths of a configuration set, like search paths
+/// which don't exist. Problems which are already in `reported_problems` are skipped, and the others are added to it.
+pub fn path_problem_popups(
+    set: &ConfigurationSet,
+    server_config: &ServerConfig,
+    reported_problems: &mut HashSet<PathProblem>,
+) -> Vec<Popup> {
+    let mut is_new = |path: &Path| {
+        let problem = PathProblem { set_name: set.name.clone(), path: path.to_owned() };
+        reported_problems.insert(problem)
+    };
+
+    let mut popups = Vec::new();
+
+    // Warn the user about any search paths that don't exist, and offer to fix them.
+    for (path, absolute_path) in set.missing_search_paths(server_config) {
+        if !is_new(path) {
+            continue;
+        }
+        let message = format!(
+            "The Slice search path '{}' of configuration set '{}' does not exist, so it was skipped.",
+            path.display(),
+            set.name,
+        );
+        let params = ShowNotificationParams {
+            configuration_set: Some(set.name.clone()),
+            uri: server_config.paths.convert_slice_path_to_uri(&absolute_path),
+            kind: Some(NotificationKind::ConfigurationError),
+            ..ShowNotificationParams::new(message, MessageType::Warning)
+        };
+        let mut actions = vec![PopupAction::RemoveFromConfiguration(path.display().to_string())];
+        if path.extension().is_none() {
+            actions.insert(0, PopupAction::CreateFolder(absolute_path));
+        }
+        popups.push(Popup { params, actions });
+    }
+
+    // Likewise for any reference paths that don't exist. References are only ever skipped individually, so one bad
+    // entry doesn't change what the set's other paths compile.
+    for (path, absolute_path) in set.missing_reference_paths(server_config) {
+        if !is_new(path) {
+            continue;
+        }
+        let message = format!(
+            "The Slice reference '{}' of configuration set '{}' does not exist, so it was skipped.",
+            path.display(),
+            set.name,
+        );
+        let params = ShowNotificationParams {
+            configuration_set: Some(set.name.clone()),
+            uri: server_config.paths.convert_slice_path_to_uri(&absolute_path),
+            kind: Some(NotificationKind::ConfigurationError),
+            ..ShowNotificationParams::new(message, MessageType::Warning)
+        };
+        popups.push(Popup { params, actions: vec![PopupAction::OpenSettings] });
+    }
+
+    // Warn the user about any glob patterns that don't match anything, since the set is probably missing files.
+    for pattern in set.unmatched_glob_patterns(server_config) {
+        if !is_new(pattern) {
+            continue;
+        }
+        let message = format!(
+            "The Slice search pattern '{}' of configuration set '{}' does not match any paths.",
+            pattern.display(),
+            set.name,
+        );
+        let params = ShowNotificationParams {
+            configuration_set: Some(set.name.clone()),
+            kind: Some(NotificationKind::ConfigurationError),
+            ..ShowNotificationParams::new(message, MessageType::Warning)
+        };
+        let actions = vec![PopupAction::RemoveFromConfiguration(pattern.display().to_string())];
+        popups.push(Popup { params, actions });
+    }
+
+    popups
+}
+
+/// Something that the server does in response to an action that the user selected from a popup, after the action
+/// itself has been performed by [`perform_popup_action`].
+#[derive(Debug)]
+pub enum ActionEffect {
+    /// Ask the client to open its settings editor.
+    OpenSettings(OpenSettingsParams),
+    /// Ask the client to edit the user's Slice configuration.
+    EditConfiguration(EditConfigurationParams),
+    /// Ask the client to write configuration sets into the user's settings.
+    SaveConfigurations(SaveConfigurationsParams),
+    /// Tell the user about the outcome of the action.
+    ShowNotification(ShowNotificationParams),
+    /// Re-compile every configuration set, and publish their diagnostics.
+    Recompile,
+}
+
+/// Performs an action that the user selected from a popup, and returns what the server must do to finish it (in order).
+///
+/// Anything that only involves the server's state or the file system is done here, so that everything which involves
+/// the client is left to the caller.
+pub fn perform_popup_action(action: PopupAction, server_state: &mut ServerState) -> Vec<ActionEffect> {
+    match action {
+        PopupAction::OpenSettings => vec![ActionEffect::OpenSettings(OpenSettingsParams { query: "slice".to_owned() })],
+
+        PopupAction::CreateFolder(path) => match std::fs::create_dir_all(&path) {
+            Ok(()) => {
+                let message = format!("Created folder '{}'.", path.display());
+                let params = ShowNotificationParams {
+                    uri: server_state.server_config.paths.convert_slice_path_to_uri(&path),
+                    ..ShowNotificationParams::new(message, MessageType::Info)
+                };
+                vec![ActionEffect::ShowNotification(params), ActionEffect::Recompile]
+            }
+            Err(error) => {
+                let message = format!("Failed to create folder '{}': {error}", path.display());
+                let params = ShowNotificationParams {
+                    uri: server_state.server_config.paths.convert_slice_path_to_uri(&path),
+                    kind: Some(NotificationKind::ConfigurationError),
+                    ..ShowNotificationParams::new(message, MessageType::Error)
+                };
+                vec![ActionEffect::ShowNotification(params)]
+            }
+        },
+
+        // The client owns the user's settings, so it performs these edits. Once the settings are updated, the client
+        // sends a `didChangeConfiguration` notification, which triggers a re-compilation.
+        PopupAction::AddToConfiguration(path) => {
+            let params = EditConfigurationParams { edit: ConfigurationEdit::AddPath, path };
+            vec![ActionEffect::EditConfiguration(params)]
+        }
+        PopupAction::RemoveFromConfiguration(path) => {
+            let params = EditConfigurationParams { edit: ConfigurationEdit::RemovePath, path };
+            vec![ActionEffect::EditConfiguration(params)]
+        }
+
+        // Discovered sets are grouped by the folder they were discovered in, since folders have their own settings.
+        PopupAction::SaveDiscoveredConfigurations => {
+            let mut folders: Vec<(Option<PathBuf>, Vec<serde_json::Value>)> = Vec::new();
+            for set in server_state.configuration_sets.iter().filter(|set| set.is_discovered()) {
+                let folder_path = set.slice_config.folder_path.clone();
+                match folders.iter_mut().find(|(folder, _)| *folder == folder_path) {
+                    Some((_, configurations)) => configurations.push(set.to_json()),
+                    None => folders.push((folder_path, vec![set.to_json()])),
+                }
+            }
+
+            folders
+                .into_iter()
+                .map(|(folder_path, configurations)| {
+                    let paths = &server_state.server_config.paths;
+                    let folder_uri = folder_path.and_then(|folder_path| paths.convert_slice_path_to_uri(folder_path));
+                    ActionEffect::SaveConfigurations(SaveConfigurationsParams { folder_uri, configurations })
+                })
+                .collect()
+        }
+
+        PopupAction::SuppressUnconfiguredFileNotifications => {
+            server_state.suppress_unconfigured_file_notifications = true;
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::PathResolver;
+
+    /// The directory that the test sets' relative paths are resolved against, which is used as the workspace's root.
+    fn workspace_path() -> PathBuf {
+        std::env::current_dir().unwrap().join("tests/fixtures")
+    }
+
+    fn server_config() -> ServerConfig {
+        ServerConfig { workspace_root_paths: vec![workspace_path()], ..ServerConfig::default() }
+    }
+
+    fn configuration_set(name: &str, paths: &[&str]) -> ConfigurationSet {
+        let paths = paths.iter().map(PathBuf::from).collect();
+        ConfigurationSet::discovered_set(name.to_owned(), paths, None)
+    }
+
+    /// Returns the message of each popup, along with the actions it offers.
+    fn summarize(popups: Vec<Popup>) -> Vec<(String, Vec<PopupAction>)> {
+        popups.into_iter().map(|popup| (popup.params.message, popup.actions)).collect()
+    }
+
+    #[test]
+    fn missing_search_paths_are_reported_with_actions_to_fix_them() {
+        let set = configuration_set("shapes", &["missing-folder", "missing.slice", "relative"]);
+        let popups = path_problem_popups(&set, &server_config(), &mut HashSet::new());
+
+        let expected = [
+            (
+                "The Slice search path 'missing-folder' of configuration set 'shapes' does not exist, so it was \
+                 skipped.",
+                vec![
+                    PopupAction::CreateFolder(workspace_path().join("missing-folder")),
+                    PopupAction::RemoveFromConfiguration("missing-folder".to_owned()),
+                ],
+            ),
+            (
+                "The Slice search path 'missing.slice' of configuration set 'shapes' does not exist, so it was \
+                 skipped.",
+                vec![PopupAction::RemoveFromConfiguration("missing.slice".to_owned())],
+            ),
+        ];
+        let expected = expected.map(|(message, actions)| (message.to_owned(), actions));
+        assert_eq!(summarize(popups), expected);
+    }
+
+    #[test]
+    fn missing_search_paths_are_only_reported_once() {
+        let set = configuration_set("shapes", &["missing-folder"]);
+        let server_config = server_config();
+        let mut reported_problems = HashSet::new();
+        assert_eq!(path_problem_popups(&set, &server_config, &mut reported_problems).len(), 1);
+
+        // Re-compiling the set doesn't report the problem again.
+        assert!(path_problem_popups(&set, &server_config, &mut reported_problems).is_empty());
+        assert!(path_problem_popups(&set, &server_config, &mut reported_problems).is_empty());
+
+        // But a new problem in the same set is still reported.
+        let set = configuration_set("shapes", &["missing-folder", "other-missing-folder"]);
+        let popups = path_problem_popups(&set, &server_config, &mut reported_problems);
+        assert_eq!(popups.len(), 1);
+        assert!(popups[0].params.message.contains("other-missing-folder"));
+    }
+
+    #[test]
+    fn the_same_missing_path_is_reported_for_each_set() {
+        let server_config = server_config();
+        let mut reported_problems = HashSet::new();
+        for name in ["first", "second"] {
+            let set = configuration_set(name, &["missing-folder"]);
+            let popups = path_problem_popups(&set, &server_config, &mut reported_problems);
+            assert_eq!(popups.len(), 1);
+            assert_eq!(popups[0].params.configuration_set.as_deref(), Some(name));
+        }
+    }
+
+    #[test]
+    fn only_the_missing_entries_of_mixed_reference_lists_are_reported_once() {
+        let mut set = configuration_set("shapes", &["type_ids"]);
+        let references = ["api_docs", "missing-folder", "relative/greeter.slice", "missing.slice"];
+        set.slice_config.reference_paths = references.map(PathBuf::from).to_vec();
+        let server_config = server_config();
+        let mut reported_problems = HashSet::new();
+        let popups = path_problem_popups(&set, &server_config, &mut reported_problems);
+
+        let expected = ["missing-folder", "missing.slice"].map(|reference| {
+            let message = format!(
+                "The Slice reference '{reference}' of configuration set 'shapes' does not exist, so it was skipped.",
+            );
+            (message, vec![PopupAction::OpenSettings])
+        });
+        assert_eq!(summarize(popups), expected);
+        assert!(path_problem_popups(&set, &server_config, &mut reported_problems).is_empty());
+    }
+
+    #[test]
+    fn unmatched_glob_patterns_are_only_reported_once() {
+        let set = configuration_set("shapes", &["relative/*.slice", "missing/**/*.slice", "*.ice"]);
+        let server_config = server_config();
+        let mut reported_problems = HashSet::new();
+        let popups = path_problem_popups(&set, &server_config, &mut reported_problems);
+
+        let expected = ["missing/**/*.slice", "*.ice"].map(|pattern| {
+            let message = format!(
+                "The Slice search pattern '{pattern}' of configuration set 'shapes' does not match any paths.",
+            );
+            (message, vec![PopupAction::RemoveFromConfiguration(pattern.to_owned())])
+        });
+        assert_eq!(summarize(popups), expected);
+        assert!(path_problem_popups(&set, &server_config, &mut reported_problems).is_empty());
+
+        // The same pattern in another set is reported for that set.
+        let other_set = configuration_set("other", &["*.ice"]);
+        assert_eq!(path_problem_popups(&other_set, &server_config, &mut reported_problems).len(), 1);
+    }
+
+    #[test]
+    fn problems_are_reported_again_after_the_configuration_changes() {
+        let mut server_state = ServerState { server_config: server_config(), ..ServerState::default() };
+        let set = configuration_set("shapes", &["missing-folder"]);
+        let reported_problems = &mut server_state.reported_path_problems;
+        assert_eq!(path_problem_popups(&set, &server_state.server_config, reported_problems).len(), 1);
+        assert!(!server_state.reported_path_problems.is_empty());
+
+        server_state.update_workspace_folders(Vec::new(), &[]);
+        assert!(server_state.reported_path_problems.is_empty());
+        let reported_problems = &mut server_state.reported_path_problems;
+        assert_eq!(path_problem_popups(&set, &server_state.server_config, reported_problems).len(), 1);
+    }
+
+    #[test]
+    fn selections_are_mapped_back_to_the_offered_actions() {
+        let offered_actions = vec![PopupAction::OpenSettings, PopupAction::CreateFolder("Slice".into())];
+        let selected = PopupAction::CreateFolder("Other".into()).to_message_action_item();
+        assert_eq!(
+            PopupAction::from_selection(offered_actions.clone(), &selected),
+            Some(PopupAction::CreateFolder("Slice".into())),
+        );
+
+        // Actions which weren't offered are ignored, even if the client sends them back.
+        let selected = PopupAction::SaveDiscoveredConfigurations.to_message_action_item();
+        assert_eq!(PopupAction::from_selection(offered_actions, &selected), None);
+    }
+
+    #[test]
+    fn settings_actions_are_performed_by_the_client() {
+        let mut server_state = ServerState::default();
+        let effects = perform_popup_action(PopupAction::OpenSettings, &mut server_state);
+        assert!(matches!(&effects[..], [ActionEffect::OpenSettings(params)] if params.query == "slice"));
+
+        let effects = perform_popup_action(PopupAction::AddToConfiguration("slice".to_owned()), &mut server_state);
+        assert!(matches!(
+            &effects[..],
+            [ActionEffect::EditConfiguration(EditConfigurationParams { edit: ConfigurationEdit::AddPath, path })]
+                if path == "slice"
+        ));
+
+        let effects = perform_popup_action(PopupAction::RemoveFromConfiguration("a b".to_owned()), &mut server_state);
+        assert!(matches!(
+            &effects[..],
+            [ActionEffect::EditConfiguration(EditConfigurationParams { edit: ConfigurationEdit::RemovePath, path })]
+                if path == "a b"
+        ));
+    }
+
+    #[test]
+    fn created_folders_are_reported_and_recompiled() {
+        let directory = std::env::temp_dir().join(format!("slice-language-server-actions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let folder = directory.join("nested/folder");
+
+        let effects = perform_popup_action(PopupAction::CreateFolder(folder.clone()), &mut ServerState::default());
+        assert!(folder.is_dir());
+        let [ActionEffect::ShowNotification(params), ActionEffect::Recompile] = &effects[..] else {
+            panic!("unexpected effects: {effects:?}");
+        };
+        assert!(matches!(params.message_type, MessageType::Info));
+        assert_eq!(params.uri, PathResolver::default().convert_slice_path_to_uri(&folder));
+
+        // Folders can't be created inside of files, in which case nothing is re-compiled.
+        let file = directory.join("file.slice");
+        std::fs::write(&file, "").unwrap();
+        let effects = perform_popup_action(PopupAction::CreateFolder(file.join("folder")), &mut ServerState::default());
+        let [ActionEffect::ShowNotification(params)] = &effects[..] else {
+            panic!("unexpected effects: {effects:?}");
+        };
+        assert!(matches!(params.message_type, MessageType::Error));
+        assert!(matches!(params.kind, Some(NotificationKind::ConfigurationError)));
+        assert!(params.message.starts_with("Failed to create folder"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn discovered_sets_are_saved_into_the_settings_of_their_folders() {
+        let (first_folder, second_folder) = (workspace_path().join("first"), workspace_path().join("second"));
+        let discovered_set = |name: &str, folder: &Path| {
+            ConfigurationSet::discovered_set(name.to_owned(), vec!["slice".into()], Some(folder))
+        };
+        let configuration_sets = vec![
+            discovered_set("a", &first_folder),
+            discovered_set("b", &second_folder),
+            ConfigurationSet::default_set(Some(&first_folder)),
+            discovered_set("c", &first_folder),
+        ];
+        let mut server_state = ServerState { configuration_sets, ..ServerState::default() };
+
+        let effects = perform_popup_action(PopupAction::SaveDiscoveredConfigurations, &mut server_state);
+        let saved = effects
+            .into_iter()
+            .map(|effect| match effect {
+                ActionEffect::SaveConfigurations(params) => {
+                    let names = params.configurations.iter().map(|set| set["name"].as_str().unwrap().to_owned());
+                    (params.folder_uri, names.collect::<Vec<_>>())
+                }
+                effect => panic!("unexpected effect: {effect:?}"),
+            })
+            .collect::<Vec<_>>();
+        let paths = &server_state.server_config.paths;
+        let expected = [
+            (paths.convert_slice_path_to_uri(&first_folder), vec!["a".to_owned(), "c".to_owned()]),
+            (paths.convert_slice_path_to_uri(&second_folder), vec!["b".to_owned()]),
+        ];
+        assert_eq!(saved, expected);
+    }
+
+    #[test]
+    fn suppressing_notifications_only_changes_the_servers_state() {
+        let mut server_state = ServerState::default();
+        let effects = perform_popup_action(PopupAction::SuppressUnconfiguredFileNotifications, &mut server_state);
+        assert!(effects.is_empty());
+        assert!(server_state.suppress_unconfigured_file_notifications);
+    }
+}
@@ -0,0 +1,524 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::ast_dump::type_name;
+use crate::dependency_graph::module_of;
+use slicec::grammar::{
+    Attributable, Class, Commentable, CustomType, Entity, Enum, Exception, Field, Interface, Message, MessageComponent,
+    NamedSymbol, Operation, Parameter, Struct, Type, TypeAlias, TypeRef, TypeRefDefinition, Types,
+};
+use slicec::slice_file::SliceFile;
+use slicec::visitor::Visitor;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+/// The name of the page which lists every documented module.
+pub const INDEX_PAGE: &str = "index.md";
+
+/// Generates markdown documentation of the definitions in the provided files, for the `slice.generateDocs` command.
+/// Returns the name of each page, along with its contents.
+///
+/// Each module gets its own page (named after the module, like `Foo.Bar.md`), with a section for each of its
+/// definitions, in the order they're defined in. Sections hold the definition's doc comment, along with its fields,
+/// operations (and their signatures), or enumerators. References to other documented definitions (like the types of
+/// fields, bases, and links in doc comments) link to their sections, even across pages. An index page links to every
+/// module's page. Deprecated definitions (and members) are skipped, unless `include_deprecated` is set.
+pub fn generate_docs(files: &[&SliceFile], include_deprecated: bool) -> Vec<(String, String)> {
+    // Find every definition that's documented first, so that references to them can be linked.
+    let mut collector = DefinitionCollector { include_deprecated, identifiers: HashSet::new() };
+    for file in files {
+        file.visit_with(&mut collector);
+    }
+
+    let mut writer = DocsWriter { documented: collector.identifiers, include_deprecated, modules: BTreeMap::new() };
+    for file in files {
+        file.visit_with(&mut writer);
+    }
+
+    let mut index = String::from("# API Reference\n\n");
+    let mut pages = Vec::new();
+    for (module, sections) in writer.modules {
+        let _ = writeln!(index, "- [`{module}`]({}) ({} definition(s))", page_name(&module), sections.len());
+        let mut page = format!("# Module `{module}`\n");
+        for section in sections {
+            page.push('\n');
+            page.push_str(&section);
+        }
+        pages.push((page_name(&module), page));
+    }
+    pages.push((INDEX_PAGE.to_owned(), index));
+    pages
+}
+
+/// Returns the name of the page that documents a module.
+fn page_name(module: &str) -> String {
+    format!("{}.md", module.replace("::", "."))
+}
+
+/// Returns true if the provided entity is marked with the `deprecated` attribute.
+fn is_deprecated(attributable: &dyn Attributable) -> bool {
+    let attributes = attributable.attributes();
+    attributes.iter().any(|attribute| attribute.kind.directive() == "deprecated")
+}
+
+/// Collects the fully-qualified identifiers of the definitions which are documented.
+struct DefinitionCollector {
+    include_deprecated: bool,
+    identifiers: HashSet<String>,
+}
+
+impl DefinitionCollector {
+    fn add(&mut self, entity: &dyn Entity) {
+        if self.include_deprecated || !is_deprecated(entity) {
+            self.identifiers.insert(entity.parser_scoped_identifier());
+        }
+    }
+}
+
+impl Visitor for DefinitionCollector {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.add(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.add(class_def);
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.add(exception_def);
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        self.add(interface_def);
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.add(enum_def);
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.add(custom_type_def);
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        self.add(type_alias_def);
+    }
+}
+
+/// Renders a section for each documented definition, grouped by the module it's in.
+struct DocsWriter {
+    documented: HashSet<String>,
+    include_deprecated: bool,
+    modules: BTreeMap<String, Vec<String>>,
+}
+
+impl DocsWriter {
+    /// Starts the section of a definition, with its heading, deprecation notice, and doc comment overview. Returns
+    /// `None` if the definition isn't documented.
+    fn begin_section(&self, entity: &dyn Commentable) -> Option<String> {
+        let identifier = entity.parser_scoped_identifier();
+        if !self.documented.contains(&identifier) {
+            return None;
+        }
+
+        // The anchor is written explicitly, so that links don't depend on how renderers derive anchors from headings.
+        let mut section = format!("<a id=\"{}\"></a>\n\n", entity.identifier());
+        let _ = writeln!(section, "## {} `{identifier}`\n", entity.kind());
+        if is_deprecated(entity) {
+            section.push_str("**Deprecated.**\n\n");
+        }
+        if let Some(overview) = entity.comment().and_then(|comment| comment.overview.as_ref()) {
+            let _ = writeln!(section, "{}\n", self.message(overview).trim());
+        }
+        Some(section)
+    }
+
+    /// Ends the section of a definition, with the entities that its doc comment links to, and adds it to its module.
+    fn end_section(&mut self, entity: &dyn Commentable, mut section: String) {
+        if let Some(comment) = entity.comment().filter(|comment| !comment.see.is_empty()) {
+            let see = comment.see.iter().map(|tag| match tag.linked_entity() {
+                Ok(linked_entity) => self.identifier_link(&linked_entity.parser_scoped_identifier()),
+                Err(identifier) => format!("`{}`", identifier.value),
+            });
+            let _ = writeln!(section, "See also: {}\n", see.collect::<Vec<_>>().join(", "));
+        }
+        let module = module_of(&entity.parser_scoped_identifier()).to_owned();
+        self.modules.entry(module).or_default().push(section);
+    }
+
+    /// Renders a link to the section of the definition with the provided identifier, or just its identifier (as code)
+    /// if it isn't documented.
+    fn identifier_link(&self, identifier: &str) -> String {
+        if !self.documented.contains(identifier) {
+            return format!("`{identifier}`");
+        }
+        let name = identifier.rsplit_once("::").map_or(identifier, |(_, name)| name);
+        format!("[`{identifier}`]({}#{name})", page_name(module_of(identifier)))
+    }
+
+    fn entity_link<T: NamedSymbol>(&self, type_ref: &TypeRef<T>) -> String {
+        match &type_ref.definition {
+            TypeRefDefinition::Patched(_) => self.identifier_link(&entity_name(type_ref)),
+            TypeRefDefinition::Unpatched(identifier) => format!("`{}`", identifier.value),
+        }
+    }
+
+    /// Renders a type reference, linking each of the documented types it's made of.
+    fn type_ref(&self, type_ref: &TypeRef) -> String {
+        let name = match &type_ref.definition {
+            TypeRefDefinition::Patched(definition) => self.concrete_type(definition.borrow().concrete_type()),
+            TypeRefDefinition::Unpatched(identifier) => format!("`{}`", identifier.value),
+        };
+        match type_ref.is_optional {
+            true => format!("{name}?"),
+            false => name,
+        }
+    }
+
+    fn concrete_type(&self, concrete_type: Types) -> String {
+        match concrete_type {
+            Types::Struct(struct_def) => self.identifier_link(&struct_def.parser_scoped_identifier()),
+            Types::Class(class_def) => self.identifier_link(&class_def.parser_scoped_identifier()),
+            Types::Enum(enum_def) => self.identifier_link(&enum_def.parser_scoped_identifier()),
+            Types::CustomType(custom_type_def) => self.identifier_link(&custom_type_def.parser_scoped_identifier()),
+            Types::ResultType(result_type) => format!(
+                "Result<{}, {}>",
+                self.type_ref(&result_type.success_type),
+                self.type_ref(&result_type.failure_type),
+            ),
+            Types::Sequence(sequence) => format!("sequence<{}>", self.type_ref(&sequence.element_type)),
+            Types::Dictionary(dictionary) => format!(
+                "dictionary<{}, {}>",
+                self.type_ref(&dictionary.key_type),
+                self.type_ref(&dictionary.value_type),
+            ),
+            Types::Primitive(primitive) => format!("`{}`", primitive.type_string()),
+        }
+    }
+
+    /// Renders the message of a doc comment, with its links pointing to the sections of the entities they link to.
+    fn message(&self, message: &Message) -> String {
+        message
+            .value
+            .iter()
+            .map(|component| match component {
+                MessageComponent::Text(text) => text.clone(),
+                MessageComponent::Link(tag) => match tag.linked_entity() {
+                    Ok(entity) => self.identifier_link(&entity.parser_scoped_identifier()),
+                    Err(identifier) => format!("`{}`", identifier.value),
+                },
+            })
+            .collect()
+    }
+
+    /// Renders a message so that it fits in a single table cell.
+    fn cell(&self, message: Option<&Message>) -> String {
+        let text = message.map(|message| self.message(message)).unwrap_or_default();
+        text.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|")
+    }
+
+    /// Renders the description of a member in a table cell, which starts with a notice if the member is deprecated.
+    fn deprecated_cell(&self, member: &dyn Attributable, message: Option<&Message>) -> String {
+        let cell = self.cell(message);
+        match is_deprecated(member) {
+            true if cell.is_empty() => "**Deprecated.**".to_owned(),
+            true => format!("**Deprecated.** {cell}"),
+            false => cell,
+        }
+    }
+
+    fn fields_table(&self, section: &mut String, fields: Vec<&Field>) {
+        let fields = fields.into_iter().filter(|field| self.include_deprecated || !is_deprecated(*field));
+        let mut rows = String::new();
+        for field in fields {
+            let description = field.comment().and_then(|comment| comment.overview.as_ref());
+            let _ = writeln!(
+                rows,
+                "| `{}` | {} | {} | {} | {} |",
+                field.identifier(),
+                self.type_ref(&field.data_type),
+                field.tag.as_ref().map_or(String::new(), |tag| tag.value.to_string()),
+                if field.data_type.is_optional { "yes" } else { "no" },
+                self.deprecated_cell(field, description),
+            );
+        }
+        if !rows.is_empty() {
+            section.push_str("| Field | Type | Tag | Optional | Description |\n| --- | --- | --- | --- | --- |\n");
+            section.push_str(&rows);
+            section.push('\n');
+        }
+    }
+
+    fn operation(&self, section: &mut String, operation: &Operation) {
+        let _ = writeln!(section, "### `{}`\n", operation.identifier());
+        let _ = writeln!(section, "```slice\n{}\n```\n", signature(operation));
+        if is_deprecated(operation) {
+            section.push_str("**Deprecated.**\n\n");
+        }
+
+        let comment = operation.comment();
+        if let Some(overview) = comment.and_then(|comment| comment.overview.as_ref()) {
+            let _ = writeln!(section, "{}\n", self.message(overview).trim());
+        }
+
+        let parameter_rows = |parameters: Vec<&Parameter>, is_return: bool| {
+            let mut rows = String::new();
+            for parameter in parameters {
+                let name = parameter.identifier();
+                let description = comment.and_then(|comment| match is_return {
+                    true => comment
+                        .returns
+                        .iter()
+                        .find(|tag| tag.identifier.as_ref().is_none_or(|identifier| identifier.value == name))
+                        .map(|tag| &tag.message),
+                    false => comment.params.iter().find(|tag| tag.identifier.value == name).map(|tag| &tag.message),
+                });
+                let _ = writeln!(
+                    rows,
+                    "| `{name}` | {} | {} | {} |",
+                    self.type_ref(&parameter.data_type),
+                    parameter.tag.as_ref().map_or(String::new(), |tag| tag.value.to_string()),
+                    self.cell(description),
+                );
+            }
+            rows
+        };
+        let parameters = parameter_rows(operation.parameters(), false);
+        if !parameters.is_empty() {
+            section.push_str("| Parameter | Type | Tag | Description |\n| --- | --- | --- | --- |\n");
+            section.push_str(&parameters);
+            section.push('\n');
+        }
+        let return_members = parameter_rows(operation.return_members(), true);
+        if !return_members.is_empty() {
+            section.push_str("| Return | Type | Tag | Description |\n| --- | --- | --- | --- |\n");
+            section.push_str(&return_members);
+            section.push('\n');
+        }
+
+        for exception in &operation.exception_specification {
+            let exception_name = entity_name(exception);
+            let description = comment.and_then(|comment| {
+                let tag = comment.throws.iter().find(|tag| match tag.thrown_type() {
+                    Ok(thrown_exception) => thrown_exception.parser_scoped_identifier() == exception_name,
+                    Err(identifier) => identifier.value == exception_name,
+                });
+                tag.map(|tag| &tag.message)
+            });
+            let _ = match self.cell(description) {
+                description if description.is_empty() => writeln!(section, "- Throws {}", self.entity_link(exception)),
+                description => writeln!(section, "- Throws {}: {description}", self.entity_link(exception)),
+            };
+        }
+        if !operation.exception_specification.is_empty() {
+            section.push('\n');
+        }
+    }
+}
+
+impl Visitor for DocsWriter {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        let Some(mut section) = self.begin_section(struct_def) else {
+            return;
+        };
+        if struct_def.is_compact {
+            section.push_str("This struct is compact.\n\n");
+        }
+        self.fields_table(&mut section, struct_def.fields());
+        self.end_section(struct_def, section);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        let Some(mut section) = self.begin_section(class_def) else {
+            return;
+        };
+        if let Some(base) = &class_def.base {
+            let _ = writeln!(section, "Inherits from {}.\n", self.entity_link(base));
+        }
+        self.fields_table(&mut section, class_def.fields());
+        self.end_section(class_def, section);
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        let Some(mut section) = self.begin_section(exception_def) else {
+            return;
+        };
+        if let Some(base) = &exception_def.base {
+            let _ = writeln!(section, "Inherits from {}.\n", self.entity_link(base));
+        }
+        self.fields_table(&mut section, exception_def.fields());
+        self.end_section(exception_def, section);
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        let Some(mut section) = self.begin_section(interface_def) else {
+            return;
+        };
+        if !interface_def.bases.is_empty() {
+            let bases = interface_def.bases.iter().map(|base| self.entity_link(base)).collect::<Vec<_>>();
+            let _ = writeln!(section, "Inherits from {}.\n", bases.join(", "));
+        }
+        let operations = interface_def.operations().into_iter();
+        for operation in operations.filter(|operation| self.include_deprecated || !is_deprecated(*operation)) {
+            self.operation(&mut section, operation);
+        }
+        self.end_section(interface_def, section);
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        let Some(mut section) = self.begin_section(enum_def) else {
+            return;
+        };
+        if let Some(underlying) = &enum_def.underlying {
+            let underlying = match &underlying.definition {
+                TypeRefDefinition::Patched(primitive) => primitive.borrow().type_string(),
+                TypeRefDefinition::Unpatched(identifier) => identifier.value.clone(),
+            };
+            let _ = writeln!(section, "Underlying type: `{underlying}`.\n");
+        }
+        if enum_def.is_unchecked {
+            section.push_str("This enum is unchecked.\n\n");
+        }
+        let enumerators = enum_def.enumerators().into_iter();
+        let mut rows = String::new();
+        for enumerator in enumerators.filter(|enumerator| self.include_deprecated || !is_deprecated(*enumerator)) {
+            let description = enumerator.comment().and_then(|comment| comment.overview.as_ref());
+            let _ = writeln!(
+                rows,
+                "| `{}` | {} | {} |",
+                enumerator.identifier(),
+                enumerator.value(),
+                self.deprecated_cell(enumerator, description),
+            );
+        }
+        if !rows.is_empty() {
+            section.push_str("| Enumerator | Value | Description |\n| --- | --- | --- |\n");
+            section.push_str(&rows);
+            section.push('\n');
+        }
+        self.end_section(enum_def, section);
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        if let Some(section) = self.begin_section(custom_type_def) {
+            self.end_section(custom_type_def, section);
+        }
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        let Some(mut section) = self.begin_section(type_alias_def) else {
+            return;
+        };
+        let _ = writeln!(section, "Alias of {}.\n", self.type_ref(&type_alias_def.underlying));
+        self.end_section(type_alias_def, section);
+    }
+}
+
+/// Returns the fully-qualified name of the entity that a type reference (like the base of a class) resolves to.
+fn entity_name<T: NamedSymbol>(type_ref: &TypeRef<T>) -> String {
+    match &type_ref.definition {
+        TypeRefDefinition::Patched(definition) => definition.borrow().parser_scoped_identifier(),
+        TypeRefDefinition::Unpatched(identifier) => identifier.value.clone(),
+    }
+}
+
+/// Returns the signature of an operation, as it would be written in Slice.
+fn signature(operation: &Operation) -> String {
+    let parameter = |parameter: &Parameter| {
+        let tag = parameter.tag.as_ref().map_or(String::new(), |tag| format!("tag({}) ", tag.value));
+        let stream = if parameter.is_streamed { "stream " } else { "" };
+        format!("{tag}{}: {stream}{}", parameter.identifier(), type_name(&parameter.data_type))
+    };
+
+    let idempotent = if operation.is_idempotent { "idempotent " } else { "" };
+    let parameters = operation.parameters().into_iter().map(parameter).collect::<Vec<_>>();
+    let mut signature = format!("{idempotent}{}({})", operation.identifier(), parameters.join(", "));
+    match operation.return_members().as_slice() {
+        [] => {}
+        [return_member] if return_member.tag.is_none() => {
+            let stream = if return_member.is_streamed { "stream " } else { "" };
+            let _ = write!(signature, " -> {stream}{}", type_name(&return_member.data_type));
+        }
+        return_members => {
+            let return_members = return_members.iter().map(|member| parameter(member)).collect::<Vec<_>>();
+            let _ = write!(signature, " -> ({})", return_members.join(", "));
+        }
+    }
+    let exceptions = operation.exception_specification.iter().map(entity_name).collect::<Vec<_>>();
+    match exceptions.as_slice() {
+        [] => {}
+        [exception] => {
+            let _ = write!(signature, " throws {exception}");
+        }
+        exceptions => {
+            let _ = write!(signature, " throws ({})", exceptions.join(", "));
+        }
+    }
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slicec::slice_options::SliceOptions;
+    use std::path::Path;
+
+    /// The fixture project that's documented, and the directory that the expected pages of each test are in.
+    const FIXTURE_PATH: &str = "tests/fixtures/api_docs";
+
+    /// Documents the fixture project, and compares each page against the golden file with the same name in the provided
+    /// directory (which must have exactly the same pages). If `UPDATE_GOLDEN_FILES` is set, the golden files are
+    /// re-written instead, so that any changes to the renderer can be reviewed in the diff.
+    fn assert_matches_golden_files(include_deprecated: bool, golden_directory: &str) {
+        let sources = ["canvas.slice", "fills.slice", "shapes.slice"].map(|file| format!("{FIXTURE_PATH}/{file}"));
+        let options = SliceOptions { sources: sources.to_vec(), ..SliceOptions::default() };
+        let mut state = slicec::compile_from_options(&options, |_| {}, |_| {});
+        let diagnostics = std::mem::take(&mut state.diagnostics).into_updated(&state.ast, &state.files, &options);
+        let messages = diagnostics.iter().map(|diagnostic| diagnostic.message()).collect::<Vec<_>>();
+        assert!(messages.is_empty(), "{messages:?}");
+
+        // Sections are written in the order that files are documented in, which shouldn't depend on the file system.
+        let mut files = state.files.iter().collect::<Vec<_>>();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let pages = generate_docs(&files, include_deprecated);
+
+        let golden_directory = Path::new(FIXTURE_PATH).join("golden").join(golden_directory);
+        if std::env::var_os("UPDATE_GOLDEN_FILES").is_some() {
+            let _ = std::fs::remove_dir_all(&golden_directory);
+            std::fs::create_dir_all(&golden_directory).unwrap();
+            for (name, contents) in &pages {
+                std::fs::write(golden_directory.join(name), contents).unwrap();
+            }
+        }
+
+        let mut golden_names = std::fs::read_dir(&golden_directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        golden_names.sort();
+        let mut names = pages.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, golden_names);
+
+        for (name, contents) in pages {
+            let golden = std::fs::read_to_string(golden_directory.join(&name)).unwrap();
+            // Golden files may be checked out with CRLF line terminators on Windows.
+            assert_eq!(contents, golden.replace("\r\n", "\n"), "{name} doesn't match its golden file");
+        }
+    }
+
+    #[test]
+    fn docs_match_the_golden_files() {
+        assert_matches_golden_files(false, "default");
+    }
+
+    #[test]
+    fn docs_with_deprecated_definitions_match_the_golden_files() {
+        assert_matches_golden_files(true, "deprecated");
+    }
+
+    #[test]
+    fn pages_are_named_after_their_modules() {
+        assert_eq!(page_name("Docs"), "Docs.md");
+        assert_eq!(page_name("Docs::Shapes"), "Docs.Shapes.md");
+    }
+}
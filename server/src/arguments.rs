@@ -0,0 +1,64 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::transport::Transport;
+
+/// The help text that's printed for `--help`, and along with any problems in the command-line arguments.
+pub const USAGE: &str = "\
+Usage: slice-language-server [options]
+
+Options:
+  --stdio               Communicate with the client over standard input and output (the default).
+  --listen <port>       Wait for the client to connect to the provided TCP port (on the loopback interface).
+  --connect <port>      Connect to the client on the provided TCP port (on the loopback interface).
+  --pipe <name>         Connect to the client over the provided named pipe (or unix domain socket).
+  --clientProcessId <pid>
+                        The process ID of the client. This is accepted for compatibility with clients, but ignored.
+  --version             Print the version of the server (and of the Slice compiler it was built with), and exit.
+  --help                Print this help, and exit.";
+
+/// What the server was asked to do by its command-line arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Run the language server, communicating with the client over the provided transport.
+    Serve(Transport),
+    /// Print the server's version, and exit.
+    Version,
+    /// Print the server's usage, and exit.
+    Help,
+}
+
+/// Parses the server's command-line arguments (not including the program's name). Values can be passed either as
+/// separate arguments (`--listen 5007`), or inline (`--listen=5007`). If more than one transport is selected, the last
+/// one wins.
+///
+/// Returns an error describing the problem if an argument isn't recognized, or has an invalid value.
+pub fn parse_arguments(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut transport = Transport::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+            None => (arg, None),
+        };
+        let mut value = || inline_value.clone().or_else(|| args.next()).ok_or(format!("'{flag}' requires a value"));
+        match flag.as_str() {
+            "--version" | "-V" => return Ok(Command::Version),
+            "--help" | "-h" => return Ok(Command::Help),
+            "--stdio" => transport = Transport::Stdio,
+            "--listen" => transport = Transport::Listen(parse_port(&flag, &value()?)?),
+            "--connect" => transport = Transport::Connect(parse_port(&flag, &value()?)?),
+            "--pipe" => transport = Transport::Pipe(value()?),
+            "--clientProcessId" => {
+                value()?;
+            }
+            _ => return Err(format!("unknown argument '{flag}'")),
+        }
+    }
+    Ok(Command::Serve(transport))
+}
+
+fn parse_port(flag: &str, value: &str) -> Result<u16, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{flag}' requires a port number (between 0 and 65535), but got '{value}'"))
+}
@@ -0,0 +1,270 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::InspectedFile;
+use crate::position_encoding::PositionConverter;
+use serde_json::{json, Map, Value};
+use slicec::grammar::{
+    Attributable, Class, Commentable, CustomType, Entity, Enum, Exception, Field, Identifier, Interface, Message,
+    MessageComponent, Module, NamedSymbol, Operation, Parameter, Struct, Symbol, Type, TypeAlias, TypeRef,
+    TypeRefDefinition, Types,
+};
+use slicec::slice_file::Span;
+use slicec::visitor::Visitor;
+
+/// Renders the parsed contents of a file as JSON, for the `slice/dumpAst` request.
+///
+/// The file's modules and top-level definitions are listed in the order they appear in. Each definition includes its
+/// members (like the fields of a struct), along with their spans (as LSP ranges), attributes, and doc comments. Type
+/// references are rendered as the fully-qualified names of the types they resolve to (or as written, if they couldn't
+/// be resolved).
+pub fn dump_ast(inspected: &InspectedFile) -> Value {
+    let file = inspected.file;
+    let mut visitor = AstDumper {
+        converter: PositionConverter::new(&file.raw_text, inspected.position_encoding()),
+        modules: Vec::new(),
+        definitions: Vec::new(),
+    };
+    file.visit_with(&mut visitor);
+
+    json!({
+        "path": file.relative_path,
+        "compilationMode": format!("{:?}", file.compilation_mode()),
+        "modules": visitor.modules,
+        "definitions": visitor.definitions,
+    })
+}
+
+struct AstDumper<'a> {
+    converter: PositionConverter<'a>,
+    modules: Vec<Value>,
+    definitions: Vec<Value>,
+}
+
+impl AstDumper<'_> {
+    /// Renders the properties that every entity has. The properties which are specific to each kind of entity are
+    /// added to the returned object by its caller.
+    fn entity(&self, entity: &dyn Entity) -> Map<String, Value> {
+        let mut object = Map::new();
+        object.insert("kind".to_owned(), json!(entity.kind()));
+        object.insert("name".to_owned(), json!(entity.identifier()));
+        object.insert("scopedIdentifier".to_owned(), json!(entity.parser_scoped_identifier()));
+        object.insert("range".to_owned(), self.range(entity.span()));
+        object.insert("attributes".to_owned(), self.attributes(entity));
+        object
+    }
+
+    /// Renders the properties of an entity which can have a doc comment, which is every entity except parameters
+    /// (which are documented by the `@param` tags of their operation's comment instead).
+    fn commentable(&self, commentable: &dyn Commentable) -> Map<String, Value> {
+        let mut object = self.entity(commentable);
+        object.insert("comment".to_owned(), self.comment(commentable));
+        object
+    }
+
+    fn field(&self, field: &Field) -> Value {
+        let mut object = self.commentable(field);
+        object.insert("type".to_owned(), json!(type_name(&field.data_type)));
+        object.insert("tag".to_owned(), json!(field.tag.as_ref().map(|tag| tag.value)));
+        Value::Object(object)
+    }
+
+    fn parameter(&self, parameter: &Parameter) -> Value {
+        let mut object = self.entity(parameter);
+        object.insert("type".to_owned(), json!(type_name(&parameter.data_type)));
+        object.insert("tag".to_owned(), json!(parameter.tag.as_ref().map(|tag| tag.value)));
+        object.insert("isStreamed".to_owned(), json!(parameter.is_streamed));
+        Value::Object(object)
+    }
+
+    fn operation(&self, operation: &Operation) -> Value {
+        let mut object = self.commentable(operation);
+        let parameters = operation.parameters().into_iter().map(|p| self.parameter(p)).collect::<Vec<_>>();
+        let return_members = operation.return_members().into_iter().map(|p| self.parameter(p)).collect::<Vec<_>>();
+        let exceptions = operation.exception_specification.iter().map(entity_name).collect::<Vec<_>>();
+        object.insert("parameters".to_owned(), json!(parameters));
+        object.insert("returnMembers".to_owned(), json!(return_members));
+        object.insert("throws".to_owned(), json!(exceptions));
+        object.insert("isIdempotent".to_owned(), json!(operation.is_idempotent));
+        Value::Object(object)
+    }
+
+    fn fields(&self, fields: Vec<&Field>) -> Value {
+        json!(fields.into_iter().map(|field| self.field(field)).collect::<Vec<_>>())
+    }
+
+    fn attributes(&self, attributable: &dyn Attributable) -> Value {
+        let attributes = attributable.attributes().into_iter().map(|attribute| {
+            json!({
+                "directive": attribute.kind.directive(),
+                "range": self.range(&attribute.span),
+            })
+        });
+        json!(attributes.collect::<Vec<_>>())
+    }
+
+    fn comment(&self, commentable: &dyn Commentable) -> Value {
+        let Some(comment) = commentable.comment() else {
+            return Value::Null;
+        };
+        let params = comment.params.iter().map(|tag| {
+            json!({ "name": tag.identifier.value, "message": message_text(&tag.message) })
+        });
+        let returns = comment.returns.iter().map(|tag| {
+            let name = tag.identifier.as_ref().map(|identifier| &identifier.value);
+            json!({ "name": name, "message": message_text(&tag.message) })
+        });
+        let throws = comment.throws.iter().map(|tag| {
+            let thrown_type = match tag.thrown_type() {
+                Ok(exception) => exception.parser_scoped_identifier(),
+                Err(identifier) => identifier.value.clone(),
+            };
+            json!({ "type": thrown_type, "message": message_text(&tag.message) })
+        });
+        let see = comment.see.iter().map(|tag| linked_name(tag.linked_entity()));
+        json!({
+            "overview": comment.overview.as_ref().map(message_text),
+            "params": params.collect::<Vec<_>>(),
+            "returns": returns.collect::<Vec<_>>(),
+            "throws": throws.collect::<Vec<_>>(),
+            "see": see.collect::<Vec<_>>(),
+            "range": self.range(&comment.span),
+        })
+    }
+
+    fn range(&self, span: &Span) -> Value {
+        json!(self.converter.span_to_range(span))
+    }
+}
+
+impl Visitor for AstDumper<'_> {
+    fn visit_module(&mut self, module_def: &Module) {
+        self.modules.push(json!({
+            "name": module_def.parser_scoped_identifier(),
+            "range": self.range(module_def.span()),
+            "attributes": self.attributes(module_def),
+        }));
+    }
+
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        let mut object = self.commentable(struct_def);
+        object.insert("isCompact".to_owned(), json!(struct_def.is_compact));
+        object.insert("fields".to_owned(), self.fields(struct_def.fields()));
+        self.definitions.push(Value::Object(object));
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        let mut object = self.commentable(class_def);
+        object.insert("base".to_owned(), json!(class_def.base.as_ref().map(entity_name)));
+        object.insert("compactId".to_owned(), json!(class_def.compact_id.as_ref().map(|id| id.value)));
+        object.insert("fields".to_owned(), self.fields(class_def.fields()));
+        self.definitions.push(Value::Object(object));
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        let mut object = self.commentable(exception_def);
+        object.insert("base".to_owned(), json!(exception_def.base.as_ref().map(entity_name)));
+        object.insert("fields".to_owned(), self.fields(exception_def.fields()));
+        self.definitions.push(Value::Object(object));
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        let mut object = self.commentable(interface_def);
+        let bases = interface_def.bases.iter().map(entity_name).collect::<Vec<_>>();
+        let operations = interface_def.operations().into_iter().map(|o| self.operation(o)).collect::<Vec<_>>();
+        object.insert("bases".to_owned(), json!(bases));
+        object.insert("operations".to_owned(), json!(operations));
+        self.definitions.push(Value::Object(object));
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        let mut object = self.commentable(enum_def);
+        let enumerators = enum_def.enumerators().into_iter().map(|enumerator| {
+            let mut object = self.commentable(enumerator);
+            // Enumerator values can be larger than JSON numbers can safely hold, so they're rendered as strings.
+            object.insert("value".to_owned(), json!(enumerator.value().to_string()));
+            Value::Object(object)
+        });
+        let underlying = enum_def.underlying.as_ref().map(|underlying| match &underlying.definition {
+            TypeRefDefinition::Patched(primitive) => primitive.borrow().type_string(),
+            TypeRefDefinition::Unpatched(identifier) => identifier.value.clone(),
+        });
+        object.insert("underlying".to_owned(), json!(underlying));
+        object.insert("isUnchecked".to_owned(), json!(enum_def.is_unchecked));
+        object.insert("enumerators".to_owned(), json!(enumerators.collect::<Vec<_>>()));
+        self.definitions.push(Value::Object(object));
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.definitions.push(Value::Object(self.commentable(custom_type_def)));
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        let mut object = self.commentable(type_alias_def);
+        object.insert("underlying".to_owned(), json!(type_name(&type_alias_def.underlying)));
+        self.definitions.push(Value::Object(object));
+    }
+}
+
+/// Returns the fully-qualified name of the type that a type reference resolves to, followed by a `?` if it's optional.
+pub fn type_name(type_ref: &TypeRef) -> String {
+    let name = match &type_ref.definition {
+        TypeRefDefinition::Patched(definition) => concrete_type_name(definition.borrow().concrete_type()),
+        TypeRefDefinition::Unpatched(identifier) => identifier.value.clone(),
+    };
+    match type_ref.is_optional {
+        true => format!("{name}?"),
+        false => name,
+    }
+}
+
+/// Returns the fully-qualified name of a type. Sequences and dictionaries are named after their element types.
+pub fn concrete_type_name(concrete_type: Types) -> String {
+    match concrete_type {
+        Types::Struct(struct_def) => struct_def.parser_scoped_identifier(),
+        Types::Class(class_def) => class_def.parser_scoped_identifier(),
+        Types::Enum(enum_def) => enum_def.parser_scoped_identifier(),
+        Types::CustomType(custom_type_def) => custom_type_def.parser_scoped_identifier(),
+        Types::ResultType(result_type) => format!(
+            "Result<{}, {}>",
+            type_name(&result_type.success_type),
+            type_name(&result_type.failure_type),
+        ),
+        Types::Sequence(sequence) => format!("sequence<{}>", type_name(&sequence.element_type)),
+        Types::Dictionary(dictionary) => format!(
+            "dictionary<{}, {}>",
+            type_name(&dictionary.key_type),
+            type_name(&dictionary.value_type),
+        ),
+        Types::Primitive(primitive) => primitive.type_string(),
+    }
+}
+
+/// Returns the fully-qualified name of the entity that a type reference (like the base of a class) resolves to.
+fn entity_name<T: NamedSymbol>(type_ref: &TypeRef<T>) -> String {
+    match &type_ref.definition {
+        TypeRefDefinition::Patched(definition) => definition.borrow().parser_scoped_identifier(),
+        TypeRefDefinition::Unpatched(identifier) => identifier.value.clone(),
+    }
+}
+
+/// Returns the fully-qualified name of the entity that a doc comment links to, or the link as written if it couldn't
+/// be resolved.
+fn linked_name(linked_entity: Result<&dyn Entity, &Identifier>) -> String {
+    match linked_entity {
+        Ok(entity) => entity.parser_scoped_identifier(),
+        Err(identifier) => identifier.value.clone(),
+    }
+}
+
+/// Returns the text of a doc comment's message. Links are rendered like they're written (as `{@link Name}`), but with
+/// the fully-qualified name of the entity they link to.
+fn message_text(message: &Message) -> String {
+    message
+        .value
+        .iter()
+        .map(|component| match component {
+            MessageComponent::Text(text) => text.clone(),
+            MessageComponent::Link(tag) => format!("{{@link {}}}", linked_name(tag.linked_entity())),
+        })
+        .collect()
+}
@@ -0,0 +1,167 @@
+// Copyright (c) ZeroC, Inc.
+
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use tower_lsp::jsonrpc::{Error, Id, Request, Response};
+use tower_service::Service;
+use tracing::debug;
+
+tokio::task_local! {
+    /// The cancellation token of the request that the current task is handling.
+    static CURRENT_TOKEN: CancellationToken;
+}
+
+/// Tells a request's handler that the client has cancelled the request (with `$/cancelRequest`).
+///
+/// `tower-lsp` handles `$/cancelRequest` itself, by dropping the handler of the cancelled request (which stops it at
+/// its next `.await`), and replying with a `RequestCancelled` error. Work that the handler moved onto a blocking thread
+/// (like compiling a set for `slice/dumpAst`) keeps running though, since nothing is awaiting it anymore. Such work
+/// checks the request's token between its steps instead, and stops once it's cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Returns the token of the request that the current task is handling. Tasks which aren't handling a request (or
+    /// are handling one that can't be cancelled, like a notification) get a token which is never cancelled.
+    pub fn current() -> Self {
+        CURRENT_TOKEN.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns a `RequestCancelled` error if the request was cancelled, so that work can be stopped with `?`.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::request_cancelled()),
+            false => Ok(()),
+        }
+    }
+
+    /// Like [`Iterator::find_map`], but checks the token before each item, and stops with a `RequestCancelled` error
+    /// once the request was cancelled. This is for handlers which search every configuration set without awaiting in
+    /// between, since `tower-lsp` can only stop a handler when it awaits.
+    pub fn find_map<I: IntoIterator, T>(
+        &self,
+        items: I,
+        mut f: impl FnMut(I::Item) -> Option<T>,
+    ) -> Result<Option<T>, Error> {
+        for item in items {
+            self.check()?;
+            if let Some(found) = f(item) {
+                return Ok(Some(found));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The cancellation tokens of the requests which are being handled, keyed by the IDs of the requests.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationRegistry {
+    tokens: Arc<Mutex<HashMap<Id, CancellationToken>>>,
+}
+
+impl CancellationRegistry {
+    /// Creates a token for the request with the provided ID. The token is unregistered once the request is handled.
+    fn register(&self, id: Id) -> CancellationToken {
+        let token = CancellationToken::default();
+        let mut tokens = self.tokens.lock().unwrap_or_else(PoisonError::into_inner);
+        tokens.insert(id, token.clone());
+        token
+    }
+
+    fn unregister(&self, id: &Id) {
+        self.tokens.lock().unwrap_or_else(PoisonError::into_inner).remove(id);
+    }
+
+    /// Cancels the token of the request with the provided ID, if that request is still being handled.
+    pub fn cancel(&self, id: &Id) {
+        match self.tokens.lock().unwrap_or_else(PoisonError::into_inner).get(id) {
+            Some(token) => token.cancel(),
+            None => debug!("The client cancelled request {id}, which was already handled"),
+        }
+    }
+}
+
+/// Wraps the server's service, so that each request is handled with a [`CancellationToken`], which is cancelled when
+/// the client sends `$/cancelRequest` for it. Everything is still passed on to the wrapped service, so that `tower-lsp`
+/// handles `$/cancelRequest` as usual too.
+pub struct CancellableService<S> {
+    inner: S,
+    registry: CancellationRegistry,
+}
+
+impl<S> CancellableService<S> {
+    pub fn new(inner: S) -> Self {
+        CancellableService { inner, registry: CancellationRegistry::default() }
+    }
+}
+
+impl<S> Service<Request> for CancellableService<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.method() == "$/cancelRequest" {
+            let id = request.params().and_then(|params| params.get("id")).cloned();
+            if let Some(id) = id.and_then(|id| serde_json::from_value::<Id>(id).ok()) {
+                self.registry.cancel(&id);
+            }
+        }
+
+        let Some(id) = request.id().cloned() else {
+            return Box::pin(self.inner.call(request));
+        };
+        let token = self.registry.register(id.clone());
+        let registry = self.registry.clone();
+        let response = CURRENT_TOKEN.scope(token, self.inner.call(request));
+        Box::pin(async move {
+            let response = response.await;
+            registry.unregister(&id);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_stop_once_theyre_cancelled() {
+        let token = CancellationToken::default();
+        let mut searched = Vec::new();
+        let found = token.find_map([1, 2, 3], |item| {
+            searched.push(item);
+            token.cancel();
+            None::<i32>
+        });
+        assert_eq!(found.unwrap_err().code, Error::request_cancelled().code);
+        assert_eq!(searched, [1]);
+
+        // Searches which aren't cancelled find the first match, like `Iterator::find_map`.
+        let token = CancellationToken::default();
+        let found = token.find_map([1, 2, 3], |item| (item > 1).then_some(item * 10));
+        assert_eq!(found.unwrap(), Some(20));
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::features::Feature;
+use std::collections::HashSet;
+use tower_lsp::lsp_types::{ClientCapabilities, ClientInfo, DiagnosticTag, InitializeParams, MarkupKind};
+
+/// The parts of the client's capabilities (and of its information) which affect how the server behaves.
+///
+/// These are parsed once, when the client initializes the server, so that handlers can check what the client supports
+/// without digging through the raw capabilities (most of which are optional, and nested a few levels deep).
+#[derive(Debug, Default)]
+pub struct ClientCapabilitiesSummary {
+    /// The name (and version) of the client, if it provided them.
+    pub client_info: Option<ClientInfo>,
+    /// Set if the client can render hover messages written in markdown, instead of only plain text.
+    pub supports_markdown_hover: bool,
+    /// Set if the client accepts `LocationLink`s in response to go-to-definition requests, instead of only `Location`s.
+    pub supports_definition_links: bool,
+    /// Set if the client can render diagnostics tagged as unnecessary (like unused definitions) by fading them out.
+    pub supports_unnecessary_tag: bool,
+    /// Set if the client supports pulling the server's settings with `workspace/configuration`.
+    pub supports_configuration_pull: bool,
+    /// Set if the client can show the progress of long-running work, like compiling every configuration set.
+    pub supports_work_done_progress: bool,
+    /// Set if the client pulls diagnostics from the server (and supports being asked to re-pull them), in which case the
+    /// server doesn't publish diagnostics itself.
+    pub supports_pull_diagnostics: bool,
+    /// Set if the client supports registering file watchers with `client/registerCapability`.
+    pub supports_file_watcher_registration: bool,
+    /// Set if the client supports file watchers with patterns that are relative to a base path.
+    pub supports_relative_file_watchers: bool,
+    /// The features which the client supports registering with `client/registerCapability`. These aren't advertised at
+    /// initialization, so that they can be registered and unregistered as the user turns them on and off.
+    pub dynamic_registration_features: HashSet<Feature>,
+}
+
+impl ClientCapabilitiesSummary {
+    /// Summarizes the capabilities (and information) that the client sent when initializing the server.
+    pub fn new(params: &InitializeParams) -> Self {
+        let capabilities = &params.capabilities;
+        let text_document = capabilities.text_document.as_ref();
+        let workspace = capabilities.workspace.as_ref();
+
+        let supports_markdown_hover = text_document
+            .and_then(|text_document| text_document.hover.as_ref())
+            .and_then(|hover| hover.content_format.as_ref())
+            .is_some_and(|formats| formats.contains(&MarkupKind::Markdown));
+        let supports_definition_links = text_document
+            .and_then(|text_document| text_document.definition.as_ref())
+            .and_then(|definition| definition.link_support)
+            .unwrap_or(false);
+        let supports_unnecessary_tag = text_document
+            .and_then(|text_document| text_document.publish_diagnostics.as_ref())
+            .and_then(|diagnostics| diagnostics.tag_support.as_ref())
+            .is_some_and(|tag_support| tag_support.value_set.contains(&DiagnosticTag::UNNECESSARY));
+
+        // Only pull diagnostics if the client supports both pulling them, and being told when to re-pull them.
+        let supports_pull = text_document.is_some_and(|text_document| text_document.diagnostic.is_some());
+        let supports_refresh = workspace
+            .and_then(|workspace| workspace.diagnostic.as_ref())
+            .and_then(|diagnostic| diagnostic.refresh_support)
+            .unwrap_or(false);
+
+        let watched_files = workspace.and_then(|workspace| workspace.did_change_watched_files.as_ref());
+
+        ClientCapabilitiesSummary {
+            client_info: params.client_info.clone(),
+            supports_markdown_hover,
+            supports_definition_links,
+            supports_unnecessary_tag,
+            supports_configuration_pull: workspace.and_then(|workspace| workspace.configuration).unwrap_or(false),
+            supports_work_done_progress: capabilities
+                .window
+                .as_ref()
+                .and_then(|window| window.work_done_progress)
+                .unwrap_or(false),
+            supports_pull_diagnostics: supports_pull && supports_refresh,
+            supports_file_watcher_registration: watched_files
+                .and_then(|watched_files| watched_files.dynamic_registration)
+                .unwrap_or(false),
+            supports_relative_file_watchers: watched_files
+                .and_then(|watched_files| watched_files.relative_pattern_support)
+                .unwrap_or(false),
+            dynamic_registration_features: dynamic_registration_features(capabilities),
+        }
+    }
+
+    /// Describes the client by its name and version, for logging.
+    pub fn client_description(&self) -> String {
+        match &self.client_info {
+            Some(ClientInfo { name, version: Some(version) }) => format!("{name} {version}"),
+            Some(ClientInfo { name, version: None }) => name.clone(),
+            None => "an unknown client".to_owned(),
+        }
+    }
+}
+
+/// Returns the features which the client supports registering dynamically.
+fn dynamic_registration_features(capabilities: &ClientCapabilities) -> HashSet<Feature> {
+    let text_document = capabilities.text_document.as_ref();
+    Feature::ALL
+        .into_iter()
+        .filter(|feature| {
+            let dynamic_registration = match feature {
+                Feature::Hover => text_document
+                    .and_then(|text_document| text_document.hover.as_ref())
+                    .and_then(|hover| hover.dynamic_registration),
+                Feature::Definition => text_document
+                    .and_then(|text_document| text_document.definition.as_ref())
+                    .and_then(|definition| definition.dynamic_registration),
+                Feature::References => text_document
+                    .and_then(|text_document| text_document.references.as_ref())
+                    .and_then(|references| references.dynamic_registration),
+            };
+            dynamic_registration.unwrap_or(false)
+        })
+        .collect()
+}
@@ -0,0 +1,127 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::dependency_graph::Granularity;
+use serde_json::Value;
+use tower_lsp::jsonrpc::Error;
+use std::path::PathBuf;
+use tower_lsp::lsp_types::{Position, Url};
+
+/// The commands which the server handles with `workspace/executeCommand`.
+///
+/// These are advertised to the client when initializing, which makes them available to the client's users (VS Code
+/// registers each of them as a command, which the extension adds to the command palette).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServerCommand {
+    /// Re-compiles the configuration sets that contain a document, and re-publishes their diagnostics, even if nothing
+    /// seems to have changed. This is for when the user suspects that the server is out of sync with their files.
+    Compile,
+    /// Re-compiles every configuration set from scratch, and reports how long each of them took to compile. This is for
+    /// measuring the server's performance on the user's workspace.
+    CompileAll,
+    /// Reports which configuration sets contain a document, and why. This is for when the user can't tell why a file
+    /// is (or isn't) getting diagnostics.
+    ShowFileConfiguration,
+    /// Writes every diagnostic to a SARIF file, at the path that's passed as the command's first argument. This is for
+    /// uploading the diagnostics to code scanning dashboards.
+    ExportSarif,
+    /// Writes an LSIF index of every configuration set to the path that's passed as the command's first argument. This
+    /// is for code navigation tools, which can navigate Slice files with the index, without running the server.
+    ExportIndex,
+    /// Writes the dependencies between the files (or modules) of each configuration set to the path that's passed as
+    /// the command's first argument, as a DOT or JSON graph. This is for planning refactors, or splitting up sets.
+    ExportDependencyGraph,
+    /// Reports how many files, modules, and definitions each configuration set has, along with the totals across every
+    /// set. This is for auditing how an API grows, and for checking that the server sees the files the user expects.
+    WorkspaceStats,
+    /// Writes markdown documentation of a configuration set (or of a single document) to the directory that's passed
+    /// as the command's first argument. This is for publishing the API that the user's Slice files define.
+    GenerateDocs,
+    /// Returns the Slice type ID of the type at a position of a document (like `::Foo::Bar`), along with its compact
+    /// ID if it's a class that has one. This is for copying the exact type ID, instead of reconstructing it by hand.
+    CopyTypeId,
+    /// Creates a Slice file in the directory that's passed as the command's first argument, with a header, a `mode`
+    /// statement, and a `module` declaration which matches the directory's place in its configuration set.
+    NewFile,
+    /// Lists the built-in Slice files that are bundled with the extension (or configured by the user), so the client
+    /// can open them. This is for seeing what the built-in files define, without digging through the extension's files.
+    OpenBuiltInSliceFiles,
+}
+
+impl ServerCommand {
+    pub const ALL: [ServerCommand; 11] = [
+        ServerCommand::Compile,
+        ServerCommand::CompileAll,
+        ServerCommand::ShowFileConfiguration,
+        ServerCommand::ExportSarif,
+        ServerCommand::ExportIndex,
+        ServerCommand::ExportDependencyGraph,
+        ServerCommand::WorkspaceStats,
+        ServerCommand::GenerateDocs,
+        ServerCommand::CopyTypeId,
+        ServerCommand::NewFile,
+        ServerCommand::OpenBuiltInSliceFiles,
+    ];
+
+    /// Returns the name that this command is advertised (and executed) with.
+    pub fn name(self) -> &'static str {
+        match self {
+            ServerCommand::Compile => "slice.compile",
+            ServerCommand::CompileAll => "slice.compileAll",
+            ServerCommand::ShowFileConfiguration => "slice.showFileConfiguration",
+            ServerCommand::ExportSarif => "slice.exportSarif",
+            ServerCommand::ExportIndex => "slice.exportIndex",
+            ServerCommand::ExportDependencyGraph => "slice.exportDependencyGraph",
+            ServerCommand::WorkspaceStats => "slice.workspaceStats",
+            ServerCommand::GenerateDocs => "slice.generateDocs",
+            ServerCommand::CopyTypeId => "slice.copyTypeId",
+            ServerCommand::NewFile => "slice.newFile",
+            ServerCommand::OpenBuiltInSliceFiles => "slice.openBuiltInSliceFiles",
+        }
+    }
+
+    /// Returns the command with the provided name, if the server has one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.name() == name)
+    }
+}
+
+/// Returns the URI of the document that a command was executed on, which is passed as the command's first argument.
+pub fn document_argument(command: ServerCommand, arguments: &[Value]) -> Result<Url, Error> {
+    arguments
+        .first()
+        .and_then(Value::as_str)
+        .and_then(|uri| Url::parse(uri).ok())
+        .ok_or_else(|| Error::invalid_params(format!("'{}' requires the URI of a document", command.name())))
+}
+
+/// Returns the position in a document that a command was executed at, which is passed as the command's second argument
+/// (as an LSP `Position`).
+pub fn position_argument(command: ServerCommand, arguments: &[Value]) -> Result<Position, Error> {
+    arguments
+        .get(1)
+        .and_then(|position| serde_json::from_value(position.clone()).ok())
+        .ok_or_else(|| Error::invalid_params(format!("'{}' requires a position in the document", command.name())))
+}
+
+/// Returns the path that a command writes its output to, which is passed as the command's first argument, either as an
+/// absolute path, or as a file URI.
+pub fn path_argument(command: ServerCommand, arguments: &[Value]) -> Result<PathBuf, Error> {
+    let argument = arguments.first().and_then(Value::as_str);
+    let path = match argument.and_then(|argument| Url::parse(argument).ok()) {
+        Some(uri) => uri.to_file_path().ok(),
+        None => argument.map(PathBuf::from),
+    };
+    path.filter(|path| path.is_absolute())
+        .ok_or_else(|| Error::invalid_params(format!("'{}' requires an absolute path to write to", command.name())))
+}
+
+/// Returns what the nodes of a dependency graph stand for, which is passed as the command's second argument (either
+/// `file` or `module`). Graphs are of files if it's omitted.
+pub fn granularity_argument(command: ServerCommand, arguments: &[Value]) -> Result<Granularity, Error> {
+    match arguments.get(1) {
+        None | Some(Value::Null) => Ok(Granularity::File),
+        Some(argument) => argument.as_str().and_then(Granularity::from_name).ok_or_else(|| {
+            Error::invalid_params(format!("the granularity of '{}' must be 'file' or 'module'", command.name()))
+        }),
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::{FinishedCompilation, PendingCompilation};
+use std::sync::{Mutex, PoisonError};
+use tracing::info;
+
+/// Runs each of the provided compilations, returning their results (in the same order that they were provided in).
+///
+/// Sets are compiled in parallel, but at most `max_concurrent_compilations` sets are compiled at once, so that compiling
+/// many sets doesn't starve the rest of the machine (like the editor itself). The remaining sets wait for their turn.
+/// If no limit is provided, it defaults to one less than the number of cores.
+///
+/// `on_finished` is called with the name of each set once it has been compiled (on the thread that compiled it).
+///
+/// This blocks until every set has been compiled, so it shouldn't be called from an async task directly. Nothing in
+/// here needs the server state, so it must not be locked while this runs.
+pub fn compile_sets(
+    pending_compilations: Vec<PendingCompilation>,
+    max_concurrent_compilations: Option<usize>,
+    on_finished: impl Fn(&str) + Sync,
+) -> Vec<FinishedCompilation> {
+    let compile = |pending_compilation: PendingCompilation| {
+        info!("Compiling configuration set '{}'", pending_compilation.set_name);
+        let finished_compilation = pending_compilation.run();
+        on_finished(&finished_compilation.set_name);
+        finished_compilation
+    };
+
+    let max_concurrent_compilations = max_concurrent_compilations
+        .unwrap_or_else(default_max_concurrent_compilations)
+        .clamp(1, pending_compilations.len().max(1));
+
+    // There's no point in spawning threads if the sets would be compiled one at a time anyways.
+    if max_concurrent_compilations == 1 {
+        return pending_compilations.into_iter().map(compile).collect();
+    }
+
+    // Each thread takes the next set that's waiting to be compiled, until there aren't any left.
+    let pending_compilations = Mutex::new(pending_compilations.into_iter().enumerate());
+    let finished_compilations = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..max_concurrent_compilations {
+            scope.spawn(|| loop {
+                let next = pending_compilations.lock().unwrap_or_else(PoisonError::into_inner).next();
+                let Some((index, pending_compilation)) = next else {
+                    break;
+                };
+                let finished_compilation = compile(pending_compilation);
+                let mut finished_compilations = finished_compilations.lock().unwrap_or_else(PoisonError::into_inner);
+                finished_compilations.push((index, finished_compilation));
+            });
+        }
+    });
+
+    let mut finished_compilations = finished_compilations.into_inner().unwrap_or_else(PoisonError::into_inner);
+    finished_compilations.sort_by_key(|(index, _)| *index);
+    finished_compilations
+        .into_iter()
+        .map(|(_, finished_compilation)| finished_compilation)
+        .collect()
+}
+
+/// Returns how many sets can be compiled at once if the user didn't specify a limit: one less than the number of cores
+/// (leaving a core for the editor), but at least one.
+fn default_max_concurrent_compilations() -> usize {
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    cores.saturating_sub(1).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation_gate::CompilationGate;
+    use crate::configuration::{ServerConfig, SliceConfig};
+    use crate::configuration_set::ConfigurationSet;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    /// Returns the compilations of sets with the provided names, which each compile the `relative` fixture.
+    fn pending_compilations(set_names: &[&str]) -> Vec<PendingCompilation> {
+        let server_config = ServerConfig::default();
+        let fixture_path = std::env::current_dir().unwrap().join("tests/fixtures/relative");
+        let slice_config = SliceConfig {
+            slice_search_paths: vec![fixture_path],
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+        set_names
+            .iter()
+            .map(|name| {
+                let mut set = ConfigurationSet::default();
+                set.name = name.to_string();
+                set.slice_config = slice_config.clone();
+                set.prepare_compilation(&server_config, &HashMap::new())
+            })
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sets_over_the_limit_wait_for_a_compilation_to_finish() {
+        let set_names = ["queued (first)", "queued (second)", "queued (third)"];
+        let [first_gate, second_gate, third_gate] = set_names.map(CompilationGate::close);
+        let pending_compilations = pending_compilations(&set_names);
+        let compilations = tokio::task::spawn_blocking(|| compile_sets(pending_compilations, Some(2), |_| {}));
+
+        // The gates hold the first two sets, like slow compilations would, so the third one has to wait for them.
+        first_gate.wait_for_compilations(1).await;
+        second_gate.wait_for_compilations(1).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(third_gate.compilation_count(), 0);
+
+        second_gate.open();
+        third_gate.wait_for_compilations(1).await;
+        first_gate.open();
+        third_gate.open();
+        let finished_compilations = compilations.await.unwrap();
+        let finished_names = finished_compilations.iter().map(|compilation| compilation.set_name.as_str());
+        assert!(finished_names.eq(set_names));
+    }
+}
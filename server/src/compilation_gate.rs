@@ -0,0 +1,77 @@
+// Copyright (c) ZeroC, Inc.
+
+//! Lets tests hold the compilations of a configuration set until they're released, so they can observe what the server
+//! does while a set is being compiled (like handling a hover, or shutting down).
+//!
+//! Gates are keyed by the name of the set they hold, so tests which run in parallel (with differently named sets) don't
+//! hold up each other's compilations.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+
+/// The gates which are currently closed, along with the name of the set that each of them holds.
+static CLOSED_GATES: Mutex<Vec<(String, Arc<GateState>)>> = Mutex::new(Vec::new());
+
+#[derive(Default)]
+struct GateState {
+    /// Whether the gate was opened, and how many compilations have reached it.
+    state: Mutex<(bool, usize)>,
+    opened: Condvar,
+}
+
+/// A closed gate, which holds every compilation of a set until it's opened (or dropped).
+pub struct CompilationGate {
+    state: Arc<GateState>,
+}
+
+impl CompilationGate {
+    /// Closes the gate of the set with the provided name.
+    pub fn close(set_name: &str) -> Self {
+        let state = Arc::new(GateState::default());
+        let mut closed_gates = CLOSED_GATES.lock().unwrap_or_else(PoisonError::into_inner);
+        closed_gates.push((set_name.to_owned(), state.clone()));
+        CompilationGate { state }
+    }
+
+    /// Waits until the provided number of compilations have reached the gate (and are being held by it).
+    pub async fn wait_for_compilations(&self, count: usize) {
+        while self.compilation_count() < count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// Returns how many compilations have reached the gate so far.
+    pub fn compilation_count(&self) -> usize {
+        self.state.state.lock().unwrap_or_else(PoisonError::into_inner).1
+    }
+
+    /// Opens the gate, which lets the compilations it's holding (and any later ones) run.
+    pub fn open(self) {}
+}
+
+impl Drop for CompilationGate {
+    fn drop(&mut self) {
+        let mut closed_gates = CLOSED_GATES.lock().unwrap_or_else(PoisonError::into_inner);
+        closed_gates.retain(|(_, state)| !Arc::ptr_eq(state, &self.state));
+        self.state.state.lock().unwrap_or_else(PoisonError::into_inner).0 = true;
+        self.state.opened.notify_all();
+    }
+}
+
+/// Blocks the calling compilation until the gate of the set it compiles is opened, if it's closed.
+pub fn pass(set_name: &str) {
+    let state = {
+        let closed_gates = CLOSED_GATES.lock().unwrap_or_else(PoisonError::into_inner);
+        let gate = closed_gates.iter().find(|(name, _)| name == set_name);
+        match gate {
+            Some((_, state)) => state.clone(),
+            None => return,
+        }
+    };
+
+    let mut guard = state.state.lock().unwrap_or_else(PoisonError::into_inner);
+    guard.1 += 1;
+    while !guard.0 {
+        guard = state.opened.wait(guard).unwrap_or_else(PoisonError::into_inner);
+    }
+}
@@ -1,64 +1,653 @@
 // Copyright (c) ZeroC, Inc.
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::features::Feature;
+use crate::position_encoding::PositionEncoding;
+use crate::slice_ignore::SliceIgnore;
+use crate::utils::{find_slice_files, is_virtual_document_path, normalize_path, PathKey};
+use crate::utils::{path_starts_with, paths_match, resolve_symlinks, PathResolver};
 use slicec::slice_options::SliceOptions;
+use tracing::{debug, trace};
 
 /// This struct holds configuration that affects the entire server.
 #[derive(Debug, Default)]
 pub struct ServerConfig {
-    /// This is the root path of the workspace, used to resolve relative paths. It must be an absolute path.
-    pub workspace_root_path: PathBuf,
+    /// The root path of each folder in the workspace, used to resolve relative paths. These must be absolute paths.
+    pub workspace_root_paths: Vec<PathBuf>,
     /// This is the path to the built-in Slice files that are included with the extension. It must be an absolute path.
-    pub built_in_slice_path: String,
+    /// Clients other than the extension might not provide it, in which case no set includes the built-in files.
+    pub built_in_slice_path: Option<String>,
+    /// Specifies which definitions should be reported if they're never referenced.
+    pub unused_definitions: UnusedDefinitionsMode,
+    /// Specifies whether comments containing one of the `todo_comment_markers` should be reported.
+    pub todo_comments: bool,
+    /// The markers (`TODO`, `FIXME`, etc.) that cause a comment to be reported, when `todo_comments` is enabled.
+    pub todo_comment_markers: Vec<String>,
+    /// Specifies which events cause the configuration sets containing a document to be re-compiled.
+    pub compilation_trigger: CompilationTrigger,
+    /// Specifies whether configuration sets are only compiled once one of their files is opened, instead of up front.
+    pub lazy_compilation: bool,
+    /// Set if workspace folders without any configuration sets shouldn't get a default set that compiles the entire
+    /// folder (the user turned off 'slice.compileWorkspaceByDefault'). Opened files are compiled on their own instead.
+    pub skip_default_sets: bool,
+    /// Specifies whether workspace folders without any configuration sets get sets which are discovered from their
+    /// layout, instead of a single default set that compiles the entire folder.
+    pub auto_discover_configurations: bool,
+    /// The maximum number of configuration sets that are compiled at once. If this isn't set, then it defaults to one
+    /// less than the number of cores.
+    pub max_concurrent_compilations: Option<usize>,
+    /// Files larger than this many kilobytes are still compiled, but are excluded from the per-request features (like
+    /// hover), since these would be too slow on them. If this isn't set, then files of any size are fully supported.
+    pub max_file_size_kb: Option<u64>,
+    /// Configuration sets which haven't been used for this long (and don't have any open documents) drop most of their
+    /// compilation data, and are re-compiled once they're needed again. If this isn't set, sets are never evicted.
+    pub idle_set_timeout: Option<Duration>,
+    /// The paths which are ignored by every configuration set, loaded from each workspace folder's `.sliceignore` file.
+    pub slice_ignore: SliceIgnore,
+    /// Directories (or glob patterns) of Slice files which are referenced by every configuration set, after the set's own
+    /// paths. Relative paths are resolved against every workspace folder.
+    pub referenced_directories: Vec<PathBuf>,
+    /// The language features which the user turned off. Every other feature is enabled.
+    pub disabled_features: HashSet<Feature>,
+    /// The header that the `slice.newFile` command writes at the top of the files it creates (like a copyright notice).
+    pub new_file_header: String,
+    /// The level of the events which the server logs.
+    pub log_level: LogLevel,
+    /// The file that the server's logs are also written to, if any.
+    pub log_file: Option<PathBuf>,
+    /// How the characters of a line are counted in positions. This is negotiated with the client during initialization.
+    pub position_encoding: PositionEncoding,
+    /// Converts between the URIs that the client knows files by and the paths that the server tracks them under. The
+    /// workspace folders and open documents which the client reached through a symlink are registered with it.
+    pub paths: PathResolver,
+}
+
+/// Specifies which definitions the server reports (as faded-out hints) if they're never referenced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnusedDefinitionsMode {
+    /// Unused definitions aren't reported.
+    #[default]
+    Off,
+    /// Unused definitions are reported, except for interfaces, since these are typically entry points.
+    ExcludeInterfaces,
+    /// All unused definitions are reported, including interfaces.
+    All,
+}
+
+/// Specifies which events cause the server to re-compile the configuration sets containing a document.
+/// Opening or saving a document always triggers a compilation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompilationTrigger {
+    /// Compile whenever the document is edited (after a short delay), so that diagnostics update as the user types.
+    #[default]
+    OnType,
+    /// Only compile when the document is saved.
+    OnSave,
+}
+
+/// Specifies where a configuration set's built-in Slice files are placed among the paths passed to the compiler.
+/// Since `slicec` parses files in the order that they're provided, this decides which file a redefinition is reported in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuiltInSlicePosition {
+    /// Place the built-in files before the set's own files, so that any redefinition conflicts appear in the user's
+    /// files, and not in the built-in files.
+    #[default]
+    First,
+    /// Place the built-in files after the set's own files, so that the user's own definitions take precedence, and any
+    /// redefinition conflicts appear in the built-in files instead.
+    Last,
+}
+
+/// Specifies how verbose the server's logs are. Each level includes the events of the levels before it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
 }
 
 /// This struct holds the configuration for a single compilation set.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SliceConfig {
+    /// The root path of the workspace folder whose settings this set came from, if any.
+    /// Relative paths are resolved against this folder, or against every workspace folder if this isn't set.
+    pub folder_path: Option<PathBuf>,
     /// List of paths that will be passed to the compiler as reference files/directories.
     pub slice_search_paths: Vec<PathBuf>,
+    /// List of paths (or glob patterns) that are passed to the compiler alongside the search paths, but which the user
+    /// doesn't own. Their definitions can be used, but only their errors are reported, and not their warnings.
+    pub reference_paths: Vec<PathBuf>,
+    /// List of paths (or glob patterns) that are excluded from the search paths. Excludes take precedence over paths.
+    pub exclude_paths: Vec<PathBuf>,
+    /// A path to the built-in Slice files that this set uses instead of the ones bundled with the extension, if any.
+    /// Relative paths are resolved like the set's other paths.
+    pub built_in_slice_path: Option<PathBuf>,
     /// Specifies whether to include the built-in Slice files that are bundled with the extension.
     pub include_built_in_slice_files: bool,
+    /// Specifies whether the built-in Slice files are passed to the compiler before or after the set's own files.
+    pub built_in_slice_position: BuiltInSlicePosition,
+    /// The preprocessor symbols which are defined when compiling this set, for use in `#if` directives.
+    pub defined_symbols: Vec<String>,
+    /// The lints which are allowed when compiling this set, and hence aren't reported.
+    pub allowed_lints: Vec<String>,
 }
 
 impl Default for SliceConfig {
     fn default() -> Self {
         SliceConfig {
+            folder_path: None,
             slice_search_paths: vec![],
+            reference_paths: vec![],
+            exclude_paths: vec![],
+            built_in_slice_path: None,
             include_built_in_slice_files: true,
+            built_in_slice_position: BuiltInSlicePosition::default(),
+            defined_symbols: vec![],
+            allowed_lints: vec![],
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Returns the server-wide settings that affect the diagnostics of every configuration set.
+    /// If any of these change, every set must be re-compiled.
+    pub fn compilation_settings(
+        &self,
+    ) -> (Option<String>, Vec<PathBuf>, UnusedDefinitionsMode, bool, Vec<String>, Option<u64>) {
+        (
+            self.built_in_slice_path.clone(),
+            self.referenced_directories.clone(),
+            self.unused_definitions,
+            self.todo_comments,
+            self.todo_comment_markers.clone(),
+            self.max_file_size_kb,
+        )
+    }
+}
+
+impl SliceConfig {
+    /// Returns the root paths that this set's relative paths are resolved against: the root of the folder that this set
+    /// came from, or the root of every workspace folder if the set is global.
+    pub fn root_paths<'a>(&'a self, server_config: &'a ServerConfig) -> Vec<&'a Path> {
+        match &self.folder_path {
+            Some(folder_path) => vec![folder_path.as_path()],
+            None => server_config.workspace_root_paths.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
+    /// Returns the path to the built-in Slice files that this set uses: the set's own path if it has one, or the path
+    /// to the files that are bundled with the extension otherwise. If the set's own path doesn't exist, then it falls
+    /// back to the bundled files (see `ConfigurationSet::built_in_slice_path_problem`).
+    pub fn built_in_slice_path(&self, server_config: &ServerConfig) -> Option<String> {
+        let own_path = self.built_in_slice_path.as_ref().and_then(|path| {
+            let root_paths = self.root_paths(server_config);
+            resolve_path_in_roots(&root_paths, path).into_iter().find(|resolved| resolved.is_dir())
+        });
+        match own_path {
+            Some(own_path) => Some(own_path.display().to_string()),
+            None => server_config.built_in_slice_path.clone(),
         }
     }
+
+    /// Returns this set's reference paths, resolved into the absolute paths that they're compiled from.
+    pub fn resolved_reference_paths(&self, server_config: &ServerConfig) -> Vec<PathBuf> {
+        let root_paths = self.root_paths(server_config);
+        self.reference_paths
+            .iter()
+            .flat_map(|path| resolve_search_path(&root_paths, path))
+            .map(|(path, _)| path)
+            .collect()
+    }
 }
 
 pub fn compute_slice_options(server_config: &ServerConfig, set_config: &SliceConfig) -> SliceOptions {
-    let root_path = &server_config.workspace_root_path;
-    let mut slice_options = SliceOptions::default();
+    let root_paths = set_config.root_paths(server_config);
+    let mut slice_options = SliceOptions {
+        defined_symbols: set_config.defined_symbols.clone(),
+        allowed_lints: set_config.allowed_lints.clone(),
+        ..SliceOptions::default()
+    };
     let references = &mut slice_options.references;
 
     // Add the built-in Slice files (WellKnownTypes, etc.) at the start of the list, if they should be included.
     // Putting them first ensures that any redefinition conflicts will appear in the user's files, and not these.
-    // (Since `slicec` parses files in the order that they are provided).
-    if set_config.include_built_in_slice_files {
-        references.push(server_config.built_in_slice_path.clone());
+    // (Since `slicec` parses files in the order that they are provided). Sets can put them last instead (see below).
+    let built_in_slice_path = match set_config.include_built_in_slice_files {
+        true => set_config.built_in_slice_path(server_config),
+        false => None,
+    };
+    if set_config.built_in_slice_position == BuiltInSlicePosition::First {
+        references.extend(built_in_slice_path.clone());
     }
 
+    // Each search path is stored alongside the path that `.sliceignore` rules are only checked beneath. For paths that
+    // were explicitly listed by the user, this is the path itself, since explicitly listed paths win over the ignore
+    // file. Otherwise, it's the root path the search path was resolved against.
+    let mut search_paths: Vec<(PathBuf, PathBuf)> = Vec::new();
     match set_config.slice_search_paths.as_slice() {
-        // If the user didn't specify any paths, default to using the workspace root.
-        [] => references.push(root_path.display().to_string()),
+        // If the user didn't specify any paths, default to using the root of each folder.
+        [] => {
+            for root_path in &root_paths {
+                search_paths.push((root_path.to_path_buf(), root_path.to_path_buf()));
+            }
+        }
 
-        // Otherwise, add in the user-specified search paths.
+        // Otherwise, add in the user-specified search paths, expanding any glob patterns into the paths they match.
         user_paths => {
             for path in user_paths {
-                // If the path is absolute, add it as-is. Otherwise, preface it with the workspace root.
-                let absolute_path = match path.is_absolute() {
-                    true => path.to_owned(),
-                    false => root_path.join(path),
-                };
-                references.push(absolute_path.display().to_string());
+                search_paths.extend(resolve_search_path(&root_paths, path));
+            }
+        }
+    }
+
+    // Add the set's reference paths, which are compiled just like its search paths (see `store_diagnostics_for_set`).
+    for path in &set_config.reference_paths {
+        search_paths.extend(resolve_search_path(&root_paths, path));
+    }
+
+    // Add the directories which are referenced by every set. These are workspace-wide, so they're resolved against every
+    // workspace folder, and like the set's own paths, they're skipped if they don't exist.
+    let workspace_root_paths = server_config.workspace_root_paths.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+    for path in &server_config.referenced_directories {
+        if is_glob_pattern(path) {
+            for root_path in &workspace_root_paths {
+                let matched_paths = expand_glob_pattern(root_path, path).into_iter().filter(|matched| matched.is_dir());
+                search_paths.extend(matched_paths.map(|matched| (matched, root_path.to_path_buf())));
+            }
+        } else {
+            let resolved_paths = resolve_path_in_roots(&workspace_root_paths, path);
+            let existing_paths = resolved_paths.into_iter().filter(|resolved| resolved.is_dir());
+            search_paths.extend(existing_paths.map(|resolved| (resolved.clone(), resolved)));
+        }
+    }
+
+    // The same path can be reached from multiple folders (by absolute paths, or patterns), or be spelled in different
+    // ways, but is only compiled once. Likewise, paths inside of another included directory are already compiled with
+    // that directory, so they're skipped, instead of being compiled twice (which causes redefinition errors).
+    let mut seen_paths = HashSet::new();
+    search_paths.retain(|(path, _)| seen_paths.insert(PathKey::new(path)));
+    let included_directories = search_paths
+        .iter()
+        .filter(|(path, _)| path.is_dir())
+        .map(|(path, _)| PathKey::new(path))
+        .collect::<Vec<_>>();
+    search_paths.retain(|(path, _)| {
+        let key = PathKey::new(path);
+        let is_nested = included_directories
+            .iter()
+            .any(|directory| key != *directory && key.starts_with(directory));
+        if is_nested {
+            debug!("Skipping '{}', since it's inside of another path that's already included", path.display());
+        }
+        !is_nested
+    });
+
+    // Remove any excluded or ignored paths. Since these can be inside of a search path, we have to expand directories
+    // into the files they contain, so that files can be removed individually.
+    let slice_ignore = &server_config.slice_ignore;
+    if !set_config.exclude_paths.is_empty() || !slice_ignore.is_empty() {
+        let excludes = ExcludeMatcher::new(&root_paths, &set_config.exclude_paths);
+        let mut files = Vec::new();
+        for (path, ignore_base_path) in search_paths {
+            let mut found_files = Vec::new();
+            match path.is_dir() {
+                true => find_slice_files(&path, &mut found_files),
+                false => found_files.push(path),
             }
+            files.extend(found_files.into_iter().filter(|file| {
+                !excludes.is_excluded(file) && !slice_ignore.is_ignored(file, &ignore_base_path)
+            }));
         }
+        search_paths = files.into_iter().map(|file| (file.clone(), file)).collect();
     }
 
+    let search_paths = search_paths.into_iter().map(|(path, _)| path.display().to_string());
+    references.extend(search_paths);
+    if set_config.built_in_slice_position == BuiltInSlicePosition::Last {
+        references.extend(built_in_slice_path);
+    }
+
+    debug!("Computed {} reference(s) for the compiler", references.len());
+    trace!("References: {references:?}");
     slice_options
 }
+
+/// Resolves a user-specified search path against each of the provided root paths, expanding glob patterns into the
+/// paths they match. Each resulting path is returned alongside the path that `.sliceignore` rules are checked beneath.
+pub fn resolve_search_path(root_paths: &[&Path], path: &Path) -> Vec<(PathBuf, PathBuf)> {
+    if is_glob_pattern(path) {
+        let mut search_paths = Vec::new();
+        for root_path in root_paths {
+            let matched_paths = expand_glob_pattern(root_path, path);
+            search_paths.extend(matched_paths.into_iter().map(|matched| (matched, root_path.to_path_buf())));
+        }
+        search_paths
+    } else {
+        // Paths that don't exist are skipped, instead of causing confusing errors in the compiler. The user is warned
+        // about them separately (see `ConfigurationSet::missing_search_paths`).
+        // Likewise for files which aren't Slice files (see `ConfigurationSet::non_slice_file_paths`).
+        // Documents which aren't files never exist on disk, but are compiled from their text, so they're always kept.
+        resolve_path_in_roots(root_paths, path)
+            .into_iter()
+            .filter(|resolved| {
+                let is_slice_file = resolved.is_file() && is_slice_file(resolved);
+                resolved.is_dir() || is_slice_file || is_virtual_document_path(resolved)
+            })
+            .map(|resolved| (resolved.clone(), resolved))
+            .collect()
+    }
+}
+
+/// Checks whether paths are excluded by a configuration set's `exclude` entries.
+struct ExcludeMatcher {
+    literal_paths: Vec<PathBuf>,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl ExcludeMatcher {
+    fn new(root_paths: &[&Path], exclude_paths: &[PathBuf]) -> Self {
+        let mut literal_paths = Vec::new();
+        let mut patterns = Vec::new();
+        for path in exclude_paths {
+            for root_path in root_paths {
+                let Some(absolute_path) = resolve_path(root_path, path) else {
+                    continue;
+                };
+                if is_glob_pattern(path) {
+                    // Invalid patterns can't match anything, so we ignore them.
+                    patterns.extend(glob::Pattern::new(&absolute_path.display().to_string()));
+                } else {
+                    literal_paths.push(absolute_path);
+                }
+            }
+        }
+        ExcludeMatcher { literal_paths, patterns }
+    }
+
+    /// Returns true if the provided path is excluded, either directly, or because one of its ancestors is excluded.
+    fn is_excluded(&self, path: &Path) -> bool {
+        path.ancestors().any(|ancestor| {
+            self.literal_paths.iter().any(|excluded_path| paths_match(excluded_path, ancestor))
+                || self.patterns.iter().any(|pattern| pattern.matches_path(ancestor))
+        })
+    }
+}
+
+/// Returns true if the provided file is covered by a reference that's passed to the compiler.
+/// References to directories cover every file inside of them, but references to files only cover those exact files.
+pub fn reference_covers_file(reference: &Path, file_path: &Path) -> bool {
+    match reference.is_dir() {
+        true => path_starts_with(file_path, reference),
+        false => paths_match(reference, file_path),
+    }
+}
+
+/// Returns the paths that are covered by both of the provided lists of paths. Paths are covered by both lists if they
+/// appear in both, or if they're in one list, and are nested inside of a path in the other list.
+pub fn find_overlapping_paths(first_paths: &[PathBuf], second_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut overlapping_paths = Vec::new();
+    for first_path in first_paths {
+        for second_path in second_paths {
+            if first_path.starts_with(second_path) {
+                overlapping_paths.push(first_path.clone());
+            } else if second_path.starts_with(first_path) {
+                overlapping_paths.push(second_path.clone());
+            }
+        }
+    }
+    overlapping_paths.sort();
+    overlapping_paths.dedup();
+    overlapping_paths
+}
+
+/// Returns true if the provided path has the `.slice` extension.
+pub fn is_slice_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "slice")
+}
+
+/// Resolves a user-specified path into an absolute one, after expanding any variables in it.
+/// If the path is absolute, it is returned as-is. Otherwise, it's prefaced with the provided root path.
+///
+/// Returns `None` if the path uses an environment variable that isn't set, since the path can't be resolved.
+pub fn resolve_path(root_path: &Path, path: &Path) -> Option<PathBuf> {
+    let expanded_path = match expand_path_variables(path, root_path) {
+        Ok(expanded_path) => expanded_path,
+        Err(variable) => {
+            debug!("Can't resolve '{}', since '{variable}' isn't set", path.display());
+            return None;
+        }
+    };
+    // Users can spell the same path in many ways (like `slice`, `slice/` and `./other/../slice`), so we normalize the
+    // resolved paths, so that they can be compared with each other. Symlinks are resolved too, since `slicec` reports
+    // files under the paths it was given, and those must match the (canonical) paths that the client's files map to.
+    let resolved_path = match expanded_path.is_absolute() {
+        true => resolve_symlinks(&normalize_path(&expanded_path)),
+        false => resolve_symlinks(&normalize_path(&root_path.join(expanded_path))),
+    };
+    trace!("Resolved '{}' to '{}'", path.display(), resolved_path.display());
+    Some(resolved_path)
+}
+
+/// Resolves a user-specified path against each of the provided root paths, returning the resolved paths that exist.
+///
+/// A relative path in a global configuration set usually only exists in some of the workspace's folders. If it doesn't
+/// exist in any of them, it's resolved against the first root path, so that it can be reported as missing.
+/// Paths which use an environment variable that isn't set can't be resolved, so nothing is returned for them.
+pub fn resolve_path_in_roots(root_paths: &[&Path], path: &Path) -> Vec<PathBuf> {
+    // Absolute paths can still be resolved when there aren't any root paths.
+    let root_paths = match root_paths {
+        [] => &[Path::new("")],
+        root_paths => root_paths,
+    };
+
+    let mut resolved_paths = root_paths
+        .iter()
+        .filter_map(|root_path| resolve_path(root_path, path))
+        .collect::<Vec<_>>();
+    match resolved_paths.iter().any(|resolved_path| resolved_path.exists()) {
+        true => resolved_paths.retain(|resolved_path| resolved_path.exists()),
+        false => resolved_paths.truncate(1),
+    }
+    resolved_paths
+}
+
+/// Expands the variables in a user-specified path:
+/// - `${env:NAME}` is replaced with the value of the `NAME` environment variable.
+/// - `${workspaceFolder}` is replaced with the provided root path.
+/// - A leading `~` is replaced with the user's home directory.
+///
+/// Any other `${...}` sequences are left as-is. If an environment variable isn't set, its name is returned as an error.
+pub fn expand_path_variables(path: &Path, root_path: &Path) -> Result<PathBuf, String> {
+    let Some(mut rest) = path.to_str() else {
+        return Ok(path.to_owned());
+    };
+    let mut expanded = String::new();
+
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        let home_variable = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        let home_path = std::env::var(home_variable).map_err(|_| home_variable.to_owned())?;
+        expanded.push_str(&home_path);
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find("${") {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        let variable = &rest[start + 2..start + length];
+        expanded.push_str(&rest[..start]);
+
+        if variable == "workspaceFolder" {
+            expanded.push_str(&root_path.display().to_string());
+        } else if let Some(name) = variable.strip_prefix("env:") {
+            let value = std::env::var(name).map_err(|_| name.to_owned())?;
+            expanded.push_str(&value);
+        } else {
+            expanded.push_str(&rest[start..=start + length]);
+        }
+        rest = &rest[start + length + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(PathBuf::from(expanded))
+}
+
+/// Returns the name of the first environment variable used by a user-specified path which isn't set, if there is one.
+pub fn find_unset_variable(path: &Path) -> Option<String> {
+    expand_path_variables(path, Path::new("")).err()
+}
+
+/// Returns true if the provided user-specified path is a glob pattern (like `services/*/slice`), instead of a path.
+pub fn is_glob_pattern(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| path.contains(['*', '?', '[']))
+}
+
+/// Expands a user-specified glob pattern into the (absolute) paths it currently matches.
+/// Relative patterns are resolved against the provided root path. Only directories and Slice files are returned, and an
+/// invalid pattern (or one using an environment variable that isn't set) doesn't match anything.
+pub fn expand_glob_pattern(root_path: &Path, pattern: &Path) -> Vec<PathBuf> {
+    let Some(pattern) = resolve_path(root_path, pattern) else {
+        return Vec::new();
+    };
+    let Ok(paths) = glob::glob(&pattern.display().to_string()) else {
+        return Vec::new();
+    };
+
+    paths
+        .filter_map(Result::ok)
+        .filter(|path| path.is_dir() || is_slice_file(path))
+        .map(|path| resolve_symlinks(&path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The directory that the test sets' relative paths are resolved against, which is used as the workspace's root.
+    fn workspace_path() -> PathBuf {
+        std::env::current_dir().unwrap().join("tests/fixtures")
+    }
+
+    /// Returns the options of a set which compiles the `type_ids` fixture, and references the provided paths.
+    fn slice_options(references: &[&str]) -> (ServerConfig, SliceConfig, SliceOptions) {
+        let server_config = ServerConfig { workspace_root_paths: vec![workspace_path()], ..ServerConfig::default() };
+        let set_config = SliceConfig {
+            slice_search_paths: vec!["type_ids".into()],
+            reference_paths: references.iter().map(PathBuf::from).collect(),
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+        let slice_options = compute_slice_options(&server_config, &set_config);
+        (server_config, set_config, slice_options)
+    }
+
+    fn absolute(path: &str) -> String {
+        workspace_path().join(path).display().to_string()
+    }
+
+    #[test]
+    fn invalid_references_are_skipped_and_valid_ones_are_kept() {
+        let references = ["api_docs", "missing-folder", "relative/greeter.slice", "missing.slice", "relative/x.ice"];
+        let (server_config, set_config, slice_options) = slice_options(&references);
+
+        let expected = ["type_ids", "api_docs", "relative/greeter.slice"].map(absolute);
+        assert_eq!(slice_options.references, expected);
+        let expected = ["api_docs", "relative/greeter.slice"].map(|path| workspace_path().join(path));
+        assert_eq!(set_config.resolved_reference_paths(&server_config), expected);
+    }
+
+    #[test]
+    fn sets_whose_references_are_all_invalid_only_compile_their_search_paths() {
+        // The workspace's root isn't compiled instead of the references (which would include every file in it).
+        let (_, _, slice_options) = slice_options(&["missing-folder", "missing.slice"]);
+        assert_eq!(slice_options.references, [absolute("type_ids")]);
+    }
+
+    #[test]
+    fn references_inside_of_search_paths_are_only_compiled_once() {
+        let (_, _, slice_options) = slice_options(&["type_ids/inner.slice", "missing-folder", "type_ids"]);
+        assert_eq!(slice_options.references, [absolute("type_ids")]);
+    }
+
+    #[test]
+    fn aliased_spellings_of_a_path_are_only_referenced_once() {
+        let server_config = ServerConfig { workspace_root_paths: vec![workspace_path()], ..ServerConfig::default() };
+        let absolute_path = workspace_path().join("type_ids").display().to_string();
+        let spellings = ["type_ids", "type_ids/", "./type_ids", "relative/../type_ids", "./type_ids/.", &absolute_path];
+        let set_config = SliceConfig {
+            slice_search_paths: spellings.iter().map(PathBuf::from).collect(),
+            reference_paths: vec!["api_docs/../type_ids/".into()],
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+
+        let slice_options = compute_slice_options(&server_config, &set_config);
+        assert_eq!(slice_options.references, [absolute("type_ids")]);
+    }
+
+    #[test]
+    fn built_in_files_are_referenced_first_or_last_depending_on_their_position() {
+        let built_in_slice_path = workspace_path().join("api_docs").display().to_string();
+        let server_config = ServerConfig {
+            workspace_root_paths: vec![workspace_path()],
+            built_in_slice_path: Some(built_in_slice_path.clone()),
+            referenced_directories: vec!["relative".into()],
+            ..ServerConfig::default()
+        };
+        let set_config = |built_in_slice_position| SliceConfig {
+            slice_search_paths: vec!["type_ids".into()],
+            built_in_slice_position,
+            ..SliceConfig::default()
+        };
+
+        let slice_options = compute_slice_options(&server_config, &set_config(BuiltInSlicePosition::First));
+        assert_eq!(slice_options.references, [built_in_slice_path.clone(), absolute("type_ids"), absolute("relative")]);
+        let slice_options = compute_slice_options(&server_config, &set_config(BuiltInSlicePosition::Last));
+        assert_eq!(slice_options.references, [absolute("type_ids"), absolute("relative"), built_in_slice_path]);
+    }
+
+    #[test]
+    fn referenced_directories_are_added_after_each_sets_own_paths() {
+        let server_config = ServerConfig {
+            workspace_root_paths: vec![workspace_path()],
+            referenced_directories: vec!["api_docs".into(), "missing-folder".into()],
+            ..ServerConfig::default()
+        };
+        let set_config = |paths: &[&str], references: &[&str]| SliceConfig {
+            slice_search_paths: paths.iter().map(PathBuf::from).collect(),
+            reference_paths: references.iter().map(PathBuf::from).collect(),
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+
+        let slice_options = compute_slice_options(&server_config, &set_config(&["type_ids"], &["relative"]));
+        assert_eq!(slice_options.references, ["type_ids", "relative", "api_docs"].map(absolute));
+        let slice_options = compute_slice_options(&server_config, &set_config(&["relative/greeter.slice"], &[]));
+        assert_eq!(slice_options.references, ["relative/greeter.slice", "api_docs"].map(absolute));
+    }
+
+    #[test]
+    fn referenced_directories_which_a_set_already_compiles_are_only_compiled_once() {
+        let server_config = ServerConfig {
+            workspace_root_paths: vec![workspace_path()],
+            referenced_directories: vec!["type_ids".into(), "relative".into(), workspace_path().join("relative")],
+            ..ServerConfig::default()
+        };
+        let set_config = SliceConfig {
+            slice_search_paths: vec!["type_ids".into(), "relative/sender.slice".into()],
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+
+        // The set's file is inside a referenced directory, so it's compiled with the directory, instead of on its own.
+        let slice_options = compute_slice_options(&server_config, &set_config);
+        assert_eq!(slice_options.references, ["type_ids", "relative"].map(absolute));
+    }
+}
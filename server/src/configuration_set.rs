@@ -1,81 +1,1395 @@
 // Copyright (c) ZeroC, Inc.
 
-use crate::configuration::{compute_slice_options, ServerConfig, SliceConfig};
-use crate::utils::sanitize_path;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use crate::configuration::{compute_slice_options, expand_glob_pattern, is_glob_pattern, resolve_path};
+use crate::configuration::{find_unset_variable, is_slice_file, reference_covers_file, resolve_path_in_roots};
+use crate::configuration::resolve_search_path;
+use crate::configuration::{BuiltInSlicePosition, ServerConfig, SliceConfig, UnusedDefinitionsMode};
+use crate::file_index::FileIndex;
+use crate::overlay::Overlay;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::reference_index::{ReferenceIndex, ReferenceKind};
+use crate::server_state::UnsavedDocument;
+use crate::unused_definitions::find_unused_definitions;
+use crate::utils::{find_slice_files, is_virtual_document_path, path_starts_with, paths_match, sanitize_path};
+use crate::utils::PathResolver;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use slicec::slice_options::SliceOptions;
-use slicec::{ast::Ast, diagnostics::Diagnostic, slice_file::SliceFile};
+use slicec::diagnostics::{Diagnostic, DiagnosticLevel};
 use slicec::compilation_state::CompilationState;
+use slicec::slice_file::{SliceFile, Span};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tower_lsp::lsp_types::{DiagnosticSeverity, Range, Url};
+use tracing::{debug, warn};
 
+/// Used to give each compilation its own epoch. Epochs start at 1, since an epoch of 0 means a set was never compiled.
+static NEXT_COMPILATION_EPOCH: AtomicU64 = AtomicU64::new(1);
+
+/// The results of a configuration set's compilation.
+///
+/// The set's `Ast` isn't kept, since it can't be shared between threads. Instead, each file is indexed right after it's
+/// compiled, so that this owns all of its data (see [`FileIndex`]).
 #[derive(Debug, Default)]
 pub struct CompilationData {
-    pub ast: Ast,
-    pub files: HashMap<PathBuf, SliceFile>,
+    pub files: HashMap<PathBuf, FileIndex>,
+    /// Maps the paths of any temporary files that unsaved documents were compiled from to the documents' paths.
+    pub original_paths: HashMap<String, PathBuf>,
+    /// The paths of the files which are larger than 'slice.maxFileSizeKB'. These still report diagnostics, but the
+    /// per-request features (like hover) skip them, since these would be too slow on files of this size.
+    pub degraded_files: HashSet<PathBuf>,
+    /// Every reference in the set's files, keyed by the entity they refer to.
+    pub references: ReferenceIndex,
+    /// The definitions which are never referenced. These are found while indexing the set, since this needs its `Ast`.
+    pub unused_definitions: Vec<(Url, tower_lsp::lsp_types::Diagnostic)>,
+    /// The paths of the files which had errors reported in them during compilation.
+    pub files_with_errors: HashSet<PathBuf>,
+    /// The resolver that the paths of the compiled files were converted with, which spans are converted with too.
+    pub paths: PathResolver,
 }
 
-// Necessary for using `CompilationData` within async functions.
-//
-// # Safety
-//
-// These implementations are safe because `CompilationData` is entirely self-contained and hence can go between threads.
-// Note that `files` is not self-contained on its own, since `SliceFile` references definitions owned by the `Ast`.
-unsafe impl Send for CompilationData {}
-unsafe impl Sync for CompilationData {}
+impl CompilationData {
+    /// Converts a span of one of the compiled files into a range. The text of the span's file is needed to count its
+    /// columns in the provided encoding, so spans in files that aren't indexed (or were evicted) are treated as ASCII.
+    pub fn span_to_range(&self, span: &Span, encoding: PositionEncoding) -> Range {
+        let text = self.file(&self.source_path(&span.file)).map_or("", |file| file.raw_text.as_str());
+        PositionConverter::new(text, encoding).span_to_range(span)
+    }
+
+    /// Returns the path of the file that a span refers to (by its `file` field).
+    ///
+    /// This is usually just the span's file, but unsaved documents are compiled from temporary files, so spans in
+    /// those documents refer to the temporary files instead, and must be mapped back to the documents.
+    pub fn source_path(&self, span_file: &str) -> PathBuf {
+        match self.original_paths.get(span_file) {
+            Some(original_path) => original_path.clone(),
+            None => self.paths.compiled_file_path(span_file),
+        }
+    }
+
+    /// Returns the index of the compiled file at the provided path, if it was compiled. Paths that the client sends can
+    /// be spelled with a different casing than the path the file was compiled from, which is the same file on Windows,
+    /// so if there isn't an exact match, the file is looked up case-insensitively there (see [`paths_match`]).
+    pub fn file(&self, path: &Path) -> Option<&FileIndex> {
+        self.files.get(path).or_else(|| match cfg!(windows) {
+            true => self.files.iter().find(|(file_path, _)| paths_match(file_path, path)).map(|(_, file)| file),
+            false => None,
+        })
+    }
+
+    /// Returns whether the file at the provided path is too large for the per-request features to support it.
+    pub fn is_degraded(&self, path: &Path) -> bool {
+        self.degraded_files.contains(path)
+            || (cfg!(windows) && self.degraded_files.iter().any(|file_path| paths_match(file_path, path)))
+    }
+
+    /// Takes the navigation data (what hover and definitions are served from) of any files that had errors in this
+    /// compilation from the provided previous compilation, if it has data for them.
+    ///
+    /// A file with errors (like a half-typed definition) usually loses some of its definitions, which makes navigation
+    /// in it (and in the files referencing it) collapse until it compiles cleanly again. Instead, navigation in these
+    /// files is served from the last compilation that had data for them. Everything else, like the diagnostics, always
+    /// reflects the latest compilation.
+    pub fn keep_navigation_from(&mut self, previous: &mut CompilationData) {
+        for path in &self.files_with_errors {
+            if self.degraded_files.contains(path) {
+                continue;
+            }
+            let (Some(file), Some(previous_file)) = (self.files.get_mut(path), previous.files.get_mut(path)) else {
+                continue;
+            };
+            debug!("'{}' has errors, so navigation in it uses its previous compilation", path.display());
+            file.hover_entries = std::mem::take(&mut previous_file.hover_entries);
+            file.definition_links = std::mem::take(&mut previous_file.definition_links);
+        }
+    }
+
+    /// Returns roughly how many bytes of memory this data holds. This only counts the data itself (its text, and the
+    /// elements of its collections), and not the spare capacity of any allocations, so it's an underestimate.
+    pub fn estimated_size(&self) -> usize {
+        let files = self.files.iter().map(|(path, file)| path.as_os_str().len() + file.estimated_size());
+        let original_paths = self.original_paths.iter().map(|(key, path)| key.len() + path.as_os_str().len());
+        let paths = self.degraded_files.iter().chain(&self.files_with_errors).map(|path| path.as_os_str().len());
+        let unused_definitions = self.unused_definitions.iter().map(|(uri, diagnostic)| {
+            size_of::<(Url, tower_lsp::lsp_types::Diagnostic)>() + uri.as_str().len() + diagnostic.message.len()
+        });
+        files.chain(original_paths).chain(paths).chain(unused_definitions).sum::<usize>()
+            + self.references.estimated_size()
+    }
+
+    /// Drops everything except for the definitions of each file (and the hashes of their contents), which is all that
+    /// the checks spanning multiple sets need. The set must be re-compiled before it can serve any other requests.
+    fn evict(&mut self) {
+        for file in self.files.values_mut() {
+            file.raw_text = String::new();
+            file.hover_entries = Vec::new();
+            file.definition_links = Vec::new();
+        }
+        self.references = ReferenceIndex::default();
+        self.unused_definitions = Vec::new();
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct ConfigurationSet {
+    /// The name used to refer to this set in messages. Sets which weren't given a name get one based on their position.
+    pub name: String,
     pub slice_config: SliceConfig,
     pub compilation_data: CompilationData,
+    /// The diagnostics reported by this set's most recent compilation (and the server's own analyses), grouped by file.
+    pub diagnostics: HashMap<Url, Vec<tower_lsp::lsp_types::Diagnostic>>,
+
+    /// The resolved search paths of this set, which determine which files it contains. These are resolved whenever the
+    /// configuration is applied (see [`Self::update_membership`]), instead of each time a file changes.
+    resolved_references: Vec<PathBuf>,
+
+    /// The options computed for this set's previous compilation, along with the inputs they were computed from.
+    cached_slice_options: Option<(SliceOptionsInputs, Arc<SliceOptions>)>,
+
+    /// Updated each time this set is compiled, so that each compilation can be told apart from the ones before it.
+    compilation_epoch: u64,
+    /// The epoch of the newest compilation whose diagnostics have been published.
+    published_epoch: u64,
+    /// A hash of the inputs of this set's most recently installed compilation (its options and its files' contents).
+    /// If none of these have changed since, compiling the set again would produce the same results, so it's skipped.
+    installed_fingerprint: Option<u64>,
+    /// The stamps of the files that this set's fingerprint was last computed from, so that the files which haven't
+    /// changed since aren't read again to compute its next one (see [`FileStamp`]).
+    file_stamps: Arc<HashMap<PathBuf, FileStamp>>,
+
+    /// Set if this set wasn't configured by the user, and was instead created because a folder had no sets.
+    is_default: bool,
+    /// Set if this set holds a single file, which is compiled on its own because there isn't a workspace.
+    is_stand_alone: bool,
+    /// Set if this set wasn't configured by the user, and was instead discovered from the workspace's layout.
+    is_discovered: bool,
+    /// Set if the user turned this set off (with `"disabled": true`), in which case it's kept aside and never compiled.
+    is_disabled: bool,
+    /// When this set last served a request, or was compiled. Requests only hold a read guard on the server state, so
+    /// this is behind its own lock.
+    last_used: Mutex<Option<Instant>>,
+    /// Set if this set's compilation data was evicted because it wasn't used for a while (see [`Self::evict`]).
+    is_evicted: bool,
+    /// How long this set's most recently installed compilation took, if it has been compiled.
+    last_compilation_time: Option<Duration>,
+}
+
+/// The configuration that a set's `SliceOptions` are computed from. The cached options of a set are only re-used if
+/// these haven't changed, so that any change to the set's (or the server's) configuration is picked up.
+#[derive(Clone, Debug, PartialEq)]
+struct SliceOptionsInputs {
+    slice_config: SliceConfig,
+    workspace_root_paths: Vec<PathBuf>,
+    built_in_slice_path: Option<String>,
+    referenced_directories: Vec<PathBuf>,
+}
 
-    cached_slice_options: Option<SliceOptions>,
+impl SliceOptionsInputs {
+    fn new(server_config: &ServerConfig, slice_config: &SliceConfig) -> Self {
+        SliceOptionsInputs {
+            slice_config: slice_config.clone(),
+            workspace_root_paths: server_config.workspace_root_paths.clone(),
+            built_in_slice_path: server_config.built_in_slice_path.clone(),
+            referenced_directories: server_config.referenced_directories.clone(),
+        }
+    }
 }
 
+/// The keys that a configuration set can have. Any other keys are reported as problems.
+const CONFIGURATION_SET_KEYS: [&str; 11] = [
+    "name",
+    "disabled",
+    "rootDir",
+    "paths",
+    "references",
+    "exclude",
+    "defines",
+    "addWellKnownTypes",
+    "builtInSlicePath",
+    "builtInSlicePosition",
+    "extraCompilerOptions",
+];
+
+/// The `slicec` options which take a value, either in the same argument or in the next one.
+const COMPILER_OPTIONS_WITH_VALUES: [&str; 9] = [
+    "-D", "--define", "-A", "--allow", "-R", "--reference", "-O", "--output-dir", "--diagnostic-format",
+];
+
+/// The `slicec` options which only affect how the compiler is run or what it outputs, and hence don't apply to the
+/// language server. References are specified with a set's `paths` instead.
+const UNSUPPORTED_COMPILER_OPTIONS: [&str; 7] =
+    ["-R", "--reference", "-O", "--output-dir", "--dry-run", "--disable-color", "--diagnostic-format"];
+
 impl ConfigurationSet {
     /// Parses a vector of `ConfigurationSet` from a JSON array.
-    pub fn parse_configuration_sets(config_array: &[serde_json::Value]) -> Vec<Self> {
-        config_array
-            .iter()
-            .map(ConfigurationSet::from_json)
-            .collect::<Vec<_>>()
+    /// If the sets came from a workspace folder's settings, `folder_path` is the root path of that folder.
+    ///
+    /// Each set is validated while parsing, and any problems with them are returned alongside the sets. Sets with
+    /// problems are still loaded (as far as they can be), except for entries which aren't objects at all.
+    pub fn parse_configuration_sets(
+        config_array: &[serde_json::Value],
+        folder_path: Option<&Path>,
+    ) -> (Vec<Self>, Vec<String>) {
+        let mut configuration_sets = Vec::new();
+        let mut problems = Vec::new();
+        for (index, value) in config_array.iter().enumerate() {
+            let set = ConfigurationSet::from_json(value, index, folder_path);
+            if !value.is_object() {
+                problems.push(format!("configuration set '{}' must be an object, so it was skipped", set.name));
+                continue;
+            }
+            problems.extend(validate_configuration_set(value, &set.name));
+            debug!("Parsed configuration set '{}': {:?}", set.name, set.slice_config);
+            configuration_sets.push(set);
+        }
+        (configuration_sets, problems)
+    }
+
+    /// Constructs the configuration set that's used when the user hasn't configured any sets.
+    /// If the workspace has multiple folders, each folder gets its own default set, which is passed its root path.
+    pub fn default_set(folder_path: Option<&Path>) -> Self {
+        let name = match folder_path {
+            Some(folder_path) => format!("default configuration ({})", folder_name(folder_path)),
+            None => "default configuration".to_owned(),
+        };
+        let slice_config = SliceConfig { folder_path: folder_path.map(Path::to_owned), ..SliceConfig::default() };
+        Self { name, slice_config, is_default: true, ..Self::default() }
+    }
+
+    /// Constructs a configuration set that was discovered from the workspace's layout (see `discovery.rs`).
+    /// Its paths are relative to the folder it was discovered in, which is passed as `folder_path` in multi-root
+    /// workspaces. Its name is kept as-is, so that it can be written into the folder's settings unchanged.
+    pub fn discovered_set(name: String, paths: Vec<PathBuf>, folder_path: Option<&Path>) -> Self {
+        let slice_config = SliceConfig {
+            folder_path: folder_path.map(Path::to_owned),
+            slice_search_paths: paths,
+            ..SliceConfig::default()
+        };
+        Self { name, slice_config, is_discovered: true, ..Self::default() }
+    }
+
+    /// Constructs a configuration set that compiles a single file on its own.
+    /// These are used when there isn't a workspace, since there's nothing to resolve the user's sets against.
+    pub fn stand_alone_set(file_path: &Path) -> Self {
+        let name = match file_path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().into_owned(),
+            None => file_path.display().to_string(),
+        };
+        let slice_config = SliceConfig { slice_search_paths: vec![file_path.to_owned()], ..SliceConfig::default() };
+        Self { name, slice_config, is_stand_alone: true, ..Self::default() }
+    }
+
+    /// Returns true if this set wasn't configured by the user, and was instead created by [`Self::default_set`].
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    /// Returns true if this set was created by [`Self::discovered_set`].
+    pub fn is_discovered(&self) -> bool {
+        self.is_discovered
+    }
+
+    /// Returns the JSON value of this set, in the same layout as the entries of the 'slice.configurations' setting.
+    /// This is used to write discovered sets into the user's settings, so only their names and paths are included.
+    pub fn to_json(&self) -> serde_json::Value {
+        let paths = self.slice_config.slice_search_paths.iter();
+        serde_json::json!({
+            "name": self.name,
+            "paths": paths.map(|path| path.display().to_string()).collect::<Vec<_>>(),
+        })
     }
 
-    /// Constructs a `ConfigurationSet` from a JSON value.
-    fn from_json(value: &serde_json::Value) -> Self {
+    /// Returns a description of this set's configuration, as it was resolved by the server, for `slice/projectInfo`.
+    ///
+    /// This includes the references that the set is compiled with: if the set has been compiled, these are the ones its
+    /// most recent compilation used, otherwise they're the ones it would currently be compiled with.
+    pub fn project_info(&self, server_config: &ServerConfig) -> serde_json::Value {
+        let origin = match () {
+            _ if self.is_stand_alone => "standAlone",
+            _ if self.is_default => "default",
+            _ if self.is_discovered => "discovered",
+            _ => "settings",
+        };
+        let config = &self.slice_config;
+        let display_paths = |paths: &[PathBuf]| paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+
+        let references = match &self.cached_slice_options {
+            Some((_, slice_options)) => slice_options.references.clone(),
+            None => compute_slice_options(server_config, config).references,
+        };
+
+        // A set's own built-in path is only used if it exists, otherwise the server's path is used instead.
+        let built_in_slice_path = config.built_in_slice_path(server_config);
+        let built_in_source = match &config.built_in_slice_path {
+            Some(_) if built_in_slice_path != server_config.built_in_slice_path => "configurationSet",
+            _ => "server",
+        };
+
+        let mut files = self.compilation_data.files.keys().map(|path| path.display().to_string()).collect::<Vec<_>>();
+        files.sort();
+
+        serde_json::json!({
+            "name": self.name,
+            "origin": origin,
+            "isDisabled": self.is_disabled,
+            "isCompiled": self.is_compiled(),
+            "folder": config.folder_path.as_ref().map(|path| path.display().to_string()),
+            "paths": display_paths(&config.slice_search_paths),
+            "references": display_paths(&config.reference_paths),
+            "exclude": display_paths(&config.exclude_paths),
+            "resolvedReferences": references,
+            "builtIns": {
+                "included": config.include_built_in_slice_files,
+                "path": built_in_slice_path,
+                "source": built_in_source,
+                "position": format!("{:?}", config.built_in_slice_position),
+            },
+            "defines": config.defined_symbols,
+            "allowedLints": config.allowed_lints,
+            "files": files,
+        })
+    }
+
+    /// Returns true if this set was created by [`Self::stand_alone_set`].
+    pub fn is_stand_alone(&self) -> bool {
+        self.is_stand_alone
+    }
+
+    /// Returns true if the user turned this set off in their settings.
+    pub fn is_disabled(&self) -> bool {
+        self.is_disabled
+    }
+
+    /// Returns true if this set was created by [`Self::stand_alone_set`] for a document which isn't a file (like an
+    /// untitled document). These are compiled on their own even if there's a workspace.
+    pub fn is_virtual(&self) -> bool {
+        self.is_stand_alone && self.slice_config.slice_search_paths.iter().any(|path| is_virtual_document_path(path))
+    }
+
+    /// Returns true if this set was created by [`Self::stand_alone_set`] for the provided file.
+    pub fn is_stand_alone_set_for(&self, file_path: &Path) -> bool {
+        self.is_stand_alone && self.slice_config.slice_search_paths.iter().any(|path| path == file_path)
+    }
+
+    /// Constructs a `ConfigurationSet` from a JSON value, and its index in the array of configuration sets.
+    fn from_json(value: &serde_json::Value, index: usize, folder_path: Option<&Path>) -> Self {
+        let name = parse_name(value).unwrap_or_else(|| match folder_path {
+            Some(folder_path) => format!("configuration {} ({})", index + 1, folder_name(folder_path)),
+            None => format!("configuration {}", index + 1),
+        });
+        let extra_compiler_options = parse_extra_compiler_options(value, &name);
+        let mut defined_symbols = parse_defines(value);
+        defined_symbols.extend(extra_compiler_options.defined_symbols);
+
+        // If the set has its own root directory, its relative paths are relative to that directory. We handle this by
+        // prefixing them with the directory up front, so they're resolved against the workspace root like any others.
+        // Sets without any paths include every Slice file in their root directory.
+        let root_dir = parse_root_dir(value);
+        let in_root_dir = |path: PathBuf| match &root_dir {
+            Some(root_dir) if is_relative_to_root(&path) => root_dir.join(path),
+            _ => path,
+        };
+        let mut slice_search_paths = parse_paths(value, "paths").into_iter().map(in_root_dir).collect::<Vec<_>>();
+        if slice_search_paths.is_empty() {
+            slice_search_paths.extend(root_dir.clone());
+        }
+
         let slice_config = SliceConfig {
-            slice_search_paths: parse_paths(value),
+            folder_path: folder_path.map(Path::to_owned),
+            slice_search_paths,
+            reference_paths: parse_paths(value, "references").into_iter().map(in_root_dir).collect(),
+            exclude_paths: parse_paths(value, "exclude").into_iter().map(in_root_dir).collect(),
+            built_in_slice_path: parse_built_in_slice_path(value).map(in_root_dir),
             include_built_in_slice_files: parse_include_built_in(value),
+            built_in_slice_position: parse_built_in_slice_position(value),
+            defined_symbols,
+            allowed_lints: extra_compiler_options.allowed_lints,
+        };
+        Self { name, slice_config, is_disabled: parse_disabled(value), ..Self::default() }
+    }
+
+    /// Re-resolves the search paths which determine the files that this configuration set contains.
+    /// This must be called whenever the configuration changes, and whenever files are created or deleted (since glob
+    /// patterns and `.sliceignore` files can match them).
+    pub fn update_membership(&mut self, server_config: &ServerConfig) {
+        let references = compute_slice_options(server_config, &self.slice_config).references;
+        self.resolved_references = references.into_iter().map(PathBuf::from).collect();
+    }
+
+    /// Returns true if the provided file is covered by one of this configuration set's resolved search paths.
+    pub fn contains_file(&self, file_path: &Path) -> bool {
+        self.resolved_references
+            .iter()
+            .any(|reference| reference_covers_file(reference, file_path))
+    }
+
+    /// Returns the search path of this configuration set that the provided directory is in, which is the innermost one
+    /// if the set has nested search paths. Search paths which are single files can't hold a directory, so they're
+    /// skipped.
+    pub fn search_path_containing(&self, directory: &Path) -> Option<&Path> {
+        self.resolved_references
+            .iter()
+            .filter(|reference| reference.is_dir() && directory.starts_with(reference))
+            .max_by_key(|reference| reference.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    /// Explains why this configuration set contains the provided file, for the `slice.showFileConfiguration` command:
+    /// which of its `paths` (or `references`) entries matched the file, or that it's a set which includes its whole
+    /// root directory. Also reports whether the file is one of the set's built-in Slice files.
+    ///
+    /// Returns `None` if the set doesn't contain the file.
+    pub fn file_membership(&self, server_config: &ServerConfig, file_path: &Path) -> Option<serde_json::Value> {
+        if !self.contains_file(file_path) {
+            return None;
+        }
+
+        let config = &self.slice_config;
+        let root_paths = config.root_paths(server_config);
+        let covers_file = |path: &&PathBuf| {
+            resolve_search_path(&root_paths, path)
+                .iter()
+                .any(|(resolved_path, _)| reference_covers_file(resolved_path, file_path))
         };
-        Self { slice_config, ..Self::default() }
+        let built_in_slice_path = config.built_in_slice_path(server_config);
+        let is_built_in = config.include_built_in_slice_files
+            && built_in_slice_path.is_some_and(|path| path_starts_with(file_path, Path::new(&path)));
+
+        let (reason, matched_path) = if self.is_stand_alone {
+            ("stand-alone document".to_owned(), None)
+        } else if let Some(path) = config.slice_search_paths.iter().find(covers_file) {
+            (format!("matched by the 'paths' entry '{}'", path.display()), Some(path))
+        } else if let Some(path) = config.reference_paths.iter().find(covers_file) {
+            (format!("matched by the 'references' entry '{}'", path.display()), Some(path))
+        } else if is_built_in {
+            ("built-in Slice file".to_owned(), None)
+        } else if config.slice_search_paths.is_empty() && self.is_default {
+            ("default workspace-root set".to_owned(), None)
+        } else if config.slice_search_paths.is_empty() {
+            ("the set has no 'paths', so it includes its whole root directory".to_owned(), None)
+        } else {
+            ("matched by the 'slice.referencedDirectories' setting".to_owned(), None)
+        };
+
+        Some(serde_json::json!({
+            "name": self.name,
+            "isDisabled": self.is_disabled,
+            "reason": reason,
+            "matchedPath": matched_path.map(|path| path.display().to_string()),
+            "isBuiltIn": is_built_in,
+        }))
+    }
+
+    /// Returns true if any of the provided files are covered by this configuration set's resolved search paths, or were
+    /// part of its most recent compilation (which is the case for files that have since been deleted).
+    pub fn contains_any_file(&self, file_paths: &[PathBuf]) -> bool {
+        file_paths
+            .iter()
+            .any(|file_path| self.compilation_data.file(file_path).is_some() || self.contains_file(file_path))
     }
 
-    pub fn trigger_compilation(&mut self, server_config: &ServerConfig) -> Vec<Diagnostic> {
-        // Re-compute the `slice_options` we're going to pass into the compiler, if necessary.
-        let slice_options = self.cached_slice_options.get_or_insert_with(|| {
-            compute_slice_options(server_config, &self.slice_config)
+    /// Returns the user-specified search paths of this configuration set which don't exist on disk (in any of the
+    /// set's root paths), alongside the absolute path that each of them would be created at.
+    pub fn missing_search_paths(&self, server_config: &ServerConfig) -> Vec<(&Path, PathBuf)> {
+        self.missing_paths(&self.slice_config.slice_search_paths, server_config)
+    }
+
+    /// Returns the reference paths of this configuration set which don't exist on disk, like
+    /// [`Self::missing_search_paths`]. These are skipped too, without affecting the set's other paths.
+    pub fn missing_reference_paths(&self, server_config: &ServerConfig) -> Vec<(&Path, PathBuf)> {
+        self.missing_paths(&self.slice_config.reference_paths, server_config)
+    }
+
+    fn missing_paths<'a>(&self, paths: &'a [PathBuf], server_config: &ServerConfig) -> Vec<(&'a Path, PathBuf)> {
+        let root_paths = self.slice_config.root_paths(server_config);
+        paths
+            .iter()
+            .filter(|path| !is_glob_pattern(path))
+            .filter_map(|path| {
+                let absolute_path = resolve_path_in_roots(&root_paths, path).into_iter().next()?;
+                let is_missing = !absolute_path.exists() && !is_virtual_document_path(&absolute_path);
+                is_missing.then_some((path.as_path(), absolute_path))
+            })
+            .collect()
+    }
+
+    /// Returns the user-specified search paths of this configuration set which are files, but aren't Slice files.
+    /// These can't be compiled, so they're skipped.
+    pub fn non_slice_file_paths(&self, server_config: &ServerConfig) -> Vec<&Path> {
+        let root_paths = self.slice_config.root_paths(server_config);
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .filter(|path| !is_glob_pattern(path))
+            .filter(|path| {
+                resolve_path_in_roots(&root_paths, path)
+                    .iter()
+                    .any(|resolved_path| resolved_path.is_file() && !is_slice_file(resolved_path))
+            })
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Returns the user-specified relative paths (and references) of this configuration set which use `..` to escape
+    /// the root path that they're resolved against. This is almost always a mistake, so the user is warned about them.
+    pub fn escaping_search_paths(&self, server_config: &ServerConfig) -> Vec<&Path> {
+        let root_paths = self.slice_config.root_paths(server_config);
+        let escapes_root = |path: &Path| {
+            root_paths.iter().any(|root_path| {
+                resolve_path(root_path, path).is_some_and(|resolved_path| !resolved_path.starts_with(root_path))
+            })
+        };
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .chain(&self.slice_config.reference_paths)
+            .filter(|path| is_relative_to_root(path) && escapes_root(path))
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Returns the user-specified search paths of this configuration set which are matched by the workspace's
+    /// `.sliceignore` files. These paths are still compiled, since explicitly listed paths win over the ignore files.
+    pub fn ignored_search_paths(&self, server_config: &ServerConfig) -> Vec<&Path> {
+        let root_paths = self.slice_config.root_paths(server_config);
+        let is_ignored = |path: &Path| {
+            root_paths.iter().any(|root_path| {
+                resolve_path(root_path, path).is_some_and(|absolute_path| {
+                    absolute_path.exists() && server_config.slice_ignore.is_ignored(&absolute_path, root_path)
+                })
+            })
+        };
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .filter(|path| !is_glob_pattern(path) && is_ignored(path))
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Returns the user-specified glob patterns of this configuration set which don't match any paths (in any of the
+    /// set's root paths).
+    pub fn unmatched_glob_patterns(&self, server_config: &ServerConfig) -> Vec<&Path> {
+        let root_paths = self.slice_config.root_paths(server_config);
+        let is_unmatched = |pattern: &Path| {
+            root_paths
+                .iter()
+                .all(|root_path| expand_glob_pattern(root_path, pattern).is_empty())
+        };
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .filter(|path| is_glob_pattern(path) && is_unmatched(path))
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Returns the user-specified search paths of this configuration set which are outside of every workspace folder,
+    /// resolved into absolute paths. Only paths which exist are returned, and glob patterns aren't included.
+    ///
+    /// The client only tells the server about changes to files inside of the workspace, so these must be watched.
+    pub fn external_search_paths(&self, server_config: &ServerConfig) -> Vec<PathBuf> {
+        // Stand-alone sets only hold files which are open in the client, so their changes are already reported.
+        if self.is_stand_alone {
+            return Vec::new();
+        }
+
+        let root_paths = self.slice_config.root_paths(server_config);
+        let is_external = |path: &PathBuf| {
+            server_config
+                .workspace_root_paths
+                .iter()
+                .all(|root_path| !path.starts_with(root_path))
+        };
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .chain(&self.slice_config.reference_paths)
+            .filter(|path| !is_glob_pattern(path))
+            .flat_map(|path| resolve_path_in_roots(&root_paths, path))
+            .filter(|path| path.exists() && is_external(path))
+            .collect()
+    }
+
+    /// Returns the paths which this configuration set passes to the compiler, excluding the built-in Slice files.
+    /// Depending on the set's excludes, these can be directories, or the individual files inside of them.
+    pub fn resolved_search_paths(&self, server_config: &ServerConfig) -> Vec<PathBuf> {
+        let built_in_slice_path = self.slice_config.built_in_slice_path(server_config);
+        compute_slice_options(server_config, &self.slice_config)
+            .references
+            .into_iter()
+            .filter(|reference| Some(reference) != built_in_slice_path.as_ref())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Returns a description of the problem with this set's own path to the built-in Slice files, if it has one, and it
+    /// doesn't exist or doesn't contain any Slice files. If it doesn't exist, the bundled files are used instead.
+    pub fn built_in_slice_path_problem(&self, server_config: &ServerConfig) -> Option<String> {
+        let path = self.slice_config.built_in_slice_path.as_ref()?;
+        let root_paths = self.slice_config.root_paths(server_config);
+        let Some(resolved_path) = resolve_path_in_roots(&root_paths, path).into_iter().find(|p| p.is_dir()) else {
+            return Some(format!(
+                "The built-in Slice path '{}' of configuration set '{}' does not exist, so the bundled files are used.",
+                path.display(),
+                self.name,
+            ));
+        };
+
+        let mut slice_files = Vec::new();
+        find_slice_files(&resolved_path, &mut slice_files);
+        slice_files.is_empty().then(|| {
+            format!(
+                "The built-in Slice path '{}' of configuration set '{}' does not contain any Slice files.",
+                path.display(),
+                self.name,
+            )
+        })
+    }
+
+    /// Returns the user-specified paths (and references and excludes) of this configuration set which use an environment
+    /// variable that isn't set, alongside the name of that variable. These paths can't be resolved, so they're skipped.
+    pub fn paths_with_unset_variables(&self) -> Vec<(&Path, String)> {
+        self.slice_config
+            .slice_search_paths
+            .iter()
+            .chain(&self.slice_config.reference_paths)
+            .chain(&self.slice_config.exclude_paths)
+            .filter_map(|path| Some((path.as_path(), find_unset_variable(path)?)))
+            .collect()
+    }
+
+    /// Returns true if this set has been compiled (or took the results of a compilation from another set).
+    pub fn is_compiled(&self) -> bool {
+        self.compilation_epoch > 0
+    }
+
+    /// Returns true if this set's compilation is deferred until one of its files is opened. This is only the case when
+    /// lazy compilation is enabled, and the set hasn't been compiled yet.
+    pub fn is_deferred(&self, server_config: &ServerConfig) -> bool {
+        server_config.lazy_compilation && !self.is_compiled()
+    }
+
+    /// Moves the results of a previous set's compilation into this set, which must have the same configuration.
+    /// This lets a set that was re-created from the user's settings skip re-compiling if its configuration is the same.
+    pub fn take_compilation_results(&mut self, previous_set: ConfigurationSet) {
+        self.compilation_data = previous_set.compilation_data;
+        self.diagnostics = previous_set.diagnostics;
+        self.cached_slice_options = previous_set.cached_slice_options;
+        self.compilation_epoch = previous_set.compilation_epoch;
+        self.published_epoch = previous_set.published_epoch;
+        self.installed_fingerprint = previous_set.installed_fingerprint;
+        self.file_stamps = previous_set.file_stamps;
+        self.last_used = previous_set.last_used;
+        self.is_evicted = previous_set.is_evicted;
+        self.last_compilation_time = previous_set.last_compilation_time;
+    }
+
+    /// Returns how long this set's most recently installed compilation took, if it has been compiled.
+    pub fn last_compilation_time(&self) -> Option<Duration> {
+        self.last_compilation_time
+    }
+
+    /// Returns how many of this set's diagnostics have the provided severity.
+    pub fn count_diagnostics(&self, severity: DiagnosticSeverity) -> usize {
+        self.diagnostics
+            .values()
+            .flatten()
+            .filter(|diagnostic| diagnostic.severity == Some(severity))
+            .count()
+    }
+
+    /// Records that this set was just used, which keeps it from being evicted for a while.
+    pub fn mark_used(&self) {
+        *self.last_used.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+
+    /// Returns true if this set's compilation data was evicted, in which case it must be re-compiled before it can
+    /// serve requests again.
+    pub fn is_evicted(&self) -> bool {
+        self.is_evicted
+    }
+
+    /// Returns true if this set has compilation data that can be evicted, and it hasn't been used for the provided
+    /// duration.
+    pub fn is_idle(&self, timeout: Duration) -> bool {
+        let last_used = *self.last_used.lock().unwrap_or_else(PoisonError::into_inner);
+        self.is_compiled() && !self.is_evicted && last_used.is_some_and(|last_used| last_used.elapsed() >= timeout)
+    }
+
+    /// Evicts most of this set's compilation data, keeping only the definitions of its files (see
+    /// [`CompilationData::evict`]). The set's diagnostics are kept, and it's re-compiled the next time it's needed.
+    pub fn evict(&mut self) {
+        debug!("Evicting the compilation data of configuration set '{}', since it hasn't been used lately", self.name);
+        self.compilation_data.evict();
+        self.is_evicted = true;
+        // Forget the fingerprint of the last compilation, since compiling the same inputs again is no longer a no-op.
+        self.installed_fingerprint = None;
+    }
+
+    /// Records that the diagnostics of the compilation with the provided epoch are about to be published.
+    ///
+    /// Returns `false` if the diagnostics of a newer compilation have already been published, in which case the
+    /// provided compilation's diagnostics are stale, and must be dropped instead of published.
+    fn try_mark_published(&mut self, epoch: u64) -> bool {
+        if epoch < self.published_epoch {
+            return false;
+        }
+        self.published_epoch = epoch;
+        true
+    }
+
+    /// Clears the cached `SliceOptions` of this set, so they're re-computed for the next compilation.
+    /// Changes to the configuration are detected automatically, so this is only needed when the files on disk change.
+    pub fn invalidate_cached_slice_options(&mut self) {
+        self.cached_slice_options = None;
+    }
+
+    /// Clears everything that this set caches between compilations (its options, and the fingerprint of its previous
+    /// compilation), so that its next compilation runs from scratch, even if nothing seems to have changed.
+    pub fn invalidate(&mut self) {
+        self.cached_slice_options = None;
+        self.installed_fingerprint = None;
+        self.file_stamps = Arc::default();
+    }
+
+    /// Takes a snapshot of everything that's needed to compile this configuration set, so that the compilation itself
+    /// can run without access to the set (and the server state it's stored in). See [`PendingCompilation::run`].
+    /// Any unsaved documents in the set are compiled from their unsaved text, instead of their files' contents.
+    ///
+    /// If neither the set's options nor the contents of its files have changed since its installed compilation, the
+    /// compilation is skipped once it runs, since it would produce the same results (see [`PendingCompilation::run`]).
+    /// For example, this is the case when a document is saved after it was already compiled while being edited.
+    pub fn prepare_compilation(
+        &mut self,
+        server_config: &ServerConfig,
+        unsaved_documents: &HashMap<PathBuf, UnsavedDocument>,
+    ) -> PendingCompilation {
+        let (inputs, slice_options) = self.current_slice_options(server_config);
+
+        // The compilation can only be skipped if the installed compilation is the most recently started one. Otherwise,
+        // the results of a newer compilation (of different inputs) are about to replace it, and must be replaced again.
+        let installed_fingerprint = match self.published_epoch == self.compilation_epoch {
+            true => self.installed_fingerprint,
+            false => None,
+        };
+
+        // Tag this compilation with a new epoch. Epochs are unique across all sets, so a compilation can't be mistaken
+        // for one of a set which has since replaced this set.
+        self.compilation_epoch = NEXT_COMPILATION_EPOCH.fetch_add(1, Ordering::Relaxed);
+
+        PendingCompilation {
+            installed_fingerprint,
+            ..self.snapshot(server_config, unsaved_documents, inputs, slice_options)
+        }
+    }
+
+    /// Takes a snapshot of this set like [`Self::prepare_compilation`], but for compiling the set to inspect its `Ast`
+    /// (see [`PendingCompilation::inspect_file`]), instead of to update the set. So it's never skipped, and it doesn't
+    /// affect the set's own compilations.
+    pub fn prepare_inspection(
+        &mut self,
+        server_config: &ServerConfig,
+        unsaved_documents: &HashMap<PathBuf, UnsavedDocument>,
+    ) -> PendingCompilation {
+        let (inputs, slice_options) = self.current_slice_options(server_config);
+        self.snapshot(server_config, unsaved_documents, inputs, slice_options)
+    }
+
+    /// Returns the options to compile this set with, along with the inputs they were computed from. The options are
+    /// cached, and only re-computed if their inputs changed since they were last computed.
+    fn current_slice_options(&mut self, server_config: &ServerConfig) -> (SliceOptionsInputs, Arc<SliceOptions>) {
+        // Glob patterns must be re-expanded on every compilation, so that newly matching paths are picked up.
+        if self.slice_config.slice_search_paths.iter().any(|path| is_glob_pattern(path)) {
+            self.cached_slice_options = None;
+        }
+
+        // The cached options are stale if the configuration they were computed from has changed since.
+        let inputs = SliceOptionsInputs::new(server_config, &self.slice_config);
+        if self.cached_slice_options.as_ref().is_some_and(|(cached_inputs, _)| *cached_inputs != inputs) {
+            debug!("The configuration of set '{}' changed, so its options are re-computed", self.name);
+            self.cached_slice_options = None;
+        }
+
+        // Re-compute the `slice_options` we're going to pass into the compiler, if necessary. The set's membership is
+        // updated from the re-computed options, so that it always matches the files the set was compiled with.
+        let is_recomputed = self.cached_slice_options.is_none();
+        let (_, slice_options) = self.cached_slice_options.get_or_insert_with(|| {
+            (inputs.clone(), Arc::new(compute_slice_options(server_config, &self.slice_config)))
         });
+        let slice_options = slice_options.clone();
+        if is_recomputed {
+            self.resolved_references = slice_options.references.iter().map(PathBuf::from).collect();
+        }
+        (inputs, slice_options)
+    }
+
+    /// Takes a snapshot of everything that's needed to compile this set with the provided options.
+    fn snapshot(
+        &self,
+        server_config: &ServerConfig,
+        unsaved_documents: &HashMap<PathBuf, UnsavedDocument>,
+        inputs: SliceOptionsInputs,
+        mut slice_options: Arc<SliceOptions>,
+    ) -> PendingCompilation {
+        // If any unsaved documents are in this set, compile them from an overlay. This requires a separate set of
+        // options, since the overlay's files are different for each compilation, so they're only computed if there are
+        // such documents. If we fail to create the overlay, we fall back to compiling the documents' files as they are
+        // on disk.
+        let mut overlay = None;
+        if Overlay::covers_any(&slice_options, unsaved_documents) {
+            let mut overlay_options = compute_slice_options(server_config, &self.slice_config);
+            match Overlay::create(&mut overlay_options, unsaved_documents) {
+                Ok(created_overlay) => overlay = created_overlay,
+                Err(error) => {
+                    warn!("Failed to compile the unsaved documents of configuration set '{}': {error}", self.name);
+                }
+            }
+            if overlay.is_some() {
+                slice_options = Arc::new(overlay_options);
+            }
+        }
+
+        PendingCompilation {
+            set_name: self.name.clone(),
+            epoch: self.compilation_epoch,
+            installed_fingerprint: None,
+            file_stamps: self.file_stamps.clone(),
+            inputs,
+            slice_options,
+            overlay,
+            max_file_size_kb: server_config.max_file_size_kb,
+            built_in_slice_path: self.slice_config.built_in_slice_path(server_config),
+            unused_definitions: server_config.unused_definitions,
+            position_encoding: server_config.position_encoding,
+            paths: server_config.paths.clone(),
+        }
+    }
+
+    /// Stores the results of a compilation of this set in the set, and returns the diagnostics that were reported
+    /// during it, along with the compilation data that the results replaced.
+    ///
+    /// Until this is called, the set keeps the results of its previous compilation, so that requests (like hover) which
+    /// arrive while the set is being compiled are answered from those, instead of waiting for its compilation to end.
+    ///
+    /// Returns `None` if the results are stale, in which case they're discarded: either the set's configuration changed
+    /// while it was being compiled, or the results of a newer compilation have already been stored.
+    pub fn install_compilation(
+        &mut self,
+        server_config: &ServerConfig,
+        compilation: FinishedCompilation,
+    ) -> Option<(Vec<Diagnostic>, CompilationData)> {
+        // A compilation with a newer epoch than this set's must have been of a different set, which this set replaced.
+        if compilation.epoch > self.compilation_epoch {
+            debug!("Configuration set '{}' was replaced while it was being compiled", self.name);
+            return None;
+        }
+        if compilation.inputs != SliceOptionsInputs::new(server_config, &self.slice_config) {
+            debug!("The configuration of set '{}' changed while it was being compiled", self.name);
+            return None;
+        }
+        if !self.try_mark_published(compilation.epoch) {
+            return None;
+        }
+        // Nothing is installed for unchanged compilations, but the stamps of the files they hashed are still kept.
+        self.file_stamps = Arc::new(compilation.file_stamps);
+        if compilation.is_unchanged {
+            return None;
+        }
+
+        let mut compilation_data = compilation.compilation_data;
+        compilation_data.keep_navigation_from(&mut self.compilation_data);
+        let previous_data = std::mem::replace(&mut self.compilation_data, compilation_data);
+        self.installed_fingerprint = Some(compilation.fingerprint);
+        self.is_evicted = false;
+        self.last_compilation_time = Some(compilation.elapsed);
+        self.mark_used();
+        Some((compilation.diagnostics, previous_data))
+    }
+}
+
+/// A snapshot of everything that's needed to compile a configuration set, taken by
+/// [`ConfigurationSet::prepare_compilation`].
+///
+/// Compiling a large set can take seconds, so sets aren't compiled while the server state is locked (which would block
+/// every other request until the compilation finished). Instead, the lock is only held to take this snapshot, and then
+/// again to install the results with [`ConfigurationSet::install_compilation`].
+#[derive(Debug)]
+pub struct PendingCompilation {
+    /// The name of the set being compiled, used to find the set again once the compilation has finished.
+    pub set_name: String,
+    epoch: u64,
+    /// The fingerprint of the set's installed compilation, if this compilation can be skipped when its own fingerprint
+    /// is the same (see [`compute_fingerprint`]).
+    installed_fingerprint: Option<u64>,
+    /// The stamps of the files that the set's previous fingerprint was computed from.
+    file_stamps: Arc<HashMap<PathBuf, FileStamp>>,
+    inputs: SliceOptionsInputs,
+    slice_options: Arc<SliceOptions>,
+    /// The overlay holding any unsaved documents of the set. It must be kept alive until the set has been compiled.
+    overlay: Option<Overlay>,
+    max_file_size_kb: Option<u64>,
+    built_in_slice_path: Option<String>,
+    unused_definitions: UnusedDefinitionsMode,
+    position_encoding: PositionEncoding,
+    paths: PathResolver,
+}
+
+impl PendingCompilation {
+    /// Registers a document which isn't a file with this compilation's path resolver (see
+    /// [`PathResolver::register_virtual_document`]). This is for documents which only exist for this compilation, and
+    /// so aren't registered with the server's resolver.
+    pub fn register_virtual_document(&mut self, url: &Url) {
+        self.paths.register_virtual_document(url);
+    }
+
+    /// Describes the options that the set is compiled with, for tracing.
+    pub fn describe_options(&self) -> String {
+        let SliceOptions { sources, references, defined_symbols, .. } = self.slice_options.as_ref();
+        format!(
+            "'{}': {} source file(s), references: {references:?}, defined symbols: {defined_symbols:?}",
+            self.set_name,
+            sources.len(),
+        )
+    }
+
+    /// Compiles the configuration set that this snapshot was taken of, like [`Self::run`], but instead of indexing the
+    /// results, calls `inspect` with the compiled file at the provided path, while the `Ast` is still available. The
+    /// compilation's diagnostics are ignored.
+    ///
+    /// Returns `None` if the set doesn't contain the file.
+    pub fn inspect_file<R>(self, file_path: &Path, inspect: impl FnOnce(&InspectedFile) -> R) -> Option<R> {
+        #[cfg(test)]
+        crate::compilation_gate::pass(&self.set_name);
+        let compilation_state = slicec::compile_from_options(&self.slice_options, |_| {}, |_| {});
+        let inspected_file = InspectedFile {
+            file: compilation_state
+                .files
+                .iter()
+                .find(|file| paths_match(&self.source_path(&file.relative_path), file_path))?,
+            compilation: &self,
+            files: &compilation_state.files,
+        };
+        Some(inspect(&inspected_file))
+    }
+
+    /// Compiles the configuration set that this snapshot was taken of, like [`Self::inspect_file`], but calls `inspect`
+    /// with every compiled file, each paired with the path of the document it was compiled from.
+    pub fn inspect_files<R>(self, inspect: impl FnOnce(&[(PathBuf, &SliceFile)]) -> R) -> R {
+        #[cfg(test)]
+        crate::compilation_gate::pass(&self.set_name);
+        let compilation_state = slicec::compile_from_options(&self.slice_options, |_| {}, |_| {});
+        let files = compilation_state
+            .files
+            .iter()
+            .map(|file| (self.source_path(&file.relative_path), file))
+            .collect::<Vec<_>>();
+        inspect(&files)
+    }
+
+    /// Returns the path of the file that a span of this compilation refers to (see [`CompilationData::source_path`]).
+    fn source_path(&self, span_file: &str) -> PathBuf {
+        match self.overlay.as_ref().and_then(|overlay| overlay.original_paths.get(span_file)) {
+            Some(original_path) => original_path.clone(),
+            None => self.paths.compiled_file_path(span_file),
+        }
+    }
+
+    /// Compiles the configuration set that this snapshot was taken of.
+    ///
+    /// The compilation's inputs are hashed first (which only reads the files that changed since they were last hashed),
+    /// and if they're the same as those of the set's installed compilation, the set isn't compiled again. The results
+    /// are then empty, and only mark the compilation as [unchanged](FinishedCompilation::is_unchanged).
+    pub fn run(self) -> FinishedCompilation {
+        let PendingCompilation {
+            set_name,
+            epoch,
+            installed_fingerprint,
+            file_stamps,
+            inputs,
+            slice_options,
+            overlay,
+            max_file_size_kb,
+            built_in_slice_path,
+            unused_definitions,
+            position_encoding,
+            paths,
+        } = self;
+        #[cfg(test)]
+        crate::compilation_gate::pass(&set_name);
+
+        let start_time = Instant::now();
+        let (fingerprint, file_stamps) = compute_fingerprint(&slice_options, overlay.as_ref(), &file_stamps);
+        if installed_fingerprint == Some(fingerprint) {
+            debug!("Configuration set '{set_name}' didn't change since its last compilation, so it's skipped");
+            return FinishedCompilation {
+                set_name,
+                elapsed: start_time.elapsed(),
+                epoch,
+                fingerprint,
+                file_stamps,
+                is_unchanged: true,
+                inputs,
+                compilation_data: CompilationData::default(),
+                diagnostics: Vec::new(),
+            };
+        }
 
         // Perform the compilation.
-        let compilation_state = slicec::compile_from_options(slice_options, |_| {}, |_| {});
+        let compilation_state = slicec::compile_from_options(&slice_options, |_| {}, |_| {});
         let CompilationState { ast, diagnostics, files } = compilation_state;
 
         // Process the diagnostics (filter out allowed lints, and update diagnostic levels as necessary).
-        let updated_diagnostics = diagnostics.into_updated(&ast, &files, slice_options);
+        let updated_diagnostics = diagnostics.into_updated(&ast, &files, &slice_options);
+        debug!(
+            "Compiled {} file(s) of configuration set '{}' in {:?}, with {} diagnostic(s)",
+            files.len(),
+            set_name,
+            start_time.elapsed(),
+            updated_diagnostics.len(),
+        );
+
+        // Convert the stringified paths returned by `slicec` to the paths that files are tracked under, mapping any
+        // overlay files back to the documents they hold the text of (see `compiled_file_path`).
+        let original_paths = overlay.map(|overlay| overlay.original_paths.clone()).unwrap_or_default();
+        let files = files
+            .into_iter()
+            .map(|f| match original_paths.get(&f.relative_path) {
+                Some(original_path) => (original_path.clone(), f),
+                None => (paths.compiled_file_path(&f.relative_path), f),
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Flag any files that are too large for the per-request features. The size is checked against the text that
+        // was compiled, so unsaved documents are flagged (or un-flagged) as they're edited.
+        let degraded_files = match max_file_size_kb {
+            Some(max_file_size_kb) => files
+                .iter()
+                .filter(|(_, file)| file.raw_text.len() as u64 > max_file_size_kb.saturating_mul(1024))
+                .map(|(path, _)| path.clone())
+                .collect(),
+            None => HashSet::new(),
+        };
+        if !degraded_files.is_empty() {
+            let count = degraded_files.len();
+            debug!("{count} file(s) of configuration set '{set_name}' are too large for hover and definitions");
+        }
 
-        // Convert the stringified paths returned by `slicec` to actual PathBuf objects.
-        let files = files.into_iter().map(|f| (PathBuf::from(&f.relative_path), f)).collect();
+        // Remember which files had errors, so that navigation in them can be served from an earlier compilation.
+        let files_with_errors = updated_diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level() == DiagnosticLevel::Error)
+            .filter_map(|diagnostic| diagnostic.span())
+            .map(|span| match original_paths.get(&span.file) {
+                Some(original_path) => original_path.clone(),
+                None => paths.compiled_file_path(&span.file),
+            })
+            .collect();
+
+        // Extract everything we need from the `Ast` while it's still available, since it's dropped once we return.
+        let references = ReferenceIndex::new(&files, position_encoding);
+        debug!(
+            "Indexed configuration set '{}': {} type reference(s), {} base(s), {} thrown exception(s), and {} doc \
+             comment link(s)",
+            set_name,
+            references.count(ReferenceKind::Type),
+            references.count(ReferenceKind::Inheritance),
+            references.count(ReferenceKind::Throws),
+            references.count(ReferenceKind::DocLink),
+        );
+        let built_in_slice_path = built_in_slice_path.as_deref();
+        let unused_definitions = find_unused_definitions(
+            &files,
+            &references,
+            built_in_slice_path,
+            unused_definitions,
+            position_encoding,
+            &paths,
+        );
+        let files = files
+            .iter()
+            .map(|(path, file)| {
+                let file_index = FileIndex::new(file, degraded_files.contains(path), position_encoding);
+                (path.clone(), file_index)
+            })
+            .collect();
 
-        // Store the data we got from compiling, then return the diagnostics so they can be published.
-        self.compilation_data = CompilationData { ast, files };
-        updated_diagnostics
+        FinishedCompilation {
+            set_name,
+            elapsed: start_time.elapsed(),
+            epoch,
+            fingerprint,
+            file_stamps,
+            is_unchanged: false,
+            inputs,
+            compilation_data: CompilationData {
+                files,
+                original_paths,
+                degraded_files,
+                references,
+                unused_definitions,
+                files_with_errors,
+                paths,
+            },
+            diagnostics: updated_diagnostics,
+        }
     }
 }
 
-/// Parses paths from a JSON value.
-fn parse_paths(value: &serde_json::Value) -> Vec<PathBuf> {
-    value
+/// A file that was compiled by [`PendingCompilation::inspect_file`], along with the compilation it's from.
+pub struct InspectedFile<'a> {
+    pub file: &'a SliceFile,
+    compilation: &'a PendingCompilation,
+    files: &'a [SliceFile],
+}
+
+impl InspectedFile<'_> {
+    /// Returns the encoding that positions are sent to the client in.
+    pub fn position_encoding(&self) -> PositionEncoding {
+        self.compilation.position_encoding
+    }
+
+    /// Returns every file that was compiled along with the inspected file (including the inspected file itself).
+    pub fn compiled_files(&self) -> &[SliceFile] {
+        self.files
+    }
+
+    /// Converts a span of any of the compiled files into the location of the document it's in.
+    pub fn span_to_location(&self, span: &Span) -> Option<tower_lsp::lsp_types::Location> {
+        let text = self.files.iter().find(|file| file.relative_path == span.file).map_or("", |file| &file.raw_text);
+        let range = PositionConverter::new(text, self.compilation.position_encoding).span_to_range(span);
+        let uri = self.compilation.paths.convert_slice_path_to_uri(self.compilation.source_path(&span.file))?;
+        Some(tower_lsp::lsp_types::Location { uri, range })
+    }
+}
+
+/// The results of running a [`PendingCompilation`], which are yet to be installed into the configuration set.
+#[derive(Debug)]
+pub struct FinishedCompilation {
+    /// The name of the set that was compiled.
+    pub set_name: String,
+    /// How long it took to compile (and index) the set.
+    pub elapsed: Duration,
+    epoch: u64,
+    fingerprint: u64,
+    file_stamps: HashMap<PathBuf, FileStamp>,
+    is_unchanged: bool,
+    inputs: SliceOptionsInputs,
+    compilation_data: CompilationData,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl FinishedCompilation {
+    /// Returns how long this compilation took, and how many files it compiled.
+    pub fn timing(&self) -> CompilationTiming {
+        CompilationTiming {
+            set_name: self.set_name.clone(),
+            elapsed: self.elapsed,
+            file_count: self.file_count(),
+        }
+    }
+
+    /// Returns the data of this compilation, along with the diagnostics that were reported during it, for compilations
+    /// whose results aren't installed into a set (see `validation.rs`).
+    pub fn into_results(self) -> (CompilationData, Vec<Diagnostic>) {
+        (self.compilation_data, self.diagnostics)
+    }
+
+    /// Returns a hash of the options and file contents that this compilation read, as a hex string. Compilations with
+    /// the same inputs have the same hash, but hashes are only comparable within a single run of the server.
+    pub fn input_hash(&self) -> String {
+        format!("{:016x}", self.fingerprint)
+    }
+
+    /// Returns true if the set wasn't compiled, since its inputs were the same as those of its installed compilation.
+    /// Unchanged compilations don't have any results, and are never installed.
+    pub fn is_unchanged(&self) -> bool {
+        self.is_unchanged
+    }
+
+    /// Returns how many files were compiled.
+    pub fn file_count(&self) -> usize {
+        self.compilation_data.files.len()
+    }
+
+    /// Returns how many diagnostics were reported.
+    pub fn diagnostic_count(&self) -> usize {
+        self.diagnostics.len()
+    }
+}
+
+/// How long the compilation of a set took, and how many files it compiled.
+#[derive(Debug)]
+pub struct CompilationTiming {
+    pub set_name: String,
+    pub elapsed: Duration,
+    pub file_count: usize,
+}
+
+// Compilations run on a blocking thread (with `tokio::task::spawn_blocking`), so the snapshot that's moved onto that
+// thread, and the results that are moved back off of it, must be `Send`. Once installed, a set's `CompilationData` is
+// read by requests on any of the runtime's threads at once, so it must be `Sync` too. This fails to build otherwise.
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    const fn assert_send_and_sync<T: Send + Sync>() {}
+    assert_send::<PendingCompilation>();
+    assert_send::<FinishedCompilation>();
+    assert_send_and_sync::<CompilationData>();
+};
+
+/// How long before it was hashed a file must have last been modified for its stamp to be kept (see [`FileStamp`]).
+const MIN_STAMP_AGE: Duration = Duration::from_secs(2);
+
+/// The hash of a file's contents, along with the size and modification time the file had when it was hashed. Files
+/// whose size and modification time are still the same aren't read again to be hashed, since they haven't changed.
+///
+/// Modification times are coarse on some file systems, so a file that's written again right after it was hashed can
+/// keep the same one. This is why files modified within [`MIN_STAMP_AGE`] of being hashed aren't stamped.
+#[derive(Clone, Copy, Debug)]
+struct FileStamp {
+    len: u64,
+    modified: SystemTime,
+    content_hash: u64,
+}
+
+/// Computes a hash of everything that a compilation with the provided options reads: the options themselves, and the
+/// path and contents of each Slice file they reference (in the order they're compiled in). Returns the hash, along with
+/// the stamps of the hashed files, which are used to skip reading the files which haven't changed the next time.
+///
+/// Files in the overlay are hashed under the paths of the documents they hold the text of, so compiling a document's
+/// unsaved text has the same hash as compiling the document once that text has been saved.
+fn compute_fingerprint(
+    slice_options: &SliceOptions,
+    overlay: Option<&Overlay>,
+    previous_stamps: &HashMap<PathBuf, FileStamp>,
+) -> (u64, HashMap<PathBuf, FileStamp>) {
+    let mut hasher = DefaultHasher::new();
+    slice_options.sources.hash(&mut hasher);
+    slice_options.defined_symbols.hash(&mut hasher);
+    slice_options.allowed_lints.hash(&mut hasher);
+
+    // The overlay expands directories into their files, so they're always hashed file by file.
+    let mut seen_files = HashSet::new();
+    let mut file_stamps = HashMap::new();
+    for reference in &slice_options.references {
+        let path = Path::new(reference);
+        let mut files = Vec::new();
+        match path.is_dir() {
+            true => find_slice_files(path, &mut files),
+            false => files.push(path.to_owned()),
+        }
+        for file in files.into_iter().filter(|file| seen_files.insert(file.clone())) {
+            let original_path = overlay.and_then(|overlay| overlay.original_paths.get(&file.display().to_string()));
+            original_path.unwrap_or(&file).hash(&mut hasher);
+            hash_file(file, previous_stamps, &mut file_stamps).hash(&mut hasher);
+        }
+    }
+    (hasher.finish(), file_stamps)
+}
+
+/// Returns the hash of a file's contents, or `None` if it can't be read. The file is only read if it doesn't have a
+/// stamp in `previous_stamps` that's still current. Its new stamp (if it gets one) is added to `file_stamps`.
+fn hash_file(
+    path: PathBuf,
+    previous_stamps: &HashMap<PathBuf, FileStamp>,
+    file_stamps: &mut HashMap<PathBuf, FileStamp>,
+) -> Option<u64> {
+    let metadata = fs::metadata(&path).ok();
+    let len_and_modified = metadata.and_then(|metadata| Some((metadata.len(), metadata.modified().ok()?)));
+    if let (Some((len, modified)), Some(stamp)) = (len_and_modified, previous_stamps.get(&path)) {
+        if stamp.len == len && stamp.modified == modified {
+            file_stamps.insert(path, *stamp);
+            return Some(stamp.content_hash);
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    fs::read(&path).ok()?.hash(&mut hasher);
+    let content_hash = hasher.finish();
+    if let Some((len, modified)) = len_and_modified {
+        if modified.elapsed().is_ok_and(|age| age >= MIN_STAMP_AGE) {
+            file_stamps.insert(path, FileStamp { len, modified, content_hash });
+        }
+    }
+    Some(content_hash)
+}
+
+/// Checks a configuration set's JSON value for unknown keys, values of the wrong type, and missing paths, returning a
+/// description of each problem that was found.
+fn validate_configuration_set(value: &serde_json::Value, set_name: &str) -> Vec<String> {
+    let Some(object) = value.as_object() else {
+        return Vec::new();
+    };
+    let mut problems = Vec::new();
+
+    for key in object.keys() {
+        if !CONFIGURATION_SET_KEYS.contains(&key.as_str()) {
+            problems.push(format!("configuration set '{set_name}' has an unknown key '{key}'"));
+        }
+    }
+
+    for key in ["paths", "references", "exclude", "defines", "extraCompilerOptions"] {
+        let Some(value) = object.get(key) else {
+            continue;
+        };
+        match value.as_array() {
+            Some(array) if array.iter().all(serde_json::Value::is_string) => {}
+            Some(_) => problems.push(format!(
+                "'{key}' of configuration set '{set_name}' contains entries which aren't strings, which were skipped"
+            )),
+            None => problems.push(format!("'{key}' of configuration set '{set_name}' must be an array of strings")),
+        }
+    }
+
+    let has_paths = object
         .get("paths")
         .and_then(|v| v.as_array())
+        .is_some_and(|paths| paths.iter().any(serde_json::Value::is_string));
+    if !has_paths {
+        let default_path = match parse_root_dir(value) {
+            Some(_) => "its 'rootDir'",
+            None => "the workspace",
+        };
+        problems.push(format!(
+            "configuration set '{set_name}' doesn't have any 'paths', so it includes every Slice file in {default_path}"
+        ));
+    }
+
+    if object.get("name").is_some_and(|name| !name.is_string()) {
+        problems.push(format!("'name' of configuration set '{set_name}' must be a string"));
+    }
+    if object.get("disabled").is_some_and(|value| !value.is_boolean()) {
+        problems.push(format!("'disabled' of configuration set '{set_name}' must be a boolean"));
+    }
+    if object.get("rootDir").is_some_and(|path| !path.is_string()) {
+        problems.push(format!("'rootDir' of configuration set '{set_name}' must be a string"));
+    }
+    if object.get("builtInSlicePath").is_some_and(|path| !path.is_string()) {
+        problems.push(format!("'builtInSlicePath' of configuration set '{set_name}' must be a string"));
+    }
+    let built_in_slice_position = object.get("builtInSlicePosition");
+    if built_in_slice_position.is_some_and(|position| !matches!(position.as_str(), Some("first" | "last"))) {
+        problems.push(format!(
+            "'builtInSlicePosition' of configuration set '{set_name}' must be either \"first\" or \"last\""
+        ));
+    }
+    if object.get("addWellKnownTypes").is_some_and(|value| !value.is_boolean()) {
+        problems.push(format!("'addWellKnownTypes' of configuration set '{set_name}' must be a boolean"));
+    }
+    problems.extend(parse_extra_compiler_options(value, set_name).problems);
+
+    problems
+}
+
+/// Parses an array of paths from the specified field of a JSON value.
+fn parse_paths(value: &serde_json::Value, field: &str) -> Vec<PathBuf> {
+    value
+        .get(field)
+        .and_then(|v| v.as_array())
         .map(|dirs_array| {
             dirs_array
                 .iter()
@@ -86,6 +1400,121 @@ fn parse_paths(value: &serde_json::Value) -> Vec<PathBuf> {
         .unwrap_or_default()
 }
 
+/// Parses the preprocessor symbols to define from a JSON value.
+fn parse_defines(value: &serde_json::Value) -> Vec<String> {
+    value
+        .get("defines")
+        .and_then(|v| v.as_array())
+        .map(|symbols| {
+            symbols
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
+
+/// The options from a configuration set's `extraCompilerOptions`, which use the same syntax as `slicec`'s command line.
+#[derive(Debug, Default)]
+struct ExtraCompilerOptions {
+    defined_symbols: Vec<String>,
+    allowed_lints: Vec<String>,
+    /// A description of each option that couldn't be applied, and was skipped.
+    problems: Vec<String>,
+}
+
+/// Parses the extra compiler options of a configuration set from a JSON value.
+///
+/// Options can be given their values in the same argument (`-DFOO` or `--define=FOO`), or in the next argument. Since
+/// users often write an option and its value together, each entry is also split on whitespace (`-D FOO`).
+fn parse_extra_compiler_options(value: &serde_json::Value, set_name: &str) -> ExtraCompilerOptions {
+    let mut options = ExtraCompilerOptions::default();
+    let mut arguments = value
+        .get("extraCompilerOptions")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v.as_str())
+        .flat_map(str::split_whitespace);
+
+    let location = format!("the 'extraCompilerOptions' of configuration set '{set_name}'");
+    while let Some(argument) = arguments.next() {
+        let (option, inline_value) = match (argument.split_once('='), argument.get(..2), argument.get(2..)) {
+            (Some((option, value)), _, _) if option.starts_with("--") => (option, Some(value)),
+            (_, Some(option), Some(value)) if !argument.starts_with("--") && !value.is_empty() => (option, Some(value)),
+            _ => (argument, None),
+        };
+        let value = match COMPILER_OPTIONS_WITH_VALUES.contains(&option) {
+            true => inline_value.or_else(|| arguments.next()),
+            false => None,
+        };
+
+        match option {
+            "-D" | "--define" | "-A" | "--allow" => {
+                let Some(value) = value else {
+                    options.problems.push(format!("'{option}' in {location} is missing a value, so it was skipped"));
+                    continue;
+                };
+                match option {
+                    "-D" | "--define" => options.defined_symbols.push(value.to_owned()),
+                    _ => options.allowed_lints.push(value.to_owned()),
+                }
+            }
+            _ if UNSUPPORTED_COMPILER_OPTIONS.contains(&option) => options.problems.push(format!(
+                "'{option}' in {location} isn't supported by the language server, so it was skipped"
+            )),
+            _ => options.problems.push(format!(
+                "'{argument}' in {location} isn't a known compiler option, so it was skipped"
+            )),
+        }
+    }
+    options
+}
+
+/// Parses a configuration set's own path to the built-in Slice files from a JSON value.
+fn parse_built_in_slice_path(value: &serde_json::Value) -> Option<PathBuf> {
+    value
+        .get("builtInSlicePath")
+        .and_then(|v| v.as_str())
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| PathBuf::from(sanitize_path(path)))
+}
+
+/// Parses the directory that a configuration set's relative paths are relative to from a JSON value, if it has one.
+fn parse_root_dir(value: &serde_json::Value) -> Option<PathBuf> {
+    value
+        .get("rootDir")
+        .and_then(|v| v.as_str())
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| PathBuf::from(sanitize_path(path)))
+}
+
+/// Returns true if the provided user-specified path is resolved against a root path, meaning it isn't absolute, and
+/// doesn't start with a variable or `~` (which are expanded into absolute paths).
+fn is_relative_to_root(path: &Path) -> bool {
+    let starts_with_variable = path.to_str().is_some_and(|path| path.starts_with("${") || path.starts_with('~'));
+    path.is_relative() && !starts_with_variable
+}
+
+/// Determines whether a configuration set is turned off from a JSON value.
+fn parse_disabled(value: &serde_json::Value) -> bool {
+    value
+        .get("disabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Parses where the built-in Slice files are placed among a configuration set's paths from a JSON value.
+fn parse_built_in_slice_position(value: &serde_json::Value) -> BuiltInSlicePosition {
+    match value.get("builtInSlicePosition").and_then(|v| v.as_str()) {
+        Some("last") => BuiltInSlicePosition::Last,
+        _ => BuiltInSlicePosition::First,
+    }
+}
+
 /// Determines whether to include built-in types from a JSON value.
 fn parse_include_built_in(value: &serde_json::Value) -> bool {
     value
@@ -93,3 +1522,394 @@ fn parse_include_built_in(value: &serde_json::Value) -> bool {
         .and_then(|v| v.as_bool())
         .unwrap_or(true)
 }
+
+/// Parses the name of a configuration set from a JSON value. Blank names are treated as if no name was given.
+fn parse_name(value: &serde_json::Value) -> Option<String> {
+    value
+        .get("name")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+}
+
+/// Returns the name of a workspace folder from its root path, for use in the names of configuration sets.
+fn folder_name(folder_path: &Path) -> String {
+    match folder_path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => folder_path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic_handler::store_diagnostics_for_set;
+    use crate::hover::get_hover_message;
+    use slicec::slice_file::Location;
+
+    /// Creates a configuration set with the provided paths, which doesn't include the built-in Slice files.
+    fn configuration_set(name: &str, paths: &[&Path]) -> ConfigurationSet {
+        let slice_config = SliceConfig {
+            slice_search_paths: paths.iter().map(|path| path.to_path_buf()).collect(),
+            include_built_in_slice_files: false,
+            ..SliceConfig::default()
+        };
+        ConfigurationSet { name: name.to_owned(), slice_config, ..ConfigurationSet::default() }
+    }
+
+    /// Compiles a configuration set and installs the results into it, like the server does.
+    fn compile(set: &mut ConfigurationSet, server_config: &ServerConfig) -> Option<Vec<Diagnostic>> {
+        let compilation = set.prepare_compilation(server_config, &HashMap::new());
+        let (diagnostics, _) = set.install_compilation(server_config, compilation.run())?;
+        Some(diagnostics)
+    }
+
+    /// Creates a copy of the `relative` fixtures for a test to edit, in a directory which is unique to the test.
+    fn fixture_copy(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("slice-language-server-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for file in ["greeter.slice", "sender.slice"] {
+            fs::copy(Path::new("tests/fixtures/relative").join(file), directory.join(file)).unwrap();
+        }
+        directory
+    }
+
+    /// The text of `sender.slice`, without the error that the fixture has.
+    const FIXED_SENDER: &str = "module Fixtures\n\nstruct Sender {\n    name: string\n    signature: string\n}\n";
+
+    fn error_count(diagnostics: &[Diagnostic]) -> usize {
+        let is_error = |diagnostic: &&Diagnostic| matches!(diagnostic.level(), DiagnosticLevel::Error);
+        diagnostics.iter().filter(is_error).count()
+    }
+
+    #[test]
+    fn compilations_are_skipped_if_nothing_changed_since_the_installed_compilation() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("unchanged");
+        let mut set = configuration_set("unchanged", &[&directory]);
+        assert_eq!(error_count(&compile(&mut set, &server_config).unwrap()), 1);
+
+        let compilation = set.prepare_compilation(&server_config, &HashMap::new()).run();
+        assert!(compilation.is_unchanged());
+        assert_eq!(compilation.file_count(), 0);
+        assert!(set.install_compilation(&server_config, compilation).is_none());
+        assert_eq!(set.compilation_data.files.len(), 2);
+
+        // Skipped compilations still count as published, so the set keeps being skipped until something changes.
+        assert!(compile(&mut set, &server_config).is_none());
+        fs::write(directory.join("sender.slice"), FIXED_SENDER).unwrap();
+        assert_eq!(error_count(&compile(&mut set, &server_config).unwrap()), 0);
+    }
+
+    #[test]
+    fn huge_file_size_limits_dont_degrade_any_files() {
+        // Limits too large to be converted into bytes are treated as unlimited, instead of overflowing.
+        let server_config = ServerConfig { max_file_size_kb: Some(u64::MAX), ..ServerConfig::default() };
+        let directory = fixture_copy("huge-file-size-limit");
+        let mut set = configuration_set("huge file size limit", &[&directory]);
+        compile(&mut set, &server_config).unwrap();
+        assert_eq!(set.compilation_data.files.len(), 2);
+        assert!(set.compilation_data.degraded_files.is_empty());
+    }
+
+    #[test]
+    fn files_are_hashed_when_the_compilation_runs() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("hashed-on-run");
+        let mut set = configuration_set("hashed-on-run", &[&directory]);
+        compile(&mut set, &server_config).unwrap();
+
+        // Files are only read once the compilation runs (off of the server's lock), so edits made in between count.
+        let pending = set.prepare_compilation(&server_config, &HashMap::new());
+        fs::write(directory.join("sender.slice"), FIXED_SENDER).unwrap();
+        let compilation = pending.run();
+        assert!(!compilation.is_unchanged());
+        let (diagnostics, _) = set.install_compilation(&server_config, compilation).unwrap();
+        assert_eq!(error_count(&diagnostics), 0);
+    }
+
+    #[test]
+    fn fingerprints_are_only_recorded_for_installed_compilations() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("rejected");
+        let mut set = configuration_set("rejected", &[&directory]);
+        compile(&mut set, &server_config).unwrap();
+
+        // Start compiling an edit, but change the set's configuration before it finishes, so its results are rejected.
+        fs::write(directory.join("sender.slice"), FIXED_SENDER).unwrap();
+        let pending = set.prepare_compilation(&server_config, &HashMap::new());
+        set.slice_config.defined_symbols.push("REJECTED".to_owned());
+        assert!(set.install_compilation(&server_config, pending.run()).is_none());
+
+        // Reverting the configuration compiles the edit again, since the set still holds the results from before it.
+        set.slice_config.defined_symbols.clear();
+        let diagnostics = compile(&mut set, &server_config).unwrap();
+        assert_eq!(error_count(&diagnostics), 0);
+        assert!(compile(&mut set, &server_config).is_none());
+    }
+
+    #[test]
+    fn files_which_didnt_change_since_they_were_hashed_arent_read_again() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("stamped");
+        let mut set = configuration_set("stamped", &[&directory]);
+
+        // The files were just written, so they could still change without their modification times changing.
+        compile(&mut set, &server_config).unwrap();
+        assert!(set.file_stamps.is_empty());
+
+        // Once they're older, they're stamped the next time they're hashed, even if the set's compilation is skipped.
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        for file in ["greeter.slice", "sender.slice"] {
+            fs::File::options().write(true).open(directory.join(file)).unwrap().set_modified(an_hour_ago).unwrap();
+        }
+        let compilation = set.prepare_compilation(&server_config, &HashMap::new()).run();
+        assert!(compilation.is_unchanged());
+        assert!(set.install_compilation(&server_config, compilation).is_none());
+        assert_eq!(set.file_stamps.len(), 2);
+
+        // Stamped files are hashed from their stamps instead of being read, so changing a stamp's hash changes them.
+        let mut file_stamps = (*set.file_stamps).clone();
+        let (_, sender_stamp) = file_stamps.iter_mut().find(|(path, _)| path.ends_with("sender.slice")).unwrap();
+        sender_stamp.content_hash ^= 1;
+        set.file_stamps = Arc::new(file_stamps);
+        assert!(!set.prepare_compilation(&server_config, &HashMap::new()).run().is_unchanged());
+    }
+
+    #[test]
+    fn saving_a_compiled_unsaved_document_doesnt_compile_it_again() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("saved");
+        let mut set = configuration_set("saved", &[&directory]);
+        let sender_path = directory.join("sender.slice");
+        let document = UnsavedDocument { version: 2, text: FIXED_SENDER.to_owned() };
+        let unsaved_documents = HashMap::from([(sender_path.clone(), document)]);
+
+        let compilation = set.prepare_compilation(&server_config, &unsaved_documents).run();
+        let (diagnostics, _) = set.install_compilation(&server_config, compilation).unwrap();
+        assert_eq!(error_count(&diagnostics), 0);
+
+        // Once the document is saved, the set's files have the same contents as what was compiled.
+        fs::write(&sender_path, FIXED_SENDER).unwrap();
+        let compilation = set.prepare_compilation(&server_config, &HashMap::new()).run();
+        assert!(compilation.is_unchanged());
+    }
+
+    #[test]
+    fn reverted_edits_are_compiled_while_another_compilation_is_in_flight() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("reverted");
+        let mut set = configuration_set("reverted", &[&directory]);
+        compile(&mut set, &server_config).unwrap();
+
+        // An edit starts compiling, then is reverted before it finishes.
+        let document = UnsavedDocument { version: 2, text: FIXED_SENDER.to_owned() };
+        let unsaved_documents = HashMap::from([(directory.join("sender.slice"), document)]);
+        let edited = set.prepare_compilation(&server_config, &unsaved_documents);
+        let reverted = set.prepare_compilation(&server_config, &HashMap::new());
+
+        // The reverted compilation matches the installed one, but the edit's results would be installed before it.
+        let (edited, reverted) = (edited.run(), reverted.run());
+        assert!(!reverted.is_unchanged());
+        assert_eq!(error_count(&set.install_compilation(&server_config, edited).unwrap().0), 0);
+        assert_eq!(error_count(&set.install_compilation(&server_config, reverted).unwrap().0), 1);
+    }
+
+    #[tokio::test]
+    async fn compilations_can_run_on_a_blocking_thread() {
+        let server_config = ServerConfig::default();
+        let fixture_path = std::env::current_dir().unwrap().join("tests/fixtures/relative");
+        let mut set = configuration_set("blocking", &[&fixture_path]);
+
+        let compilation = set.prepare_compilation(&server_config, &HashMap::new());
+        let finished_compilation = tokio::task::spawn_blocking(move || compilation.run()).await.unwrap();
+        let (diagnostics, _) = set.install_compilation(&server_config, finished_compilation).unwrap();
+        assert_eq!(error_count(&diagnostics), 1);
+        assert_eq!(set.compilation_data.files.len(), 2);
+    }
+
+    #[test]
+    fn navigation_in_files_with_errors_comes_from_their_last_good_compilation() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("last-good");
+        let mut set = configuration_set("last good", &[&directory]);
+        let sender_path = directory.join("sender.slice");
+        let mut compile_text = |text: &str| {
+            let document = UnsavedDocument { version: 1, text: text.to_owned() };
+            let unsaved_documents = HashMap::from([(sender_path.clone(), document)]);
+            let compilation = set.prepare_compilation(&server_config, &unsaved_documents);
+            let (diagnostics, _) = set.install_compilation(&server_config, compilation.run()).unwrap();
+            let sender_file = set.compilation_data.file(&sender_path).unwrap();
+            let hover = get_hover_message(sender_file, &Location { row: 4, col: 12 });
+            (error_count(&diagnostics), sender_file.raw_text.clone(), hover)
+        };
+        let string_message = Some("A UTF-8 string.".to_owned());
+        assert_eq!(compile_text(FIXED_SENDER), (0, FIXED_SENDER.to_owned(), string_message.clone()));
+
+        // The diagnostics (and the text) are from the latest compilation, but hovering still works like it did before.
+        let broken_text = "module Fixtures\n\nstruct Sender {\n    name: string\n    signature:";
+        let (error_count, text, hover) = compile_text(broken_text);
+        assert!(error_count > 0);
+        assert_eq!((text.as_str(), hover), (broken_text, string_message));
+
+        // Once the file compiles cleanly again, its navigation is from the latest compilation too.
+        let int_text = FIXED_SENDER.replace("name: string", "name: int32");
+        let int_message = Some("A 32-bit signed integer type.".to_owned());
+        assert_eq!(compile_text(&int_text), (0, int_text.clone(), int_message));
+    }
+
+    #[test]
+    fn compilations_that_finish_after_a_newer_one_are_dropped() {
+        let server_config = ServerConfig::default();
+        let directory = fixture_copy("stale");
+        let mut set = configuration_set("stale", &[&directory]);
+        let sender_path = directory.join("sender.slice");
+
+        // The older compilation has the fixed text of an unsaved document, and the newer one has the text on disk (which
+        // has an error).
+        let document = UnsavedDocument { version: 2, text: FIXED_SENDER.to_owned() };
+        let older = set.prepare_compilation(&server_config, &HashMap::from([(sender_path.clone(), document)]));
+        let newer = set.prepare_compilation(&server_config, &HashMap::new());
+        let (older, newer) = (older.run(), newer.run());
+
+        // The newer compilation finishes first, so the older one's results are stale once it finishes too.
+        let (diagnostics, _) = set.install_compilation(&server_config, newer).unwrap();
+        assert_eq!(error_count(&diagnostics), 1);
+        assert!(set.install_compilation(&server_config, older).is_none());
+        let sender_file = set.compilation_data.file(&sender_path).unwrap();
+        assert_eq!(sender_file.raw_text, fs::read_to_string(&sender_path).unwrap());
+    }
+
+    #[test]
+    fn toggling_the_built_in_files_of_a_compiled_set_changes_its_next_references() {
+        let working_directory = std::env::current_dir().unwrap();
+        let built_in_slice_path = working_directory.join("tests/fixtures/type_ids");
+        let built_in_slice_path_string = built_in_slice_path.display().to_string();
+        let server_config =
+            ServerConfig { built_in_slice_path: Some(built_in_slice_path_string.clone()), ..ServerConfig::default() };
+        let fixture_path = working_directory.join("tests/fixtures/relative");
+        let mut set = configuration_set("toggled built-ins", &[&fixture_path]);
+        compile(&mut set, &server_config).unwrap();
+        let is_built_in = |path: &&PathBuf| path.starts_with(&built_in_slice_path);
+        assert!(!set.compilation_data.files.keys().any(|path| is_built_in(&path)));
+
+        // The set's configuration is edited in place, so its cached options must be re-computed without being told to.
+        for include_built_in_slice_files in [true, false] {
+            let previous_references = set.cached_slice_options.as_ref().unwrap().1.references.clone();
+            set.slice_config.include_built_in_slice_files = include_built_in_slice_files;
+            let compilation = set.prepare_compilation(&server_config, &HashMap::new());
+            assert_ne!(compilation.slice_options.references, previous_references);
+            let includes_built_ins = compilation.slice_options.references.contains(&built_in_slice_path_string);
+            assert_eq!(includes_built_ins, include_built_in_slice_files);
+
+            set.install_compilation(&server_config, compilation.run()).unwrap();
+            let has_built_in_files = set.compilation_data.files.keys().any(|path| is_built_in(&path));
+            assert_eq!(has_built_in_files, include_built_in_slice_files);
+        }
+    }
+
+    #[test]
+    fn overlay_options_are_only_computed_for_sets_covering_an_unsaved_document() {
+        let server_config = ServerConfig::default();
+        let fixture_path = std::env::current_dir().unwrap().join("tests/fixtures/relative");
+        let mut set = configuration_set("relative", &[&fixture_path]);
+        let unsaved_document = |path: PathBuf| {
+            let document = UnsavedDocument { version: 1, text: "module Fixtures".to_owned() };
+            HashMap::from([(path, document)])
+        };
+
+        // Without unsaved documents in the set, its cached options are compiled as they are.
+        let outside_documents = unsaved_document(fixture_path.with_file_name("outside.slice"));
+        for unsaved_documents in [HashMap::new(), outside_documents] {
+            let inspection = set.prepare_inspection(&server_config, &unsaved_documents);
+            let (_, cached_options) = set.cached_slice_options.as_ref().unwrap();
+            assert!(Arc::ptr_eq(&inspection.slice_options, cached_options));
+            assert!(inspection.overlay.is_none());
+        }
+
+        // With one, the set is compiled from an overlay, with its own options, and the cached options are untouched.
+        let inside_documents = unsaved_document(fixture_path.join("sender.slice"));
+        let inspection = set.prepare_inspection(&server_config, &inside_documents);
+        let (_, cached_options) = set.cached_slice_options.as_ref().unwrap();
+        assert!(!Arc::ptr_eq(&inspection.slice_options, cached_options));
+        let overlay = inspection.overlay.as_ref().unwrap();
+        let original_paths = overlay.original_paths.values().collect::<Vec<_>>();
+        assert_eq!(original_paths, [&fixture_path.join("sender.slice")]);
+        assert_eq!(cached_options.references, [fixture_path.display().to_string()]);
+    }
+
+    #[test]
+    fn files_reported_with_relative_paths_are_tracked_under_absolute_paths() {
+        // Without a workspace, relative paths are passed to `slicec` as they are, which reports its files relative to
+        // the working directory (the crate's directory during tests).
+        let working_directory = std::env::current_dir().unwrap();
+        let mut spellings = vec![
+            "tests/fixtures/relative",
+            "./tests/fixtures/relative/",
+            "tests/x/../fixtures/relative",
+        ];
+        if cfg!(windows) {
+            spellings.extend([r"tests\fixtures\relative", r".\tests/fixtures\relative\"]);
+        }
+
+        for spelling in spellings {
+            let server_config = ServerConfig::default();
+            let mut set = configuration_set("relative", &[Path::new(spelling)]);
+            let diagnostics = compile(&mut set, &server_config).unwrap();
+
+            let fixture_path = working_directory.join("tests/fixtures/relative");
+            let expected_path = |file: &str| server_config.paths.canonical_path(&fixture_path.join(file));
+            let mut paths = set.compilation_data.files.keys().cloned().collect::<Vec<_>>();
+            paths.sort();
+            assert_eq!(paths, [expected_path("greeter.slice"), expected_path("sender.slice")], "{spelling}");
+
+            // The URI that the client sends for each file finds its compiled file, like requests do.
+            for path in &paths {
+                let uri = Url::from_file_path(path).unwrap();
+                let file_path = server_config.paths.url_to_sanitized_file_path(&uri).unwrap();
+                assert!(set.compilation_data.file(&file_path).is_some(), "{spelling}: {uri}");
+            }
+
+            // And the diagnostics are published under URIs which convert back into the same paths.
+            store_diagnostics_for_set(diagnostics, &mut set, &server_config);
+            let diagnostics = set
+                .diagnostics
+                .iter()
+                .map(|(uri, diagnostics)| (server_config.paths.url_to_sanitized_file_path(uri).unwrap(), diagnostics))
+                .collect::<HashMap<_, _>>();
+            assert_eq!(diagnostics.len(), 2, "{spelling}");
+            assert!(diagnostics[&expected_path("greeter.slice")].is_empty(), "{spelling}");
+            let sender_diagnostics = diagnostics[&expected_path("sender.slice")];
+            assert_eq!(sender_diagnostics.len(), 1, "{spelling}");
+            assert_eq!(sender_diagnostics[0].range.start, tower_lsp::lsp_types::Position::new(4, 15), "{spelling}");
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn compiled_files_are_looked_up_case_insensitively_on_windows() {
+        let file_index = FileIndex {
+            raw_text: "module Proto".to_owned(),
+            content_hash: 0,
+            definitions: Vec::new(),
+            hover_entries: Vec::new(),
+            definition_links: Vec::new(),
+        };
+        let compiled_path = PathBuf::from(r"C:\Work\Proto\greeter.slice");
+        let mut compilation_data = CompilationData::default();
+        compilation_data.files.insert(compiled_path.clone(), file_index);
+        compilation_data.degraded_files.insert(compiled_path);
+
+        let paths = [r"C:\Work\Proto\greeter.slice", r"c:\work\proto\greeter.slice", r"C:\WORK\PROTO\Greeter.slice"];
+        for path in paths {
+            assert!(compilation_data.file(Path::new(path)).is_some(), "{path}");
+            assert!(compilation_data.is_degraded(Path::new(path)), "{path}");
+        }
+        assert!(compilation_data.file(Path::new(r"C:\Work\Proto\other.slice")).is_none());
+
+        // Membership is checked case-insensitively too.
+        let reference = Path::new(r"c:\work\proto\greeter.slice");
+        assert!(reference_covers_file(reference, Path::new(r"C:\Work\Proto\greeter.slice")));
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::CompilationData;
+use crate::reference_index::ReferenceKind;
+use crate::server_state::ServerState;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// What the nodes of a dependency graph stand for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    /// Each node is a Slice file.
+    File,
+    /// Each node is a module, which groups the definitions of every file that's in it.
+    Module,
+}
+
+impl Granularity {
+    /// Returns the granularity with the provided name (`file` or `module`), if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "file" => Some(Granularity::File),
+            "module" => Some(Granularity::Module),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Granularity::File => "file",
+            Granularity::Module => "module",
+        }
+    }
+}
+
+/// The dependencies between the files (or modules) of each configuration set, for the `slice.exportDependencyGraph`
+/// command.
+///
+/// There's an edge from one node to another if the first node defines a type that's referenced by the second node (as a
+/// type, a base, or a thrown exception). Links in doc comments aren't dependencies, so they're skipped, and so are
+/// references within a single node. Graphs are only ever written as lists of nodes and edges, so cycles (which Slice
+/// allows between files) are written like any other edges.
+pub struct DependencyGraph {
+    granularity: Granularity,
+    sets: Vec<SetGraph>,
+}
+
+/// The dependency graph of a single configuration set. Edges are weighted by how many references they stand for.
+struct SetGraph {
+    name: String,
+    nodes: BTreeSet<String>,
+    edges: BTreeMap<(String, String), usize>,
+}
+
+impl DependencyGraph {
+    /// Builds the dependency graph of every compiled configuration set, from what's indexed after each compilation.
+    /// Files are named by their paths, relative to the workspace folder that they're in (if any).
+    pub fn new(server_state: &ServerState, granularity: Granularity) -> Self {
+        let root_paths = &server_state.server_config.workspace_root_paths;
+        let sets = server_state
+            .configuration_sets
+            .iter()
+            .filter(|set| set.is_compiled())
+            .map(|set| SetGraph::new(&set.name, &set.compilation_data, granularity, root_paths))
+            .collect();
+        DependencyGraph { granularity, sets }
+    }
+
+    /// Returns how many nodes and edges the graph has, across every configuration set.
+    pub fn size(&self) -> (usize, usize) {
+        let nodes = self.sets.iter().map(|set| set.nodes.len()).sum();
+        let edges = self.sets.iter().map(|set| set.edges.len()).sum();
+        (nodes, edges)
+    }
+
+    /// Renders the graph as JSON, with the nodes and edges of each configuration set.
+    pub fn to_json(&self) -> Value {
+        let sets = self.sets.iter().map(|set| {
+            let edges = set.edges.iter().map(|((from, to), references)| {
+                json!({ "from": from, "to": to, "references": references })
+            });
+            json!({
+                "name": set.name,
+                "nodes": set.nodes,
+                "edges": edges.collect::<Vec<_>>(),
+            })
+        });
+        json!({
+            "granularity": self.granularity.name(),
+            "configurationSets": sets.collect::<Vec<_>>(),
+        })
+    }
+
+    /// Renders the graph in the DOT language (used by Graphviz), with a cluster for each configuration set. A file can
+    /// be in multiple sets, so each node is named after its set, and labeled with the file (or module) it stands for.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph \"slice\" {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (set_index, set) in self.sets.iter().enumerate() {
+            let node_ids = set
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(node_index, node)| (node.as_str(), format!("n{set_index}_{node_index}")))
+                .collect::<HashMap<_, _>>();
+
+            let _ = writeln!(dot, "    subgraph \"cluster_{set_index}\" {{");
+            let _ = writeln!(dot, "        label=\"{}\";", escape_dot(&set.name));
+            for node in &set.nodes {
+                let _ = writeln!(dot, "        {} [label=\"{}\"];", node_ids[node.as_str()], escape_dot(node));
+            }
+            for ((from, to), references) in &set.edges {
+                let (from, to) = (&node_ids[from.as_str()], &node_ids[to.as_str()]);
+                let _ = writeln!(dot, "        {from} -> {to} [label=\"{references}\"];");
+            }
+            dot.push_str("    }\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl SetGraph {
+    fn new(name: &str, compilation_data: &CompilationData, granularity: Granularity, root_paths: &[PathBuf]) -> Self {
+        let node_of_file = |path: &Path, module: Option<&str>| match granularity {
+            Granularity::File => Some(display_path(path, root_paths)),
+            Granularity::Module => module.map(str::to_owned),
+        };
+
+        // Find the node that each entity is defined in. Every file is a node, even if it isn't referenced.
+        let mut nodes = BTreeSet::new();
+        let mut defining_nodes = HashMap::new();
+        for (path, file) in &compilation_data.files {
+            for definition in &file.definitions {
+                if let Some(node) = node_of_file(path, Some(module_of(&definition.identifier))) {
+                    defining_nodes.insert(definition.identifier.as_str(), node);
+                }
+            }
+            nodes.extend(node_of_file(path, file.definitions.first().map(|d| module_of(&d.identifier))));
+        }
+
+        // Add an edge for each reference, from the node that defines the referenced entity, to the node it's in.
+        let mut edges = BTreeMap::new();
+        for (identifier, references) in compilation_data.references.iter() {
+            let Some(defining_node) = defining_nodes.get(identifier) else {
+                continue;
+            };
+            for reference in references.iter().filter(|reference| reference.kind != ReferenceKind::DocLink) {
+                // A reference is in the module of the closest definition before it, since files can hold multiple.
+                let definitions = compilation_data.files.get(&reference.path).map_or(&[][..], |file| &file.definitions);
+                let enclosing_definition = definitions
+                    .iter()
+                    .take_while(|definition| definition.range.start <= reference.range.start)
+                    .last()
+                    .or(definitions.first());
+                let module = enclosing_definition.map(|definition| module_of(&definition.identifier));
+                let Some(referencing_node) = node_of_file(&reference.path, module) else {
+                    continue;
+                };
+                if referencing_node != *defining_node {
+                    *edges.entry((defining_node.clone(), referencing_node)).or_default() += 1;
+                }
+            }
+        }
+
+        SetGraph { name: name.to_owned(), nodes, edges }
+    }
+}
+
+/// Returns the module that a top-level definition is in, from its fully-qualified identifier (like `Foo::Bar` for
+/// `Foo::Bar::Baz`).
+pub fn module_of(identifier: &str) -> &str {
+    identifier.rsplit_once("::").map_or(identifier, |(module, _)| module)
+}
+
+/// Returns a path relative to the workspace folder it's in, or the path as-is if it isn't in one.
+fn display_path(path: &Path, root_paths: &[PathBuf]) -> String {
+    let relative_path = root_paths.iter().find_map(|root_path| path.strip_prefix(root_path).ok());
+    relative_path.unwrap_or(path).display().to_string()
+}
+
+/// Escapes a string so that it can be written between double quotes in the DOT language.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
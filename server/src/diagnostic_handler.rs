@@ -1,48 +1,252 @@
 // Copyright (c) ZeroC, Inc.
 
-use crate::configuration_set::ConfigurationSet;
-use crate::utils::{convert_slice_path_to_uri, span_to_range};
-use crate::{notifications, show_popup};
+use crate::configuration::{reference_covers_file, ServerConfig};
+use crate::configuration_set::{CompilationData, ConfigurationSet};
+use crate::server_state::ServerState;
+use crate::todo_comments::find_todo_comments;
+use crate::position_encoding::PositionEncoding;
 
 use slicec::diagnostics::{Diagnostic, DiagnosticLevel, Note};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
-use tower_lsp::lsp_types::{DiagnosticRelatedInformation, Location, NumberOrString, Url};
+use std::hash::{Hash, Hasher};
+use tower_lsp::lsp_types::{DiagnosticRelatedInformation, DiagnosticSeverity, DocumentDiagnosticReport};
+use tower_lsp::lsp_types::{FullDocumentDiagnosticReport, Location, NumberOrString, PreviousResultId, Range};
+use tower_lsp::lsp_types::{RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport};
+use tower_lsp::lsp_types::{UnchangedDocumentDiagnosticReport, Url};
+use tower_lsp::lsp_types::{WorkspaceDiagnosticReport, WorkspaceDocumentDiagnosticReport};
+use tower_lsp::lsp_types::{WorkspaceFullDocumentDiagnosticReport, WorkspaceUnchangedDocumentDiagnosticReport};
 use tower_lsp::Client;
 
-/// Publishes diagnostics for all files in a given configuration set.
+/// Converts the diagnostics reported by a configuration set's compilation into LSP diagnostics, runs the server's own
+/// analyses over the set, and stores the results in the set, grouped by file.
 ///
-/// This function takes a client and a configuration set, generates updated diagnostics,
-/// and then publishes these diagnostics to the LSP client.
-pub async fn publish_diagnostics_for_set(
-    client: &Client,
+/// Every file in the set gets an entry, even if it has no diagnostics, so that publishing the set's diagnostics also
+/// clears any outdated ones. Files which are covered by the set's reference paths only keep their errors, since the
+/// user doesn't own them. Any diagnostics that do not have a span are returned for further processing.
+pub fn store_diagnostics_for_set(
     diagnostics: Vec<Diagnostic>,
     configuration_set: &mut ConfigurationSet,
-) {
+    server_config: &ServerConfig,
+) -> Vec<Diagnostic> {
     // Initialize a map to hold diagnostics grouped by file (URL)
     let mut map = configuration_set
         .compilation_data
         .files
         .keys()
-        .filter_map(|uri| Some((convert_slice_path_to_uri(uri)?, vec![])))
+        .filter_map(|path| Some((server_config.paths.convert_slice_path_to_uri(path)?, vec![])))
         .collect::<HashMap<Url, Vec<tower_lsp::lsp_types::Diagnostic>>>();
 
     // Process the diagnostics and populate the map.
-    let spanless_diagnostics = process_diagnostics(diagnostics, &mut map);
-    for diagnostic in spanless_diagnostics {
-        show_popup(
-            client,
-            diagnostic.message(),
-            notifications::MessageType::Error,
-        )
-        .await;
+    let compilation_data = &configuration_set.compilation_data;
+    let encoding = server_config.position_encoding;
+    let spanless_diagnostics = process_diagnostics(diagnostics, compilation_data, encoding, &mut map);
+    add_analysis_diagnostics(configuration_set, server_config, &mut map);
+
+    let reference_paths = configuration_set.slice_config.resolved_reference_paths(server_config);
+    if !reference_paths.is_empty() {
+        for (uri, diagnostics) in &mut map {
+            let is_reference = server_config.paths.url_to_sanitized_file_path(uri).is_some_and(|file_path| {
+                reference_paths
+                    .iter()
+                    .any(|reference| reference_covers_file(reference, &file_path))
+            });
+            if is_reference {
+                diagnostics.retain(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR));
+            }
+        }
     }
 
-    // Publish the diagnostics for each file
-    for (uri, lsp_diagnostics) in map {
+    configuration_set.diagnostics = map;
+    spanless_diagnostics
+}
+
+/// Publishes the diagnostics for each of the provided files.
+///
+/// If the client pulls diagnostics instead, this asks the client to re-pull them, rather than publishing them.
+pub async fn publish_diagnostics(client: &Client, server_state: &ServerState, uris: HashSet<Url>) {
+    if server_state.client.supports_pull_diagnostics {
+        // This is a request, and the client will pull the diagnostics (which requires the server state) before
+        // responding to it, so we can't wait for its response here.
+        let client = client.clone();
+        tokio::spawn(async move { client.workspace_diagnostic_refresh().await });
+        return;
+    }
+
+    for uri in uris {
+        let lsp_diagnostics = diagnostics_for_file(server_state, &uri);
         client.publish_diagnostics(uri, lsp_diagnostics, None).await;
     }
 }
 
+/// Returns the diagnostics for a file.
+///
+/// Since a file can be part of multiple configuration sets, its diagnostics are combined from every set that it's in,
+/// along with any diagnostics that span multiple sets. Identical diagnostics reported by different sets are only
+/// included once. When there's more than one set, the source of each compiler diagnostic is labeled with the names of
+/// the sets that reported it, so users can tell which set a diagnostic came from.
+pub fn diagnostics_for_file(server_state: &ServerState, uri: &Url) -> Vec<tower_lsp::lsp_types::Diagnostic> {
+    let mut lsp_diagnostics = Vec::new();
+    let mut reporting_sets: Vec<Vec<&str>> = Vec::new();
+    for set in &server_state.configuration_sets {
+        for diagnostic in set.diagnostics.get(uri).into_iter().flatten() {
+            match lsp_diagnostics.iter().position(|other| other == diagnostic) {
+                Some(index) => reporting_sets[index].push(set.name.as_str()),
+                None => {
+                    lsp_diagnostics.push(diagnostic.clone());
+                    reporting_sets.push(vec![set.name.as_str()]);
+                }
+            }
+        }
+    }
+
+    if server_state.configuration_sets.len() > 1 {
+        for (diagnostic, set_names) in lsp_diagnostics.iter_mut().zip(reporting_sets) {
+            if diagnostic.source.as_deref() == Some("slicec") {
+                diagnostic.source = Some(format!("slicec ({})", set_names.join(", ")));
+            }
+        }
+    }
+
+    let cross_set_diagnostics = server_state.cross_set_diagnostics.get(uri).into_iter().flatten();
+    let project_file_diagnostics = server_state.project_file_diagnostics.get(uri).into_iter().flatten();
+    for diagnostic in cross_set_diagnostics.chain(project_file_diagnostics) {
+        if !lsp_diagnostics.contains(diagnostic) {
+            lsp_diagnostics.push(diagnostic.clone());
+        }
+    }
+
+    // Clients which can't fade out unnecessary code (like unused definitions) still show the diagnostics themselves.
+    if !server_state.client.supports_unnecessary_tag {
+        for diagnostic in &mut lsp_diagnostics {
+            diagnostic.tags = None;
+        }
+    }
+    lsp_diagnostics
+}
+
+/// Returns the diagnostic report for a single file, in response to a `textDocument/diagnostic` request.
+///
+/// If the file's diagnostics haven't changed since the client's previous pull, the report only says so.
+pub fn document_diagnostic_report(
+    server_state: &ServerState,
+    uri: &Url,
+    previous_result_id: Option<String>,
+) -> DocumentDiagnosticReport {
+    let items = diagnostics_for_file(server_state, uri);
+    let result_id = compute_result_id(&items);
+
+    if previous_result_id.as_ref() == Some(&result_id) {
+        let unchanged_document_diagnostic_report = UnchangedDocumentDiagnosticReport { result_id };
+        DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+            related_documents: None,
+            unchanged_document_diagnostic_report,
+        })
+    } else {
+        let full_document_diagnostic_report = FullDocumentDiagnosticReport { result_id: Some(result_id), items };
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report,
+        })
+    }
+}
+
+/// Returns the diagnostic reports for every file tracked by the server, in response to a `workspace/diagnostic`
+/// request.
+///
+/// Files whose diagnostics haven't changed since the client's previous pull only get an 'unchanged' report. Files which
+/// the client has results for, but which are no longer tracked by the server, get an empty report, so that their
+/// outdated diagnostics are cleared.
+pub fn workspace_diagnostic_report(
+    server_state: &ServerState,
+    previous_result_ids: Vec<PreviousResultId>,
+) -> WorkspaceDiagnosticReport {
+    let mut previous_result_ids = previous_result_ids
+        .into_iter()
+        .map(|previous| (previous.uri, previous.value))
+        .collect::<HashMap<_, _>>();
+
+    let mut tracked_files = server_state.files_with_diagnostics();
+    tracked_files.extend(previous_result_ids.keys().cloned());
+
+    let items = tracked_files
+        .into_iter()
+        .map(|uri| {
+            let items = diagnostics_for_file(server_state, &uri);
+            let result_id = compute_result_id(&items);
+
+            if previous_result_ids.remove(&uri).as_ref() == Some(&result_id) {
+                let unchanged_document_diagnostic_report = UnchangedDocumentDiagnosticReport { result_id };
+                WorkspaceDocumentDiagnosticReport::Unchanged(WorkspaceUnchangedDocumentDiagnosticReport {
+                    uri,
+                    version: None,
+                    unchanged_document_diagnostic_report,
+                })
+            } else {
+                let full_document_diagnostic_report = FullDocumentDiagnosticReport { result_id: Some(result_id), items };
+                WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                    uri,
+                    version: None,
+                    full_document_diagnostic_report,
+                })
+            }
+        })
+        .collect();
+
+    WorkspaceDiagnosticReport { items }
+}
+
+/// Returns every diagnostic that the server currently holds as JSON, for the `slice/diagnostics` request.
+///
+/// Unlike the diagnostics that are published, these are grouped by the configuration set that reported them (without
+/// combining the diagnostics of files that are in multiple sets). The diagnostics which aren't reported by a set (like
+/// problems in project files) are listed separately, under `otherDiagnostics`.
+pub fn diagnostics_report(server_state: &ServerState) -> serde_json::Value {
+    let files_report = |diagnostics: &HashMap<Url, Vec<tower_lsp::lsp_types::Diagnostic>>| {
+        let mut files = diagnostics
+            .iter()
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .map(|(uri, diagnostics)| serde_json::json!({ "uri": uri, "diagnostics": diagnostics }))
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a["uri"].as_str().cmp(&b["uri"].as_str()));
+        files
+    };
+
+    let configuration_sets = server_state
+        .configuration_sets
+        .iter()
+        .map(|set| {
+            serde_json::json!({
+                "name": set.name,
+                "errors": set.count_diagnostics(DiagnosticSeverity::ERROR),
+                "warnings": set.count_diagnostics(DiagnosticSeverity::WARNING),
+                "files": files_report(&set.diagnostics),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut other_diagnostics = server_state.cross_set_diagnostics.clone();
+    for (uri, diagnostics) in &server_state.project_file_diagnostics {
+        other_diagnostics.entry(uri.clone()).or_default().extend(diagnostics.iter().cloned());
+    }
+    serde_json::json!({
+        "configurationSets": configuration_sets,
+        "otherDiagnostics": files_report(&other_diagnostics),
+    })
+}
+
+/// Computes the result ID of a file's diagnostics, which lets the client tell us which diagnostics it already has.
+///
+/// Since the ID is derived from the diagnostics themselves, the same diagnostics always get the same ID, and we don't
+/// need to remember which IDs we've handed out.
+fn compute_result_id(diagnostics: &[tower_lsp::lsp_types::Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .expect("failed to serialize diagnostics")
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 /// Processes a list of diagnostics and updates the publish map with LSP-compatible diagnostics.
 ///
 /// This function filters out any diagnostics that do not have a span or cannot be converted
@@ -50,6 +254,8 @@ pub async fn publish_diagnostics_for_set(
 /// Any diagnostics that do not have a span are returned for further processing.
 pub fn process_diagnostics(
     diagnostics: Vec<slicec::diagnostics::Diagnostic>,
+    compilation_data: &CompilationData,
+    encoding: PositionEncoding,
     publish_map: &mut HashMap<Url, Vec<tower_lsp::lsp_types::Diagnostic>>,
 ) -> Vec<slicec::diagnostics::Diagnostic> {
     let mut spanless_diagnostics = Vec::new();
@@ -57,13 +263,13 @@ pub fn process_diagnostics(
         .into_iter()
         .filter_map(|diagnostic| {
             let span = diagnostic.span().cloned();
-            match try_into_lsp_diagnostic(diagnostic) {
+            match try_into_lsp_diagnostic(diagnostic, compilation_data, encoding) {
                 Ok(lsp_diagnostic) => {
                     // The empty span case is handled by the `try_into_lsp_diagnostic` function.
                     let file = span
                         .expect("If the span was empty, try_into_lsp_diagnostic should have hit the error case")
                         .file;
-                    let uri = convert_slice_path_to_uri(file)?;
+                    let uri = compilation_data.paths.convert_slice_path_to_uri(compilation_data.source_path(&file))?;
                     Some((uri, lsp_diagnostic))
                 }
                 Err(diagnostic) => {
@@ -78,6 +284,50 @@ pub fn process_diagnostics(
     spanless_diagnostics
 }
 
+/// Runs the server's own analyses over a configuration set, and adds any diagnostics they report to the publish map.
+///
+/// These are checks that `slicec` doesn't perform itself, and which are controlled by the user's settings.
+pub fn add_analysis_diagnostics(
+    configuration_set: &ConfigurationSet,
+    server_config: &ServerConfig,
+    publish_map: &mut HashMap<Url, Vec<tower_lsp::lsp_types::Diagnostic>>,
+) {
+    // Unused definitions are found while the set is compiled, since this requires the set's `Ast`.
+    let built_in_slice_path = configuration_set.slice_config.built_in_slice_path(server_config);
+    for (uri, lsp_diagnostic) in &configuration_set.compilation_data.unused_definitions {
+        publish_map.entry(uri.clone()).or_default().push(lsp_diagnostic.clone());
+    }
+
+    if server_config.todo_comments {
+        let todo_comments = find_todo_comments(
+            &configuration_set.compilation_data,
+            built_in_slice_path.as_deref(),
+            &server_config.todo_comment_markers,
+            server_config.position_encoding,
+        );
+        for (uri, lsp_diagnostic) in todo_comments {
+            publish_map.entry(uri).or_default().push(lsp_diagnostic);
+        }
+    }
+
+    // Explain why hover and definitions don't work in files that are too large for them.
+    let degraded_files = &configuration_set.compilation_data.degraded_files;
+    for uri in degraded_files.iter().filter_map(|path| server_config.paths.convert_slice_path_to_uri(path)) {
+        let max_file_size_kb = server_config.max_file_size_kb.unwrap_or_default();
+        publish_map.entry(uri).or_default().push(tower_lsp::lsp_types::Diagnostic {
+            range: Range::default(),
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            code: Some(NumberOrString::String("file-too-large".to_owned())),
+            source: Some("slice-language-server".to_owned()),
+            message: format!(
+                "This file is larger than {max_file_size_kb} KB, so hover and go to definition are disabled for it. \
+                 You can raise this limit with the 'slice.maxFileSizeKB' setting."
+            ),
+            ..tower_lsp::lsp_types::Diagnostic::default()
+        });
+    }
+}
+
 /// Clears the diagnostics for all tracked files in the configuration sets.
 ///
 /// This function iterates over all configuration sets, collects all tracked file URIs,
@@ -89,7 +339,7 @@ pub async fn clear_diagnostics(client: &Client, configuration_sets: &[Configurat
             .compilation_data
             .files
             .keys()
-            .filter_map(convert_slice_path_to_uri)
+            .filter_map(|path| configuration_set.compilation_data.paths.convert_slice_path_to_uri(path))
             .for_each(|uri| {
                 all_tracked_files.insert(uri);
             });
@@ -105,6 +355,8 @@ pub async fn clear_diagnostics(client: &Client, configuration_sets: &[Configurat
 #[allow(clippy::result_large_err)]
 pub fn try_into_lsp_diagnostic(
     diagnostic: Diagnostic,
+    compilation_data: &CompilationData,
+    encoding: PositionEncoding,
 ) -> Result<tower_lsp::lsp_types::Diagnostic, slicec::diagnostics::Diagnostic> {
     let severity = match diagnostic.level() {
         DiagnosticLevel::Error => Some(tower_lsp::lsp_types::DiagnosticSeverity::ERROR),
@@ -114,7 +366,7 @@ pub fn try_into_lsp_diagnostic(
 
     // Map the spans to ranges, if span is none, return the slicec diagnostic
     let range = match diagnostic.span() {
-        Some(span) => span_to_range(span.clone()),
+        Some(span) => compilation_data.span_to_range(span, encoding),
         None => return Err(diagnostic),
     };
 
@@ -123,7 +375,7 @@ pub fn try_into_lsp_diagnostic(
         diagnostic
             .notes()
             .iter()
-            .filter_map(try_into_lsp_diagnostic_related_information)
+            .filter_map(|note| try_into_lsp_diagnostic_related_information(note, compilation_data, encoding))
             .collect(),
     );
 
@@ -143,10 +395,12 @@ pub fn try_into_lsp_diagnostic(
 // A helper function that converts a slicec note into an lsp diagnostic related information
 fn try_into_lsp_diagnostic_related_information(
     note: &Note,
+    compilation_data: &CompilationData,
+    encoding: PositionEncoding,
 ) -> Option<tower_lsp::lsp_types::DiagnosticRelatedInformation> {
     let span = note.span.clone()?;
-    let uri = convert_slice_path_to_uri(&span.file)?;
-    let range = span_to_range(span);
+    let uri = compilation_data.paths.convert_slice_path_to_uri(compilation_data.source_path(&span.file))?;
+    let range = compilation_data.span_to_range(&span, encoding);
 
     Some(DiagnosticRelatedInformation {
         location: Location { uri, range },
@@ -0,0 +1,61 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::ConfigurationSet;
+use crate::slice_ignore::SliceIgnore;
+use crate::utils::find_slice_files;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Discovers plausible configuration sets for a workspace folder that the user hasn't configured any sets for.
+///
+/// Each top-level directory of the folder that contains Slice files (at any depth) gets its own set, so that unrelated
+/// projects (or vendored and generated Slice files) aren't compiled together. Slice files directly inside of the folder
+/// are grouped into one more set. Files which are ignored by the workspace's `.sliceignore` files, or are inside of the
+/// provided `skipped_paths` (the directories that every set already references), are skipped. Directories which only
+/// contain skipped files don't get a set at all.
+///
+/// `folder_path` is the folder the sets are stored under, which is `None` if the workspace only has a single folder.
+pub fn discover_configuration_sets(
+    root_path: &Path,
+    folder_path: Option<&Path>,
+    slice_ignore: &SliceIgnore,
+    skipped_paths: &[PathBuf],
+) -> Vec<ConfigurationSet> {
+    let mut slice_files = Vec::new();
+    find_slice_files(root_path, &mut slice_files);
+
+    // Group the files by the top-level directory they're in. Files directly in the root are grouped under `None`.
+    let mut groups: BTreeMap<Option<PathBuf>, Vec<PathBuf>> = BTreeMap::new();
+    for file in slice_files {
+        if slice_ignore.is_ignored(&file, root_path) || skipped_paths.iter().any(|path| file.starts_with(path)) {
+            continue;
+        }
+        let Ok(relative_path) = file.strip_prefix(root_path) else {
+            continue;
+        };
+        let mut components = relative_path.components();
+        let top_level_path = components.next().map(|component| PathBuf::from(component.as_os_str()));
+        match components.next() {
+            Some(_) => {
+                groups.entry(top_level_path).or_default();
+            }
+            None => groups.entry(None).or_default().push(relative_path.to_owned()),
+        }
+    }
+
+    // Sets use relative paths, so that they can be written into the user's settings as-is. Directories are listed on
+    // their own (since every file in them is included), whereas files in the root are listed individually.
+    let sets = groups
+        .into_iter()
+        .map(|(directory, files)| match directory {
+            Some(directory) => {
+                let name = directory.display().to_string();
+                ConfigurationSet::discovered_set(name, vec![directory], folder_path)
+            }
+            None => ConfigurationSet::discovered_set(".".to_owned(), files, folder_path),
+        })
+        .collect::<Vec<_>>();
+    debug!("Discovered {} configuration set(s) in '{}'", sets.len(), root_path.display());
+    sets
+}
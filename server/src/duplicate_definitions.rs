@@ -0,0 +1,175 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::ServerConfig;
+use crate::configuration_set::ConfigurationSet;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::utils::path_starts_with;
+use slicec::grammar::{Class, CustomType, Entity, Enum, Exception, Interface, Struct, TypeAlias};
+use slicec::slice_file::SliceFile;
+use slicec::visitor::Visitor;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Range, Url,
+};
+
+/// Finds all the definitions which have the same fully-qualified identifier as a definition in a different
+/// configuration set, and returns a warning diagnostic for each of them, grouped by file.
+///
+/// `slicec` already reports redefinitions within a single set, but it can't see across sets. Even though these
+/// definitions don't conflict in the language server, the code generated from the sets will collide downstream.
+/// Definitions in the built-in Slice files are skipped, since they're included in most sets, and sets which share the
+/// same file aren't reported either, since that's still a single definition.
+pub fn find_duplicate_definitions(
+    configuration_sets: &[ConfigurationSet],
+    server_config: &ServerConfig,
+) -> HashMap<Url, Vec<Diagnostic>> {
+    // Collect the definitions of every set, grouped by their fully-qualified identifier.
+    let mut definitions_by_identifier: HashMap<String, Vec<Definition>> = HashMap::new();
+    for (set_index, configuration_set) in configuration_sets.iter().enumerate() {
+        // Each set can use its own built-in Slice files.
+        let built_in_slice_path = configuration_set.slice_config.built_in_slice_path(server_config);
+        let built_in_slice_path = built_in_slice_path.as_deref();
+        for (path, file) in &configuration_set.compilation_data.files {
+            if built_in_slice_path.is_some_and(|built_in_path| path_starts_with(path, Path::new(built_in_path))) {
+                continue;
+            }
+
+            for named_definition in &file.definitions {
+                let definition = Definition {
+                    set_index,
+                    kind: named_definition.kind,
+                    path: path.clone(),
+                    range: named_definition.range,
+                };
+                let identifier = named_definition.identifier.clone();
+                definitions_by_identifier.entry(identifier).or_default().push(definition);
+            }
+        }
+    }
+
+    let set_name = |set_index: usize| format!("'{}'", configuration_sets[set_index].name);
+
+    let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+    for (identifier, definitions) in &definitions_by_identifier {
+        for definition in definitions {
+            let duplicates = definitions
+                .iter()
+                .filter(|other| other.set_index != definition.set_index && other.path != definition.path)
+                .collect::<Vec<_>>();
+            if duplicates.is_empty() {
+                continue;
+            }
+            let Some(uri) = server_config.paths.convert_slice_path_to_uri(&definition.path) else {
+                continue;
+            };
+
+            // A set can have several duplicates (in different files), but each set is only named once.
+            let other_sets = duplicates.iter().map(|other| other.set_index).collect::<BTreeSet<_>>();
+            let other_sets = other_sets.into_iter().map(set_name).collect::<Vec<_>>();
+            let message = format!(
+                "{} '{identifier}' is defined in both {} and {}, so the code generated for these configuration sets may collide",
+                definition.kind,
+                set_name(definition.set_index),
+                other_sets.join(", "),
+            );
+
+            let related_information = duplicates
+                .iter()
+                .filter_map(|other| {
+                    Some(DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: server_config.paths.convert_slice_path_to_uri(&other.path)?,
+                            range: other.range,
+                        },
+                        message: format!("'{identifier}' is also defined here, in {}", set_name(other.set_index)),
+                    })
+                })
+                .collect();
+
+            diagnostics.entry(uri).or_default().push(Diagnostic {
+                range: definition.range,
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String("cross-set-redefinition".to_owned())),
+                source: Some("slice-language-server".to_owned()),
+                message,
+                related_information: Some(related_information),
+                ..Diagnostic::default()
+            });
+        }
+    }
+    diagnostics
+}
+
+/// A definition that could conflict with definitions in other configuration sets.
+struct Definition {
+    set_index: usize,
+    kind: &'static str,
+    path: PathBuf,
+    range: Range,
+}
+
+/// A top-level definition of a file, found while indexing the file.
+#[derive(Debug)]
+pub struct NamedDefinition {
+    /// The fully-qualified identifier of the definition.
+    pub identifier: String,
+    pub kind: &'static str,
+    /// The range of the definition's identifier.
+    pub range: Range,
+}
+
+/// Visits a file and returns each of its top-level definitions, with their ranges counted in the provided encoding.
+pub fn collect_named_definitions(file: &SliceFile, encoding: PositionEncoding) -> Vec<NamedDefinition> {
+    let positions = PositionConverter::new(&file.raw_text, encoding);
+    let mut visitor = DefinitionCollector { positions, definitions: Vec::new() };
+    file.visit_with(&mut visitor);
+
+    visitor.definitions
+}
+
+/// Collects the fully-qualified identifier of every top-level definition in the visited file.
+struct DefinitionCollector<'a> {
+    positions: PositionConverter<'a>,
+    definitions: Vec<NamedDefinition>,
+}
+
+impl DefinitionCollector<'_> {
+    fn add_definition(&mut self, entity: &dyn Entity) {
+        self.definitions.push(NamedDefinition {
+            identifier: entity.parser_scoped_identifier(),
+            kind: entity.kind(),
+            range: self.positions.span_to_range(&entity.raw_identifier().span),
+        });
+    }
+}
+
+impl Visitor for DefinitionCollector<'_> {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.add_definition(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.add_definition(class_def);
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.add_definition(exception_def);
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        self.add_definition(interface_def);
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.add_definition(enum_def);
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.add_definition(custom_type_def);
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        self.add_definition(type_alias_def);
+    }
+}
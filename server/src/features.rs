@@ -0,0 +1,61 @@
+// Copyright (c) ZeroC, Inc.
+
+use tower_lsp::lsp_types::request::{GotoDefinition, HoverRequest, References, Request};
+use tower_lsp::lsp_types::{Registration, TextDocumentRegistrationOptions, Unregistration};
+
+/// The language features which users can turn off with the 'slice.features' settings.
+///
+/// Disabled features aren't advertised to the client. If the client supports registering a feature dynamically, the
+/// server registers it after initialization instead of advertising it, so that it can be turned on and off at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Hover,
+    Definition,
+    References,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [Feature::Hover, Feature::Definition, Feature::References];
+
+    /// Returns the name of this feature's setting, under 'slice.features'.
+    pub fn setting_name(self) -> &'static str {
+        match self {
+            Feature::Hover => "hover",
+            Feature::Definition => "definition",
+            Feature::References => "references",
+        }
+    }
+
+    /// Returns the LSP method of the request that this feature handles.
+    fn method(self) -> &'static str {
+        match self {
+            Feature::Hover => HoverRequest::METHOD,
+            Feature::Definition => GotoDefinition::METHOD,
+            Feature::References => References::METHOD,
+        }
+    }
+
+    /// The ID that this feature is registered with, so that it can be unregistered later.
+    fn registration_id(self) -> String {
+        format!("slice-feature-{}", self.setting_name())
+    }
+
+    /// Creates the registration of this feature, which the server sends to the client with `client/registerCapability`.
+    /// Since it doesn't specify a document selector, the client uses the one that it started the server with.
+    pub fn create_registration(self) -> Registration {
+        let options = TextDocumentRegistrationOptions { document_selector: None };
+        Registration {
+            id: self.registration_id(),
+            method: self.method().to_owned(),
+            register_options: Some(serde_json::to_value(options).expect("failed to serialize registration options")),
+        }
+    }
+
+    /// Creates the unregistration for the registration returned by [`Feature::create_registration`].
+    pub fn create_unregistration(self) -> Unregistration {
+        Unregistration {
+            id: self.registration_id(),
+            method: self.method().to_owned(),
+        }
+    }
+}
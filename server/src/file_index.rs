@@ -0,0 +1,58 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::duplicate_definitions::{collect_named_definitions, NamedDefinition};
+use crate::hover::{index_hover_messages, HoverEntry};
+use crate::jump_definition::{index_definition_links, DefinitionLink};
+use crate::position_encoding::PositionEncoding;
+use crate::utils::hash_text;
+use slicec::slice_file::SliceFile;
+
+/// Everything the server needs to know about a compiled Slice file, once its compilation has finished.
+///
+/// A `SliceFile` references definitions which are owned by the `Ast` it was compiled into, and neither of them can be
+/// shared between threads. So instead of keeping them around, the data that the language features need is extracted
+/// into an index right after compilation (on the thread that compiled the file), and the `Ast` is dropped. The index
+/// owns all of its data, so it can be used from any thread.
+#[derive(Debug)]
+pub struct FileIndex {
+    /// The text of the file, as it was compiled.
+    pub raw_text: String,
+    /// A hash of the file's text, which is used to tell whether the file changed since it was compiled.
+    pub content_hash: u64,
+    /// The top-level definitions of the file.
+    pub definitions: Vec<NamedDefinition>,
+    /// The spans of the file which can be hovered over, in the order they were visited in.
+    pub hover_entries: Vec<HoverEntry>,
+    /// The spans of the file which refer to a definition, in the order they were visited in.
+    pub definition_links: Vec<DefinitionLink>,
+}
+
+impl FileIndex {
+    /// Indexes the provided file. If `is_degraded` is set, the file is too large for the per-request features, so the
+    /// data they need isn't collected. The ranges of the file's definitions are counted in the provided encoding.
+    pub fn new(file: &SliceFile, is_degraded: bool, encoding: PositionEncoding) -> Self {
+        let (hover_entries, definition_links) = if is_degraded {
+            (Vec::new(), Vec::new())
+        } else {
+            (index_hover_messages(file), index_definition_links(file))
+        };
+        FileIndex {
+            raw_text: file.raw_text.clone(),
+            content_hash: hash_text(&file.raw_text),
+            definitions: collect_named_definitions(file, encoding),
+            hover_entries,
+            definition_links,
+        }
+    }
+
+    /// Returns roughly how many bytes of memory this index holds (see [`CompilationData::estimated_size`]).
+    ///
+    /// [`CompilationData::estimated_size`]: crate::configuration_set::CompilationData::estimated_size
+    pub fn estimated_size(&self) -> usize {
+        let definitions = self.definitions.iter().map(|d| size_of::<NamedDefinition>() + d.identifier.len());
+        let hover_entries = self.hover_entries.iter().map(HoverEntry::estimated_size);
+        let definition_links = self.definition_links.iter().map(DefinitionLink::estimated_size);
+        let data_size = definitions.chain(hover_entries).chain(definition_links).sum::<usize>();
+        size_of::<Self>() + self.raw_text.len() + data_size
+    }
+}
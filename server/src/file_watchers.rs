@@ -0,0 +1,80 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::project_file::PROJECT_FILE_NAME;
+use crate::utils::PathResolver;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::notification::{DidChangeWatchedFiles, Notification};
+use tower_lsp::lsp_types::{
+    DidChangeWatchedFilesRegistrationOptions, FileSystemWatcher, GlobPattern, OneOf, Registration, RelativePattern,
+    Unregistration,
+};
+
+/// The ID that the server's file watchers are registered with, so that they can be replaced later.
+const REGISTRATION_ID: &str = "slice-file-watchers";
+
+/// Creates the registration of the server's file watchers, which the server sends to the client with
+/// `client/registerCapability`. This always includes a watcher for the Slice files in the workspace, so that the server
+/// is notified about changes made outside of the editor (like switching branches), and a watcher for the workspace's
+/// project files, since clients other than our extension don't watch them for us.
+///
+/// The client only tells the server about changes to files inside of the workspace, so the provided paths (which are
+/// outside of it) are watched separately. Directories are watched for changes to any Slice files inside of them, and
+/// files are watched for changes to themselves. If the client supports relative patterns, the watchers are based at
+/// each path. Otherwise, they use absolute patterns, which not every client can watch outside of the workspace.
+pub fn create_registration(
+    external_paths: &[PathBuf],
+    paths: &PathResolver,
+    relative_pattern_support: bool,
+) -> Registration {
+    let workspace_watcher = FileSystemWatcher {
+        glob_pattern: GlobPattern::String("**/*.slice".to_owned()),
+        kind: None,
+    };
+    let project_file_watcher = FileSystemWatcher {
+        glob_pattern: GlobPattern::String(format!("**/{PROJECT_FILE_NAME}")),
+        kind: None,
+    };
+    let external_watchers = external_paths
+        .iter()
+        .filter_map(|path| create_watcher(path, paths, relative_pattern_support));
+    let watchers = [workspace_watcher, project_file_watcher]
+        .into_iter()
+        .chain(external_watchers)
+        .collect();
+    let options = DidChangeWatchedFilesRegistrationOptions { watchers };
+
+    Registration {
+        id: REGISTRATION_ID.to_owned(),
+        method: DidChangeWatchedFiles::METHOD.to_owned(),
+        register_options: Some(serde_json::to_value(options).expect("failed to serialize registration options")),
+    }
+}
+
+/// Creates the unregistration for the file watchers returned by [`create_registration`].
+pub fn create_unregistration() -> Unregistration {
+    Unregistration {
+        id: REGISTRATION_ID.to_owned(),
+        method: DidChangeWatchedFiles::METHOD.to_owned(),
+    }
+}
+
+/// Creates a watcher for a single path, or returns `None` if the path can't be watched.
+fn create_watcher(path: &Path, paths: &PathResolver, relative_pattern_support: bool) -> Option<FileSystemWatcher> {
+    let (base_path, pattern) = match path.is_dir() {
+        true => (path, "**/*.slice".to_owned()),
+        false => (path.parent()?, path.file_name()?.to_string_lossy().into_owned()),
+    };
+
+    let glob_pattern = match relative_pattern_support {
+        true => GlobPattern::Relative(RelativePattern {
+            base_uri: OneOf::Right(paths.convert_slice_path_to_uri(base_path)?),
+            pattern,
+        }),
+        // Glob patterns always use forward slashes, even on Windows.
+        false => {
+            let base_path = base_path.display().to_string().replace('\\', "/");
+            GlobPattern::String(format!("{}/{pattern}", base_path.trim_end_matches('/')))
+        }
+    };
+    Some(FileSystemWatcher { glob_pattern, kind: None })
+}
@@ -0,0 +1,76 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::requests::{Diagnostics, DumpAst, Health, ProjectInfo, ReloadConfigurations, ResolveType};
+use crate::requests::{SetBuiltInSlicePath, ValidateFile};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tower_lsp::lsp_types::request::{
+    DocumentDiagnosticRequest, ExecuteCommand, GotoDefinition, HoverRequest, Initialize, References, Request, Shutdown,
+    WorkspaceDiagnosticRequest,
+};
+
+/// The methods of the requests that the server counts, which is every request that it handles.
+const COUNTED_METHODS: [&str; 16] = [
+    Initialize::METHOD,
+    Shutdown::METHOD,
+    GotoDefinition::METHOD,
+    HoverRequest::METHOD,
+    References::METHOD,
+    DocumentDiagnosticRequest::METHOD,
+    WorkspaceDiagnosticRequest::METHOD,
+    ExecuteCommand::METHOD,
+    DumpAst::METHOD,
+    ProjectInfo::METHOD,
+    ResolveType::METHOD,
+    ValidateFile::METHOD,
+    Diagnostics::METHOD,
+    SetBuiltInSlicePath::METHOD,
+    ReloadConfigurations::METHOD,
+    Health::METHOD,
+];
+
+/// The counters behind `slice/health`: when the server started, and how many requests of each method it has handled.
+///
+/// Every request handler records itself here, so the counters are plain atomics, which handlers can bump without
+/// taking any lock (or waiting on one).
+#[derive(Debug)]
+pub struct ServerHealth {
+    start_time: Instant,
+    request_counts: [AtomicU64; COUNTED_METHODS.len()],
+}
+
+impl Default for ServerHealth {
+    fn default() -> Self {
+        ServerHealth {
+            start_time: Instant::now(),
+            request_counts: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl ServerHealth {
+    /// Records that a request with the provided method was handled. Methods that aren't counted are ignored.
+    pub fn record_request(&self, method: &str) {
+        if let Some(index) = COUNTED_METHODS.iter().position(|counted_method| *counted_method == method) {
+            self.request_counts[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns how long ago the server started.
+    pub fn uptime(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Returns how many requests of each method were handled, keyed by method. Methods which were never requested are
+    /// left out.
+    pub fn request_counts(&self) -> Value {
+        let counts = COUNTED_METHODS
+            .iter()
+            .zip(&self.request_counts)
+            .map(|(method, count)| (*method, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .map(|(method, count)| (method.to_owned(), Value::from(count)));
+        Value::Object(counts.collect::<Map<_, _>>())
+    }
+}
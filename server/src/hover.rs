@@ -1,33 +1,54 @@
 // Copyright (c) ZeroC, Inc.
 
-use crate::utils::position_to_location;
+use crate::file_index::FileIndex;
 use slicec::{
     grammar::{Element, Enum, Primitive, Symbol, TypeRef, TypeRefDefinition, Types},
-    slice_file::{Location, SliceFile},
+    slice_file::{Location, SliceFile, Span},
     visitor::Visitor,
 };
-use tower_lsp::lsp_types::Position;
 
-pub fn get_hover_message(file: &SliceFile, position: Position) -> Option<String> {
-    let mut visitor = HoverVisitor::new(position_to_location(position));
+/// The hover message for the source code within a span, found while indexing a file.
+#[derive(Debug)]
+pub struct HoverEntry {
+    span: Span,
+    message: Option<String>,
+    /// Whether this entry's message replaces the message of an earlier entry that also contains the hovered position,
+    /// instead of only being used if none of the earlier entries had a message.
+    replaces_previous: bool,
+}
+
+impl HoverEntry {
+    /// Returns roughly how many bytes of memory this entry holds, including its message and the path in its span.
+    pub fn estimated_size(&self) -> usize {
+        let message_size = self.message.as_ref().map_or(0, String::len);
+        size_of::<Self>() + self.span.file.len() + message_size
+    }
+}
+
+pub fn get_hover_message(file: &FileIndex, search_location: &Location) -> Option<String> {
+    // Entries are stored in the order they were visited in, so we can replay them like the visitor would've found them.
+    let mut found_message = None;
+    for entry in file.hover_entries.iter().filter(|entry| search_location.is_within(&entry.span)) {
+        if entry.replaces_previous || found_message.is_none() {
+            found_message = entry.message.clone();
+        }
+    }
+    found_message
+}
+
+/// Visits a file and returns an entry for each span of it that can be hovered over.
+pub fn index_hover_messages(file: &SliceFile) -> Vec<HoverEntry> {
+    let mut visitor = HoverVisitor { entries: Vec::new() };
     file.visit_with(&mut visitor);
 
-    visitor.found_message
+    visitor.entries
 }
 
 struct HoverVisitor {
-    pub search_location: Location,
-    pub found_message: Option<String>,
+    pub entries: Vec<HoverEntry>,
 }
 
 impl HoverVisitor {
-    pub fn new(search_location: Location) -> Self {
-        HoverVisitor {
-            search_location,
-            found_message: None,
-        }
-    }
-
     fn construct_message<T: Element + ?Sized>(
         primitive: &Primitive,
         typeref: &TypeRef<T>,
@@ -66,25 +87,18 @@ impl HoverVisitor {
 impl Visitor for HoverVisitor {
     fn visit_enum(&mut self, enum_def: &Enum) {
         if let Some(underlying) = &enum_def.underlying {
-            if !&self.search_location.is_within(underlying.span()) {
+            let TypeRefDefinition::Patched(definition) = &underlying.definition else {
                 return;
-            }
-            if let Some(underlying_def) = &enum_def.underlying {
-                let TypeRefDefinition::Patched(definition) = &underlying_def.definition else {
-                    return;
-                };
-                self.found_message = Some(Self::construct_message(definition.borrow(), underlying))
-            }
+            };
+            self.entries.push(HoverEntry {
+                span: underlying.span().clone(),
+                message: Some(Self::construct_message(definition.borrow(), underlying)),
+                replaces_previous: true,
+            });
         }
     }
 
     fn visit_type_ref(&mut self, typeref: &TypeRef) {
-        if self.found_message.is_some() {
-            return;
-        }
-        if !&self.search_location.is_within(typeref.span()) {
-            return;
-        }
         let TypeRefDefinition::Patched(type_def) = &typeref.definition else {
             return;
         };
@@ -93,6 +107,10 @@ impl Visitor for HoverVisitor {
             Types::Primitive(x) => Some(Self::construct_message(x, typeref)),
             _ => None,
         };
-        self.found_message = type_description;
+        self.entries.push(HoverEntry {
+            span: typeref.span().clone(),
+            message: type_description,
+            replaces_previous: false,
+        });
     }
 }
@@ -1,6 +1,6 @@
 // Copyright (c) ZeroC, Inc.
 
-use crate::utils::position_to_location;
+use crate::file_index::FileIndex;
 use slicec::{
     grammar::{
         Class, Commentable, CustomType, Entity, Enum, Enumerator, Exception, Field, Identifier,
@@ -10,30 +10,52 @@ use slicec::{
     slice_file::{Location, SliceFile, Span},
     visitor::Visitor,
 };
-use tower_lsp::lsp_types::Position;
 
-pub fn get_definition_span(file: &SliceFile, position: Position) -> Option<Span> {
-    let mut visitor = JumpVisitor::new(position_to_location(position));
+/// A span of source code which refers to a definition (the identifier of which is at `target`), found while indexing a
+/// file.
+#[derive(Debug)]
+pub struct DefinitionLink {
+    pub span: Span,
+    pub target: Option<Span>,
+}
+
+impl DefinitionLink {
+    /// Returns roughly how many bytes of memory this link holds, including the paths in its spans.
+    pub fn estimated_size(&self) -> usize {
+        size_of::<Self>() + self.span.file.len() + self.target.as_ref().map_or(0, |target| target.file.len())
+    }
+}
+
+/// Returns the span of the reference at the provided location, along with the span of the definition it refers to.
+/// Clients which support `LocationLink`s highlight the entire reference while the user is navigating.
+pub fn get_definition_link<'a>(file: &'a FileIndex, search_location: &Location) -> Option<(&'a Span, &'a Span)> {
+    // Links are stored in the order they were visited in, and later links take precedence over earlier ones (this is
+    // how nested type references, like the element type of a sequence, are resolved to the innermost reference).
+    let mut found_link = None;
+    for link in file.definition_links.iter().filter(|link| search_location.is_within(&link.span)) {
+        found_link = Some(link);
+    }
+    found_link.and_then(|link| Some((&link.span, link.target.as_ref()?)))
+}
+
+/// Visits a file and returns a link for each span of it that refers to a definition.
+pub fn index_definition_links(file: &SliceFile) -> Vec<DefinitionLink> {
+    let mut visitor = JumpVisitor { links: Vec::new() };
     file.visit_with(&mut visitor);
 
-    visitor.found_span
+    visitor.links
 }
 
 struct JumpVisitor {
-    pub search_location: Location,
-    pub found_span: Option<Span>,
+    pub links: Vec<DefinitionLink>,
 }
 
 impl JumpVisitor {
-    pub fn new(search_location: Location) -> Self {
-        JumpVisitor {
-            search_location,
-            found_span: None,
-        }
+    fn add_link(&mut self, span: &Span, target: Option<Span>) {
+        self.links.push(DefinitionLink { span: span.clone(), target });
     }
 
-    // This function checks to see if the search location is within the span of the comment
-    // and if it is, it checks to see if the comment contains a link to an entity.
+    // This function checks to see if the comment contains a link to an entity.
     fn check_comment(&mut self, commentable: &dyn Commentable) {
         if let Some(comment) = commentable.comment() {
             if let Some(overview) = &comment.overview {
@@ -59,7 +81,7 @@ impl JumpVisitor {
         }
     }
 
-    // This function checks to see if the search location is within the span of the link
+    // This function adds a link for each link to an entity in the message.
     fn check_message_links(&mut self, message: &Message) {
         for component in &message.value {
             if let MessageComponent::Link(l) = component {
@@ -68,17 +90,15 @@ impl JumpVisitor {
         }
     }
 
-    // This function checks to see if the search location is within the span of the entity
-    // and if it is, it sets the found_span to the span of the entity
+    // This function checks to see if the link resolved to an entity,
+    // and if it did, it adds a link from the provided span to the span of the entity
     fn check_and_set_span<T: Entity + ?Sized>(
         &mut self,
         linked_entity_result: Result<&T, &Identifier>,
         span: &Span,
     ) {
         if let Ok(entity) = linked_entity_result {
-            if self.search_location.is_within(span) {
-                self.found_span = Some(entity.raw_identifier().span().clone());
-            };
+            self.add_link(span, Some(entity.raw_identifier().span().clone()));
         }
     }
 }
@@ -91,36 +111,30 @@ impl Visitor for JumpVisitor {
     fn visit_class(&mut self, class_def: &Class) {
         self.check_comment(class_def);
         if let Some(base_ref) = &class_def.base {
-            if self.search_location.is_within(&base_ref.span) {
-                let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
-                    return;
-                };
-                self.found_span = Some(type_def.borrow().raw_identifier().span().clone());
-            }
+            let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
+                return;
+            };
+            self.add_link(&base_ref.span, Some(type_def.borrow().raw_identifier().span().clone()));
         }
     }
 
     fn visit_exception(&mut self, exception_def: &Exception) {
         self.check_comment(exception_def);
         if let Some(base_ref) = &exception_def.base {
-            if self.search_location.is_within(&base_ref.span) {
-                let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
-                    return;
-                };
-                self.found_span = Some(type_def.borrow().raw_identifier().span().clone());
-            }
+            let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
+                return;
+            };
+            self.add_link(&base_ref.span, Some(type_def.borrow().raw_identifier().span().clone()));
         }
     }
 
     fn visit_interface(&mut self, interface_def: &Interface) {
         self.check_comment(interface_def);
         for base_ref in &interface_def.bases {
-            if self.search_location.is_within(&base_ref.span) {
-                let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
-                    continue;
-                };
-                self.found_span = Some(type_def.borrow().raw_identifier().span().clone());
+            let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
+                continue;
             };
+            self.add_link(&base_ref.span, Some(type_def.borrow().raw_identifier().span().clone()));
         }
     }
 
@@ -131,12 +145,10 @@ impl Visitor for JumpVisitor {
     fn visit_operation(&mut self, operation_def: &Operation) {
         self.check_comment(operation_def);
         for base_ref in &operation_def.exception_specification {
-            if self.search_location.is_within(&base_ref.span) {
-                let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
-                    continue;
-                };
-                self.found_span = Some(type_def.borrow().raw_identifier().span().clone());
+            let TypeRefDefinition::Patched(type_def) = &base_ref.definition else {
+                continue;
             };
+            self.add_link(&base_ref.span, Some(type_def.borrow().raw_identifier().span().clone()));
         }
     }
 
@@ -157,18 +169,16 @@ impl Visitor for JumpVisitor {
     }
 
     fn visit_type_ref(&mut self, typeref_def: &TypeRef) {
-        if self.search_location.is_within(typeref_def.span()) {
-            let TypeRefDefinition::Patched(type_def) = &typeref_def.definition else {
-                return;
-            };
-            let entity_def: Option<&dyn Entity> = match type_def.borrow().concrete_type() {
-                Types::Struct(x) => Some(x),
-                Types::Class(x) => Some(x),
-                Types::Enum(x) => Some(x),
-                Types::CustomType(x) => Some(x),
-                _ => None,
-            };
-            self.found_span = entity_def.map(|e| e.raw_identifier().span().clone());
-        }
+        let TypeRefDefinition::Patched(type_def) = &typeref_def.definition else {
+            return;
+        };
+        let entity_def: Option<&dyn Entity> = match type_def.borrow().concrete_type() {
+            Types::Struct(x) => Some(x),
+            Types::Class(x) => Some(x),
+            Types::Enum(x) => Some(x),
+            Types::CustomType(x) => Some(x),
+            _ => None,
+        };
+        self.add_link(typeref_def.span(), entity_def.map(|e| e.raw_identifier().span().clone()));
     }
 }
@@ -0,0 +1,2963 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::actions::{path_problem_popups, perform_popup_action, ActionEffect, PopupAction};
+use crate::api_docs::generate_docs;
+use crate::ast_dump::dump_ast;
+use crate::cancellation::{CancellableService, CancellationToken};
+use crate::commands::{document_argument, granularity_argument, path_argument, position_argument, ServerCommand};
+use crate::compilation::compile_sets;
+use crate::configuration::{CompilationTrigger, ServerConfig};
+use crate::configuration_set::{CompilationTiming, ConfigurationSet, FinishedCompilation, InspectedFile};
+use crate::configuration_set::PendingCompilation;
+use crate::dependency_graph::{DependencyGraph, Granularity};
+use crate::diagnostic_handler::{clear_diagnostics, publish_diagnostics, store_diagnostics_for_set};
+use crate::diagnostic_handler::{diagnostics_report, document_diagnostic_report, workspace_diagnostic_report};
+use crate::features::Feature;
+use crate::health::ServerHealth;
+use crate::hover::get_hover_message;
+use crate::jump_definition::get_definition_link;
+use crate::reference_index::find_references;
+use crate::logging::Logger;
+use crate::new_file::{new_file_contents, new_file_target, NewFileTarget, DEFAULT_FILE_NAME};
+use crate::notifications::{DidCompile, DidCompileParams, EditConfiguration, NotificationKind, OpenSettings};
+use crate::notifications::{SaveConfigurations, ShowNotification, ShowNotificationParams};
+use crate::server_state::{OpenDocument, ServerState, SetOverlap, UnsavedDocument};
+use crate::progress::CompilationProgress;
+use crate::requests::{DumpAst, DumpAstParams, ProjectInfo, ResolveType, ResolveTypeResult, RESOLVE_TYPE_VERSION};
+use crate::requests::{Diagnostics, DiagnosticsParams, ValidateFile, ValidateFileParams, ValidateFileResult};
+use crate::requests::{SetBuiltInSlicePath, SetBuiltInSlicePathParams, SetBuiltInSlicePathResult};
+use crate::requests::{Health, ReloadConfigurations, ReloadConfigurationsResult};
+use crate::validation::PendingValidation;
+use crate::workspace_stats::{count_files, workspace_stats};
+use crate::resolve_type::{resolve_symbol, resolve_type_id};
+use crate::position_encoding::PositionConverter;
+use crate::project_file::PROJECT_FILE_NAME;
+use crate::slice_ignore::SLICE_IGNORE_FILE_NAME;
+use crate::lsif::write_lsif_dump;
+use crate::sarif::sarif_log;
+use crate::trace::{log_trace, trace_request};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::{ErrorKind, Write};
+use std::ops::DerefMut;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::RwLock;
+use tokio::task::AbortHandle;
+use tower_lsp::lsp_types::notification::{Notification, SetTrace};
+use tower_lsp::lsp_types::request::Request;
+use tower_lsp::{jsonrpc::Error, lsp_types::*, Client, ClientSocket, LanguageServer, LspService, Server};
+use tracing::{debug, info, warn};
+use utils::{apply_content_changes, find_slice_files, is_virtual_document_path, real_path, ResolvedPath};
+
+mod actions;
+mod api_docs;
+mod ast_dump;
+mod cancellation;
+mod client_capabilities;
+mod commands;
+mod compilation;
+#[cfg(test)]
+mod compilation_gate;
+mod configuration;
+mod configuration_set;
+mod dependency_graph;
+mod diagnostic_handler;
+mod discovery;
+mod duplicate_definitions;
+mod features;
+mod file_index;
+mod file_watchers;
+mod health;
+mod hover;
+mod jump_definition;
+mod logging;
+mod lsif;
+mod new_file;
+mod notifications;
+mod overlay;
+mod position_encoding;
+mod progress;
+mod reference_index;
+mod project_file;
+mod requests;
+mod resolve_type;
+mod sarif;
+mod server_state;
+mod slice_ignore;
+mod symbol_cache;
+#[cfg(test)]
+#[path = "../tests/support/client.rs"]
+mod test_client;
+mod todo_comments;
+mod trace;
+mod unused_definitions;
+mod utils;
+mod validation;
+mod workspace_stats;
+
+/// How long to wait after a document is edited before compiling it, so that we don't compile after every keystroke.
+const COMPILATION_DELAY: Duration = Duration::from_millis(300);
+
+/// Creates the language server's service, and the socket that it sends its requests and notifications to the client
+/// through. These can be served over any transport with [`tower_lsp::Server`] (which is what [`serve`] does).
+///
+/// [`serve`] also wraps the service, so that cancelled requests stop the work they moved onto blocking threads (see
+/// `cancellation.rs`). Services served without it are still cancelled by `tower-lsp`, but only up to their handlers.
+pub fn build_service() -> (LspService<SliceLanguageServer>, ClientSocket) {
+    // `tower-lsp` doesn't handle `$/setTrace` itself, so it's registered as a custom method,
+    // along with the server's own requests.
+    LspService::build(|client| {
+        let logger = Logger::init(client.clone());
+        info!("{}", version_message());
+        SliceLanguageServer::new(client, logger)
+    })
+    .custom_method(SetTrace::METHOD, SliceLanguageServer::set_trace)
+    .custom_method(DumpAst::METHOD, SliceLanguageServer::dump_ast)
+    .custom_method(ProjectInfo::METHOD, SliceLanguageServer::project_info)
+    .custom_method(ResolveType::METHOD, SliceLanguageServer::resolve_type)
+    .custom_method(ValidateFile::METHOD, SliceLanguageServer::validate_file)
+    .custom_method(Diagnostics::METHOD, SliceLanguageServer::diagnostics)
+    .custom_method(SetBuiltInSlicePath::METHOD, SliceLanguageServer::set_built_in_slice_path)
+    .custom_method(ReloadConfigurations::METHOD, SliceLanguageServer::reload_configurations)
+    .custom_method(Health::METHOD, SliceLanguageServer::health)
+    .finish()
+}
+
+/// Runs the language server until the client disconnects (or asks it to exit), reading the client's messages from
+/// `input`, and writing the server's messages to `output`.
+///
+/// Any streams can be used, like the server's standard input and output, a TCP connection, or an in-memory stream (from
+/// `tokio::io::duplex`) for running the server in the same process as its client.
+pub async fn serve(input: impl AsyncRead + Unpin, output: impl AsyncWrite) {
+    let (service, socket) = build_service();
+    Server::new(input, output, socket).serve(CancellableService::new(service)).await;
+}
+
+/// Returns the capabilities that the server advertises to a client which initializes it with the provided parameters.
+pub fn server_capabilities(params: InitializeParams) -> ServerCapabilities {
+    let mut server_state = ServerState::default();
+    server_state.update_from_initialize_params(params);
+    SliceLanguageServer::capabilities(&server_state)
+}
+
+/// Returns the versions of the server and of the Slice compiler (`slicec`) that it was built with, which is printed by
+/// `--version`, and logged when the server starts.
+pub fn version_message() -> String {
+    format!(
+        "slice-language-server {} (slicec {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("SLICEC_VERSION"),
+    )
+}
+
+/// The Slice language server, which implements [`LanguageServer`] for a single client.
+///
+/// Use [`build_service`] (or [`serve`]) to create one, since the server relies on custom methods which aren't part of
+/// [`LanguageServer`].
+#[derive(Clone)]
+pub struct SliceLanguageServer {
+    client: Client,
+    /// The state of the server, shared between the handlers of every request and notification.
+    ///
+    /// Handlers which only read the state (like hover) take a read guard, so they can run concurrently. Everything that
+    /// changes the state takes a write guard, and makes all of its changes before releasing (or downgrading) it. This
+    /// way, readers never observe a half-updated set: a set's configuration, compilation data, and diagnostics are
+    /// always replaced under a single write guard (see `install_compilations`), and compilations themselves run without
+    /// holding any guard at all.
+    server_state: Arc<RwLock<ServerState>>,
+    logger: Logger,
+    /// The timers that delay compilations (like until the user stops typing). These are aborted when shutting down.
+    timers: Arc<Mutex<Vec<AbortHandle>>>,
+    /// Each compilation holds a read guard on this while it runs, so that shutting down can wait for them to finish.
+    compilations_in_flight: Arc<RwLock<()>>,
+    /// The counters behind `slice/health`, which every request handler records itself in.
+    health: Arc<ServerHealth>,
+}
+
+impl SliceLanguageServer {
+    fn new(client: tower_lsp::Client, logger: Logger) -> Self {
+        let server_state = Arc::new(RwLock::new(ServerState::default()));
+        Self {
+            client,
+            server_state,
+            logger,
+            timers: Arc::default(),
+            compilations_in_flight: Arc::default(),
+            health: Arc::default(),
+        }
+    }
+
+    /// Spawns a task which runs the provided future once the compilation delay has elapsed, unless the server is shut
+    /// down before then.
+    fn spawn_timer(&self, future: impl Future<Output = ()> + Send + 'static) {
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(COMPILATION_DELAY).await;
+            future.await;
+        });
+
+        let mut timers = self.timers.lock().unwrap_or_else(PoisonError::into_inner);
+        timers.retain(|timer| !timer.is_finished());
+        timers.push(task.abort_handle());
+    }
+
+    /// Pulls the 'slice' settings from the client with `workspace/configuration`.
+    /// Returns `None` if the client fails to provide them, or doesn't have any.
+    async fn pull_settings(&self) -> Option<serde_json::Value> {
+        let item = ConfigurationItem { scope_uri: None, section: Some("slice".to_owned()) };
+        match self.client.configuration(vec![item]).await {
+            Ok(mut values) => values.pop().filter(serde_json::Value::is_object),
+            Err(error) => {
+                warn!("Failed to pull the 'slice' settings from the client: {error}");
+                None
+            }
+        }
+    }
+
+    /// Applies the user's logging settings, which take effect immediately.
+    fn update_logger(&self, server_config: &ServerConfig) {
+        self.logger.set_level(server_config.log_level);
+
+        let log_file = server_config.log_file.as_deref();
+        if let Err(error) = self.logger.set_log_file(log_file) {
+            let path = log_file.unwrap_or(Path::new("")).display();
+            warn!("Failed to open the log file '{path}': {error}");
+        }
+    }
+
+    fn capabilities(server_state: &ServerState) -> ServerCapabilities {
+        // Features which the client can register dynamically are registered after initialization instead.
+        let is_advertised = |feature| {
+            !server_state.server_config.disabled_features.contains(&feature)
+                && !server_state.client.dynamic_registration_features.contains(&feature)
+        };
+        let definition_provider = is_advertised(Feature::Definition).then_some(OneOf::Left(true));
+        let hover_provider = is_advertised(Feature::Hover).then_some(HoverProviderCapability::Simple(true));
+        let references_provider = is_advertised(Feature::References).then_some(OneOf::Left(true));
+
+        let execute_command_provider = Some(ExecuteCommandOptions {
+            commands: ServerCommand::ALL.iter().map(|command| command.name().to_owned()).collect(),
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        });
+
+        let diagnostic_provider = Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+            identifier: Some("slicec".to_owned()),
+            inter_file_dependencies: true,
+            workspace_diagnostics: true,
+            ..Default::default()
+        }));
+
+        let text_document_sync = Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                    include_text: Some(false),
+                })),
+                ..Default::default()
+            },
+        ));
+
+        let workspace = Some(WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: Some(OneOf::Left(true)),
+            }),
+            file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                did_rename: Some(FileOperationRegistrationOptions {
+                    filters: vec![
+                        rename_filter("**/*.slice", FileOperationPatternKind::File),
+                        rename_filter("**/*", FileOperationPatternKind::Folder),
+                    ],
+                }),
+                ..Default::default()
+            }),
+        });
+
+        ServerCapabilities {
+            position_encoding: Some(server_state.server_config.position_encoding.kind()),
+            text_document_sync,
+            workspace,
+            definition_provider,
+            hover_provider,
+            references_provider,
+            diagnostic_provider,
+            execute_command_provider,
+            ..Default::default()
+        }
+    }
+
+    /// Converts the URI of a document into the path that it's tracked under (see
+    /// [`PathResolver::url_to_document_path`](utils::PathResolver::url_to_document_path)).
+    async fn document_path(&self, uri: &Url) -> Option<PathBuf> {
+        self.server_state.read().await.server_config.paths.url_to_document_path(uri)
+    }
+
+    async fn handle_file_change(&self, file_path: &Path) {
+        info!("File '{}' changed", file_path.display());
+
+        // If there isn't a workspace, the file is compiled on its own.
+        self.server_state.write().await.add_stand_alone_set(file_path);
+
+        // Saving a document without changing it (or a formatter rewriting it to the same text) is a no-op.
+        if self.server_state.read().await.is_file_unchanged(file_path) {
+            debug!("'{}' didn't change since it was last compiled, so it isn't re-compiled", file_path.display());
+            return;
+        }
+
+        self.compile_matching_sets(|set, _| set.contains_file(file_path)).await;
+    }
+
+    /// Handles the `slice.compile` command: clears the caches of each configuration set that contains the document,
+    /// then re-compiles them, and re-publishes their diagnostics. Returns the names of the sets, along with how many
+    /// errors and warnings they have.
+    async fn compile_document(&self, uri: &Url) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let file_path = self
+            .document_path(uri)
+            .await
+            .ok_or_else(|| Error::invalid_params(format!("'{uri}' isn't a Slice document")))?;
+        info!("Re-compiling the configuration sets of '{}'", file_path.display());
+
+        // Explicit scope to ensure the server state lock guard is dropped before we start compilation.
+        {
+            let mut server_guard = self.server_state.write().await;
+            server_guard.add_stand_alone_set(&file_path);
+            for set in server_guard.configuration_sets.iter_mut() {
+                if set.contains_file(&file_path) {
+                    set.invalidate();
+                }
+            }
+        }
+        self.compile_matching_sets(|set, _| set.contains_file(&file_path)).await;
+
+        let server_guard = self.server_state.read().await;
+        let sets = server_guard
+            .configuration_sets
+            .iter()
+            .filter(|set| set.contains_file(&file_path))
+            .collect::<Vec<_>>();
+        let count_severity = |severity| sets.iter().map(|set| set.count_diagnostics(severity)).sum::<usize>();
+        Ok(json!({
+            "configurationSets": sets.iter().map(|set| set.name.as_str()).collect::<Vec<_>>(),
+            "errors": count_severity(DiagnosticSeverity::ERROR),
+            "warnings": count_severity(DiagnosticSeverity::WARNING),
+        }))
+    }
+
+    /// Handles the `slice.showFileConfiguration` command: reports each configuration set that contains the document
+    /// (including disabled sets), along with why it contains the document.
+    async fn show_file_configuration(&self, uri: &Url) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let file_path = self
+            .document_path(uri)
+            .await
+            .ok_or_else(|| Error::invalid_params(format!("'{uri}' isn't a Slice document")))?;
+
+        let server_guard = self.server_state.read().await;
+        let server_config = &server_guard.server_config;
+        let sets = server_guard
+            .configuration_sets
+            .iter()
+            .chain(&server_guard.disabled_sets)
+            .filter_map(|set| set.file_membership(server_config, &file_path))
+            .collect::<Vec<_>>();
+        debug!("'{}' is part of {} configuration set(s)", file_path.display(), sets.len());
+        Ok(json!({ "uri": uri, "configurationSets": sets }))
+    }
+
+    /// Handles the `slice.openBuiltInSliceFiles` command, by listing the Slice files in the directory that the built-in
+    /// Slice files are read from. Each file is listed with its name, its path relative to the directory, and its URI,
+    /// along with the package it's in: the top-level directory that holds it (like `WellKnownTypes`), if it's in one.
+    /// Files are sorted by their relative paths, so files in the same package are listed together.
+    async fn built_in_slice_files(&self) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let built_in_slice_path = self.server_state.read().await.server_config.built_in_slice_path.clone();
+        let Some(directory) = built_in_slice_path.map(PathBuf::from) else {
+            let message = "the built-in Slice files aren't available, since 'slice.builtInSlicePath' isn't set";
+            return Err(Error::invalid_params(message));
+        };
+        if !directory.is_dir() {
+            let message = format!("the built-in Slice files' directory '{}' doesn't exist", directory.display());
+            return Err(Error::invalid_params(message));
+        }
+
+        // The directory could be large (or on a slow drive), so it's searched on a blocking thread.
+        let search_directory = directory.clone();
+        let search = move || {
+            let mut paths = Vec::new();
+            find_slice_files(&search_directory, &mut paths);
+            paths
+        };
+        let paths = tokio::task::spawn_blocking(search).await.map_err(|error| {
+            warn!("Failed to list the built-in Slice files in '{}': {error}", directory.display());
+            Error::internal_error()
+        })?;
+
+        let server_guard = self.server_state.read().await;
+        let files = paths
+            .iter()
+            .filter_map(|path| {
+                let relative_path = path.strip_prefix(&directory).ok()?;
+                let mut components = relative_path.components();
+                let name = components.next_back()?.as_os_str().to_string_lossy();
+                let package = components.next().map(|package| package.as_os_str().to_string_lossy());
+                Some(json!({
+                    "name": name,
+                    "package": package,
+                    "relativePath": relative_path.to_string_lossy().replace('\\', "/"),
+                    "uri": server_guard.server_config.paths.convert_slice_path_to_uri(path)?,
+                }))
+            })
+            .collect::<Vec<_>>();
+        debug!("Found {} built-in Slice file(s) in '{}'", files.len(), directory.display());
+        Ok(json!({ "path": directory, "files": files }))
+    }
+
+    /// Handles the `slice.exportSarif` command: writes every diagnostic to a SARIF file at the provided path (see
+    /// [`sarif_log`]). Sets which were compiled already aren't re-compiled, but sets whose compilation was deferred are
+    /// compiled first, so that every set's diagnostics are included. Returns the path, and how many results it holds.
+    async fn export_sarif(&self, path: &Path) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.compile_matching_sets(|set, _| !set.is_compiled()).await;
+        drop(self.compilations_in_flight.write().await);
+
+        let sarif_log = sarif_log(&*self.server_state.read().await);
+        let result_count = sarif_log["runs"][0]["results"].as_array().map_or(0, Vec::len);
+        let contents = serde_json::to_string_pretty(&sarif_log).map_err(|_| Error::internal_error())?;
+        if let Err(error) = std::fs::write(path, contents) {
+            warn!("Failed to write SARIF file '{}': {error}", path.display());
+            return Err(Error::invalid_params(format!("failed to write '{}': {error}", path.display())));
+        }
+
+        info!("Exported {result_count} diagnostic(s) to '{}'", path.display());
+        Ok(json!({ "path": path, "results": result_count }))
+    }
+
+    /// Handles the `slice.exportIndex` command: writes an LSIF index of every configuration set to the provided path
+    /// (see [`write_lsif_dump`]). Like with `slice.exportSarif`, only sets whose compilation was deferred are compiled
+    /// first. Returns the path, along with how many documents, entities, and ranges the index holds.
+    async fn export_index(&self, path: &Path) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.compile_matching_sets(|set, _| !set.is_compiled()).await;
+        drop(self.compilations_in_flight.write().await);
+
+        let server_guard = self.server_state.read().await;
+        let dump = std::fs::File::create(path)
+            .and_then(|file| write_lsif_dump(&server_guard, std::io::BufWriter::new(file)));
+        let summary = match dump {
+            Ok(summary) => summary,
+            Err(error) => {
+                warn!("Failed to write LSIF index '{}': {error}", path.display());
+                return Err(Error::invalid_params(format!("failed to write '{}': {error}", path.display())));
+            }
+        };
+
+        info!("Exported an index of {} document(s) to '{}'", summary.documents, path.display());
+        Ok(json!({
+            "path": path,
+            "documents": summary.documents,
+            "entities": summary.entities,
+            "ranges": summary.ranges,
+        }))
+    }
+
+    /// Handles the `slice.exportDependencyGraph` command: writes the dependency graph of every configuration set to the
+    /// provided path (see [`DependencyGraph`]). The graph is written as JSON if the path ends with `.json`, and in the
+    /// DOT language otherwise. Like with `slice.exportSarif`, only sets whose compilation was deferred are compiled
+    /// first. Returns the path, along with how many nodes and edges the graph has.
+    async fn export_dependency_graph(
+        &self,
+        path: &Path,
+        granularity: Granularity,
+    ) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.compile_matching_sets(|set, _| !set.is_compiled()).await;
+        drop(self.compilations_in_flight.write().await);
+
+        let graph = DependencyGraph::new(&*self.server_state.read().await, granularity);
+        let contents = match path.extension().is_some_and(|extension| extension == "json") {
+            true => serde_json::to_string_pretty(&graph.to_json()).map_err(|_| Error::internal_error())?,
+            false => graph.to_dot(),
+        };
+        if let Err(error) = std::fs::write(path, contents) {
+            warn!("Failed to write dependency graph '{}': {error}", path.display());
+            return Err(Error::invalid_params(format!("failed to write '{}': {error}", path.display())));
+        }
+
+        let (nodes, edges) = graph.size();
+        info!("Exported a dependency graph of {nodes} node(s) and {edges} edge(s) to '{}'", path.display());
+        Ok(json!({ "path": path, "nodes": nodes, "edges": edges }))
+    }
+
+    /// Handles the `slice.compileAll` command: clears the caches of every configuration set, then re-compiles them all,
+    /// and re-publishes their diagnostics. Returns how long the compilation took, along with how long each set took to
+    /// compile, how many files it has, and how many errors and warnings it has.
+    async fn compile_all(&self) -> serde_json::Value {
+        // Wait for any compilations that are already running to finish, so that their results don't get interleaved
+        // with ours, and so that they don't skew our timings. Compilations are queued in the order they're requested.
+        drop(self.compilations_in_flight.write().await);
+        info!("Re-compiling every configuration set");
+
+        for set in self.server_state.write().await.configuration_sets.iter_mut() {
+            set.invalidate();
+        }
+        let start_time = Instant::now();
+        let timings = self.compile_matching_sets(|_, _| true).await;
+        let elapsed = start_time.elapsed();
+
+        let server_guard = self.server_state.read().await;
+        let sets = timings
+            .iter()
+            .map(|timing| {
+                let set = server_guard.configuration_sets.iter().find(|set| set.name == timing.set_name);
+                let errors = set.map_or(0, |set| set.count_diagnostics(DiagnosticSeverity::ERROR));
+                let warnings = set.map_or(0, |set| set.count_diagnostics(DiagnosticSeverity::WARNING));
+                info!(
+                    "Compiled configuration set '{}' in {:?}: {} file(s), {errors} error(s), {warnings} warning(s)",
+                    timing.set_name, timing.elapsed, timing.file_count,
+                );
+                json!({
+                    "name": timing.set_name,
+                    "milliseconds": timing.elapsed.as_millis() as u64,
+                    "files": timing.file_count,
+                    "errors": errors,
+                    "warnings": warnings,
+                })
+            })
+            .collect::<Vec<_>>();
+        info!("Compiled {} configuration set(s) in {elapsed:?}", sets.len());
+        json!({ "milliseconds": elapsed.as_millis() as u64, "configurationSets": sets })
+    }
+
+    /// Compiles each configuration set that matches the provided predicate, and publishes their diagnostics.
+    /// Returns how long each set took to compile (sets which were skipped, since they didn't change, aren't included).
+    async fn compile_matching_sets(
+        &self,
+        is_match: impl Fn(&ConfigurationSet, &ServerConfig) -> bool,
+    ) -> Vec<CompilationTiming> {
+        let _in_flight = self.compilations_in_flight.read().await;
+        let mut server_guard = self.server_state.write().await;
+        if server_guard.is_shutting_down {
+            return Vec::new();
+        }
+        let ServerState {
+            configuration_sets,
+            server_config,
+            unsaved_documents,
+            client,
+            trace,
+            ..
+        } = server_guard.deref_mut();
+
+        // Snapshot each matching configuration set, then compile them without holding the lock.
+        let pending_compilations = configuration_sets
+            .iter_mut()
+            .filter(|set| is_match(set, server_config))
+            .map(|set| set.prepare_compilation(server_config, unsaved_documents))
+            .collect::<Vec<_>>();
+        let max_concurrent_compilations = server_config.max_concurrent_compilations;
+        // Compiling a single set (like after an edit) is usually quick, so the client isn't asked to show its progress.
+        let show_progress = client.supports_work_done_progress && pending_compilations.len() > 1;
+        let trace = *trace;
+        drop(server_guard);
+        let finished_compilations =
+            run_compilations(&self.client, pending_compilations, max_concurrent_compilations, show_progress, trace)
+                .await;
+        let timings = finished_compilations
+            .iter()
+            .filter(|compilation| !compilation.is_unchanged())
+            .map(FinishedCompilation::timing)
+            .collect();
+
+        // The client stops listening once the server is shut down, so there's no point in publishing anything.
+        let mut server_guard = self.server_state.write().await;
+        if server_guard.is_shutting_down {
+            return Vec::new();
+        }
+        let mut publish_uris = HashSet::new();
+        let mut compiled_sets = Vec::new();
+        let mut spanless_diagnostics =
+            install_compilations(&mut server_guard, finished_compilations, &mut publish_uris, &mut compiled_sets);
+
+        // Multiple sets can report the same configuration error, but we only want to notify the user once.
+        spanless_diagnostics.dedup_by(|(_, d1), (_, d2)| d1.message() == d2.message());
+
+        // Re-run the checks that span multiple configuration sets, since any set's compilation can affect them.
+        publish_uris.extend(server_guard.update_cross_set_diagnostics());
+
+        // Publish the diagnostics for each file. Everything has been updated by now, so readers can be let in.
+        debug!("Publishing diagnostics for the compiled configuration sets.");
+        let server_guard = server_guard.downgrade();
+        publish_diagnostics(&self.client, &server_guard, publish_uris).await;
+
+        drop(server_guard);
+        self.notify_compiled_sets(compiled_sets).await;
+        self.report_configuration_errors(spanless_diagnostics);
+        timings
+    }
+
+    /// Registers file watchers with the client for any search paths outside of the workspace, replacing the watchers
+    /// that were previously registered. This does nothing if the paths haven't changed, or if the client doesn't support
+    /// registering file watchers.
+    async fn update_file_watchers(&self) {
+        let mut server_guard = self.server_state.write().await;
+        if !server_guard.client.supports_file_watcher_registration {
+            return;
+        }
+
+        let external_paths = server_guard.external_search_paths();
+        let was_registered = server_guard.file_watchers_registered;
+        if was_registered && external_paths == server_guard.watched_external_paths {
+            return;
+        }
+        server_guard.file_watchers_registered = true;
+        server_guard.watched_external_paths = external_paths.clone();
+        let relative_pattern_support = server_guard.client.supports_relative_file_watchers;
+        let paths = &server_guard.server_config.paths;
+        let registration = file_watchers::create_registration(&external_paths, paths, relative_pattern_support);
+        drop(server_guard);
+
+        if was_registered {
+            let unregistration = file_watchers::create_unregistration();
+            if let Err(error) = self.client.unregister_capability(vec![unregistration]).await {
+                warn!("Failed to unregister file watchers: {error}");
+            }
+        }
+
+        if let Err(error) = self.client.register_capability(vec![registration]).await {
+            warn!("Failed to register file watchers: {error}");
+        }
+    }
+
+    /// Registers the enabled features with the client, and unregisters the disabled ones, so that turning a feature on or
+    /// off takes effect immediately. This only applies to the features which the client can register dynamically. The
+    /// rest are advertised at initialization, so changes to them only take effect when the server is restarted.
+    async fn update_feature_registrations(&self) {
+        let mut server_guard = self.server_state.write().await;
+        let ServerState { server_config, client, registered_features, .. } = server_guard.deref_mut();
+
+        let mut registrations = Vec::new();
+        let mut unregistrations = Vec::new();
+        for &feature in client.dynamic_registration_features.iter() {
+            let is_enabled = !server_config.disabled_features.contains(&feature);
+            let is_registered = registered_features.contains(&feature);
+            match (is_enabled, is_registered) {
+                (true, false) => {
+                    registrations.push(feature.create_registration());
+                    registered_features.insert(feature);
+                }
+                (false, true) => {
+                    unregistrations.push(feature.create_unregistration());
+                    registered_features.remove(&feature);
+                }
+                _ => {}
+            }
+        }
+        drop(server_guard);
+
+        if !unregistrations.is_empty() {
+            if let Err(error) = self.client.unregister_capability(unregistrations).await {
+                warn!("Failed to unregister features: {error}");
+            }
+        }
+        if !registrations.is_empty() {
+            if let Err(error) = self.client.register_capability(registrations).await {
+                warn!("Failed to register features: {error}");
+            }
+        }
+    }
+
+    /// Queues Slice files which were changed outside of the editor to be compiled, and compiles the configuration sets
+    /// containing them after a short delay.
+    ///
+    /// Operations like switching branches can change hundreds of files at once, which the client might report across
+    /// many notifications. Any changes reported during the delay are compiled together, in a single batch.
+    async fn queue_file_changes(&self, file_paths: Vec<PathBuf>) {
+        let mut server_guard = self.server_state.write().await;
+        let is_batch_scheduled = !server_guard.pending_file_changes.is_empty();
+        server_guard.pending_file_changes.extend(file_paths);
+        drop(server_guard);
+
+        if is_batch_scheduled {
+            return;
+        }
+
+        let backend = self.clone();
+        self.spawn_timer(async move {
+            let pending_file_changes = std::mem::take(&mut backend.server_state.write().await.pending_file_changes);
+            let changed_paths = pending_file_changes.into_iter().collect::<Vec<_>>();
+            info!("{} Slice file(s) changed outside of the editor", changed_paths.len());
+
+            // Sets whose compilation is deferred are compiled from disk once they're needed anyways.
+            backend
+                .compile_matching_sets(|set, server_config| {
+                    !set.is_deferred(server_config) && set.contains_any_file(&changed_paths)
+                })
+                .await;
+        });
+    }
+
+    /// Compiles the configuration sets containing an edited document, once the user has stopped typing for a moment.
+    ///
+    /// If the document is edited again before the delay has elapsed, this compilation is skipped, since the compilation
+    /// scheduled by the newer edit will include its changes.
+    fn schedule_compilation(&self, file_path: PathBuf, version: i32) {
+        let backend = self.clone();
+        self.spawn_timer(async move {
+            let server_guard = backend.server_state.read().await;
+            let is_latest_version = server_guard
+                .unsaved_documents
+                .get(&file_path)
+                .is_some_and(|document| document.version == version);
+            drop(server_guard);
+
+            if is_latest_version {
+                backend.handle_file_change(&file_path).await;
+            }
+        });
+    }
+
+    /// Checks whether the provided file is part of any configuration set, and if it isn't, notifies the user and offers
+    /// to add the file's folder to their configuration.
+    async fn notify_if_unconfigured(&self, file_path: &Path) {
+        let server_guard = self.server_state.read().await;
+        let ServerState {
+            configuration_sets,
+            disabled_sets,
+            server_config,
+            suppress_unconfigured_file_notifications,
+            ..
+        } = &*server_guard;
+
+        // Without a workspace, there's no configuration to add the file to.
+        if *suppress_unconfigured_file_notifications
+            || server_config.workspace_root_paths.is_empty()
+            || configuration_sets.iter().any(|set| set.contains_file(file_path))
+        {
+            return;
+        }
+
+        // Files in disabled sets are configured, the user just turned their set off.
+        if let Some(set) = disabled_sets.iter().find(|set| set.contains_file(file_path)) {
+            info!("'{}' is only part of the disabled configuration set '{}'", file_path.display(), set.name);
+            return;
+        }
+
+        let Some(folder) = file_path.parent() else { return };
+        let folder = configuration_path(&server_config.workspace_root_paths, folder);
+
+        let message = format!(
+            "'{}' isn't part of any configured Slice project, so it won't be compiled.",
+            file_path.display(),
+        );
+        let params = ShowNotificationParams {
+            uri: server_config.paths.convert_slice_path_to_uri(file_path),
+            kind: Some(NotificationKind::FileNotInSet),
+            ..ShowNotificationParams::new(message, notifications::MessageType::Info)
+        };
+        let actions = vec![
+            PopupAction::AddToConfiguration(folder),
+            PopupAction::SuppressUnconfiguredFileNotifications,
+        ];
+        self.show_popup_with_actions(params, actions);
+    }
+
+    /// Notifies the user about any errors which weren't tied to a specific file, offering to open their settings,
+    /// since these errors are almost always caused by an incorrect configuration.
+    /// Each error is paired with the name of the configuration set that reported it.
+    fn report_configuration_errors(&self, spanless_diagnostics: Vec<(String, slicec::diagnostics::Diagnostic)>) {
+        for (set_name, diagnostic) in spanless_diagnostics {
+            let message = format!("{} (in configuration set '{set_name}')", diagnostic.message());
+            let params = ShowNotificationParams {
+                code: Some(diagnostic.code().to_owned()),
+                configuration_set: Some(set_name),
+                kind: Some(NotificationKind::ConfigurationError),
+                ..ShowNotificationParams::new(message, notifications::MessageType::Error)
+            };
+            self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+        }
+    }
+
+    /// Handles `$/setTrace`, which the client sends to change how much the server traces its work to it.
+    async fn set_trace(&self, params: SetTraceParams) {
+        info!("Trace level set to '{:?}'", params.value);
+        self.server_state.write().await.trace = params.value;
+    }
+
+    /// Handles `slice/dumpAst`, by re-compiling the configuration set that contains the document, and rendering the
+    /// document's `Ast` as JSON. The `Ast` isn't kept after compiling a set, so this is the only way to get it back.
+    async fn dump_ast(&self, params: DumpAstParams) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.health.record_request(DumpAst::METHOD);
+        self.inspect_document(params.text_document.uri, dump_ast).await
+    }
+
+    /// Handles `slice/resolveType`, by re-compiling the configuration set that contains the document, and resolving the
+    /// symbol at the requested position in the document's `Ast`. Unlike hover, this resolves every kind of symbol, so
+    /// it needs more than what's indexed after compiling a set.
+    async fn resolve_type(&self, params: TextDocumentPositionParams) -> tower_lsp::jsonrpc::Result<ResolveTypeResult> {
+        self.health.record_request(ResolveType::METHOD);
+        let position = params.position;
+        let symbol = self
+            .inspect_document(params.text_document.uri, move |inspected| {
+                // The document was compiled from the client's copy of its text, so the position can be converted as-is.
+                let converter = PositionConverter::new(&inspected.file.raw_text, inspected.position_encoding());
+                resolve_symbol(inspected, &converter.position_to_location(position))
+            })
+            .await?;
+        Ok(ResolveTypeResult { version: RESOLVE_TYPE_VERSION, symbol })
+    }
+
+    /// Handles the `slice.newFile` command, by creating a Slice file with the provided name in the provided directory,
+    /// and returning its URI so the client can open it. The file starts with the 'slice.newFile.header' setting, the
+    /// mode used by most of its set's files, and a module that's inferred from where the directory is within the set
+    /// (see [`new_file_target`]).
+    ///
+    /// If the directory isn't in any configuration set, the file is still created (with a module named after its path
+    /// in the workspace), but the result says that it isn't in a set, and the user is offered to add the directory to
+    /// their configuration, like when opening a file that isn't in any set.
+    async fn new_file(&self, directory: &Path, file_name: &str) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let file_name = match file_name.ends_with(".slice") {
+            true => file_name.to_owned(),
+            false => format!("{file_name}.slice"),
+        };
+        if file_name.contains(['/', '\\']) {
+            return Err(Error::invalid_params(format!("'{file_name}' isn't a valid file name")));
+        }
+        if !directory.is_dir() {
+            return Err(Error::invalid_params(format!("'{}' isn't a directory", directory.display())));
+        }
+        let file_path = directory.join(&file_name);
+
+        let server_guard = self.server_state.read().await;
+        let server_config = &server_guard.server_config;
+        let NewFileTarget { set_name, module_name, mode } = new_file_target(&server_guard, directory, &file_path);
+        let contents = new_file_contents(&server_config.new_file_header, mode, &module_name);
+        let folder = configuration_path(&server_config.workspace_root_paths, directory);
+        let has_workspace = !server_config.workspace_root_paths.is_empty();
+        let uri = server_config.paths.convert_slice_path_to_uri(&file_path);
+        drop(server_guard);
+
+        // The file is only created if it doesn't exist yet, so that a file which was created in the meantime (by the
+        // user, or by another request) is never overwritten.
+        let created_path = file_path.clone();
+        let create = move || {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(&created_path)?;
+            file.write_all(contents.as_bytes())
+        };
+        match tokio::task::spawn_blocking(create).await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) if error.kind() == ErrorKind::AlreadyExists => {
+                return Err(Error::invalid_params(format!("'{}' already exists", file_path.display())));
+            }
+            Ok(Err(error)) => {
+                warn!("Failed to create '{}': {error}", file_path.display());
+                return Err(Error::invalid_params(format!("failed to write '{}': {error}", file_path.display())));
+            }
+            Err(error) => {
+                warn!("Failed to create '{}': {error}", file_path.display());
+                return Err(Error::internal_error());
+            }
+        }
+        info!("Created '{}' with module '{module_name}'", file_path.display());
+
+        // The file isn't compiled by any set, so offer to add its directory to the configuration.
+        if set_name.is_none() && has_workspace {
+            let message = format!(
+                "'{}' isn't part of any configured Slice project, so it won't be compiled.",
+                file_path.display(),
+            );
+            let params = ShowNotificationParams {
+                uri: uri.clone(),
+                kind: Some(NotificationKind::FileNotInSet),
+                ..ShowNotificationParams::new(message, notifications::MessageType::Info)
+            };
+            self.show_popup_with_actions(params, vec![PopupAction::AddToConfiguration(folder)]);
+        }
+
+        Ok(json!({
+            "uri": uri,
+            "module": module_name,
+            "mode": mode,
+            "configurationSet": set_name,
+        }))
+    }
+
+    /// Handles the `slice.copyTypeId` command, by re-compiling the configuration set that contains the document, and
+    /// resolving the type at the provided position in the document's `Ast` (see [`resolve_type_id`]). Fails if there
+    /// isn't a type at the position, so that the client doesn't copy anything.
+    async fn copy_type_id(&self, uri: Url, position: Position) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let type_id = self
+            .inspect_document(uri, move |inspected| {
+                // The document was compiled from the client's copy of its text, so the position can be converted as-is.
+                let converter = PositionConverter::new(&inspected.file.raw_text, inspected.position_encoding());
+                resolve_type_id(inspected, &converter.position_to_location(position))
+            })
+            .await?;
+        type_id.ok_or_else(|| Error::invalid_params("there isn't a type with a type ID at this position"))
+    }
+
+    /// Handles `slice/validateFile`, by compiling the provided text on its own (see [`PendingValidation`]).
+    async fn validate_file(&self, params: ValidateFileParams) -> tower_lsp::jsonrpc::Result<ValidateFileResult> {
+        self.health.record_request(ValidateFile::METHOD);
+        let ValidateFileParams { file_name, text, defines } = params;
+        let server_guard = self.server_state.read().await;
+        let validation = PendingValidation::new(&server_guard.server_config, &file_name, text, defines)
+            .ok_or_else(|| Error::invalid_params(format!("'{file_name}' isn't a valid file name")))?;
+        drop(server_guard);
+
+        // Validations can wait for a blocking thread, so they're skipped if the request is cancelled in the meantime.
+        let token = CancellationToken::current();
+        let validate = move || token.check().map(|_| validation.run());
+        match tokio::task::spawn_blocking(validate).await {
+            Ok(Ok(diagnostics)) => Ok(ValidateFileResult { diagnostics }),
+            Ok(Err(error)) => Err(error),
+            Err(error) => {
+                warn!("Failed to validate '{file_name}': {error}");
+                Err(Error::internal_error())
+            }
+        }
+    }
+
+    /// Handles `slice/diagnostics`, by reporting the diagnostics of each configuration set's most recent compilation
+    /// (see [`diagnostics_report`]). If `recompile` is set, every set is re-compiled first, like `slice.compileAll`.
+    async fn diagnostics(&self, params: DiagnosticsParams) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.health.record_request(Diagnostics::METHOD);
+        if params.recompile {
+            self.compile_all().await;
+        } else {
+            // Wait for any compilations that are already running, so that their diagnostics are included.
+            drop(self.compilations_in_flight.write().await);
+        }
+        Ok(diagnostics_report(&*self.server_state.read().await))
+    }
+
+    /// Handles `slice/setBuiltInSlicePath`, by replacing the server-wide path to the built-in Slice files, then
+    /// re-compiling every set which was already compiled, so that none of them keep referencing the previous path
+    /// (which usually no longer exists). The path must be an existing directory that holds at least one Slice file.
+    async fn set_built_in_slice_path(
+        &self,
+        params: SetBuiltInSlicePathParams,
+    ) -> tower_lsp::jsonrpc::Result<SetBuiltInSlicePathResult> {
+        self.health.record_request(SetBuiltInSlicePath::METHOD);
+        let path = real_path(Path::new(&params.path)).display().to_string();
+        if !Path::new(&path).is_absolute() {
+            return Err(Error::invalid_params(format!("'{path}' isn't an absolute path")));
+        }
+        if !Path::new(&path).is_dir() {
+            return Err(Error::invalid_params(format!("'{path}' isn't an existing directory")));
+        }
+        let mut files = Vec::new();
+        find_slice_files(Path::new(&path), &mut files);
+        if files.is_empty() {
+            return Err(Error::invalid_params(format!("'{path}' doesn't contain any Slice files")));
+        }
+
+        // Wait for any running compilations to finish, since they were started with the previous path.
+        drop(self.compilations_in_flight.write().await);
+        let previous_uris = {
+            let mut server_guard = self.server_state.write().await;
+            info!("Built-in Slice path changed to '{path}', with {} Slice file(s)", files.len());
+            let previous_uris = server_guard.files_with_diagnostics();
+            server_guard.server_config.built_in_slice_path = Some(path.clone());
+            for set in &mut server_guard.configuration_sets {
+                set.invalidate();
+            }
+            server_guard.invalidate_cached_slice_options();
+            previous_uris
+        };
+
+        // Sets which haven't been compiled yet (because of lazy compilation) pick up the new path once they are.
+        self.compile_sets_and_publish_diagnostics(ConfigurationSet::is_compiled, previous_uris).await;
+        self.update_file_watchers().await;
+        Ok(SetBuiltInSlicePathResult { path, files: files.len() })
+    }
+
+    /// Handles the `slice.workspaceStats` command: counts the files, modules, and definitions of every configuration
+    /// set (see [`workspace_stats`]). Definitions are counted from each set's `Ast`, which isn't kept after compiling
+    /// it, so every set is re-compiled for this (like for `slice/dumpAst`), without affecting its own results.
+    async fn workspace_stats(&self, include_built_in: bool) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let in_flight = self.compilations_in_flight.clone().read_owned().await;
+        let mut server_guard = self.server_state.write().await;
+        let ServerState { configuration_sets, server_config, unsaved_documents, .. } = server_guard.deref_mut();
+        let inspections = configuration_sets
+            .iter_mut()
+            .map(|set| {
+                let built_in_slice_path = set.slice_config.built_in_slice_path(server_config);
+                (set.name.clone(), built_in_slice_path, set.prepare_inspection(server_config, unsaved_documents))
+            })
+            .collect::<Vec<_>>();
+        drop(server_guard);
+
+        // The sets are compiled one after the other, which stops once the request is cancelled. The compilations hold the
+        // in-flight guard until they're done (even if the request was cancelled), so that shutting down waits for them.
+        let token = CancellationToken::current();
+        let count_sets = move || {
+            let _in_flight = in_flight;
+            let mut sets = Vec::new();
+            for (name, built_in_slice_path, inspection) in inspections {
+                token.check()?;
+                let files = inspection.inspect_files(|files| {
+                    count_files(files, built_in_slice_path.as_deref(), include_built_in)
+                });
+                sets.push((name, files));
+            }
+            Ok(workspace_stats(sets))
+        };
+        match tokio::task::spawn_blocking(count_sets).await {
+            Ok(result) => result,
+            Err(error) => {
+                warn!("Failed to count the definitions of the configuration sets: {error}");
+                Err(Error::internal_error())
+            }
+        }
+    }
+
+    /// Handles the `slice.generateDocs` command: writes markdown documentation to the provided directory (see
+    /// [`generate_docs`]). The target is either the name of a configuration set, whose files are all documented
+    /// (except for the built-in files), or the URI of a document, which is documented on its own. Like with
+    /// `slice.workspaceStats`, the set is re-compiled for this. Returns the directory, and the names of the pages.
+    async fn generate_docs(
+        &self,
+        directory: &Path,
+        target: &str,
+        include_deprecated: bool,
+    ) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        let document_path = match Url::parse(target) {
+            Ok(uri) => self.document_path(&uri).await,
+            Err(_) => None,
+        };
+
+        let in_flight = self.compilations_in_flight.clone().read_owned().await;
+        let mut server_guard = self.server_state.write().await;
+        let ServerState { configuration_sets, server_config, unsaved_documents, .. } = server_guard.deref_mut();
+        let set = match &document_path {
+            Some(document_path) => configuration_sets.iter_mut().find(|set| set.contains_file(document_path)),
+            None => configuration_sets.iter_mut().find(|set| set.name == target),
+        };
+        let Some(set) = set else {
+            let message = format!("'{target}' isn't a configuration set, or a document in one");
+            return Err(Error::invalid_params(message));
+        };
+        let built_in_slice_path = set.slice_config.built_in_slice_path(server_config);
+        let inspection = set.prepare_inspection(server_config, unsaved_documents);
+        drop(server_guard);
+
+        // If the request is cancelled while the set is compiling, the documentation isn't written.
+        let directory = directory.to_owned();
+        let output_directory = directory.clone();
+        let token = CancellationToken::current();
+        let generate = move || {
+            let _in_flight = in_flight;
+            let pages = inspection.inspect_files(|files| {
+                token.check()?;
+                let is_built_in = |path: &Path| built_in_slice_path.as_ref().is_some_and(|p| path.starts_with(p));
+                let files = files
+                    .iter()
+                    .filter(|(path, _)| match &document_path {
+                        Some(document_path) => path == document_path,
+                        None => !is_built_in(path),
+                    })
+                    .map(|(_, file)| *file)
+                    .collect::<Vec<_>>();
+                Ok(generate_docs(&files, include_deprecated))
+            })?;
+            let write_pages = || {
+                std::fs::create_dir_all(&output_directory)?;
+                for (page_name, contents) in &pages {
+                    std::fs::write(output_directory.join(page_name), contents)?;
+                }
+                std::io::Result::Ok(())
+            };
+            Ok((write_pages(), pages.into_iter().map(|(page_name, _)| page_name).collect::<Vec<_>>()))
+        };
+        match tokio::task::spawn_blocking(generate).await {
+            Ok(Ok((Ok(()), pages))) => {
+                info!("Generated {} documentation page(s) in '{}'", pages.len(), directory.display());
+                Ok(json!({ "path": directory, "pages": pages }))
+            }
+            Ok(Ok((Err(error), _))) => {
+                warn!("Failed to write documentation to '{}': {error}", directory.display());
+                Err(Error::invalid_params(format!("failed to write to '{}': {error}", directory.display())))
+            }
+            Ok(Err(error)) => Err(error),
+            Err(error) => {
+                warn!("Failed to generate documentation for '{target}': {error}");
+                Err(Error::internal_error())
+            }
+        }
+    }
+
+    /// Handles `slice/reloadConfigurations`, by re-reading the configuration from scratch, for when the client missed
+    /// a change (like a project file that was edited outside of the editor). The settings are pulled from the client
+    /// again if it supports it, otherwise the most recent settings are re-applied. Project files and `.sliceignore`
+    /// files are always re-read from disk.
+    ///
+    /// Sets which didn't change keep their previous results, like after `workspace/didChangeConfiguration`, and only
+    /// sets whose inputs changed are re-compiled, so reloading an unchanged configuration leaves every diagnostic as
+    /// it is.
+    async fn reload_configurations(&self) -> tower_lsp::jsonrpc::Result<ReloadConfigurationsResult> {
+        self.health.record_request(ReloadConfigurations::METHOD);
+        info!("Reloading the configuration");
+        let supports_configuration_pull = self.server_state.read().await.client.supports_configuration_pull;
+        let pulled_settings = match supports_configuration_pull {
+            true => self.pull_settings().await,
+            false => None,
+        };
+
+        let (previous_uris, result) = {
+            let mut server_guard = self.server_state.write().await;
+            let previous_uris = server_guard.files_with_diagnostics();
+            let previous_sets = server_guard.configuration_snapshot();
+            let previous_compilation_settings = server_guard.server_config.compilation_settings();
+
+            let settings = pulled_settings.or_else(|| server_guard.settings.clone());
+            server_guard.reload_slice_ignore();
+            server_guard.update_from_settings(settings.as_ref());
+            // Files could have been created or deleted without the server being told, so every set's files are found
+            // again. This doesn't affect which sets are re-compiled, since that depends on what their files contain.
+            server_guard.invalidate_cached_slice_options();
+            self.update_logger(&server_guard.server_config);
+
+            let current_sets = server_guard.configuration_snapshot();
+            let mut result = ReloadConfigurationsResult {
+                settings_changed: previous_compilation_settings != server_guard.server_config.compilation_settings(),
+                ..ReloadConfigurationsResult::default()
+            };
+            for (name, config) in &current_sets {
+                match previous_sets.get(name) {
+                    None => result.added.push(name.clone()),
+                    Some(previous_config) if previous_config != config => result.modified.push(name.clone()),
+                    Some(_) => result.unchanged += 1,
+                }
+            }
+            result.removed = previous_sets.into_keys().filter(|name| !current_sets.contains_key(name)).collect();
+            (previous_uris, result)
+        };
+        self.trace_configuration_reload().await;
+
+        self.report_configuration_problems().await;
+        self.report_set_overlaps().await;
+        self.report_discovered_sets().await;
+        self.report_unconfigured_folders().await;
+        // Sets whose inputs didn't change are skipped (see `PendingCompilation::run`).
+        self.compile_sets_and_publish_diagnostics(|_| true, previous_uris).await;
+        self.update_file_watchers().await;
+        self.update_feature_registrations().await;
+
+        info!(
+            "Reloaded the configuration: {} added, {} removed, {} modified, and {} unchanged set(s)",
+            result.added.len(),
+            result.removed.len(),
+            result.modified.len(),
+            result.unchanged,
+        );
+        Ok(result)
+    }
+
+    /// Re-compiles the configuration set that contains a document, and calls `inspect` with the document's compiled
+    /// file, while its `Ast` is still available (see [`PendingCompilation::inspect_file`]). The document must be part
+    /// of a set which has already been compiled.
+    async fn inspect_document<R: Send + 'static>(
+        &self,
+        uri: Url,
+        inspect: impl FnOnce(&InspectedFile) -> R + Send + 'static,
+    ) -> tower_lsp::jsonrpc::Result<R> {
+        let file_path = self
+            .document_path(&uri)
+            .await
+            .ok_or_else(|| Error::invalid_params(format!("'{uri}' isn't a Slice document")))?;
+
+        let in_flight = self.compilations_in_flight.clone().read_owned().await;
+        let mut server_guard = self.server_state.write().await;
+        let ServerState { configuration_sets, server_config, unsaved_documents, .. } = server_guard.deref_mut();
+        let Some(set) = configuration_sets
+            .iter_mut()
+            .find(|set| set.compilation_data.file(&file_path).is_some())
+        else {
+            let message = format!("'{uri}' isn't part of any compiled configuration set");
+            return Err(Error::invalid_params(message));
+        };
+        debug!("Inspecting '{}' from configuration set '{}'", file_path.display(), set.name);
+        let pending_compilation = set.prepare_inspection(server_config, unsaved_documents);
+        drop(server_guard);
+
+        // If the request is cancelled while the set is compiling, the compiled file isn't inspected.
+        let token = CancellationToken::current();
+        let inspection = move || {
+            let _in_flight = in_flight;
+            token.check()?;
+            let inspect = |inspected: &InspectedFile| token.check().map(|_| inspect(inspected));
+            pending_compilation.inspect_file(&file_path, inspect).transpose()
+        };
+        match tokio::task::spawn_blocking(inspection).await {
+            Ok(Ok(Some(result))) => Ok(result),
+            Ok(Err(error)) => Err(error),
+            Ok(Ok(None)) => Err(Error::invalid_params(format!("'{uri}' is no longer part of its configuration set"))),
+            Err(error) => {
+                warn!("Failed to inspect '{uri}': {error}");
+                Err(Error::internal_error())
+            }
+        }
+    }
+
+    /// Handles `slice/projectInfo`, by describing every configuration set as it was resolved by the server (including
+    /// disabled sets), so that users can see which paths were actually compiled, without digging through the logs.
+    async fn project_info(&self) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.health.record_request(ProjectInfo::METHOD);
+        let server_guard = self.server_state.read().await;
+        let server_config = &server_guard.server_config;
+        let configuration_sets = server_guard
+            .configuration_sets
+            .iter()
+            .chain(&server_guard.disabled_sets)
+            .map(|set| set.project_info(server_config))
+            .collect::<Vec<_>>();
+        Ok(json!({
+            "workspaceRoots": server_config.workspace_root_paths,
+            "builtInSlicePath": server_config.built_in_slice_path,
+            "configurationSets": configuration_sets,
+        }))
+    }
+
+    /// Handles `slice/health`, by reporting the server's vitals: how long it has been running, how much memory its
+    /// compilation data roughly holds, how long each set's last compilation took, how many requests of each method it
+    /// has handled, and whether any compilations are running (or waiting for their delay to elapse).
+    ///
+    /// This only reads counters and sizes, so it's cheap enough to poll. It doesn't wait on running compilations.
+    async fn health(&self) -> tower_lsp::jsonrpc::Result<serde_json::Value> {
+        self.health.record_request(Health::METHOD);
+        // Compilations hold a read guard while they run, so a write guard is only unavailable while one is running.
+        let is_compiling = self.compilations_in_flight.try_write().is_err();
+        let queued_compilations = {
+            let timers = self.timers.lock().unwrap_or_else(PoisonError::into_inner);
+            timers.iter().filter(|timer| !timer.is_finished()).count()
+        };
+
+        let server_guard = self.server_state.read().await;
+        let mut tracked_files = HashSet::new();
+        let configuration_sets = server_guard
+            .configuration_sets
+            .iter()
+            .map(|set| {
+                tracked_files.extend(set.compilation_data.files.keys());
+                json!({
+                    "name": set.name,
+                    "files": set.compilation_data.files.len(),
+                    "estimatedBytes": set.compilation_data.estimated_size(),
+                    "lastCompileMilliseconds": set.last_compilation_time().map(|elapsed| elapsed.as_millis() as u64),
+                    "isEvicted": set.is_evicted(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let estimated_bytes = configuration_sets
+            .iter()
+            .filter_map(|set| set["estimatedBytes"].as_u64())
+            .sum::<u64>();
+
+        Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "uptimeSeconds": self.health.uptime().as_secs(),
+            "configurationSets": configuration_sets,
+            "configurationSetCount": server_guard.configuration_sets.len(),
+            "trackedFiles": tracked_files.len(),
+            "openDocuments": server_guard.open_documents.len(),
+            "estimatedBytes": estimated_bytes,
+            "requests": self.health.request_counts(),
+            "isCompiling": is_compiling,
+            "queuedCompilations": queued_compilations,
+        }))
+    }
+
+    /// Traces that the configuration was reloaded, along with the resolved configuration of each set (when verbose).
+    async fn trace_configuration_reload(&self) {
+        let server_guard = self.server_state.read().await;
+        let configuration_sets = &server_guard.configuration_sets;
+        let message = format!("Reloaded the configuration: {} configuration set(s)", configuration_sets.len());
+        let verbose = || {
+            let describe_set = |set: &ConfigurationSet| {
+                let config = &set.slice_config;
+                format!(
+                    "'{}': paths: {:?}, references: {:?}, excludes: {:?}, defined symbols: {:?}",
+                    set.name,
+                    config.slice_search_paths,
+                    config.reference_paths,
+                    config.exclude_paths,
+                    config.defined_symbols,
+                )
+            };
+            configuration_sets.iter().map(describe_set).collect::<Vec<_>>().join("\n")
+        };
+        log_trace(&self.client, server_guard.trace, message, verbose).await;
+    }
+
+    /// Notifies the user about any problems that were found in their configuration sets, offering to open their settings.
+    /// All of the problems are reported in a single notification, so that the user isn't flooded with popups.
+    async fn report_configuration_problems(&self) {
+        let problems = std::mem::take(&mut self.server_state.write().await.configuration_problems);
+        if problems.is_empty() {
+            return;
+        }
+
+        for problem in &problems {
+            warn!("Problem in the Slice configuration: {problem}");
+        }
+
+        let message = match problems.as_slice() {
+            [problem] => format!("Found a problem in the Slice configuration: {problem}."),
+            _ => format!("Found {} problems in the Slice configuration: {}.", problems.len(), problems.join("; ")),
+        };
+        let params = ShowNotificationParams {
+            kind: Some(NotificationKind::ConfigurationError),
+            ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+        };
+        self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+    }
+
+    /// Warns the user about any configuration sets that include the same paths, since the files in those paths are
+    /// compiled (and report their diagnostics) once for each set. Each overlap is only reported once.
+    async fn report_set_overlaps(&self) {
+        let mut server_guard = self.server_state.write().await;
+        let overlaps = server_guard.find_set_overlaps();
+        if overlaps.is_empty() || overlaps == server_guard.reported_set_overlaps {
+            server_guard.reported_set_overlaps = overlaps;
+            return;
+        }
+
+        let mut descriptions = Vec::new();
+        for SetOverlap { first_set, second_set, paths } in &overlaps {
+            let path_list = paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+            warn!("Configuration sets '{first_set}' and '{second_set}' both include: {}", path_list.join(", "));
+
+            let description = match path_list.as_slice() {
+                [path] => format!("'{first_set}' and '{second_set}' both include '{path}'"),
+                [path, rest @ ..] => format!(
+                    "'{first_set}' and '{second_set}' both include '{path}' (and {} other path(s))",
+                    rest.len(),
+                ),
+                [] => unreachable!("overlaps always have at least one path"),
+            };
+            descriptions.push(description);
+        }
+        server_guard.reported_set_overlaps = overlaps;
+        drop(server_guard);
+
+        let message = format!(
+            "Some configuration sets include the same Slice files, which are compiled once for each set: {}.",
+            descriptions.join("; "),
+        );
+        let params = ShowNotificationParams {
+            kind: Some(NotificationKind::ConfigurationError),
+            ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+        };
+        self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+    }
+
+    /// Tells the user about any configuration sets which were discovered from the workspace's layout, offering to save
+    /// them into their settings. The user is only told again if the discovered sets change.
+    async fn report_discovered_sets(&self) {
+        let mut server_guard = self.server_state.write().await;
+        let discovered_sets = server_guard
+            .configuration_sets
+            .iter()
+            .filter(|set| set.is_discovered())
+            .map(|set| set.name.clone())
+            .collect::<Vec<_>>();
+        if discovered_sets.is_empty() || discovered_sets == server_guard.reported_discovered_sets {
+            server_guard.reported_discovered_sets = discovered_sets;
+            return;
+        }
+
+        let message = format!(
+            "No Slice configuration sets are configured, so these were discovered from the workspace's layout: {}.",
+            discovered_sets.join(", "),
+        );
+        info!("{message}");
+        server_guard.reported_discovered_sets = discovered_sets;
+        drop(server_guard);
+
+        let params = ShowNotificationParams::new(message, notifications::MessageType::Info);
+        let actions = vec![PopupAction::SaveDiscoveredConfigurations, PopupAction::OpenSettings];
+        self.show_popup_with_actions(params, actions);
+    }
+
+    /// Tells the user (once) that some workspace folders don't have any configuration sets, so only the opened files in
+    /// them are compiled, each on its own.
+    async fn report_unconfigured_folders(&self) {
+        let mut server_guard = self.server_state.write().await;
+        if !server_guard.has_unconfigured_folders || server_guard.reported_unconfigured_folders {
+            return;
+        }
+        server_guard.reported_unconfigured_folders = true;
+        drop(server_guard);
+
+        let message = "No Slice configuration sets are configured, so each opened Slice file is compiled on its own. \
+                       Add configuration sets to 'slice.configurations' to compile your Slice files together."
+            .to_owned();
+        let params = ShowNotificationParams::new(message, notifications::MessageType::Info);
+        self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+    }
+
+    /// Shows a popup to the user that offers them the provided actions, and then performs whichever action they select.
+    /// If the client doesn't support popups with actions, this falls back to a plain `custom/showNotification`.
+    ///
+    /// This is done in a separate task, since the user can take arbitrarily long to respond (or never respond at all),
+    /// and we don't want to stall the caller (or hold onto the server state) while waiting.
+    fn show_popup_with_actions(&self, params: ShowNotificationParams, actions: Vec<PopupAction>) {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let items = actions.iter().map(PopupAction::to_message_action_item).collect();
+            let response = backend
+                .client
+                .show_message_request(params.message_type.into(), &params.message, Some(items))
+                .await;
+
+            match response {
+                Ok(selected) => {
+                    if let Some(action) = selected.and_then(|item| PopupAction::from_selection(actions, &item)) {
+                        backend.handle_popup_action(action).await;
+                    }
+                }
+                Err(_) => show_popup(&backend.client, params).await,
+            }
+        });
+    }
+
+    /// Performs an action that the user selected from a popup (see [`perform_popup_action`]), and then carries out
+    /// whatever the action requires of the client.
+    async fn handle_popup_action(&self, action: PopupAction) {
+        let effects = perform_popup_action(action, &mut *self.server_state.write().await);
+        for effect in effects {
+            match effect {
+                ActionEffect::OpenSettings(params) => self.client.send_notification::<OpenSettings>(params).await,
+                ActionEffect::EditConfiguration(params) => {
+                    self.client.send_notification::<EditConfiguration>(params).await;
+                }
+                ActionEffect::SaveConfigurations(params) => {
+                    self.client.send_notification::<SaveConfigurations>(params).await;
+                }
+                ActionEffect::ShowNotification(params) => show_popup(&self.client, params).await,
+                ActionEffect::Recompile => self.compile_and_publish_diagnostics().await,
+            }
+        }
+    }
+
+    /// Loads the cached symbols of each configuration set which hasn't been compiled yet, so that navigation works
+    /// right away, instead of only once the set's first compilation finishes (which can take a while for large sets).
+    async fn load_symbol_caches(&self) {
+        let cache_paths = {
+            let server_guard = self.server_state.read().await;
+            let Some(cache_directory) = &server_guard.cache_directory else {
+                return;
+            };
+            let server_config = &server_guard.server_config;
+            server_guard
+                .configuration_sets
+                .iter()
+                .map(|set| (set.name.clone(), symbol_cache::cache_path(cache_directory, set, server_config)))
+                .collect::<Vec<_>>()
+        };
+
+        let load_caches = move || {
+            cache_paths
+                .into_iter()
+                .filter_map(|(set_name, cache_path)| Some((set_name, symbol_cache::load(&cache_path)?)))
+                .collect::<Vec<_>>()
+        };
+        let cached_data = match tokio::task::spawn_blocking(load_caches).await {
+            Ok(cached_data) => cached_data,
+            Err(error) => {
+                warn!("Failed to load the symbol caches: {error}");
+                return;
+            }
+        };
+
+        // A set could have been compiled while its cache was loading, in which case its cache is already outdated.
+        let mut server_guard = self.server_state.write().await;
+        for (set_name, compilation_data) in cached_data {
+            let set = server_guard.configuration_sets.iter_mut().find(|set| set.name == set_name);
+            if let Some(set) = set.filter(|set| !set.is_compiled()) {
+                set.compilation_data = compilation_data;
+            }
+        }
+    }
+
+    /// Triggers and compilation and publishes any diagnostics that are reported.
+    /// It does this for all configuration sets.
+    async fn compile_and_publish_diagnostics(&self) {
+        self.compile_sets_and_publish_diagnostics(|_| true, HashSet::new()).await;
+    }
+
+    /// Compiles each configuration set that matches the provided predicate (warning the user about any problems with
+    /// its paths), then publishes the diagnostics of the compiled sets, along with the diagnostics of `publish_uris`.
+    async fn compile_sets_and_publish_diagnostics(
+        &self,
+        should_compile: impl Fn(&ConfigurationSet) -> bool,
+        mut publish_uris: HashSet<Url>,
+    ) {
+        let _in_flight = self.compilations_in_flight.read().await;
+        let mut server_guard = self.server_state.write().await;
+        if server_guard.is_shutting_down {
+            return;
+        }
+        let ServerState {
+            configuration_sets,
+            server_config,
+            open_documents,
+            unsaved_documents,
+            client,
+            trace,
+            reported_path_problems,
+            ..
+        } = server_guard.deref_mut();
+
+        info!("Publishing diagnostics for all configuration sets.");
+        let mut sets_to_compile = Vec::new();
+        for configuration_set in configuration_sets.iter_mut() {
+            if !should_compile(configuration_set) {
+                debug!("Configuration set '{}' didn't change, so it isn't re-compiled", configuration_set.name);
+                continue;
+            }
+
+            // With lazy compilation, sets are only compiled once one of their files has been opened.
+            if configuration_set.is_deferred(server_config)
+                && !open_documents
+                    .keys()
+                    .any(|document_path| configuration_set.contains_file(document_path))
+            {
+                let name = &configuration_set.name;
+                info!("Compilation of configuration set '{name}' is deferred until one of its files is opened");
+                continue;
+            }
+
+            // Warn the user about any problems with the set's paths, which they haven't been told about yet.
+            for popup in path_problem_popups(configuration_set, server_config, reported_path_problems) {
+                warn!("{}", popup.params.message);
+                self.show_popup_with_actions(popup.params, popup.actions);
+            }
+
+            // Warn the user if the set's own built-in Slice path doesn't exist, or doesn't contain any Slice files.
+            if let Some(message) = configuration_set.built_in_slice_path_problem(server_config) {
+                warn!("{message}");
+                let params = ShowNotificationParams {
+                    configuration_set: Some(configuration_set.name.clone()),
+                    kind: Some(NotificationKind::ConfigurationError),
+                    ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+                };
+                self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+            }
+
+            // Warn the user about any search paths which are files, but aren't Slice files, since these are skipped.
+            for path in configuration_set.non_slice_file_paths(server_config) {
+                let message = format!(
+                    "The Slice search path '{}' of configuration set '{}' is not a Slice file, so it was skipped.",
+                    path.display(),
+                    configuration_set.name,
+                );
+                warn!("{message}");
+                let params = ShowNotificationParams {
+                    configuration_set: Some(configuration_set.name.clone()),
+                    kind: Some(NotificationKind::ConfigurationError),
+                    ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+                };
+                let actions = vec![PopupAction::RemoveFromConfiguration(path.display().to_string())];
+                self.show_popup_with_actions(params, actions);
+            }
+
+            // Warn the user about any relative paths that lead out of the workspace, since they're probably mistakes.
+            for path in configuration_set.escaping_search_paths(server_config) {
+                let message = format!(
+                    "The Slice search path '{}' of configuration set '{}' is outside of the workspace folder it's \
+                     relative to. Use an absolute path if this is intentional.",
+                    path.display(),
+                    configuration_set.name,
+                );
+                warn!("{message}");
+                let params = ShowNotificationParams {
+                    configuration_set: Some(configuration_set.name.clone()),
+                    kind: Some(NotificationKind::ConfigurationError),
+                    ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+                };
+                self.show_popup_with_actions(params, vec![PopupAction::OpenSettings]);
+            }
+
+            // Warn the user about any paths that use environment variables which aren't set, since these are skipped.
+            for (path, variable) in configuration_set.paths_with_unset_variables() {
+                let message = format!(
+                    "The path '{}' of configuration set '{}' was skipped, since the environment variable '{variable}' isn't set.",
+                    path.display(),
+                    configuration_set.name,
+                );
+                let params = ShowNotificationParams {
+                    configuration_set: Some(configuration_set.name.clone()),
+                    kind: Some(NotificationKind::ConfigurationError),
+                    ..ShowNotificationParams::new(message, notifications::MessageType::Warning)
+                };
+                show_popup(&self.client, params).await;
+            }
+
+            // Explain why any ignored search paths are still being compiled.
+            for path in configuration_set.ignored_search_paths(server_config) {
+                let message = format!(
+                    "'{}' matches the workspace's '{SLICE_IGNORE_FILE_NAME}' file, but is still compiled since it's explicitly listed in the paths of configuration set '{}'.",
+                    path.display(),
+                    configuration_set.name,
+                );
+                info!("{message}");
+            }
+
+            sets_to_compile.push(configuration_set);
+        }
+
+        // Compile each set without holding the lock, then store the diagnostics that were reported during it.
+        let pending_compilations = sets_to_compile
+            .into_iter()
+            .map(|set| set.prepare_compilation(server_config, unsaved_documents))
+            .collect::<Vec<_>>();
+        let max_concurrent_compilations = server_config.max_concurrent_compilations;
+        let show_progress = client.supports_work_done_progress;
+        let trace = *trace;
+        drop(server_guard);
+        let finished_compilations =
+            run_compilations(&self.client, pending_compilations, max_concurrent_compilations, show_progress, trace)
+                .await;
+
+        // The client stops listening once the server is shut down, so there's no point in publishing anything.
+        let mut server_guard = self.server_state.write().await;
+        if server_guard.is_shutting_down {
+            return;
+        }
+        let mut compiled_sets = Vec::new();
+        let spanless_diagnostics =
+            install_compilations(&mut server_guard, finished_compilations, &mut publish_uris, &mut compiled_sets);
+
+        // Re-run the checks that span multiple configuration sets, now that every set has been compiled.
+        publish_uris.extend(server_guard.update_cross_set_diagnostics());
+        publish_uris.extend(server_guard.project_file_diagnostics.keys().cloned());
+
+        // Publish the diagnostics of every set. Everything has been updated by now, so readers can be let in.
+        let server_guard = server_guard.downgrade();
+        publish_diagnostics(&self.client, &server_guard, publish_uris).await;
+
+        drop(server_guard);
+        self.notify_compiled_sets(compiled_sets).await;
+        self.report_configuration_errors(spanless_diagnostics);
+    }
+
+    /// Sends a `slice/didCompile` notification for each set whose compilation was installed. These are sent after the
+    /// sets' diagnostics have been published, so that listeners see the same results as the user.
+    async fn notify_compiled_sets(&self, compiled_sets: Vec<DidCompileParams>) {
+        for params in compiled_sets {
+            self.client.send_notification::<DidCompile>(params).await;
+        }
+    }
+}
+
+/// Runs the provided compilations on a blocking thread, so that they don't hold up the async runtime, and reports their
+/// progress to the client (or logs it, if `show_progress` is false). The progress is ended before this returns, even
+/// if the compilations failed. The start and end of each compilation are also traced, if the client asked for tracing.
+///
+/// The server state must not be locked while this runs, so that other requests can be handled in the meantime.
+async fn run_compilations(
+    client: &Client,
+    pending_compilations: Vec<PendingCompilation>,
+    max_concurrent_compilations: Option<usize>,
+    show_progress: bool,
+    trace: TraceValue,
+) -> Vec<FinishedCompilation> {
+    let mut progress = CompilationProgress::begin(client, show_progress, pending_compilations.len()).await;
+    let message = format!("Compiling {} configuration set(s)", pending_compilations.len());
+    let options = || pending_compilations.iter().map(PendingCompilation::describe_options).collect::<Vec<_>>();
+    log_trace(client, trace, message, || options().join("\n")).await;
+
+    // The sender is dropped once the compilations are done (or have panicked), which closes the channel.
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let compilations = move || {
+        let on_finished = |set_name: &str| {
+            let _ = sender.send(set_name.to_owned());
+        };
+        compile_sets(pending_compilations, max_concurrent_compilations, on_finished)
+    };
+    let compilations = tokio::task::spawn_blocking(compilations);
+    while let Some(set_name) = receiver.recv().await {
+        progress.report_finished(&set_name).await;
+    }
+
+    let finished_compilations = match compilations.await {
+        Ok(finished_compilations) => finished_compilations,
+        Err(error) => {
+            warn!("Failed to compile the configuration sets: {error}");
+            Vec::new()
+        }
+    };
+    progress.end().await;
+
+    for finished_compilation in &finished_compilations {
+        if finished_compilation.is_unchanged() {
+            let set_name = &finished_compilation.set_name;
+            let message = format!("Skipped configuration set '{set_name}', since it didn't change");
+            let verbose = || "Its options and files are the same as its last compilation's".to_owned();
+            log_trace(client, trace, message, verbose).await;
+            continue;
+        }
+        let message = format!(
+            "Compiled configuration set '{}' in {:?}",
+            finished_compilation.set_name, finished_compilation.elapsed,
+        );
+        let verbose = || {
+            format!(
+                "{} file(s), {} diagnostic(s)",
+                finished_compilation.file_count(),
+                finished_compilation.diagnostic_count(),
+            )
+        };
+        log_trace(client, trace, message, verbose).await;
+    }
+    finished_compilations
+}
+
+/// Installs the results of each compilation into the set that was compiled, and stores the diagnostics it reported.
+/// The files whose diagnostics need to be published are added to `publish_uris`, and any diagnostics that don't have
+/// a span are returned (along with the name of their set) so they can be reported separately.
+///
+/// Results are discarded if their set was removed or re-configured while it was being compiled, or if the results of a
+/// newer compilation of the set have already been installed, so that outdated diagnostics are never published.
+///
+/// Each set's previous results are replaced in a single step, so readers see either the old or new results of a set,
+/// never a mix of both. The replaced results are dropped on a blocking thread, since freeing a large set's data can
+/// take a while, and the server state is locked until this returns.
+fn install_compilations(
+    server_state: &mut ServerState,
+    finished_compilations: Vec<FinishedCompilation>,
+    publish_uris: &mut HashSet<Url>,
+    compiled_sets: &mut Vec<DidCompileParams>,
+) -> Vec<(String, slicec::diagnostics::Diagnostic)> {
+    let ServerState { configuration_sets, server_config, cache_directory, .. } = server_state;
+
+    let mut spanless_diagnostics = Vec::new();
+    let mut replaced_data = Vec::new();
+    let mut caches = Vec::new();
+    for finished_compilation in finished_compilations {
+        let set_name = finished_compilation.set_name.clone();
+        let Some(set) = configuration_sets.iter_mut().find(|set| set.name == set_name) else {
+            debug!("Configuration set '{set_name}' was removed while it was being compiled");
+            continue;
+        };
+
+        // Remember which files had diagnostics before, so they're cleared if they're no longer in the set.
+        let previous_files = set.diagnostics.keys().cloned().collect::<Vec<_>>();
+        let input_hash = finished_compilation.input_hash();
+        let is_unchanged = finished_compilation.is_unchanged();
+        let Some((diagnostics, previous_data)) = set.install_compilation(server_config, finished_compilation) else {
+            // Sets which didn't change keep their diagnostics, but they're still published again, since the caller
+            // can have cleared them (like when the workspace folders change).
+            if is_unchanged {
+                publish_uris.extend(previous_files);
+            }
+            continue;
+        };
+        replaced_data.push(previous_data);
+        if let Some(cache_directory) = cache_directory {
+            let cache_path = symbol_cache::cache_path(cache_directory, set, server_config);
+            caches.push((cache_path, symbol_cache::serialize(&set.compilation_data)));
+        }
+
+        let spanless = store_diagnostics_for_set(diagnostics, set, server_config);
+        spanless_diagnostics.extend(spanless.into_iter().map(|diagnostic| (set_name.clone(), diagnostic)));
+        publish_uris.extend(previous_files);
+        publish_uris.extend(set.diagnostics.keys().cloned());
+
+        let mut uris = set
+            .compilation_data
+            .files
+            .keys()
+            .filter_map(|path| server_config.paths.convert_slice_path_to_uri(path))
+            .collect::<Vec<_>>();
+        uris.sort();
+        compiled_sets.push(DidCompileParams {
+            configuration_set: set_name,
+            success: set.count_diagnostics(DiagnosticSeverity::ERROR) == 0,
+            uris,
+            input_hash,
+        });
+    }
+
+    server_state.evict_idle_sets();
+
+    if !replaced_data.is_empty() {
+        tokio::task::spawn_blocking(move || drop(replaced_data));
+    }
+    if !caches.is_empty() {
+        let cache_write = tokio::task::spawn_blocking(move || {
+            for (cache_path, cache) in caches {
+                symbol_cache::save(&cache_path, &cache);
+            }
+        });
+        server_state.symbol_cache_writes.retain(|cache_write| !cache_write.is_finished());
+        server_state.symbol_cache_writes.push(cache_write);
+    }
+    spanless_diagnostics
+}
+
+// `tower-lsp` handles `$/cancelRequest` by dropping the handler of the cancelled request. Requests which re-compile a
+// set on a blocking thread (like `slice/dumpAst`) also check their `CancellationToken`, so that thread stops early too.
+// So do requests which search every set without awaiting in between (like hover, definition, and references), since
+// dropping their handler can't stop them until they're done.
+#[tower_lsp::async_trait]
+impl LanguageServer for SliceLanguageServer {
+    async fn initialize(
+        &self,
+        params: InitializeParams,
+    ) -> tower_lsp::jsonrpc::Result<InitializeResult> {
+        self.health.record_request(request::Initialize::METHOD);
+        let mut server_guard = self.server_state.write().await;
+        server_guard.update_from_initialize_params(params);
+        self.update_logger(&server_guard.server_config);
+        info!("Initialized by {}", server_guard.client.client_description());
+
+        let capabilities = SliceLanguageServer::capabilities(&server_guard);
+        let server_info = ServerInfo {
+            name: env!("CARGO_PKG_NAME").to_owned(),
+            version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+        };
+        Ok(InitializeResult {
+            capabilities,
+            server_info: Some(server_info),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        // Clients other than our extension usually don't send settings when initializing, so we pull them instead.
+        if self.server_state.read().await.pull_settings_on_initialized {
+            if let Some(settings) = self.pull_settings().await {
+                let mut server_guard = self.server_state.write().await;
+                server_guard.update_from_settings(Some(&settings));
+                self.update_logger(&server_guard.server_config);
+            }
+        }
+
+        if self.server_state.read().await.server_config.built_in_slice_path.is_none() {
+            info!("No built-in Slice path was provided, so the IceRPC well-known types won't be available.");
+        }
+
+        // Now that the server and client are fully initialized, it's safe to compile and publish any diagnostics.
+        self.report_configuration_problems().await;
+        self.report_set_overlaps().await;
+        self.report_discovered_sets().await;
+        self.report_unconfigured_folders().await;
+        self.load_symbol_caches().await;
+        self.compile_and_publish_diagnostics().await;
+        self.update_file_watchers().await;
+        self.update_feature_registrations().await;
+    }
+
+    async fn shutdown(&self) -> tower_lsp::jsonrpc::Result<()> {
+        self.health.record_request(request::Shutdown::METHOD);
+        info!("Shutting down");
+
+        // Stop any new compilations from starting, and cancel the ones which haven't started yet.
+        self.server_state.write().await.is_shutting_down = true;
+        for timer in self.timers.lock().unwrap_or_else(PoisonError::into_inner).drain(..) {
+            timer.abort();
+        }
+
+        // Compilations can't be interrupted, so wait for the running ones to finish. Once they do, they see that the
+        // server is shutting down, and drop their results instead of publishing them.
+        drop(self.compilations_in_flight.write().await);
+
+        // Then wait for the symbol caches of the compilations which finished before, so they're completely written.
+        let cache_writes = std::mem::take(&mut self.server_state.write().await.symbol_cache_writes);
+        for cache_write in cache_writes {
+            if let Err(error) = cache_write.await {
+                warn!("Failed to write the symbol caches: {error}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        info!("Extension settings changed");
+
+        // Our extension pushes the entire 'slice' section, but many clients only notify the server that something
+        // changed (with empty or missing settings), and expect the server to pull the settings itself.
+        let has_pushed_settings = params
+            .settings
+            .get("slice")
+            .and_then(|settings| settings.as_object())
+            .is_some_and(|settings| !settings.is_empty());
+        let supports_configuration_pull = self.server_state.read().await.client.supports_configuration_pull;
+        let pulled_settings = match has_pushed_settings || !supports_configuration_pull {
+            true => None,
+            false => self.pull_settings().await,
+        };
+
+        // Explicit scope to ensure the server state lock guard is dropped before we start compilation.
+        let previous_uris = {
+            let mut server_guard = self.server_state.write().await;
+
+            // Remember which files currently have diagnostics. These are re-published after updating, so that the
+            // diagnostics of removed sets are cleared, and the diagnostics of renamed sets are labeled correctly.
+            let previous_uris = server_guard.files_with_diagnostics();
+
+            // Update the stored configuration sets from the data provided in (or pulled after) the client notification
+            match pulled_settings {
+                Some(settings) => server_guard.update_from_settings(Some(&settings)),
+                None => server_guard.update_configurations_from_params(params),
+            }
+            self.update_logger(&server_guard.server_config);
+            previous_uris
+        };
+        self.trace_configuration_reload().await;
+
+        // Let the user know about any problems in their new configuration, then compile the sets which changed (the
+        // others kept their previous results) and publish the diagnostics for all files
+        self.report_configuration_problems().await;
+        self.report_set_overlaps().await;
+        self.report_discovered_sets().await;
+        self.report_unconfigured_folders().await;
+        self.compile_sets_and_publish_diagnostics(|set| !set.is_compiled(), previous_uris).await;
+        self.update_file_watchers().await;
+        self.update_feature_registrations().await;
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<GotoDefinitionResponse>> {
+        self.health.record_request(request::GotoDefinition::METHOD);
+        let start_time = Instant::now();
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        // Convert the URI to a file path and back to a URL to ensure that the URI is formatted correctly for Windows.
+        let server_guard = self.server_state.read().await;
+        let file_path = server_guard.server_config.paths.url_to_document_path(&uri).ok_or_else(Error::internal_error)?;
+
+        // Clients which can't unregister features keep sending requests for them, even after they've been disabled.
+        if server_guard.server_config.disabled_features.contains(&Feature::Definition) {
+            return Ok(None);
+        }
+
+        // Find the configuration set that contains the file
+        let configuration_sets = &server_guard.configuration_sets;
+
+        // Get the definition span and convert it to a GotoDefinitionResponse
+        let encoding = server_guard.server_config.position_encoding;
+        let supports_definition_links = server_guard.client.supports_definition_links;
+        let response = CancellationToken::current().find_map(configuration_sets, |set| {
+            if set.compilation_data.is_degraded(&file_path) {
+                return None;
+            }
+            set.compilation_data
+                .file(&file_path)
+                .inspect(|_| set.mark_used())
+                .and_then(|file| {
+                    let search_location = server_guard.position_to_location(&file_path, &file.raw_text, position);
+                    get_definition_link(file, &search_location)
+                })
+                .map(|(origin, target)| {
+                    let target_range = set.compilation_data.span_to_range(target, encoding);
+                    match supports_definition_links {
+                        true => GotoDefinitionResponse::Link(vec![LocationLink {
+                            origin_selection_range: Some(set.compilation_data.span_to_range(origin, encoding)),
+                            target_uri: uri.clone(),
+                            target_range,
+                            target_selection_range: target_range,
+                        }]),
+                        false => GotoDefinitionResponse::Scalar(Location { uri: uri.clone(), range: target_range }),
+                    }
+                })
+        })?;
+
+        let trace = server_guard.trace;
+        drop(server_guard);
+        trace_request(&self.client, trace, "textDocument/definition", start_time, &file_path).await;
+        Ok(response)
+    }
+
+    async fn hover(&self, params: HoverParams) -> tower_lsp::jsonrpc::Result<Option<Hover>> {
+        self.health.record_request(request::HoverRequest::METHOD);
+        let start_time = Instant::now();
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        // Convert the URI to a file path and back to a URL to ensure that the URI is formatted correctly for Windows.
+        let server_guard = self.server_state.read().await;
+        let file_path = server_guard.server_config.paths.url_to_document_path(&uri).ok_or_else(Error::internal_error)?;
+
+        // Clients which can't unregister features keep sending requests for them, even after they've been disabled.
+        if server_guard.server_config.disabled_features.contains(&Feature::Hover) {
+            return Ok(None);
+        }
+
+        // Find the configuration set that contains the file and get the hover info
+        let configuration_sets = &server_guard.configuration_sets;
+
+        // Clients which can't render markdown are sent plain text instead, which they show as-is.
+        let hover_format = match server_guard.client.supports_markdown_hover {
+            true => MarkupKind::Markdown,
+            false => MarkupKind::PlainText,
+        };
+        let hover = CancellationToken::current().find_map(configuration_sets, |set| {
+            if set.compilation_data.is_degraded(&file_path) {
+                return None;
+            }
+            set.compilation_data
+                .file(&file_path)
+                .inspect(|_| set.mark_used())
+                .and_then(|file| {
+                    let search_location = server_guard.position_to_location(&file_path, &file.raw_text, position);
+                    get_hover_message(file, &search_location)
+                })
+                .map(|message| Hover {
+                    contents: HoverContents::Markup(MarkupContent { kind: hover_format.clone(), value: message }),
+                    range: None,
+                })
+        })?;
+
+        let trace = server_guard.trace;
+        drop(server_guard);
+        trace_request(&self.client, trace, "textDocument/hover", start_time, &file_path).await;
+        Ok(hover)
+    }
+
+    async fn references(&self, params: ReferenceParams) -> tower_lsp::jsonrpc::Result<Option<Vec<Location>>> {
+        self.health.record_request(request::References::METHOD);
+        let start_time = Instant::now();
+        let position = params.text_document_position.position;
+
+        // Convert the URI to a file path and back to a URL to ensure that the URI is formatted correctly for Windows.
+        let uri = &params.text_document_position.text_document.uri;
+        let server_guard = self.server_state.read().await;
+        let file_path = server_guard.server_config.paths.url_to_document_path(uri).ok_or_else(Error::internal_error)?;
+
+        // Clients which can't unregister features keep sending requests for them, even after they've been disabled.
+        if server_guard.server_config.disabled_features.contains(&Feature::References) {
+            return Ok(None);
+        }
+
+        // Find the configuration set that contains the file, and look up the references in its index.
+        let include_declaration = params.context.include_declaration;
+        let references = CancellationToken::current().find_map(&server_guard.configuration_sets, |set| {
+            if set.compilation_data.is_degraded(&file_path) || set.compilation_data.file(&file_path).is_none() {
+                return None;
+            }
+            set.mark_used();
+            find_references(&set.compilation_data, &file_path, position, include_declaration)
+        })?;
+
+        let trace = server_guard.trace;
+        drop(server_guard);
+        trace_request(&self.client, trace, "textDocument/references", start_time, &file_path).await;
+        Ok(references)
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> tower_lsp::jsonrpc::Result<DocumentDiagnosticReportResult> {
+        self.health.record_request(request::DocumentDiagnosticRequest::METHOD);
+        let start_time = Instant::now();
+
+        // Convert the URI to a file path and back to a URL to ensure that the URI is formatted correctly for Windows.
+        let server_guard = self.server_state.read().await;
+        let paths = &server_guard.server_config.paths;
+        let file_path = paths.url_to_document_path(&params.text_document.uri).ok_or_else(Error::internal_error)?;
+        let uri = paths.convert_slice_path_to_uri(&file_path).ok_or_else(Error::internal_error)?;
+        let report = document_diagnostic_report(&server_guard, &uri, params.previous_result_id);
+        let trace = server_guard.trace;
+        drop(server_guard);
+        trace_request(&self.client, trace, "textDocument/diagnostic", start_time, &file_path).await;
+        Ok(DocumentDiagnosticReportResult::Report(report))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<serde_json::Value>> {
+        self.health.record_request(request::ExecuteCommand::METHOD);
+        let Some(command) = ServerCommand::from_name(&params.command) else {
+            return Err(Error::invalid_params(format!("unknown command '{}'", params.command)));
+        };
+        match command {
+            ServerCommand::Compile => {
+                let uri = document_argument(command, &params.arguments)?;
+                self.compile_document(&uri).await.map(Some)
+            }
+            ServerCommand::CompileAll => Ok(Some(self.compile_all().await)),
+            ServerCommand::ShowFileConfiguration => {
+                let uri = document_argument(command, &params.arguments)?;
+                self.show_file_configuration(&uri).await.map(Some)
+            }
+            ServerCommand::ExportSarif => {
+                let path = path_argument(command, &params.arguments)?;
+                self.export_sarif(&path).await.map(Some)
+            }
+            ServerCommand::ExportIndex => {
+                let path = path_argument(command, &params.arguments)?;
+                self.export_index(&path).await.map(Some)
+            }
+            ServerCommand::ExportDependencyGraph => {
+                let path = path_argument(command, &params.arguments)?;
+                let granularity = granularity_argument(command, &params.arguments)?;
+                self.export_dependency_graph(&path, granularity).await.map(Some)
+            }
+            ServerCommand::WorkspaceStats => {
+                // The built-in Slice files are only counted if the command's first argument is `true`.
+                let include_built_in = params.arguments.first().and_then(serde_json::Value::as_bool).unwrap_or(false);
+                self.workspace_stats(include_built_in).await.map(Some)
+            }
+            ServerCommand::GenerateDocs => {
+                let directory = path_argument(command, &params.arguments)?;
+                let Some(target) = params.arguments.get(1).and_then(serde_json::Value::as_str) else {
+                    let name = command.name();
+                    let message = format!("'{name}' requires the name of a configuration set, or a document's URI");
+                    return Err(Error::invalid_params(message));
+                };
+                let include_deprecated = params.arguments.get(2).and_then(serde_json::Value::as_bool).unwrap_or(false);
+                self.generate_docs(&directory, target, include_deprecated).await.map(Some)
+            }
+            ServerCommand::NewFile => {
+                let directory = path_argument(command, &params.arguments)?;
+                let file_name = params.arguments.get(1).and_then(serde_json::Value::as_str);
+                self.new_file(&directory, file_name.unwrap_or(DEFAULT_FILE_NAME)).await.map(Some)
+            }
+            ServerCommand::OpenBuiltInSliceFiles => self.built_in_slice_files().await.map(Some),
+            ServerCommand::CopyTypeId => {
+                let uri = document_argument(command, &params.arguments)?;
+                let position = position_argument(command, &params.arguments)?;
+                self.copy_type_id(uri, position).await.map(Some)
+            }
+        }
+    }
+
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> tower_lsp::jsonrpc::Result<WorkspaceDiagnosticReportResult> {
+        self.health.record_request(request::WorkspaceDiagnosticRequest::METHOD);
+        let server_guard = self.server_state.read().await;
+        let report = workspace_diagnostic_report(&server_guard, params.previous_result_ids);
+        Ok(WorkspaceDiagnosticReportResult::Report(report))
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        info!("Workspace folders changed");
+
+        // Explicit scope to ensure the server state lock guard is dropped before we start compilation.
+        {
+            let mut server_guard = self.server_state.write().await;
+
+            // Removed folders can contain files that are referenced by other folders' sets, and added folders can
+            // change which files global sets contain. So like with configuration changes, we clear the diagnostics for
+            // all files and then re-publish them.
+            clear_diagnostics(&self.client, &server_guard.configuration_sets).await;
+
+            // Removed folders are converted before they're unregistered, so they're converted into the same paths that
+            // they were added with.
+            let paths = &mut server_guard.server_config.paths;
+            let removed_paths = params
+                .event
+                .removed
+                .iter()
+                .filter_map(|folder| paths.url_to_sanitized_file_path(&folder.uri))
+                .collect::<Vec<_>>();
+            for folder in &params.event.removed {
+                paths.unregister(&folder.uri);
+            }
+            let added_paths = params
+                .event
+                .added
+                .iter()
+                .filter_map(|folder| paths.register_folder(&folder.uri))
+                .collect::<Vec<_>>();
+            server_guard.update_workspace_folders(added_paths, &removed_paths);
+        }
+
+        // Global sets are resolved against every folder, so the added folders can cause sets to overlap.
+        self.report_set_overlaps().await;
+        self.report_discovered_sets().await;
+        self.report_unconfigured_folders().await;
+
+        // Trigger a compilation and publish the diagnostics for all files
+        self.compile_and_publish_diagnostics().await;
+        self.update_file_watchers().await;
+    }
+
+    async fn did_rename_files(&self, params: RenameFilesParams) {
+        let mut server_guard = self.server_state.write().await;
+        let mut renamed_paths = Vec::new();
+        let mut publish_uris = HashSet::new();
+        for file_rename in params.files {
+            let paths = &server_guard.server_config.paths;
+            let old_path = Url::parse(&file_rename.old_uri).ok().and_then(|uri| paths.url_to_sanitized_file_path(&uri));
+            let new_path = Url::parse(&file_rename.new_uri).ok().and_then(|uri| paths.url_to_sanitized_file_path(&uri));
+            let (Some(old_path), Some(new_path)) = (old_path, new_path) else {
+                continue;
+            };
+            info!("'{}' was renamed to '{}'", old_path.display(), new_path.display());
+
+            // Keep tracking any documents that were moved, under their new paths (folders can hold many documents).
+            let moved_path = |path: &Path| Some(new_path.join(path.strip_prefix(&old_path).ok()?));
+            let open_documents = std::mem::take(&mut server_guard.open_documents);
+            server_guard.open_documents = open_documents
+                .into_iter()
+                .map(|(path, document)| (moved_path(&path).unwrap_or(path), document))
+                .collect();
+            let unsaved_documents = std::mem::take(&mut server_guard.unsaved_documents);
+            server_guard.unsaved_documents = unsaved_documents
+                .into_iter()
+                .map(|(path, document)| (moved_path(&path).unwrap_or(path), document))
+                .collect();
+
+            // Without a workspace, the file's stand-alone set is replaced by one for its new path.
+            if let Some(configuration_set) = server_guard.remove_stand_alone_set(&old_path) {
+                publish_uris.extend(configuration_set.diagnostics.into_keys());
+                server_guard.add_stand_alone_set(&new_path);
+            }
+            renamed_paths.extend([old_path, new_path]);
+        }
+        if renamed_paths.is_empty() {
+            return;
+        }
+
+        // Renaming files changes which files are in each set, so their cached options are outdated. Re-compiling the
+        // sets that contained the old paths also clears the diagnostics that were published for them.
+        server_guard.invalidate_cached_slice_options();
+        publish_diagnostics(&self.client, &server_guard.downgrade(), publish_uris).await;
+        self.compile_matching_sets(|set, _| set.contains_any_file(&renamed_paths)).await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let mut server_guard = self.server_state.write().await;
+        let changed_paths = params
+            .changes
+            .iter()
+            .filter_map(|change| server_guard.server_config.paths.url_to_sanitized_file_path(&change.uri))
+            .collect::<Vec<_>>();
+        let slice_ignore_paths = server_guard
+            .server_config
+            .workspace_root_paths
+            .iter()
+            .map(|root_path| root_path.join(SLICE_IGNORE_FILE_NAME))
+            .collect::<Vec<_>>();
+
+        // If a workspace folder's `.sliceignore` file changed, the paths of every configuration set might have changed.
+        let slice_ignore_changed = changed_paths.iter().any(|path| slice_ignore_paths.contains(path));
+        if slice_ignore_changed {
+            info!("'{SLICE_IGNORE_FILE_NAME}' changed");
+
+            server_guard.reload_slice_ignore();
+            clear_diagnostics(&self.client, &server_guard.configuration_sets).await;
+
+            // Discovered sets depend on which files are ignored, so they're re-discovered by re-applying the settings.
+            let rediscover_sets = server_guard.server_config.auto_discover_configurations;
+            if rediscover_sets {
+                server_guard.reload_project_files();
+            }
+            drop(server_guard);
+            if rediscover_sets {
+                self.report_discovered_sets().await;
+                self.report_unconfigured_folders().await;
+            }
+            self.compile_and_publish_diagnostics().await;
+            return;
+        }
+
+        // If a workspace folder's project file changed, its configuration sets might have changed. This is handled like
+        // a change to the user's settings: sets that didn't change keep their previous results.
+        let project_file_paths = server_guard.project_file_paths();
+        if changed_paths.iter().any(|path| project_file_paths.contains(path)) {
+            info!("'{PROJECT_FILE_NAME}' changed");
+
+            let previous_uris = server_guard.files_with_diagnostics();
+            server_guard.reload_project_files();
+            drop(server_guard);
+
+            self.report_configuration_problems().await;
+            self.report_set_overlaps().await;
+            self.report_discovered_sets().await;
+            self.report_unconfigured_folders().await;
+            self.compile_sets_and_publish_diagnostics(|set| !set.is_compiled(), previous_uris).await;
+            self.update_file_watchers().await;
+            return;
+        }
+
+        // Queue any Slice files in the workspace which were changed outside of the editor to be compiled. Files with
+        // unsaved changes are skipped, since they're compiled from their unsaved text, which takes priority over disk.
+        let paths = &server_guard.server_config.paths;
+        let changed_slice_files = params
+            .changes
+            .iter()
+            .filter_map(|change| Some((paths.url_to_sanitized_file_path(&change.uri)?, change.typ)))
+            .filter(|(path, _)| path.extension().is_some_and(|extension| extension == "slice"))
+            .filter(|(path, _)| {
+                let root_paths = &server_guard.server_config.workspace_root_paths;
+                root_paths.iter().any(|root_path| path.starts_with(root_path))
+            })
+            .filter(|(path, _)| !server_guard.unsaved_documents.contains_key(path))
+            .collect::<Vec<_>>();
+        if !changed_slice_files.is_empty() {
+            // Creating or deleting files changes which files are in each set, so their cached options are outdated.
+            if changed_slice_files.iter().any(|(_, typ)| *typ != FileChangeType::CHANGED) {
+                server_guard.invalidate_cached_slice_options();
+            }
+
+            let file_paths = changed_slice_files.into_iter().map(|(path, _)| path).collect();
+            drop(server_guard);
+            self.queue_file_changes(file_paths).await;
+            server_guard = self.server_state.write().await;
+        }
+
+        // Then, check whether any files changed in the watched paths outside of the workspace.
+        let changed_external_paths = server_guard
+            .watched_external_paths
+            .iter()
+            .filter(|external_path| changed_paths.iter().any(|path| path.starts_with(external_path)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if changed_external_paths.is_empty() {
+            return;
+        }
+        if params.changes.iter().any(|change| change.typ != FileChangeType::CHANGED) {
+            server_guard.invalidate_cached_slice_options();
+        }
+        drop(server_guard);
+
+        // If a watched path was deleted, we re-compile everything, which warns the user about the missing path, and
+        // stop watching it, so that we aren't notified about it again.
+        if changed_external_paths.iter().any(|external_path| !external_path.exists()) {
+            self.compile_and_publish_diagnostics().await;
+            self.update_file_watchers().await;
+            return;
+        }
+
+        // Otherwise, only the sets which reference the changed paths need to be re-compiled.
+        self.compile_matching_sets(|set, server_config| {
+            set.external_search_paths(server_config)
+                .iter()
+                .any(|external_path| changed_external_paths.contains(external_path))
+        })
+        .await;
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        // Resolving the document's symlinks accesses the file system, so it's done before the server state is locked.
+        let resolved_path = ResolvedPath::new(&params.text_document.uri);
+        let mut server_guard = self.server_state.write().await;
+
+        // Sets which found a document through a symlink of its own (instead of through a symlinked folder) tracked it
+        // under the symlink's path, so they're re-compiled to track it under its real path instead.
+        let mut relocating_sets = HashSet::new();
+        if let Some(resolved_path) = resolved_path {
+            let client_path = resolved_path.client_path().to_owned();
+            if server_guard.server_config.paths.register(resolved_path) {
+                for set in &mut server_guard.configuration_sets {
+                    if set.compilation_data.file(&client_path).is_some() {
+                        set.invalidate();
+                        relocating_sets.insert(set.name.clone());
+                    }
+                }
+            }
+        }
+
+        if let Some(file_path) = server_guard.server_config.paths.url_to_document_path(&params.text_document.uri) {
+            let version = params.text_document.version;
+
+            // Documents which aren't files (like untitled documents) are never on disk, so they're always compiled from
+            // their unsaved text. Their virtual paths are registered, so that they're converted back into their URIs.
+            if is_virtual_document_path(&file_path) {
+                server_guard.server_config.paths.register_virtual_document(&params.text_document.uri);
+                let document = UnsavedDocument { version, text: params.text_document.text.clone() };
+                server_guard.unsaved_documents.insert(file_path.clone(), document);
+            }
+            let document = OpenDocument { version, text: params.text_document.text };
+            server_guard.open_documents.insert(file_path.clone(), document);
+            drop(server_guard);
+            if !relocating_sets.is_empty() {
+                self.compile_matching_sets(|set, _| relocating_sets.contains(&set.name)).await;
+            }
+            self.handle_file_change(&file_path).await;
+            self.notify_if_unconfigured(&file_path).await;
+        }
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let mut server_guard = self.server_state.write().await;
+        let Some(file_path) = server_guard.server_config.paths.url_to_document_path(&params.text_document.uri) else {
+            return;
+        };
+
+        // We use incremental document syncing, so the changes only hold the parts of the document which changed. They
+        // must be applied in order, since each change's range is relative to the text after the previous change.
+        let version = params.text_document.version;
+        let encoding = server_guard.server_config.position_encoding;
+        let Some(open_document) = server_guard.open_documents.get_mut(&file_path) else {
+            warn!("Received changes for '{}', which isn't open", file_path.display());
+            return;
+        };
+        if !apply_content_changes(&mut open_document.text, params.content_changes, encoding) {
+            warn!("The incrementally synced text of '{}' drifted from the client's copy", file_path.display());
+        }
+        open_document.version = version;
+
+        let document = UnsavedDocument { version, text: open_document.text.clone() };
+        server_guard.unsaved_documents.insert(file_path.clone(), document);
+
+        if server_guard.server_config.compilation_trigger == CompilationTrigger::OnType {
+            drop(server_guard);
+            self.schedule_compilation(file_path, version);
+        }
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let mut server_guard = self.server_state.write().await;
+        let paths = &server_guard.server_config.paths;
+        if let Some(file_path) = paths.url_to_sanitized_file_path(&params.text_document.uri) {
+            // The document's text is on disk now, so there's no need to compile it from its unsaved text anymore.
+            server_guard.unsaved_documents.remove(&file_path);
+            drop(server_guard);
+            self.handle_file_change(&file_path).await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let mut server_guard = self.server_state.write().await;
+        if let Some(file_path) = server_guard.server_config.paths.url_to_document_path(&params.text_document.uri) {
+            server_guard.open_documents.remove(&file_path);
+            let unsaved_document = server_guard.unsaved_documents.remove(&file_path);
+
+            // Without a workspace, closed files are no longer compiled, so their diagnostics are cleared. The same goes
+            // for documents which aren't files, which includes untitled documents that were just saved to a file.
+            if let Some(configuration_set) = server_guard.remove_stand_alone_set(&file_path) {
+                server_guard.server_config.paths.unregister(&params.text_document.uri);
+                let mut publish_uris = configuration_set.diagnostics.into_keys().collect::<HashSet<_>>();
+                publish_uris.extend(server_guard.update_cross_set_diagnostics());
+                publish_diagnostics(&self.client, &server_guard.downgrade(), publish_uris).await;
+                return;
+            }
+            server_guard.evict_idle_sets();
+
+            // Files which aren't in any set (like the files of a set that was re-configured since) aren't
+            // re-published anymore, so whatever was last published for them is cleared once they're closed. Files in a
+            // set keep their diagnostics, since the client shows the diagnostics of the entire workspace. These are
+            // still published under the URI that the client opened them with, so only untracked documents are
+            // unregistered.
+            // Diagnostics are stored under the URIs that files are published under, which can be spelled differently
+            // than the URI that the client sent, so it's converted first.
+            let uri = server_guard.server_config.paths.convert_slice_path_to_uri(&file_path);
+            let uri = uri.unwrap_or_else(|| params.text_document.uri.clone());
+            let is_tracked = server_guard.configuration_sets.iter().any(|set| {
+                set.diagnostics.contains_key(&uri) || set.contains_file(&file_path)
+            });
+            if !is_tracked {
+                server_guard.server_config.paths.unregister(&params.text_document.uri);
+                let publish_uris = HashSet::from([uri]);
+                publish_diagnostics(&self.client, &server_guard.downgrade(), publish_uris).await;
+            } else {
+                drop(server_guard);
+            }
+
+            // If the document was closed without saving, its changes were discarded, so we re-compile it from disk.
+            if unsaved_document.is_some() && !is_virtual_document_path(&file_path) {
+                self.handle_file_change(&file_path).await;
+            }
+        }
+    }
+}
+
+/// Creates a filter for the renames that the client notifies the server about.
+fn rename_filter(glob: &str, kind: FileOperationPatternKind) -> FileOperationFilter {
+    FileOperationFilter {
+        scheme: Some("file".to_owned()),
+        pattern: FileOperationPattern { glob: glob.to_owned(), matches: Some(kind), options: None },
+    }
+}
+
+/// Returns the path that a folder is added to the configuration with: relative to its workspace folder when possible,
+/// since that's how users write their paths, or absolute otherwise.
+fn configuration_path(workspace_root_paths: &[PathBuf], folder: &Path) -> String {
+    let relative_folder = workspace_root_paths
+        .iter()
+        .find_map(|root_path| folder.strip_prefix(root_path).ok());
+    match relative_folder {
+        Some(relative) if relative.as_os_str().is_empty() => ".".to_owned(),
+        Some(relative) => relative.display().to_string(),
+        None => folder.display().to_string(),
+    }
+}
+
+async fn show_popup(client: &Client, params: ShowNotificationParams) {
+    client.send_notification::<ShowNotification>(params).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation_gate::CompilationGate;
+    use crate::test_client::TestClient;
+    use serde_json::Value;
+    use std::fs;
+
+    /// Returns the absolute path of a directory in `tests/fixtures`.
+    fn fixture_path(name: &str) -> PathBuf {
+        std::env::current_dir().unwrap().join("tests/fixtures").join(name)
+    }
+
+    /// Copies the `relative` fixture into a new temporary directory (so tests can change its files), and returns it.
+    fn relative_fixture_copy(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("slice-language-server-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for file in ["greeter.slice", "sender.slice"] {
+            fs::copy(fixture_path("relative").join(file), directory.join(file)).unwrap();
+        }
+        directory
+    }
+
+    /// Returns initialization options with a single configuration set, which compiles the provided directory.
+    fn single_set_options(set_name: &str, directory: &Path) -> Value {
+        json!({
+            "configurations": [{ "name": set_name, "paths": [directory], "addWellKnownTypes": false }],
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn compilations_that_finish_after_shutdown_dont_publish_anything() {
+        let set_name = "shutdown while compiling";
+        let gate = CompilationGate::close(set_name);
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        client.initialize(&fixture_path, single_set_options(set_name, &fixture_path)).await;
+        gate.wait_for_compilations(1).await;
+
+        // Shut down while the set is compiling, then let the compilation finish.
+        let shutdown = client.send_request(request::Shutdown::METHOD, Value::Null);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        gate.open();
+        assert_eq!(client.response(shutdown).await, Ok(Value::Null));
+
+        assert!(client.take_notifications(notification::PublishDiagnostics::METHOD).is_empty());
+        assert!(client.take_notifications(DidCompile::METHOD).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutting_down_waits_for_symbol_caches_to_be_written() {
+        let cache_directory = std::env::temp_dir().join(format!("slice-language-server-caches-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache_directory);
+        fs::create_dir_all(&cache_directory).unwrap();
+
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        let mut options = single_set_options("cached", &fixture_path);
+        options["cacheDirectory"] = json!(cache_directory);
+        client.initialize(&fixture_path, options).await;
+        assert_eq!(client.published_diagnostics(&fixture_path.join("sender.slice")).await.len(), 1);
+
+        // The caches are written in the background once the set is compiled, which shutting down must wait for.
+        client.request(request::Shutdown::METHOD, Value::Null).await;
+        let caches = fs::read_dir(&cache_directory).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(caches.len(), 1);
+        let cache = serde_json::from_slice::<Value>(&fs::read(&caches[0]).unwrap()).unwrap();
+        assert!(cache.is_object());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn removing_a_folder_keeps_the_sets_that_reference_its_files() {
+        // The 'app' folder's set references the Slice files in the 'shared' folder, which has a set of its own.
+        let shared_path = relative_fixture_copy("shared-folder");
+        let app_path = shared_path.with_file_name(format!("slice-language-server-app-folder-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&app_path);
+        fs::create_dir_all(&app_path).unwrap();
+        let app_text = "module App\n\nstruct Note {\n    greeting: Fixtures::Greeting\n}\n";
+        fs::write(app_path.join("app.slice"), app_text).unwrap();
+
+        let folder = |path: &Path| json!({ "uri": Url::from_directory_path(path).unwrap(), "name": "folder" });
+        let folder_configuration = |path: &Path, set: Value| {
+            json!({ "uri": Url::from_directory_path(path).unwrap(), "configurations": [set] })
+        };
+        let shared_reference = format!("../{}", shared_path.file_name().unwrap().to_str().unwrap());
+        let app_set = json!({
+            "name": "app",
+            "paths": ["."],
+            "references": [shared_reference],
+            "addWellKnownTypes": false,
+        });
+        let shared_set = json!({ "name": "shared", "paths": ["."], "addWellKnownTypes": false });
+        let params = json!({
+            "processId": null,
+            "capabilities": {},
+            "workspaceFolders": [folder(&app_path), folder(&shared_path)],
+            "initializationOptions": {
+                "folderConfigurations": [
+                    folder_configuration(&app_path, app_set),
+                    folder_configuration(&shared_path, shared_set),
+                ],
+            },
+        });
+        let mut client = TestClient::start();
+        client.request(request::Initialize::METHOD, params).await;
+        client.notify(notification::Initialized::METHOD, json!({}));
+        let sender_path = shared_path.join("sender.slice");
+        assert_eq!(client.published_diagnostics(&sender_path).await.len(), 1);
+        wait_for_compilations(&mut client).await;
+
+        let event = json!({ "event": { "added": [], "removed": [folder(&shared_path)] } });
+        client.notify(notification::DidChangeWorkspaceFolders::METHOD, event);
+
+        // The shared folder's set is dropped, but the 'app' set still compiles its files, and reports their errors.
+        assert!(client.published_diagnostics(&sender_path).await.is_empty());
+        assert_eq!(client.published_diagnostics(&sender_path).await.len(), 1);
+        let project_info = client.request(ProjectInfo::METHOD, Value::Null).await;
+        assert_eq!(project_info["workspaceRoots"], json!([app_path]));
+        let [app_set] = project_info["configurationSets"].as_array().unwrap().as_slice() else {
+            panic!("expected only the 'app' set: {project_info:#}");
+        };
+        assert_eq!(app_set["name"], "app");
+        assert!(app_set["files"].as_array().unwrap().contains(&json!(sender_path)));
+    }
+
+    /// Sends `slice/diagnostics`, which waits for any running compilations (or inspections) to finish.
+    async fn wait_for_compilations(client: &mut TestClient) {
+        client.request(Diagnostics::METHOD, json!({})).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn unchanged_sets_publish_their_diagnostics_again_after_they_were_cleared() {
+        let workspace = relative_fixture_copy("ignore");
+        let mut client = TestClient::start();
+        client.initialize(&workspace, single_set_options("republished", &workspace)).await;
+        let sender_path = workspace.join("sender.slice");
+        assert_eq!(client.published_diagnostics(&sender_path).await.len(), 1);
+
+        // Changing the '.sliceignore' file clears every file's diagnostics, even though the set doesn't change (so its
+        // compilation is skipped).
+        let slice_ignore_path = workspace.join(SLICE_IGNORE_FILE_NAME);
+        fs::write(&slice_ignore_path, "unrelated.slice\n").unwrap();
+        let change = json!({ "uri": Url::from_file_path(&slice_ignore_path).unwrap(), "type": 1 });
+        client.notify(notification::DidChangeWatchedFiles::METHOD, json!({ "changes": [change] }));
+        assert!(client.published_diagnostics(&sender_path).await.is_empty());
+        assert_eq!(client.published_diagnostics(&sender_path).await.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn readers_never_see_a_set_with_its_new_configuration_and_old_compilation_data() {
+        let set_name = "toggled";
+        let directories = [fixture_path("relative"), fixture_path("type_ids")];
+        let mut client = TestClient::start();
+        client.initialize(&fixture_path(""), single_set_options(set_name, &directories[0])).await;
+        wait_for_compilations(&mut client).await;
+
+        // Keep moving the set between directories, while reading its configuration and compiled files concurrently.
+        // Each read must see the set's files match its paths (see `SliceLanguageServer::server_state` for why).
+        let mut project_info_requests = Vec::new();
+        for index in 1..=20 {
+            let options = single_set_options(set_name, &directories[index % 2]);
+            client.notify(notification::DidChangeConfiguration::METHOD, json!({ "settings": { "slice": options } }));
+            for _ in 0..3 {
+                project_info_requests.push(client.send_request(ProjectInfo::METHOD, Value::Null));
+            }
+        }
+        wait_for_compilations(&mut client).await;
+        project_info_requests.push(client.send_request(ProjectInfo::METHOD, Value::Null));
+
+        let canonical_paths = |paths: &Value| {
+            let paths = paths.as_array().unwrap().iter();
+            paths.map(|path| fs::canonicalize(path.as_str().unwrap()).unwrap()).collect::<Vec<_>>()
+        };
+        let mut file_counts = Vec::new();
+        for id in project_info_requests {
+            let project_info = client.response(id).await.unwrap();
+            let [set] = project_info["configurationSets"].as_array().unwrap().as_slice() else {
+                panic!("expected a single set: {project_info:#}");
+            };
+            let paths = canonical_paths(&set["paths"]);
+            for file in canonical_paths(&set["files"]) {
+                assert!(paths.iter().any(|path| file.starts_with(path)), "{file:?} isn't in {paths:?}");
+            }
+            file_counts.push(set["files"].as_array().unwrap().len());
+        }
+        assert_ne!(file_counts.last(), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn open_documents_are_tracked_from_when_theyre_opened_until_theyre_closed() {
+        // The server isn't initialized, so it doesn't send anything to the client, and its handlers can be called
+        // directly.
+        let (service, _socket) = build_service();
+        let server = service.inner();
+        let greeter_path = fixture_path("relative").join("greeter.slice");
+        let uri = Url::from_file_path(&greeter_path).unwrap();
+        let disk_text = fs::read_to_string(&greeter_path).unwrap();
+        let document_state = || async {
+            let server_guard = server.server_state.read().await;
+            let open_document = server_guard.open_documents.get(&greeter_path);
+            let open_document = open_document.map(|document| (document.version, document.text.clone()));
+            (open_document, server_guard.document_text(&greeter_path).unwrap())
+        };
+        assert_eq!(document_state().await, (None, disk_text.clone()));
+
+        let opened_text = disk_text.replace("Greeting", "Salutation");
+        let text_document = TextDocumentItem::new(uri.clone(), "slice".to_owned(), 1, opened_text.clone());
+        server.did_open(DidOpenTextDocumentParams { text_document }).await;
+        assert_eq!(document_state().await, (Some((1, opened_text.clone())), opened_text.clone()));
+
+        // Changes either replace the whole text, or only the range that they hold.
+        let changed_text = opened_text.replace("Salutation", "Welcome");
+        let change = |range, text: &str| {
+            TextDocumentContentChangeEvent { range, range_length: None, text: text.to_owned() }
+        };
+        let full_change = change(None, &changed_text);
+        let ranged_change = change(Some(Range::new(Position::new(0, 0), Position::new(0, 6))), "MODULE");
+        let text_document = VersionedTextDocumentIdentifier::new(uri.clone(), 3);
+        let content_changes = vec![full_change, ranged_change];
+        server.did_change(DidChangeTextDocumentParams { text_document, content_changes }).await;
+        let changed_text = changed_text.replacen("module", "MODULE", 1);
+        assert_eq!(document_state().await, (Some((3, changed_text.clone())), changed_text));
+
+        // Once it's closed, its text is read from disk again.
+        let text_document = TextDocumentIdentifier::new(uri);
+        server.did_close(DidCloseTextDocumentParams { text_document }).await;
+        assert_eq!(document_state().await, (None, disk_text));
+        assert!(server.server_state.read().await.unsaved_documents.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn documents_which_arent_files_are_compiled_under_their_uri_until_theyre_closed() {
+        let (service, _socket) = build_service();
+        let server = service.inner();
+        let uri = Url::parse(&format!("untitled:{}", "Untitled-1/".repeat(100))).unwrap();
+        let text = "module Test\nstruct Point { x: Missing }\n".to_owned();
+        let text_document = TextDocumentItem::new(uri.clone(), "slice".to_owned(), 1, text);
+        server.did_open(DidOpenTextDocumentParams { text_document }).await;
+        let diagnostics = |server_state: &ServerState| {
+            let sets = server_state.configuration_sets.iter();
+            sets.filter_map(|set| set.diagnostics.get(&uri)).map(Vec::len).collect::<Vec<_>>()
+        };
+        assert_eq!(diagnostics(&*server.server_state.read().await), [1]);
+
+        // Once it's closed, it's no longer compiled, and its virtual path is forgotten.
+        let text_document = TextDocumentIdentifier::new(uri.clone());
+        server.did_close(DidCloseTextDocumentParams { text_document }).await;
+        let server_guard = server.server_state.read().await;
+        assert!(diagnostics(&server_guard).is_empty());
+        let file_path = server_guard.server_config.paths.url_to_document_path(&uri).unwrap();
+        assert_eq!(server_guard.server_config.paths.convert_slice_path_to_uri(file_path), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn new_files_never_overwrite_existing_files() {
+        let (service, _socket) = build_service();
+        let server = service.inner();
+        let directory = std::env::temp_dir().join(format!("slice-language-server-new-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        server.new_file(&directory, "Created").await.unwrap();
+        let created_text = fs::read_to_string(directory.join("Created.slice")).unwrap();
+        fs::write(directory.join("Existing.slice"), "module Existing").unwrap();
+
+        for file_name in ["Created", "Existing.slice"] {
+            let error = server.new_file(&directory, file_name).await.unwrap_err();
+            assert!(error.message.contains("already exists"), "{}", error.message);
+        }
+        assert_eq!(fs::read_to_string(directory.join("Created.slice")).unwrap(), created_text);
+        assert_eq!(fs::read_to_string(directory.join("Existing.slice")).unwrap(), "module Existing");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+
+    fn open_document(client: &TestClient, path: &Path) -> Url {
+        let uri = Url::from_file_path(path).unwrap();
+        let text = fs::read_to_string(path).unwrap();
+        let text_document = json!({ "uri": uri, "languageId": "slice", "version": 1, "text": text });
+        client.notify(notification::DidOpenTextDocument::METHOD, json!({ "textDocument": text_document }));
+        uri
+    }
+
+    /// Replaces the whole text of an open document, like typing in the client would.
+    fn change_document(client: &TestClient, uri: &Url, version: i32, text: &str) {
+        let params = json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": [{ "text": text }],
+        });
+        client.notify(notification::DidChangeTextDocument::METHOD, params);
+    }
+
+    /// Hovers over the provided position of a document, and returns the hover's message.
+    async fn hover_message(client: &mut TestClient, uri: &Url, line: u32, character: u32) -> Value {
+        let params = json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+        let mut hover = client.request(request::HoverRequest::METHOD, params).await;
+        hover["contents"]["value"].take()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn hovers_are_answered_while_a_set_is_compiling() {
+        let set_name = "hover while compiling";
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        client.initialize(&fixture_path, single_set_options(set_name, &fixture_path)).await;
+        client.published_diagnostics(&fixture_path.join("sender.slice")).await;
+        let greeter_path = fixture_path.join("greeter.slice");
+        let uri = open_document(&client, &greeter_path);
+        wait_for_compilations(&mut client).await;
+
+        // Editing the document compiles its set again, which the gate holds like a slow compilation would.
+        let gate = CompilationGate::close(set_name);
+        let text = fs::read_to_string(&greeter_path).unwrap() + "\n// A comment.\n";
+        change_document(&client, &uri, 2, &text);
+        gate.wait_for_compilations(1).await;
+
+        let hover = tokio::time::timeout(Duration::from_secs(5), hover_message(&mut client, &uri, 3, 12)).await;
+        assert_eq!(hover.expect("the hover waited for the compilation"), json!("A UTF-8 string."));
+        assert_eq!(gate.compilation_count(), 1);
+
+        gate.open();
+        wait_for_compilations(&mut client).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn hovers_are_answered_from_the_previous_compilation_until_the_new_one_is_installed() {
+        let set_name = "hover before install";
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        client.initialize(&fixture_path, single_set_options(set_name, &fixture_path)).await;
+        client.published_diagnostics(&fixture_path.join("sender.slice")).await;
+        let greeter_path = fixture_path.join("greeter.slice");
+        let uri = open_document(&client, &greeter_path);
+        wait_for_compilations(&mut client).await;
+
+        // Change the type of a field from 'string' to 'int32', and hover over it while the set is being compiled.
+        let gate = CompilationGate::close(set_name);
+        let text = fs::read_to_string(&greeter_path).unwrap().replace("text: string", "text: int32");
+        change_document(&client, &uri, 2, &text);
+        gate.wait_for_compilations(1).await;
+        assert_eq!(hover_message(&mut client, &uri, 3, 12).await, json!("A UTF-8 string."));
+
+        gate.open();
+        wait_for_compilations(&mut client).await;
+        assert_eq!(hover_message(&mut client, &uri, 3, 12).await, json!("A 32-bit signed integer type."));
+    }
+
+    /// Closes a document, and waits until the server has handled it.
+    async fn close_document(client: &mut TestClient, uri: &Url) {
+        client.notify(notification::DidCloseTextDocument::METHOD, json!({ "textDocument": { "uri": uri } }));
+        while client.request(Health::METHOD, Value::Null).await["openDocuments"] != 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// Returns the diagnostics that were published for the provided file since the notifications were last taken.
+    fn take_published_diagnostics(client: &mut TestClient, uri: &Url) -> Vec<Value> {
+        let notifications = client.take_notifications(notification::PublishDiagnostics::METHOD);
+        let is_file = |params: &&Value| params["uri"] == uri.as_str();
+        notifications.iter().filter(is_file).map(|params| params["diagnostics"].clone()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn closing_a_document_which_isnt_in_any_set_clears_its_diagnostics() {
+        let mut client = TestClient::start();
+        let relative_path = fixture_path("relative");
+        client.initialize(&fixture_path(""), single_set_options("closed untracked", &relative_path)).await;
+        client.published_diagnostics(&relative_path.join("sender.slice")).await;
+
+        let outer_path = fixture_path("type_ids").join("outer.slice");
+        let uri = open_document(&client, &outer_path);
+        wait_for_compilations(&mut client).await;
+        take_published_diagnostics(&mut client, &uri);
+        close_document(&mut client, &uri).await;
+        assert!(client.published_diagnostics(&outer_path).await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn closing_a_document_which_is_in_a_set_keeps_its_diagnostics() {
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        client.initialize(&fixture_path, single_set_options("closed tracked", &fixture_path)).await;
+        client.published_diagnostics(&fixture_path.join("sender.slice")).await;
+
+        // The document's error is removed, so that closing it (which discards the change) publishes its error again.
+        let sender_path = fixture_path.join("sender.slice");
+        let uri = open_document(&client, &sender_path);
+        change_document(&client, &uri, 2, "module Fixtures\n");
+        assert!(client.published_diagnostics(&sender_path).await.is_empty());
+        close_document(&mut client, &uri).await;
+        assert_eq!(client.published_diagnostics(&sender_path).await.len(), 1);
+
+        // Its diagnostics are never cleared in between.
+        wait_for_compilations(&mut client).await;
+        let published = take_published_diagnostics(&mut client, &uri);
+        assert!(published.iter().all(|diagnostics| diagnostics.as_array().unwrap().len() == 1), "{published:?}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn each_server_publishes_symlinked_files_under_the_uris_its_client_sent() {
+        let workspace_path = relative_fixture_copy("symlinked-workspace");
+        let linked_workspace_path = workspace_path.with_extension("link");
+        let _ = fs::remove_file(&linked_workspace_path);
+        std::os::unix::fs::symlink(&workspace_path, &linked_workspace_path).unwrap();
+
+        // One client opens the workspace through the symlink, and another one opens it through its real path.
+        let mut linked_client = TestClient::start();
+        let options = single_set_options("symlinked workspace", &linked_workspace_path);
+        linked_client.initialize(&linked_workspace_path, options).await;
+        let mut client = TestClient::start();
+        client.initialize(&workspace_path, single_set_options("real workspace", &workspace_path)).await;
+
+        // Each server publishes the diagnostics under the URIs that its own client knows the files by.
+        let linked_diagnostics = linked_client.published_diagnostics(&linked_workspace_path.join("sender.slice")).await;
+        assert_eq!(linked_diagnostics.len(), 1);
+        assert_eq!(client.published_diagnostics(&workspace_path.join("sender.slice")).await.len(), 1);
+
+        fs::remove_file(&linked_workspace_path).unwrap();
+        fs::remove_dir_all(&workspace_path).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancelled_requests_dont_write_documentation() {
+        let set_name = "cancelled docs";
+        let output_directory = std::env::temp_dir().join(format!("slice-language-server-docs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_directory);
+        let mut client = TestClient::start();
+        let fixture_path = fixture_path("relative");
+        client.initialize(&fixture_path, single_set_options(set_name, &fixture_path)).await;
+        client.published_diagnostics(&fixture_path.join("sender.slice")).await;
+
+        let gate = CompilationGate::close(set_name);
+        let params = json!({ "command": "slice.generateDocs", "arguments": [output_directory, set_name] });
+        let id = client.send_request(request::ExecuteCommand::METHOD, params);
+        gate.wait_for_compilations(1).await;
+
+        // The client is answered right away, even though the set is still being compiled for the request.
+        client.notify(notification::Cancel::METHOD, json!({ "id": id }));
+        let error = client.response(id).await.unwrap_err();
+        assert_eq!(error["code"], json!(-32800));
+
+        gate.open();
+        wait_for_compilations(&mut client).await;
+        assert!(!output_directory.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancelled_requests_stop_compiling_the_remaining_sets() {
+        let set_names = ["cancelled stats (first)", "cancelled stats (second)"];
+        let fixture_path = fixture_path("relative");
+        let configuration = |name| json!({ "name": name, "paths": [fixture_path], "addWellKnownTypes": false });
+        let configurations = set_names.map(configuration);
+        let mut client = TestClient::start();
+        client.initialize(&fixture_path, json!({ "configurations": configurations })).await;
+        wait_for_compilations(&mut client).await;
+
+        let gates = set_names.map(CompilationGate::close);
+        let params = json!({ "command": "slice.workspaceStats", "arguments": [] });
+        let id = client.send_request(request::ExecuteCommand::METHOD, params);
+        gates[0].wait_for_compilations(1).await;
+        client.notify(notification::Cancel::METHOD, json!({ "id": id }));
+        assert!(client.response(id).await.is_err());
+
+        // Once the first set is done compiling, the second one isn't compiled, since the request was cancelled.
+        let [first_gate, second_gate] = gates;
+        first_gate.open();
+        wait_for_compilations(&mut client).await;
+        assert_eq!(second_gate.compilation_count(), 0);
+    }
+}
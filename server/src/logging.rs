@@ -0,0 +1,169 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::LogLevel;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tower_lsp::lsp_types::MessageType;
+use tower_lsp::Client;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+/// Controls how verbose the server's logs are, and whether they're also written to a file.
+///
+/// The server logs with the `tracing` macros. Every event that passes the current level is sent to the client's output
+/// with `window/logMessage`, and (if a log file is set) appended to the log file, along with a timestamp.
+#[derive(Clone)]
+pub struct Logger {
+    level_handle: reload::Handle<LevelFilter, Registry>,
+    log_file: LogFile,
+}
+
+impl Logger {
+    /// Installs the server's logger as the global `tracing` subscriber, sending its output to the provided client.
+    /// Until the user's settings are applied, everything at the `info` level (or above) is logged.
+    ///
+    /// Only one subscriber can be installed per process, so if several servers run in the same process (like in tests),
+    /// only the first one's logger is installed, and the others' events are logged through it.
+    pub fn init(client: Client) -> Self {
+        let (level_filter, level_handle) = reload::Layer::new(LevelFilter::INFO);
+        let log_file = LogFile::default();
+
+        // Messages are sent to the client from a single task, so that they arrive in the order they were logged.
+        let (sender, mut receiver) = unbounded_channel();
+        tokio::spawn(async move {
+            while let Some((message_type, message)) = receiver.recv().await {
+                client.log_message(message_type, message).await;
+            }
+        });
+
+        let file_layer = tracing_subscriber::fmt::layer()
+            .with_writer(log_file.clone())
+            .with_ansi(false);
+        tracing_subscriber::registry()
+            .with(level_filter)
+            .with(file_layer)
+            .with(ClientLayer { sender })
+            .try_init()
+            .ok();
+
+        Logger { level_handle, log_file }
+    }
+
+    /// Sets the level of the events that are logged. This takes effect immediately.
+    pub fn set_level(&self, level: LogLevel) {
+        let level_filter = match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        };
+        if let Err(error) = self.level_handle.reload(level_filter) {
+            tracing::warn!("Failed to set the log level: {error}");
+        }
+    }
+
+    /// Sets the file that events are appended to, or stops writing them to a file if `path` is `None`.
+    /// If the file can't be opened, the previous log file (if any) is kept.
+    pub fn set_log_file(&self, path: Option<&Path>) -> io::Result<()> {
+        let mut current_file = self.log_file.lock();
+        if current_file.as_ref().map(|(current_path, _)| current_path.as_path()) == path {
+            return Ok(());
+        }
+
+        *current_file = match path {
+            Some(path) => Some((path.to_owned(), OpenOptions::new().create(true).append(true).open(path)?)),
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+/// The file that events are written to (if any), along with its path.
+#[derive(Clone, Default)]
+struct LogFile(Arc<Mutex<Option<(PathBuf, File)>>>);
+
+impl LogFile {
+    fn lock(&self) -> MutexGuard<'_, Option<(PathBuf, File)>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogFileWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogFileWriter(self.lock())
+    }
+}
+
+/// Writes to the log file, or discards everything if there isn't one.
+struct LogFileWriter<'a>(MutexGuard<'a, Option<(PathBuf, File)>>);
+
+impl Write for LogFileWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.as_mut() {
+            Some((_, file)) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.as_mut() {
+            Some((_, file)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A layer that forwards events to the task which sends them to the client.
+struct ClientLayer {
+    sender: UnboundedSender<(MessageType, String)>,
+}
+
+impl<S: Subscriber> Layer<S> for ClientLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        // Only warnings and errors are forwarded from other crates. Their debug events include the messages that are
+        // sent to the client, so forwarding those would cause an endless feedback loop.
+        let metadata = event.metadata();
+        if !metadata.target().starts_with(env!("CARGO_CRATE_NAME")) && *metadata.level() > Level::WARN {
+            return;
+        }
+
+        let message_type = match *metadata.level() {
+            Level::ERROR => MessageType::ERROR,
+            Level::WARN => MessageType::WARNING,
+            Level::INFO => MessageType::INFO,
+            _ => MessageType::LOG,
+        };
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        // This only fails if the server is shutting down, in which case there's no one to send the message to anyways.
+        let _ = self.sender.send((message_type, visitor.message));
+    }
+}
+
+/// Formats an event's message, followed by any other fields it has (as `name=value` pairs).
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.message, "{value:?}"),
+            name => write!(self.message, " {name}={value:?}"),
+        };
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::file_index::FileIndex;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::server_state::ServerState;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tower_lsp::lsp_types::{Position, Range};
+
+/// The version of the LSIF format that [`write_lsif_dump`] produces.
+const LSIF_VERSION: &str = "0.6.0";
+
+/// The scheme of the monikers that entities are exported with. Their identifiers are the entities' fully-qualified
+/// identifiers (like `Foo::Bar`), which are unique within a configuration set, and stable across compilations.
+const MONIKER_SCHEME: &str = "slice";
+
+/// How many documents, entities, and ranges were written to an LSIF dump.
+#[derive(Debug, Default)]
+pub struct LsifSummary {
+    pub documents: usize,
+    pub entities: usize,
+    pub ranges: usize,
+}
+
+/// Writes an LSIF dump of every configuration set to the provided writer, for the `slice.exportIndex` command.
+///
+/// The dump holds the definitions and references of each entity, along with a hover and a moniker for it, so that code
+/// navigation tools can navigate Slice files without running the server. It's built from what's indexed after each set
+/// is compiled, so a file that's in multiple sets is only included once, and entities with the same fully-qualified
+/// identifier in different sets are treated as the same entity.
+///
+/// Elements are written one line at a time as they're produced, so the dump is never held in memory all at once.
+pub fn write_lsif_dump(server_state: &ServerState, writer: impl Write) -> io::Result<LsifSummary> {
+    let encoding = server_state.server_config.position_encoding;
+
+    // Collect the files and entities of every set up front, so that they can be written in a stable order.
+    let mut files: BTreeMap<PathBuf, &FileIndex> = BTreeMap::new();
+    let mut entities: BTreeMap<&str, IndexedEntity> = BTreeMap::new();
+    for set in &server_state.configuration_sets {
+        let compilation_data = &set.compilation_data;
+        for (path, file) in &compilation_data.files {
+            files.entry(path.clone()).or_insert(file);
+            for definition in &file.definitions {
+                let entity = entities.entry(&definition.identifier).or_default();
+                entity.kind.get_or_insert(definition.kind);
+                entity.definitions.insert((path.clone(), RangeKey::from(definition.range)));
+            }
+        }
+        for (identifier, references) in compilation_data.references.iter() {
+            let entity = entities.entry(identifier).or_default();
+            for reference in references {
+                entity.references.insert((reference.path.clone(), RangeKey::from(reference.range)));
+            }
+        }
+    }
+
+    let mut emitter = Emitter { writer, next_id: 1 };
+    let mut summary = LsifSummary::default();
+    let paths = &server_state.server_config.paths;
+    let project_root = server_state
+        .server_config
+        .workspace_root_paths
+        .first()
+        .and_then(|root_path| paths.convert_slice_path_to_uri(root_path));
+    emitter.vertex(
+        "metaData",
+        json!({
+            "version": LSIF_VERSION,
+            "projectRoot": project_root,
+            "positionEncoding": "utf-16",
+            "toolInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+        }),
+    )?;
+    let project_id = emitter.vertex("project", json!({ "kind": "slice" }))?;
+
+    // Documents are identified by their paths, and converted into URIs when they're written.
+    let mut documents: BTreeMap<PathBuf, Document> = BTreeMap::new();
+    for (path, file) in &files {
+        let Some(uri) = paths.convert_slice_path_to_uri(path) else {
+            continue;
+        };
+        let id = emitter.vertex("document", json!({ "uri": uri, "languageId": "slice" }))?;
+        documents.insert(path.clone(), Document { id, text: &file.raw_text, range_ids: Vec::new() });
+    }
+    summary.documents = documents.len();
+
+    for (identifier, entity) in &entities {
+        // Skip entities that aren't defined or referenced in any of the documents.
+        let mut locations = entity.definitions.iter().chain(&entity.references);
+        if !locations.any(|(path, _)| documents.contains_key(path)) {
+            continue;
+        }
+        summary.entities += 1;
+
+        let result_set_id = emitter.vertex("resultSet", json!({}))?;
+        let moniker = json!({
+            "scheme": MONIKER_SCHEME,
+            "identifier": identifier,
+            "kind": "export",
+            "unique": "scheme",
+        });
+        let moniker_id = emitter.vertex("moniker", moniker)?;
+        emitter.edge("moniker", result_set_id, moniker_id)?;
+
+        let hover_text = match entity.kind {
+            Some(kind) => format!("{kind} `{identifier}`"),
+            None => format!("`{identifier}`"),
+        };
+        let hover = json!({ "result": { "contents": { "kind": "markdown", "value": hover_text } } });
+        let hover_id = emitter.vertex("hoverResult", hover)?;
+        emitter.edge("textDocument/hover", result_set_id, hover_id)?;
+
+        // Write a range for each definition and reference, grouped by the document they're in.
+        let mut definition_ranges: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        let mut reference_ranges: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        let mut write_range = |(path, range): &(PathBuf, RangeKey), ranges: &mut BTreeMap<u64, Vec<u64>>| {
+            let Some(document) = documents.get_mut(path) else {
+                return Ok(());
+            };
+            let range = to_utf16(document.text, range.into(), encoding);
+            let range_id = emitter.vertex("range", json!({ "start": range.start, "end": range.end }))?;
+            emitter.edge("next", range_id, result_set_id)?;
+            document.range_ids.push(range_id);
+            ranges.entry(document.id).or_default().push(range_id);
+            summary.ranges += 1;
+            io::Result::Ok(())
+        };
+        for location in &entity.definitions {
+            write_range(location, &mut definition_ranges)?;
+        }
+        for location in &entity.references {
+            write_range(location, &mut reference_ranges)?;
+        }
+
+        if !definition_ranges.is_empty() {
+            let definition_result_id = emitter.vertex("definitionResult", json!({}))?;
+            emitter.edge("textDocument/definition", result_set_id, definition_result_id)?;
+            for (document_id, range_ids) in &definition_ranges {
+                emitter.item_edge(definition_result_id, range_ids, *document_id, None)?;
+            }
+        }
+
+        let reference_result_id = emitter.vertex("referenceResult", json!({}))?;
+        emitter.edge("textDocument/references", result_set_id, reference_result_id)?;
+        for (document_id, range_ids) in &definition_ranges {
+            emitter.item_edge(reference_result_id, range_ids, *document_id, Some("definitions"))?;
+        }
+        for (document_id, range_ids) in &reference_ranges {
+            emitter.item_edge(reference_result_id, range_ids, *document_id, Some("references"))?;
+        }
+    }
+
+    for document in documents.values().filter(|document| !document.range_ids.is_empty()) {
+        emitter.edges("contains", document.id, &document.range_ids)?;
+    }
+    let document_ids = documents.values().map(|document| document.id).collect::<Vec<_>>();
+    if !document_ids.is_empty() {
+        emitter.edges("contains", project_id, &document_ids)?;
+    }
+
+    emitter.writer.flush()?;
+    Ok(summary)
+}
+
+/// The definitions and references of an entity, across every configuration set.
+#[derive(Default)]
+struct IndexedEntity<'a> {
+    kind: Option<&'a str>,
+    definitions: BTreeSet<(PathBuf, RangeKey)>,
+    references: BTreeSet<(PathBuf, RangeKey)>,
+}
+
+/// A document that was written to the dump, along with the ranges that were written in it.
+struct Document<'a> {
+    id: u64,
+    text: &'a str,
+    range_ids: Vec<u64>,
+}
+
+/// A range, in a form which can be ordered (so that the same range is only written once, even if it was indexed by
+/// multiple sets).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct RangeKey([u32; 4]);
+
+impl From<Range> for RangeKey {
+    fn from(range: Range) -> Self {
+        RangeKey([range.start.line, range.start.character, range.end.line, range.end.character])
+    }
+}
+
+impl From<&RangeKey> for Range {
+    fn from(RangeKey([start_line, start_character, end_line, end_character]): &RangeKey) -> Self {
+        Range::new(Position::new(*start_line, *start_character), Position::new(*end_line, *end_character))
+    }
+}
+
+/// Converts a range that was indexed in the provided encoding into UTF-16, which is what LSIF positions are counted in.
+fn to_utf16(text: &str, range: Range, encoding: PositionEncoding) -> Range {
+    if encoding == PositionEncoding::Utf16 {
+        return range;
+    }
+    let indexed = PositionConverter::new(text, encoding);
+    let utf16 = PositionConverter::new(text, PositionEncoding::Utf16);
+    let convert = |position| utf16.location_to_position(&indexed.position_to_location(position));
+    Range::new(convert(range.start), convert(range.end))
+}
+
+/// Writes the elements of an LSIF dump (one per line), giving each of them the next ID.
+struct Emitter<W: Write> {
+    writer: W,
+    next_id: u64,
+}
+
+impl<W: Write> Emitter<W> {
+    fn emit(&mut self, mut element: Value) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        element["id"] = json!(id);
+        serde_json::to_writer(&mut self.writer, &element)?;
+        self.writer.write_all(b"\n")?;
+        Ok(id)
+    }
+
+    fn vertex(&mut self, label: &str, mut properties: Value) -> io::Result<u64> {
+        properties["type"] = json!("vertex");
+        properties["label"] = json!(label);
+        self.emit(properties)
+    }
+
+    fn edge(&mut self, label: &str, out_v: u64, in_v: u64) -> io::Result<u64> {
+        self.emit(json!({ "type": "edge", "label": label, "outV": out_v, "inV": in_v }))
+    }
+
+    fn edges(&mut self, label: &str, out_v: u64, in_vs: &[u64]) -> io::Result<u64> {
+        self.emit(json!({ "type": "edge", "label": label, "outV": out_v, "inVs": in_vs }))
+    }
+
+    /// Writes an `item` edge, which adds ranges of a document to a definition or reference result.
+    fn item_edge(&mut self, out_v: u64, in_vs: &[u64], document: u64, property: Option<&str>) -> io::Result<u64> {
+        let mut edge = json!({ "type": "edge", "label": "item", "outV": out_v, "inVs": in_vs, "document": document });
+        if let Some(property) = property {
+            edge["property"] = json!(property);
+        }
+        self.emit(edge)
+    }
+}
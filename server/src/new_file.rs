@@ -0,0 +1,203 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::server_state::ServerState;
+use std::path::{Component, Path, PathBuf};
+
+/// The name of the file that `slice.newFile` creates, if the client doesn't provide one.
+pub const DEFAULT_FILE_NAME: &str = "NewFile.slice";
+
+/// Where a new Slice file goes, and what it starts with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NewFileTarget {
+    /// The name of the configuration set that the file is in, if it's in one.
+    pub set_name: Option<String>,
+    /// The module that the file declares.
+    pub module_name: String,
+    /// The mode that the file uses.
+    pub mode: &'static str,
+}
+
+/// Returns where a new file at the provided path goes, for the `slice.newFile` command.
+///
+/// If the file is in a configuration set, its module is inferred from where its directory is within the set's search
+/// path, and its mode from the set's other files. Otherwise, its module is inferred from where its directory is within
+/// the workspace (or from the directory alone if there isn't one), and it uses `Slice2`.
+pub fn new_file_target(server_state: &ServerState, directory: &Path, file_path: &Path) -> NewFileTarget {
+    let containing_set = server_state
+        .configuration_sets
+        .iter()
+        .filter(|set| !set.is_stand_alone() && set.contains_file(file_path))
+        .find_map(|set| set.search_path_containing(directory).map(|search_path| (set, search_path)));
+    match containing_set {
+        Some((set, search_path)) => {
+            let file_texts = set.compilation_data.files.values().map(|file| file.raw_text.as_str());
+            NewFileTarget {
+                set_name: Some(set.name.clone()),
+                module_name: infer_module_name(directory, search_path),
+                mode: infer_mode(file_texts),
+            }
+        }
+        None => {
+            let root_paths = &server_state.server_config.workspace_root_paths;
+            let root_path = root_paths.iter().find(|root| directory.starts_with(root));
+            let module_name = infer_module_name(directory, root_path.map_or(directory, PathBuf::as_path));
+            NewFileTarget { set_name: None, module_name, mode: infer_mode(std::iter::empty()) }
+        }
+    }
+}
+
+/// Returns the name of the module that a file in the provided directory should declare, for the `slice.newFile`
+/// command: the directory's path relative to the search path it's in, with each directory becoming a nested module
+/// (so `Foo/Bar` becomes `Foo::Bar`).
+///
+/// Files directly in the search path don't have any directories to go on, so they're named after the search path's own
+/// directory instead. Directory names which aren't valid Slice identifiers are fixed up, so the result always parses.
+pub fn infer_module_name(directory: &Path, search_path: &Path) -> String {
+    let relative_path = directory.strip_prefix(search_path).unwrap_or(Path::new(""));
+    let mut modules = relative_path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(to_identifier(&name.to_string_lossy())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if modules.is_empty() {
+        let name = directory.file_name().map(|name| name.to_string_lossy());
+        modules.push(to_identifier(name.as_deref().unwrap_or_default()));
+    }
+    modules.join("::")
+}
+
+/// Returns the mode that a new file should use, which is the mode used by most of the provided files (the other files
+/// of its configuration set). Files without a `mode` statement use `Slice2`, which is also used if there aren't any.
+pub fn infer_mode<'a>(file_texts: impl Iterator<Item = &'a str>) -> &'static str {
+    let (mut slice1_files, mut slice2_files) = (0, 0);
+    for text in file_texts {
+        let is_slice1 = text.lines().any(|line| {
+            let line = line.trim();
+            line.strip_prefix("mode")
+                .and_then(|rest| rest.trim_start().strip_prefix('='))
+                .is_some_and(|mode| mode.trim() == "Slice1")
+        });
+        match is_slice1 {
+            true => slice1_files += 1,
+            false => slice2_files += 1,
+        }
+    }
+    if slice1_files > slice2_files { "Slice1" } else { "Slice2" }
+}
+
+/// Returns the contents of a new Slice file: the provided header (as a comment), a `mode` statement, and a `module`
+/// declaration. Each line of the header is turned into a comment, unless it's one already.
+pub fn new_file_contents(header: &str, mode: &str, module_name: &str) -> String {
+    let mut contents = String::new();
+    for line in header.lines() {
+        match line.trim_start().starts_with("//") || line.trim().is_empty() {
+            true => contents.push_str(line.trim_end()),
+            false => contents.push_str(&format!("// {}", line.trim_end())),
+        }
+        contents.push('\n');
+    }
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    contents.push_str(&format!("mode = {mode}\n\nmodule {module_name}\n"));
+    contents
+}
+
+/// Turns a directory name into a valid Slice identifier, by replacing any characters which can't be in an identifier
+/// with underscores, and prefixing names which don't start with a letter.
+fn to_identifier(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+    match identifier.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        true => identifier,
+        false => format!("M{identifier}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration_set::ConfigurationSet;
+    use std::collections::HashMap;
+
+    /// A workspace with a configuration set for its `slice` directory (whose files use `Slice1`), and an `other`
+    /// directory which isn't in any set.
+    fn fixture_path() -> PathBuf {
+        std::env::current_dir().unwrap().join("tests/fixtures/new_file")
+    }
+
+    /// Returns a server with the fixture's configuration set, which has been compiled.
+    fn server_state() -> ServerState {
+        let mut server_state = ServerState::default();
+        server_state.server_config.workspace_root_paths = vec![fixture_path()];
+
+        let search_path = fixture_path().join("slice");
+        let mut set = ConfigurationSet::discovered_set("shapes".to_owned(), vec![search_path], None);
+        set.slice_config.include_built_in_slice_files = false;
+        let server_config = &server_state.server_config;
+        let compilation = set.prepare_compilation(server_config, &HashMap::new());
+        set.install_compilation(server_config, compilation.run()).unwrap();
+        server_state.configuration_sets.push(set);
+        server_state
+    }
+
+    fn target(server_state: &ServerState, directory: &Path) -> NewFileTarget {
+        new_file_target(server_state, directory, &directory.join(DEFAULT_FILE_NAME))
+    }
+
+    #[test]
+    fn nested_directories_become_nested_modules() {
+        let server_state = server_state();
+        let directory = fixture_path().join("slice/Shapes/Round-ish");
+        let expected = NewFileTarget {
+            set_name: Some("shapes".to_owned()),
+            module_name: "Shapes::Round_ish".to_owned(),
+            mode: "Slice1",
+        };
+        assert_eq!(target(&server_state, &directory), expected);
+
+        let directory = fixture_path().join("slice/Shapes");
+        assert_eq!(target(&server_state, &directory).module_name, "Shapes");
+    }
+
+    #[test]
+    fn files_directly_in_a_search_path_are_named_after_it() {
+        let server_state = server_state();
+        let target = target(&server_state, &fixture_path().join("slice"));
+        assert_eq!(target.set_name.as_deref(), Some("shapes"));
+        assert_eq!(target.module_name, "slice");
+    }
+
+    #[test]
+    fn directories_outside_of_every_set_are_named_after_their_path_in_the_workspace() {
+        let server_state = server_state();
+        let expected = NewFileTarget { set_name: None, module_name: "other::Nested".to_owned(), mode: "Slice2" };
+        assert_eq!(target(&server_state, &fixture_path().join("other/Nested")), expected);
+    }
+
+    #[test]
+    fn directories_without_a_workspace_are_named_after_themselves() {
+        let server_state = ServerState::default();
+        let expected = NewFileTarget { set_name: None, module_name: "Nested".to_owned(), mode: "Slice2" };
+        assert_eq!(target(&server_state, &fixture_path().join("other/Nested")), expected);
+    }
+
+    #[test]
+    fn directory_names_are_turned_into_identifiers() {
+        let search_path = Path::new("/workspace/slice");
+        assert_eq!(infer_module_name(&search_path.join("my-shapes/3d"), search_path), "my_shapes::M3d");
+        assert_eq!(infer_module_name(search_path, search_path), "slice");
+    }
+
+    #[test]
+    fn headers_are_turned_into_comments() {
+        let contents = new_file_contents("Copyright (c) ZeroC, Inc.\n// Already a comment", "Slice2", "Foo::Bar");
+        let expected = "// Copyright (c) ZeroC, Inc.\n// Already a comment\n\nmode = Slice2\n\nmodule Foo::Bar\n";
+        assert_eq!(contents, expected);
+        assert_eq!(new_file_contents("", "Slice1", "Foo"), "mode = Slice1\n\nmodule Foo\n");
+    }
+}
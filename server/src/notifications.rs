@@ -1,8 +1,24 @@
 // Copyright (c) ZeroC, Inc.
 
+//! This module contains the custom notifications that the server sends to the client, in addition to the ones defined
+//! by the Language Server Protocol. All of their methods are prefixed with `custom/`.
+//!
+//! - `custom/showNotification`: asks the client to show a message to the user.
+//!   Only `message` and `message_type` are guaranteed to be present. The remaining fields are optional, and are
+//!   omitted when the server doesn't know them, so clients must be prepared to handle their absence.
+//! - `custom/editConfiguration`: asks the client to add or remove a path from the user's Slice configuration.
+//! - `custom/saveConfigurations`: asks the client to write configuration sets into the user's settings.
+//! - `custom/openSettings`: asks the client to open its settings editor.
+//!
+//! Notifications which are meant for other tools (rather than for the extension itself) are prefixed with `slice/`
+//! instead, like the custom requests in [`crate::requests`]:
+//!
+//! - `slice/didCompile`: sent whenever the results of a configuration set's compilation have been stored, so that
+//!   external build steps (like code generation) can be chained to it. See [`DidCompileParams`] for its payload.
+
 use lsp_types::notification::Notification;
 use serde::{Deserialize, Serialize};
-use tower_lsp::lsp_types;
+use tower_lsp::lsp_types::{self, Url};
 
 #[derive(Debug)]
 pub struct ShowNotification;
@@ -16,11 +32,135 @@ impl Notification for ShowNotification {
 pub struct ShowNotificationParams {
     pub message: String,
     pub message_type: MessageType,
+    /// The code of the diagnostic that caused this notification, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The name of the configuration set this notification relates to, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configuration_set: Option<String>,
+    /// The URI of the file (or directory) this notification relates to, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<Url>,
+    /// A machine-readable description of what this notification is about.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<NotificationKind>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl ShowNotificationParams {
+    /// Creates a notification that only contains a message, without any of the optional fields set.
+    pub fn new(message: String, message_type: MessageType) -> Self {
+        ShowNotificationParams {
+            message,
+            message_type,
+            code: None,
+            configuration_set: None,
+            uri: None,
+            kind: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum MessageType {
     Error,
     Warning,
     Info,
 }
+
+impl From<MessageType> for lsp_types::MessageType {
+    fn from(message_type: MessageType) -> Self {
+        match message_type {
+            MessageType::Error => lsp_types::MessageType::ERROR,
+            MessageType::Warning => lsp_types::MessageType::WARNING,
+            MessageType::Info => lsp_types::MessageType::INFO,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationKind {
+    /// The user's configuration is incorrect, or couldn't be applied.
+    ConfigurationError,
+    /// A file was opened that isn't part of any configuration set.
+    FileNotInSet,
+}
+
+#[derive(Debug)]
+pub struct EditConfiguration;
+
+impl Notification for EditConfiguration {
+    type Params = EditConfigurationParams;
+    const METHOD: &'static str = "custom/editConfiguration";
+}
+
+/// Asks the client to edit the `paths` of the user's Slice configuration, since only the client can modify settings.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EditConfigurationParams {
+    pub edit: ConfigurationEdit,
+    /// The path to add or remove. It is relative to the workspace root when possible, and absolute otherwise.
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum ConfigurationEdit {
+    AddPath,
+    RemovePath,
+}
+
+#[derive(Debug)]
+pub struct SaveConfigurations;
+
+impl Notification for SaveConfigurations {
+    type Params = SaveConfigurationsParams;
+    const METHOD: &'static str = "custom/saveConfigurations";
+}
+
+/// Asks the client to replace the user's 'slice.configurations' setting with the provided configuration sets.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SaveConfigurationsParams {
+    /// The URI of the workspace folder whose settings are updated, or `None` to update the workspace's settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_uri: Option<Url>,
+    /// The configuration sets, in the same layout as the setting's entries.
+    pub configurations: Vec<serde_json::Value>,
+}
+
+#[derive(Debug)]
+pub struct OpenSettings;
+
+impl Notification for OpenSettings {
+    type Params = OpenSettingsParams;
+    const METHOD: &'static str = "custom/openSettings";
+}
+
+/// Asks the client to open its settings editor.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OpenSettingsParams {
+    /// The query used to filter the displayed settings.
+    pub query: String,
+}
+
+#[derive(Debug)]
+pub struct DidCompile;
+
+impl Notification for DidCompile {
+    type Params = DidCompileParams;
+    const METHOD: &'static str = "slice/didCompile";
+}
+
+/// Tells the client that a configuration set was compiled. This is sent for failed compilations too, since their
+/// results replace the set's previous ones, so listeners should check `success` before acting on it.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCompileParams {
+    /// The name of the configuration set that was compiled.
+    pub configuration_set: String,
+    /// Whether the compilation succeeded, meaning that no errors were reported in the set (warnings are allowed).
+    pub success: bool,
+    /// The URIs of the files that were compiled (including referenced files), sorted.
+    pub uris: Vec<Url>,
+    /// A hash of the set's options and the contents of its files. Compiling the same inputs again produces the same
+    /// hash, so this can be used to skip redundant build steps. It's only comparable within a single run of the server.
+    pub input_hash: String,
+}
@@ -0,0 +1,116 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::reference_covers_file;
+use crate::server_state::UnsavedDocument;
+use crate::utils::find_slice_files;
+use slicec::slice_options::SliceOptions;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fs, io};
+
+/// Used to give each overlay its own directory, so that concurrent compilations never share files.
+static NEXT_OVERLAY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Holds the unsaved text of documents in temporary files, so they can be compiled in place of the documents' files.
+///
+/// `slicec` can only compile files from disk, so to compile the text of a document that hasn't been saved, we write it
+/// to a temporary file, and point the compiler at that file instead of the document's own file. Since the compiler
+/// reports spans in terms of the temporary files, [`Overlay::original_paths`] is used to map them back.
+///
+/// The temporary files are deleted when the overlay is dropped, which is safe as soon as compilation has finished,
+/// since `slicec` reads the contents of every file up front.
+#[derive(Debug)]
+pub struct Overlay {
+    directory: PathBuf,
+    /// Maps the path of each temporary file to the path of the document it holds the text of.
+    pub original_paths: HashMap<String, PathBuf>,
+}
+
+impl Overlay {
+    /// Returns true if any of the unsaved documents are covered by the provided options' references, in which case
+    /// they're compiled from an overlay.
+    pub fn covers_any(slice_options: &SliceOptions, unsaved_documents: &HashMap<PathBuf, UnsavedDocument>) -> bool {
+        unsaved_documents
+            .keys()
+            .any(|document_path| Self::covers(slice_options, document_path))
+    }
+
+    fn covers(slice_options: &SliceOptions, document_path: &Path) -> bool {
+        slice_options
+            .references
+            .iter()
+            .any(|reference| reference_covers_file(Path::new(reference), document_path))
+    }
+
+    /// Creates an overlay for any unsaved documents that are covered by the provided options' references, and updates
+    /// the options to reference the overlay's temporary files instead of the documents' files.
+    ///
+    /// Returns `None` if none of the unsaved documents are covered by the options, in which case the options are left
+    /// untouched.
+    pub fn create(
+        slice_options: &mut SliceOptions,
+        unsaved_documents: &HashMap<PathBuf, UnsavedDocument>,
+    ) -> io::Result<Option<Overlay>> {
+        let covered_documents = unsaved_documents
+            .iter()
+            .filter(|(path, _)| Self::covers(slice_options, path))
+            .collect::<Vec<_>>();
+        if covered_documents.is_empty() {
+            return Ok(None);
+        }
+
+        // We can't swap out individual files from a directory the compiler searches, so we expand every directory into
+        // the Slice files it contains. Paths that aren't directories are left as-is (including paths that don't exist),
+        // so the compiler still reports any problems with them.
+        let mut references = Vec::new();
+        let mut seen_paths = HashSet::new();
+        for reference in &slice_options.references {
+            let path = Path::new(reference);
+            let mut files = Vec::new();
+            match path.is_dir() {
+                true => find_slice_files(path, &mut files),
+                false => files.push(path.to_owned()),
+            }
+            for file in files {
+                if seen_paths.insert(file.clone()) {
+                    references.push(file);
+                }
+            }
+        }
+
+        let directory = std::env::temp_dir().join(format!(
+            "slice-language-server-{}-{}",
+            std::process::id(),
+            NEXT_OVERLAY_ID.fetch_add(1, Ordering::Relaxed),
+        ));
+        let mut overlay = Overlay { directory, original_paths: HashMap::new() };
+
+        for (index, (document_path, document)) in covered_documents.into_iter().enumerate() {
+            // Each file gets its own sub-directory, so that it can keep its original file name.
+            let file_directory = overlay.directory.join(index.to_string());
+            let overlay_path = file_directory.join(document_path.file_name().unwrap_or_default());
+            fs::create_dir_all(&file_directory)?;
+            fs::write(&overlay_path, &document.text)?;
+
+            // Replace the document's file with the overlay file. Documents which don't exist on disk yet (but are in a
+            // referenced directory) won't have been found by the search, and are added instead.
+            match references.iter_mut().find(|path| *path == document_path) {
+                Some(path) => *path = overlay_path.clone(),
+                None => references.push(overlay_path.clone()),
+            }
+            let overlay_path = overlay_path.display().to_string();
+            overlay.original_paths.insert(overlay_path, document_path.clone());
+        }
+
+        slice_options.references = references.iter().map(|path| path.display().to_string()).collect();
+        Ok(Some(overlay))
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        // There's nothing useful we can do if this fails, the files are in a temporary directory anyways.
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
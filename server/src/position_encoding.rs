@@ -0,0 +1,344 @@
+// Copyright (c) ZeroC, Inc.
+
+use slicec::slice_file::{Location, Span};
+use tower_lsp::lsp_types::{ClientCapabilities, Position, PositionEncodingKind, Range};
+use tracing::debug;
+
+/// How the `character` of an LSP position counts the characters of its line.
+///
+/// `slicec` counts columns in characters (unicode scalar values), whereas LSP counts them in UTF-16 code units, unless
+/// the client and server agree on a different encoding during initialization. The two only differ on lines with
+/// non-ASCII characters, so columns can only be converted with the text of their line (see [`PositionConverter`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PositionEncoding {
+    Utf8,
+    /// The encoding that every client supports, which is used unless the client offers a different one.
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Picks the encoding to use from the ones that the client supports. UTF-32 is preferred, since it counts
+    /// characters the same way that `slicec` does, followed by UTF-8. Otherwise, we fall back to UTF-16.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        let encodings = capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_deref())
+            .unwrap_or_default();
+        if encodings.contains(&PositionEncodingKind::UTF32) {
+            PositionEncoding::Utf32
+        } else if encodings.contains(&PositionEncodingKind::UTF8) {
+            PositionEncoding::Utf8
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+
+    /// Returns the kind that this encoding is advertised as, in the server's capabilities.
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Converts a (0-based) column of the provided line, counted in characters, into this encoding. Columns past the end
+    /// of the line (like the column of the line's terminator) are counted as one unit per column.
+    pub fn encode_column(self, line: &str, column: usize) -> u32 {
+        let prefix_length = line.char_indices().nth(column).map_or(line.len(), |(index, _)| index);
+        let past_end = column.saturating_sub(line.chars().count());
+        self.text_length(&line[..prefix_length]).saturating_add(clamp_to_u32(past_end))
+    }
+
+    /// Returns how many units of this encoding the provided text takes up.
+    pub fn text_length(self, text: &str) -> u32 {
+        text.chars().map(|c| self.width(c)).sum()
+    }
+
+    /// Converts a (0-based) column of the provided line, counted in this encoding, into characters. Columns which fall
+    /// inside of a character are moved to the start of the next character.
+    pub fn decode_column(self, line: &str, character: u32) -> usize {
+        let mut units = 0;
+        for (index, c) in line.chars().enumerate() {
+            if units >= character {
+                return index;
+            }
+            units += self.width(c);
+        }
+        line.chars().count() + character.saturating_sub(units) as usize
+    }
+
+    /// Returns how many units of this encoding the provided character takes up.
+    fn width(self, c: char) -> u32 {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8() as u32,
+            PositionEncoding::Utf16 => c.len_utf16() as u32,
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+/// Converts between `slicec`'s locations and LSP positions within a single file.
+///
+/// The file's lines are only split up if the conversion depends on them (if the file has non-ASCII characters, and the
+/// encoding isn't UTF-32), so that converting the positions of ASCII files is as cheap as it was before encodings were
+/// negotiated.
+pub struct PositionConverter<'a> {
+    encoding: PositionEncoding,
+    lines: Option<Vec<&'a str>>,
+}
+
+impl<'a> PositionConverter<'a> {
+    /// Creates a converter for the file with the provided text.
+    pub fn new(text: &'a str, encoding: PositionEncoding) -> Self {
+        let lines = match encoding == PositionEncoding::Utf32 || text.is_ascii() {
+            true => None,
+            false => Some(text.lines().collect()),
+        };
+        PositionConverter { encoding, lines }
+    }
+
+    /// Converts a [`slicec::slice_file::Span`] into a [`tower_lsp::lsp_types::Range`].
+    pub fn span_to_range(&self, span: &Span) -> Range {
+        Range::new(self.location_to_position(&span.start), self.location_to_position(&span.end))
+    }
+
+    /// Converts a [`slicec::slice_file::Location`] into a [`tower_lsp::lsp_types::Position`].
+    pub fn location_to_position(&self, location: &Location) -> Position {
+        let row = to_zero_based(location.row, "row");
+        let column = to_zero_based(location.col, "column");
+        let character = match self.line(row) {
+            Some(line) => self.encoding.encode_column(line, column),
+            None => clamp_to_u32(column),
+        };
+        Position::new(clamp_to_u32(row), character)
+    }
+
+    /// Converts a [`tower_lsp::lsp_types::Position`] into a [`slicec::slice_file::Location`].
+    pub fn position_to_location(&self, position: Position) -> Location {
+        let column = match self.line(position.line as usize) {
+            Some(line) => self.encoding.decode_column(line, position.character),
+            None => position.character as usize,
+        };
+        Location { row: position.line as usize + 1, col: column.saturating_add(1) }
+    }
+
+    /// Returns the text of the provided (0-based) line, if the conversion of its columns depends on it.
+    fn line(&self, row: usize) -> Option<&'a str> {
+        self.lines.as_ref().map(|lines| lines.get(row).copied().unwrap_or_default())
+    }
+}
+
+/// Converts a 1-based row or column reported by `slicec` into a 0-based one.
+///
+/// `slicec` never reports a row or column of 0 for text in a file, but spans which don't point at any text (like the
+/// spans of synthetic definitions) can have them. Subtracting from these would wrap around (or panic in debug builds),
+/// so they're clamped to 0 instead, and logged, since they usually point at an issue in the compiler.
+fn to_zero_based(value: usize, name: &str) -> usize {
+    value.checked_sub(1).unwrap_or_else(|| {
+        debug!("Clamped a {name} of 0 reported by 'slicec', which should be 1-based");
+        0
+    })
+}
+
+/// Converts a row or column into the `u32` that LSP positions use. Values which don't fit are clamped to `u32::MAX`,
+/// instead of being truncated into an unrelated position.
+fn clamp_to_u32(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or_else(|_| {
+        debug!("Clamped a row or column of {value} to the largest one that LSP positions can hold");
+        u32::MAX
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_index::FileIndex;
+    use crate::hover::get_hover_message;
+    use crate::jump_definition::get_definition_link;
+    use crate::reference_index::{ReferenceIndex, ReferenceKind};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use tower_lsp::lsp_types::GeneralClientCapabilities;
+
+    const ENCODINGS: [PositionEncoding; 3] = [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32];
+
+    /// Lines with ASCII text, characters outside of ASCII but inside the basic multilingual plane (which take up more
+    /// than 1 byte in UTF-8), and characters outside of it (which take up a surrogate pair in UTF-16).
+    const LINES: [&str; 5] = ["module Foo", "/// Grüße, 日本語", "/// 😀 👍🏽", "a😀b日c", ""];
+
+    fn capabilities(encodings: Option<Vec<PositionEncodingKind>>) -> ClientCapabilities {
+        let general = GeneralClientCapabilities { position_encodings: encodings, ..Default::default() };
+        ClientCapabilities { general: Some(general), ..Default::default() }
+    }
+
+    #[test]
+    fn negotiate_prefers_utf32_then_utf8() {
+        use PositionEncodingKind as Kind;
+
+        assert_eq!(PositionEncoding::negotiate(&ClientCapabilities::default()), PositionEncoding::Utf16);
+        assert_eq!(PositionEncoding::negotiate(&capabilities(None)), PositionEncoding::Utf16);
+        assert_eq!(PositionEncoding::negotiate(&capabilities(Some(vec![]))), PositionEncoding::Utf16);
+        assert_eq!(PositionEncoding::negotiate(&capabilities(Some(vec![Kind::UTF16]))), PositionEncoding::Utf16);
+
+        let encodings = vec![Kind::UTF16, Kind::UTF8];
+        assert_eq!(PositionEncoding::negotiate(&capabilities(Some(encodings))), PositionEncoding::Utf8);
+        let encodings = vec![Kind::UTF8, Kind::UTF16, Kind::UTF32];
+        assert_eq!(PositionEncoding::negotiate(&capabilities(Some(encodings))), PositionEncoding::Utf32);
+
+        // Unknown encodings are ignored.
+        let encodings = vec![Kind::new("utf-7")];
+        assert_eq!(PositionEncoding::negotiate(&capabilities(Some(encodings))), PositionEncoding::Utf16);
+
+        for encoding in ENCODINGS {
+            let negotiated = PositionEncoding::negotiate(&capabilities(Some(vec![encoding.kind()])));
+            assert_eq!(negotiated, encoding);
+        }
+    }
+
+    #[test]
+    fn columns_are_counted_in_each_encoding() {
+        let line = "a😀b日c";
+        let expected_columns = [
+            (PositionEncoding::Utf8, [0, 1, 5, 6, 9, 10]),
+            (PositionEncoding::Utf16, [0, 1, 3, 4, 5, 6]),
+            (PositionEncoding::Utf32, [0, 1, 2, 3, 4, 5]),
+        ];
+        for (encoding, expected_columns) in expected_columns {
+            let columns = (0..=5).map(|column| encoding.encode_column(line, column)).collect::<Vec<_>>();
+            assert_eq!(columns, expected_columns, "{encoding:?}");
+        }
+    }
+
+    #[test]
+    fn columns_round_trip_through_each_encoding() {
+        for encoding in ENCODINGS {
+            for line in LINES {
+                // Include the columns past the end of the line, like the column of its terminator.
+                for column in 0..line.chars().count() + 3 {
+                    let character = encoding.encode_column(line, column);
+                    assert_eq!(encoding.decode_column(line, character), column, "{encoding:?}: {line:?}, {column}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn columns_inside_of_a_character_are_moved_past_it() {
+        let line = "a😀b";
+        // The low surrogate of the emoji.
+        assert_eq!(PositionEncoding::Utf16.decode_column(line, 2), 2);
+        // The 2nd, 3rd and 4th bytes of the emoji.
+        for character in 2..=4 {
+            assert_eq!(PositionEncoding::Utf8.decode_column(line, character), 2);
+        }
+    }
+
+    #[test]
+    fn positions_round_trip_through_each_encoding() {
+        let text = LINES.join("\n");
+        for encoding in ENCODINGS {
+            let converter = PositionConverter::new(&text, encoding);
+            for (row, line) in LINES.iter().enumerate() {
+                for column in 0..=line.chars().count() {
+                    let location = Location { row: row + 1, col: column + 1 };
+                    let position = converter.location_to_position(&location);
+                    assert_eq!(position.line as usize, row);
+                    assert_eq!(position.character, encoding.encode_column(line, column));
+
+                    let round_tripped = converter.position_to_location(position);
+                    assert_eq!((round_tripped.row, round_tripped.col), (location.row, location.col));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_rows_and_columns_are_clamped_to_the_start() {
+        assert_eq!(to_zero_based(0, "row"), 0);
+        assert_eq!(to_zero_based(1, "row"), 0);
+        assert_eq!(to_zero_based(7, "column"), 6);
+
+        for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32] {
+            let converter = PositionConverter::new("module 😀\n", encoding);
+            let position = converter.location_to_position(&Location { row: 0, col: 0 });
+            assert_eq!(position, Position::new(0, 0));
+        }
+    }
+
+    #[test]
+    fn large_rows_and_columns_are_clamped_to_u32() {
+        assert_eq!(clamp_to_u32(0), 0);
+        assert_eq!(clamp_to_u32(u32::MAX as usize), u32::MAX);
+        assert_eq!(clamp_to_u32(usize::MAX), u32::MAX);
+
+        for text in ["module Foo\n", "module 日本\n"] {
+            for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32] {
+                let converter = PositionConverter::new(text, encoding);
+                let position = converter.location_to_position(&Location { row: usize::MAX, col: usize::MAX });
+                assert_eq!(position, Position::new(u32::MAX, u32::MAX));
+            }
+        }
+    }
+
+    #[test]
+    fn large_positions_are_converted_without_overflowing() {
+        for text in ["module Foo\n", "module 日本\n"] {
+            for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32] {
+                let converter = PositionConverter::new(text, encoding);
+                let location = converter.position_to_location(Position::new(u32::MAX, u32::MAX));
+                assert_eq!((location.row, location.col), (u32::MAX as usize + 1, u32::MAX as usize + 1));
+            }
+        }
+        assert_eq!(PositionEncoding::Utf16.encode_column("😀", usize::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn references_after_emoji_and_cjk_text_are_found_in_each_encoding() {
+        // The doc comment's link and the field's type are both preceded by non-ASCII text on the same line.
+        let lines = [
+            "module Test",
+            "/// 説明文 😀👍🏽 see {@link Bar}",
+            "struct Foo { /* 日本語 😀 */ bar: Bar }",
+            "struct Baz { /* 😀 */ count: int32 }",
+            "struct Bar {}",
+        ];
+        let text = lines.join("\n");
+        let mut state = slicec::compile_from_strings(&[&text], None, |_| {}, |_| {});
+        assert!(!state.diagnostics.has_errors(), "{:?}", state.diagnostics);
+        let path = PathBuf::from(&state.files[0].relative_path);
+        let files = HashMap::from([(path.clone(), state.files.remove(0))]);
+        let file = &files[&path];
+
+        let link_column = lines[1].find("@link").unwrap();
+        let type_column = lines[2].find("Bar").unwrap();
+        let int_column = lines[3].find("int32").unwrap();
+        for encoding in ENCODINGS {
+            let character = |row: usize, column: usize| encoding.text_length(&lines[row][..column]);
+
+            // The references are indexed with ranges counted in the encoding.
+            let references = ReferenceIndex::new(&files, encoding);
+            let references = references.references_to("Test::Bar");
+            let link = references.iter().find(|r| r.kind == ReferenceKind::DocLink).unwrap();
+            assert_eq!(link.range.start, Position::new(1, character(1, link_column)), "{encoding:?}");
+            let type_ref = references.iter().find(|r| r.kind == ReferenceKind::Type).unwrap();
+            assert_eq!(type_ref.range.start, Position::new(2, character(2, type_column)), "{encoding:?}");
+            assert_eq!(type_ref.range.end, Position::new(2, character(2, type_column + 3)), "{encoding:?}");
+
+            // Positions that the client sends in the encoding find the entities that were under its cursor.
+            let converter = PositionConverter::new(&text, encoding);
+            let file_index = FileIndex::new(file, false, encoding);
+            let location = converter.position_to_location(Position::new(2, character(2, type_column) + 1));
+            let (_, definition) = get_definition_link(&file_index, &location).unwrap();
+            assert_eq!((definition.start.row, definition.start.col), (5, 8), "{encoding:?}");
+
+            let location = converter.position_to_location(Position::new(3, character(3, int_column) + 1));
+            assert!(get_hover_message(&file_index, &location).is_some(), "{encoding:?}");
+            let location = converter.position_to_location(Position::new(3, character(3, int_column) - 2));
+            assert!(get_hover_message(&file_index, &location).is_none(), "{encoding:?}");
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) ZeroC, Inc.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
+use tower_lsp::lsp_types::{
+    NumberOrString, ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+use tower_lsp::Client;
+use tracing::{debug, info};
+
+/// Used to give each progress a unique token.
+static NEXT_PROGRESS_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Reports the progress of compiling configuration sets to the client, with `$/progress` notifications.
+///
+/// If the client doesn't support work done progress (or fails to create it), the progress is logged instead.
+/// Progress must always be ended with [`CompilationProgress::end`], otherwise the client keeps showing it.
+pub struct CompilationProgress {
+    client: Client,
+    /// The token of the progress shown by the client, or `None` if it's logged instead.
+    token: Option<ProgressToken>,
+    /// The number of configuration sets being compiled.
+    total: usize,
+    /// The number of configuration sets which have finished compiling so far.
+    finished: usize,
+}
+
+impl CompilationProgress {
+    /// Starts reporting the progress of compiling `total` configuration sets. If `is_supported` is false (or there's
+    /// nothing to compile), the client isn't asked to show the progress, and it's only logged.
+    pub async fn begin(client: &Client, is_supported: bool, total: usize) -> Self {
+        let mut progress = CompilationProgress { client: client.clone(), token: None, total, finished: 0 };
+        if !is_supported || total == 0 {
+            return progress;
+        }
+
+        let id = NEXT_PROGRESS_ID.fetch_add(1, Ordering::Relaxed);
+        let token = NumberOrString::String(format!("slice-compilation-{id}"));
+        let params = WorkDoneProgressCreateParams { token: token.clone() };
+        if let Err(error) = client.send_request::<WorkDoneProgressCreate>(params).await {
+            debug!("The client failed to create a progress for the compilation: {error}");
+            return progress;
+        }
+
+        progress.token = Some(token);
+        progress
+            .notify(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Compiling Slice files".to_owned(),
+                cancellable: Some(false),
+                message: Some(format!("0/{total} configuration set(s)")),
+                percentage: Some(0),
+            }))
+            .await;
+        progress
+    }
+
+    /// Reports that the configuration set with the provided name has finished compiling.
+    pub async fn report_finished(&mut self, set_name: &str) {
+        self.finished += 1;
+        let message = format!("Compiled '{set_name}' ({}/{})", self.finished, self.total);
+        if self.token.is_none() {
+            info!("{message}");
+            return;
+        }
+
+        let percentage = (self.finished * 100 / self.total.max(1)) as u32;
+        self.notify(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(message),
+            percentage: Some(percentage),
+        }))
+        .await;
+    }
+
+    /// Ends the progress, whether or not every configuration set finished compiling.
+    pub async fn end(self) {
+        if self.token.is_some() {
+            self.notify(WorkDoneProgress::End(WorkDoneProgressEnd { message: None })).await;
+        }
+    }
+
+    async fn notify(&self, value: WorkDoneProgress) {
+        if let Some(token) = &self.token {
+            let params = ProgressParams { token: token.clone(), value: ProgressParamsValue::WorkDone(value) };
+            self.client.send_notification::<Progress>(params).await;
+        }
+    }
+}
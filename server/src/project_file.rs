@@ -0,0 +1,71 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::ConfigurationSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+
+/// The name of the project file that can be checked into the root of a workspace folder, to share the folder's
+/// configuration sets with users of any editor. It holds the same data as the 'slice.configurations' setting:
+///
+/// ```json
+/// { "configurations": [{ "name": "...", "paths": ["..."] }] }
+/// ```
+pub const PROJECT_FILE_NAME: &str = ".sliceproject.json";
+
+/// The configuration sets that were loaded from a workspace folder's project file, along with any problems in the file.
+#[derive(Debug)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub configuration_sets: Vec<ConfigurationSet>,
+    /// The problems that were found in the file, which are reported as diagnostics on the file itself.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ProjectFile {
+    /// Loads the project file at the root of the provided workspace folder. Relative paths in its configuration sets are
+    /// resolved against that folder. Returns `None` if the folder doesn't have a project file (or it can't be read).
+    pub fn load(root_path: &Path) -> Option<Self> {
+        let path = root_path.join(PROJECT_FILE_NAME);
+        let contents = fs::read_to_string(&path).ok()?;
+
+        let mut project_file = ProjectFile { path, configuration_sets: Vec::new(), diagnostics: Vec::new() };
+        let value = match serde_json::from_str::<serde_json::Value>(&contents) {
+            Ok(value) => value,
+            Err(error) => {
+                // `serde_json` reports 1-based positions, and 0 if the error doesn't have a position.
+                let position = Position::new(
+                    error.line().saturating_sub(1) as u32,
+                    error.column().saturating_sub(1) as u32,
+                );
+                let message = format!("Failed to parse '{PROJECT_FILE_NAME}': {error}");
+                project_file.add_diagnostic(position, DiagnosticSeverity::ERROR, message);
+                return Some(project_file);
+            }
+        };
+
+        let Some(configurations) = value.get("configurations").and_then(|v| v.as_array()) else {
+            let message = format!("'{PROJECT_FILE_NAME}' must be an object with a 'configurations' array");
+            project_file.add_diagnostic(Position::default(), DiagnosticSeverity::ERROR, message);
+            return Some(project_file);
+        };
+
+        let (configuration_sets, problems) = ConfigurationSet::parse_configuration_sets(configurations, Some(root_path));
+        project_file.configuration_sets = configuration_sets;
+        for problem in problems {
+            project_file.add_diagnostic(Position::default(), DiagnosticSeverity::WARNING, problem);
+        }
+        Some(project_file)
+    }
+
+    fn add_diagnostic(&mut self, position: Position, severity: DiagnosticSeverity, message: String) {
+        self.diagnostics.push(Diagnostic {
+            range: Range::new(position, position),
+            severity: Some(severity),
+            code: Some(NumberOrString::String("project-file".to_owned())),
+            source: Some("slice-language-server".to_owned()),
+            message,
+            ..Diagnostic::default()
+        });
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::CompilationData;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::utils::paths_match;
+use slicec::grammar::{
+    Class, Commentable, CustomType, Entity, Enum, Enumerator, Exception, Field, Identifier, Interface, Message,
+    MessageComponent, NamedSymbol, Operation, Struct, Symbol, TypeAlias, TypeRef, TypeRefDefinition, Types,
+};
+use slicec::slice_file::{SliceFile, Span};
+use slicec::visitor::Visitor;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{Location, Position, Range};
+
+/// How an entity is referenced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// The entity is used as a type, like the type of a field (or the element type of a sequence).
+    Type,
+    /// The entity is inherited from by a class, exception, or interface.
+    Inheritance,
+    /// The entity is thrown by an operation.
+    Throws,
+    /// The entity is linked to from a doc comment.
+    DocLink,
+}
+
+/// A span of source code which refers to an entity.
+#[derive(Debug)]
+pub struct Reference {
+    /// The path of the file that the reference is in.
+    pub path: PathBuf,
+    pub range: Range,
+    pub kind: ReferenceKind,
+}
+
+/// Maps the fully-qualified identifier of each entity in a configuration set to every reference to it, across all of
+/// the set's files (including the built-in files).
+///
+/// The index is built with a single pass over the set's files right after it's compiled, so that the features which
+/// need to know who references an entity can look it up, instead of visiting every file on each request.
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    references: HashMap<String, Vec<Reference>>,
+}
+
+impl ReferenceIndex {
+    /// Visits each of the provided files and indexes every reference in them, with ranges in the provided encoding.
+    pub fn new(files: &HashMap<PathBuf, SliceFile>, encoding: PositionEncoding) -> Self {
+        let positions = PositionConverter::new("", encoding);
+        let mut visitor = ReferenceVisitor { path: PathBuf::new(), positions, references: HashMap::new() };
+        for (path, file) in files {
+            visitor.path.clone_from(path);
+            visitor.positions = PositionConverter::new(&file.raw_text, encoding);
+            file.visit_with(&mut visitor);
+        }
+        ReferenceIndex { references: visitor.references }
+    }
+
+    /// Returns every reference to the entity with the provided fully-qualified identifier.
+    pub fn references_to(&self, identifier: &str) -> &[Reference] {
+        self.references.get(identifier).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the fully-qualified identifier of each referenced entity, along with every reference to it.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Reference])> {
+        self.references
+            .iter()
+            .map(|(identifier, references)| (identifier.as_str(), references.as_slice()))
+    }
+
+    /// Returns whether the entity with the provided fully-qualified identifier is referenced anywhere.
+    pub fn is_referenced(&self, identifier: &str) -> bool {
+        !self.references_to(identifier).is_empty()
+    }
+
+    /// Returns the fully-qualified identifier of the entity referenced at the provided position, if there is one.
+    pub fn find_referenced_entity(&self, path: &Path, position: Position) -> Option<&str> {
+        self.references.iter().find_map(|(identifier, references)| {
+            references
+                .iter()
+                .any(|reference| paths_match(&reference.path, path) && range_contains(reference.range, position))
+                .then_some(identifier.as_str())
+        })
+    }
+
+    /// Returns roughly how many bytes of memory the index holds (see [`CompilationData::estimated_size`]).
+    pub fn estimated_size(&self) -> usize {
+        self.references
+            .iter()
+            .flat_map(|(identifier, references)| {
+                let entry_size = identifier.len() + size_of::<Vec<Reference>>();
+                let reference_sizes = references.iter().map(|r| size_of::<Reference>() + r.path.as_os_str().len());
+                std::iter::once(entry_size).chain(reference_sizes)
+            })
+            .sum()
+    }
+
+    /// Returns how many references of the provided kind are in the index.
+    pub fn count(&self, kind: ReferenceKind) -> usize {
+        self.references
+            .values()
+            .flatten()
+            .filter(|reference| reference.kind == kind)
+            .count()
+    }
+}
+
+/// Returns the locations of every reference to the entity at the provided position, which is either a reference to it,
+/// or the identifier of its definition. If `include_declaration` is set, the entity's definition is returned as well.
+pub fn find_references(
+    compilation_data: &CompilationData,
+    path: &Path,
+    position: Position,
+    include_declaration: bool,
+) -> Option<Vec<Location>> {
+    let file = compilation_data.file(path)?;
+    let identifier = compilation_data
+        .references
+        .find_referenced_entity(path, position)
+        .or_else(|| {
+            file.definitions
+                .iter()
+                .find(|definition| range_contains(definition.range, position))
+                .map(|definition| definition.identifier.as_str())
+        })?;
+
+    let mut locations = Vec::new();
+    if include_declaration {
+        for (path, file) in &compilation_data.files {
+            let definitions = file.definitions.iter().filter(|definition| definition.identifier == identifier);
+            for definition in definitions {
+                if let Some(uri) = compilation_data.paths.convert_slice_path_to_uri(path) {
+                    locations.push(Location { uri, range: definition.range });
+                }
+            }
+        }
+    }
+    for reference in compilation_data.references.references_to(identifier) {
+        if let Some(uri) = compilation_data.paths.convert_slice_path_to_uri(&reference.path) {
+            locations.push(Location { uri, range: reference.range });
+        }
+    }
+    Some(locations)
+}
+
+fn range_contains(range: Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+/// Collects every reference in the visited files, keyed by the fully-qualified identifier of the referenced entity.
+struct ReferenceVisitor<'a> {
+    /// The path of the file that's currently being visited.
+    path: PathBuf,
+    /// Converts the positions of the file that's currently being visited.
+    positions: PositionConverter<'a>,
+    references: HashMap<String, Vec<Reference>>,
+}
+
+impl ReferenceVisitor<'_> {
+    fn add_reference<T: NamedSymbol + ?Sized>(&mut self, type_ref: &TypeRef<T>, kind: ReferenceKind) {
+        if let TypeRefDefinition::Patched(definition) = &type_ref.definition {
+            self.add_entity(definition.borrow(), &type_ref.span, kind);
+        }
+    }
+
+    fn add_entity<T: NamedSymbol + ?Sized>(&mut self, entity: &T, span: &Span, kind: ReferenceKind) {
+        let reference = Reference { path: self.path.clone(), range: self.positions.span_to_range(span), kind };
+        let identifier = entity.parser_scoped_identifier();
+        self.references.entry(identifier).or_default().push(reference);
+    }
+
+    fn add_linked_entity<T: Entity + ?Sized>(&mut self, linked_entity: Result<&T, &Identifier>, span: &Span) {
+        if let Ok(entity) = linked_entity {
+            self.add_entity(entity, span, ReferenceKind::DocLink);
+        }
+    }
+
+    // Adds any entities which are linked to from the doc comment of the provided element.
+    fn add_comment_links(&mut self, commentable: &dyn Commentable) {
+        let Some(comment) = commentable.comment() else {
+            return;
+        };
+
+        if let Some(overview) = &comment.overview {
+            self.add_message_links(overview);
+        }
+        for returns in &comment.returns {
+            self.add_message_links(&returns.message);
+        }
+        for param in &comment.params {
+            self.add_message_links(&param.message);
+        }
+        for see in &comment.see {
+            self.add_linked_entity(see.linked_entity(), see.span());
+        }
+        for throws in &comment.throws {
+            self.add_message_links(&throws.message);
+            self.add_linked_entity(throws.thrown_type(), throws.span());
+        }
+    }
+
+    fn add_message_links(&mut self, message: &Message) {
+        for component in &message.value {
+            if let MessageComponent::Link(link) = component {
+                self.add_linked_entity(link.linked_entity(), link.span());
+            }
+        }
+    }
+}
+
+impl Visitor for ReferenceVisitor<'_> {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.add_comment_links(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.add_comment_links(class_def);
+        if let Some(base_ref) = &class_def.base {
+            self.add_reference(base_ref, ReferenceKind::Inheritance);
+        }
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.add_comment_links(exception_def);
+        if let Some(base_ref) = &exception_def.base {
+            self.add_reference(base_ref, ReferenceKind::Inheritance);
+        }
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        self.add_comment_links(interface_def);
+        for base_ref in &interface_def.bases {
+            self.add_reference(base_ref, ReferenceKind::Inheritance);
+        }
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.add_comment_links(enum_def);
+    }
+
+    fn visit_operation(&mut self, operation_def: &Operation) {
+        self.add_comment_links(operation_def);
+        for exception_ref in &operation_def.exception_specification {
+            self.add_reference(exception_ref, ReferenceKind::Throws);
+        }
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.add_comment_links(custom_type_def);
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        self.add_comment_links(type_alias_def);
+    }
+
+    fn visit_field(&mut self, field_def: &Field) {
+        self.add_comment_links(field_def);
+    }
+
+    fn visit_enumerator(&mut self, enumerator_def: &Enumerator) {
+        self.add_comment_links(enumerator_def);
+    }
+
+    fn visit_type_ref(&mut self, typeref_def: &TypeRef) {
+        let TypeRefDefinition::Patched(type_def) = &typeref_def.definition else {
+            return;
+        };
+
+        let span = &typeref_def.span;
+        match type_def.borrow().concrete_type() {
+            Types::Struct(x) => self.add_entity(x, span, ReferenceKind::Type),
+            Types::Class(x) => self.add_entity(x, span, ReferenceKind::Type),
+            Types::Enum(x) => self.add_entity(x, span, ReferenceKind::Type),
+            Types::CustomType(x) => self.add_entity(x, span, ReferenceKind::Type),
+            Types::Sequence(x) => self.visit_type_ref(&x.element_type),
+            Types::Dictionary(x) => {
+                self.visit_type_ref(&x.key_type);
+                self.visit_type_ref(&x.value_type);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (c) ZeroC, Inc.
+
+//! This module contains the custom requests that clients can send to the server, in addition to the ones defined by the
+//! Language Server Protocol. All of their methods are prefixed with `slice/`.
+//!
+//! - `slice/dumpAst`: returns the parsed contents of a document, as JSON (see [`crate::ast_dump`]).
+//! - `slice/projectInfo`: returns the configuration sets, as they were resolved by the server, and the files they hold.
+//! - `slice/resolveType`: returns the symbol at a position of a document, as structured data (see [`ResolvedSymbol`]).
+//! - `slice/validateFile`: compiles a piece of Slice text on its own, and returns its diagnostics.
+//! - `slice/diagnostics`: returns every diagnostic that the server currently holds, grouped by configuration set.
+//! - `slice/setBuiltInSlicePath`: changes the directory that the built-in Slice files are read from, and recompiles.
+//! - `slice/reloadConfigurations`: re-reads the configuration from scratch, and returns which sets changed.
+//! - `slice/health`: returns the server's vitals, like its uptime, memory use, and how many requests it handled.
+//!
+//! The results of these requests are meant for other tools to build on, so their layouts are part of the server's
+//! protocol. Results which other extensions are expected to depend on carry a `version`, which is bumped whenever a
+//! field is removed or changes meaning (fields can be added without bumping it).
+
+use lsp_types::request::Request;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{self, Diagnostic, Location, TextDocumentIdentifier, TextDocumentPositionParams};
+
+#[derive(Debug)]
+pub struct DumpAst;
+
+impl Request for DumpAst {
+    type Params = DumpAstParams;
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "slice/dumpAst";
+}
+
+/// Asks the server for the parsed contents of a document, which must be part of a compiled configuration set.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpAstParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug)]
+pub struct ProjectInfo;
+
+impl Request for ProjectInfo {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "slice/projectInfo";
+}
+
+/// The version of the layout of [`ResolveTypeResult`].
+pub const RESOLVE_TYPE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub struct ResolveType;
+
+impl Request for ResolveType {
+    type Params = TextDocumentPositionParams;
+    type Result = ResolveTypeResult;
+    const METHOD: &'static str = "slice/resolveType";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveTypeResult {
+    /// Always [`RESOLVE_TYPE_VERSION`].
+    pub version: u32,
+    /// The symbol at the requested position, or `None` if there isn't a symbol there.
+    pub symbol: Option<ResolvedSymbol>,
+}
+
+/// A symbol that was resolved by `slice/resolveType`. This is the data behind hover and go to definition: either a
+/// definition (if the position is on its identifier), or the type that a type reference resolves to.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSymbol {
+    /// The kind of the symbol, as the compiler names it (like `struct`, `field`, `sequence`, or `primitive`).
+    pub kind: String,
+    /// The fully-qualified name of the symbol (like `Foo::Bar`), or its type string if it's a built-in type.
+    pub name: String,
+    /// Where the symbol is defined. This is `None` for sequences, dictionaries, and primitive types.
+    pub definition: Option<Location>,
+    /// Whether the type reference at the position is optional. This is always `false` for definitions.
+    pub is_optional: bool,
+    /// The member (field or parameter) that the position is in, if any.
+    pub member: Option<MemberContext>,
+    /// The fully-qualified name of the type that a type alias resolves to, if the symbol is a type alias.
+    pub resolved_type: Option<String>,
+}
+
+/// The member that a resolved symbol belongs to (either the member itself, or its type).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberContext {
+    /// Either `field` or `parameter`.
+    pub kind: String,
+    /// The fully-qualified name of the member.
+    pub name: String,
+    /// The type of the member, followed by a `?` if it's optional.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Whether the member's type is optional.
+    pub is_optional: bool,
+    /// The member's tag, if it's tagged.
+    pub tag: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct ValidateFile;
+
+impl Request for ValidateFile {
+    type Params = ValidateFileParams;
+    type Result = ValidateFileResult;
+    const METHOD: &'static str = "slice/validateFile";
+}
+
+/// Asks the server to compile a piece of Slice text, which doesn't need to be saved anywhere.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateFileParams {
+    /// The name of the file that the text is from (or is going to be written to), which is used in diagnostics.
+    pub file_name: String,
+    /// The Slice text to compile.
+    pub text: String,
+    /// The preprocessor symbols to define while compiling the text.
+    #[serde(default)]
+    pub defines: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateFileResult {
+    /// The diagnostics that were reported in the text, with ranges in the text.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug)]
+pub struct Diagnostics;
+
+impl Request for Diagnostics {
+    type Params = DiagnosticsParams;
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "slice/diagnostics";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsParams {
+    /// Whether to re-compile every configuration set before returning the diagnostics, instead of returning the ones
+    /// from each set's most recent compilation.
+    #[serde(default)]
+    pub recompile: bool,
+}
+
+#[derive(Debug)]
+pub struct SetBuiltInSlicePath;
+
+impl Request for SetBuiltInSlicePath {
+    type Params = SetBuiltInSlicePathParams;
+    type Result = SetBuiltInSlicePathResult;
+    const METHOD: &'static str = "slice/setBuiltInSlicePath";
+}
+
+/// Asks the server to read the built-in Slice files from a different directory, like after the extension was updated
+/// (which moves its bundled files), without restarting the server.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBuiltInSlicePathParams {
+    /// The absolute path of the directory holding the built-in Slice files.
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBuiltInSlicePathResult {
+    /// The new path, as the server stored it.
+    pub path: String,
+    /// How many Slice files were found in the directory (including its subdirectories).
+    pub files: usize,
+}
+
+#[derive(Debug)]
+pub struct ReloadConfigurations;
+
+impl Request for ReloadConfigurations {
+    type Params = ();
+    type Result = ReloadConfigurationsResult;
+    const METHOD: &'static str = "slice/reloadConfigurations";
+}
+
+/// Which configuration sets changed when the configuration was reloaded. Sets are identified by their names, so a
+/// renamed set is listed as both removed and added. Each list is sorted.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadConfigurationsResult {
+    /// The sets which didn't exist before reloading.
+    pub added: Vec<String>,
+    /// The sets which no longer exist after reloading.
+    pub removed: Vec<String>,
+    /// The sets whose configuration changed (including being enabled or disabled).
+    pub modified: Vec<String>,
+    /// How many sets didn't change. These keep the results of their previous compilation, unless `settingsChanged`.
+    pub unchanged: usize,
+    /// Whether a server-wide setting which affects every set changed, in which case every set is recompiled.
+    pub settings_changed: bool,
+}
+
+#[derive(Debug)]
+pub struct Health;
+
+impl Request for Health {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "slice/health";
+}
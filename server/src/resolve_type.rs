@@ -0,0 +1,293 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::ast_dump::{concrete_type_name, type_name};
+use crate::configuration_set::InspectedFile;
+use crate::requests::{MemberContext, ResolvedSymbol};
+use serde_json::{json, Value};
+use slicec::grammar::{
+    Class, CustomType, Entity, Enum, Enumerator, Exception, Field, Interface, NamedSymbol, Operation, Parameter,
+    Struct, Symbol, TypeAlias, TypeRef, TypeRefDefinition, Types,
+};
+use slicec::slice_file::Location;
+use slicec::visitor::Visitor;
+
+/// Returns the symbol at the provided location of an inspected file, for the `slice/resolveType` request.
+///
+/// Like with go to definition, the innermost symbol wins: on `sequence<Foo>`, the location resolves to `Foo` if it's on
+/// `Foo`, and to the sequence otherwise.
+pub fn resolve_symbol(inspected: &InspectedFile, location: &Location) -> Option<ResolvedSymbol> {
+    let mut visitor = ResolveVisitor { inspected, location, member: None, symbol: None };
+    inspected.file.visit_with(&mut visitor);
+    visitor.symbol
+}
+
+/// The kinds of symbols which have a Slice type ID. Other symbols (like operations, fields, and built-in types) don't.
+const TYPE_ID_KINDS: [&str; 6] = ["struct", "class", "exception", "interface", "enum", "custom type"];
+
+/// Returns the Slice type ID of the type at the provided location of an inspected file, for the `slice.copyTypeId`
+/// command. The location is resolved like with `slice/resolveType`, so it can be on a type's identifier, or on a
+/// reference to it.
+///
+/// Type IDs are the fully-qualified identifier of the type, with a leading `::` (like `::Foo::Bar`). Classes can also
+/// be given a compact ID, which is returned along with their type ID. Returns `None` if there isn't a type there.
+pub fn resolve_type_id(inspected: &InspectedFile, location: &Location) -> Option<Value> {
+    let symbol = resolve_symbol(inspected, location).filter(|symbol| TYPE_ID_KINDS.contains(&symbol.kind.as_str()))?;
+
+    // Compact IDs aren't part of the resolved symbol, so the class is looked up again, in whichever file defines it.
+    let compact_id = match symbol.kind.as_str() {
+        "class" => {
+            let mut visitor = CompactIdVisitor { class_name: &symbol.name, compact_id: None };
+            for file in inspected.compiled_files() {
+                file.visit_with(&mut visitor);
+            }
+            visitor.compact_id
+        }
+        _ => None,
+    };
+    Some(json!({
+        "kind": symbol.kind,
+        "name": symbol.name,
+        "typeId": format!("::{}", symbol.name),
+        "compactId": compact_id,
+    }))
+}
+
+/// Finds the compact ID of the class with the provided fully-qualified identifier, if it has one.
+struct CompactIdVisitor<'a> {
+    class_name: &'a str,
+    compact_id: Option<u32>,
+}
+
+impl Visitor for CompactIdVisitor<'_> {
+    fn visit_class(&mut self, class_def: &Class) {
+        if class_def.parser_scoped_identifier() == self.class_name {
+            self.compact_id = class_def.compact_id.as_ref().map(|compact_id| compact_id.value);
+        }
+    }
+}
+
+struct ResolveVisitor<'a> {
+    inspected: &'a InspectedFile<'a>,
+    location: &'a Location,
+    /// The member that the location is in, if one has been visited.
+    member: Option<MemberContext>,
+    symbol: Option<ResolvedSymbol>,
+}
+
+impl ResolveVisitor<'_> {
+    fn entity_symbol(&self, entity: &dyn Entity) -> ResolvedSymbol {
+        ResolvedSymbol {
+            kind: entity.kind().to_owned(),
+            name: entity.parser_scoped_identifier(),
+            definition: self.inspected.span_to_location(entity.raw_identifier().span()),
+            is_optional: false,
+            member: None,
+            resolved_type: None,
+        }
+    }
+
+    /// Resolves the location to the provided entity, if it's on the entity's identifier.
+    fn check_identifier(&mut self, entity: &dyn Entity) {
+        if self.location.is_within(entity.raw_identifier().span()) {
+            self.symbol = Some(self.entity_symbol(entity));
+        }
+    }
+
+    /// Resolves the location to the entity that a type reference (like the base of a class) refers to, if it's on the
+    /// type reference.
+    fn check_entity_ref<T: Entity>(&mut self, type_ref: &TypeRef<T>) {
+        let TypeRefDefinition::Patched(definition) = &type_ref.definition else {
+            return;
+        };
+        if self.location.is_within(type_ref.span()) {
+            self.symbol = Some(self.entity_symbol(definition.borrow()));
+        }
+    }
+
+    /// Records the member that the location is in, and resolves the location to the member, if it's on its identifier.
+    fn check_member(&mut self, member: &dyn Entity, data_type: &TypeRef, tag: Option<u32>) {
+        if !self.location.is_within(member.span()) {
+            return;
+        }
+        let context = MemberContext {
+            kind: member.kind().to_owned(),
+            name: member.parser_scoped_identifier(),
+            type_name: type_name(data_type),
+            is_optional: data_type.is_optional,
+            tag,
+        };
+        self.member = Some(context.clone());
+        if self.location.is_within(member.raw_identifier().span()) {
+            self.symbol = Some(ResolvedSymbol { member: Some(context), ..self.entity_symbol(member) });
+        }
+    }
+}
+
+impl Visitor for ResolveVisitor<'_> {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.check_identifier(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.check_identifier(class_def);
+        if let Some(base_ref) = &class_def.base {
+            self.check_entity_ref(base_ref);
+        }
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.check_identifier(exception_def);
+        if let Some(base_ref) = &exception_def.base {
+            self.check_entity_ref(base_ref);
+        }
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        self.check_identifier(interface_def);
+        for base_ref in &interface_def.bases {
+            self.check_entity_ref(base_ref);
+        }
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.check_identifier(enum_def);
+    }
+
+    fn visit_operation(&mut self, operation_def: &Operation) {
+        self.check_identifier(operation_def);
+        for exception_ref in &operation_def.exception_specification {
+            self.check_entity_ref(exception_ref);
+        }
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.check_identifier(custom_type_def);
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        if self.location.is_within(type_alias_def.raw_identifier().span()) {
+            let resolved_type = Some(type_name(&type_alias_def.underlying));
+            self.symbol = Some(ResolvedSymbol { resolved_type, ..self.entity_symbol(type_alias_def) });
+        }
+    }
+
+    fn visit_field(&mut self, field_def: &Field) {
+        self.check_member(field_def, &field_def.data_type, field_def.tag.as_ref().map(|tag| tag.value));
+    }
+
+    fn visit_parameter(&mut self, parameter_def: &Parameter) {
+        self.check_member(parameter_def, &parameter_def.data_type, parameter_def.tag.as_ref().map(|tag| tag.value));
+    }
+
+    fn visit_enumerator(&mut self, enumerator_def: &Enumerator) {
+        self.check_identifier(enumerator_def);
+    }
+
+    fn visit_type_ref(&mut self, type_ref_def: &TypeRef) {
+        let TypeRefDefinition::Patched(definition) = &type_ref_def.definition else {
+            return;
+        };
+        if !self.location.is_within(type_ref_def.span()) {
+            return;
+        }
+
+        let entity_def: Option<&dyn Entity> = match definition.borrow().concrete_type() {
+            Types::Struct(x) => Some(x),
+            Types::Class(x) => Some(x),
+            Types::Enum(x) => Some(x),
+            Types::CustomType(x) => Some(x),
+            _ => None,
+        };
+        let symbol = match entity_def {
+            Some(entity_def) => self.entity_symbol(entity_def),
+            None => ResolvedSymbol {
+                kind: definition.borrow().kind().to_owned(),
+                name: concrete_type_name(definition.borrow().concrete_type()),
+                definition: None,
+                is_optional: false,
+                member: None,
+                resolved_type: None,
+            },
+        };
+        let member = self.member.clone();
+        self.symbol = Some(ResolvedSymbol { is_optional: type_ref_def.is_optional, member, ..symbol });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::ServerConfig;
+    use crate::configuration_set::ConfigurationSet;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    /// A project with classes (with and without compact IDs) and other types in nested modules, across two files.
+    const FIXTURE_PATH: &str = "tests/fixtures/type_ids";
+
+    /// Returns the type ID (and compact ID) of the type at the first occurrence of `needle` in a fixture file.
+    fn type_id_at(file_name: &str, needle: &str) -> Option<(String, Option<u32>)> {
+        let fixture_path = std::env::current_dir().unwrap().join(FIXTURE_PATH);
+        let mut set = ConfigurationSet::discovered_set("type IDs".to_owned(), vec![fixture_path.clone()], None);
+        set.slice_config.include_built_in_slice_files = false;
+
+        let file_path = fixture_path.join(file_name);
+        let location = location_of(&file_path, needle);
+        let inspection = set.prepare_inspection(&ServerConfig::default(), &HashMap::new());
+        let type_id = inspection.inspect_file(&file_path, |inspected| resolve_type_id(inspected, &location))?;
+        type_id.map(|type_id| {
+            let compact_id = type_id["compactId"].as_u64().map(|compact_id| compact_id as u32);
+            (type_id["typeId"].as_str().unwrap().to_owned(), compact_id)
+        })
+    }
+
+    fn location_of(file_path: &Path, needle: &str) -> Location {
+        let text = std::fs::read_to_string(file_path).unwrap();
+        let (row, line) = text.lines().enumerate().find(|(_, line)| line.contains(needle)).unwrap();
+        let col = line.find(needle).unwrap();
+        Location { row: row + 1, col: col + 1 }
+    }
+
+    #[test]
+    fn type_ids_are_fully_qualified_across_nested_modules() {
+        assert_eq!(type_id_at("inner.slice", "Derived"), Some(("::Outer::Inner::Derived".to_owned(), None)));
+        assert_eq!(type_id_at("inner.slice", "Failure"), Some(("::Outer::Inner::Failure".to_owned(), None)));
+        assert_eq!(type_id_at("inner.slice", "Service"), Some(("::Outer::Inner::Service".to_owned(), None)));
+        assert_eq!(type_id_at("inner.slice", "Color"), Some(("::Outer::Inner::Color".to_owned(), None)));
+        assert_eq!(type_id_at("inner.slice", "Point"), Some(("::Outer::Inner::Point".to_owned(), None)));
+
+        // Types in the parent module don't include the nested module.
+        assert_eq!(type_id_at("outer.slice", "Holder"), Some(("::Outer::Holder".to_owned(), Some(123))));
+    }
+
+    #[test]
+    fn classes_with_compact_ids_return_them_along_with_their_type_ids() {
+        assert_eq!(type_id_at("inner.slice", "Base(7)"), Some(("::Outer::Inner::Base".to_owned(), Some(7))));
+
+        // Classes without a compact ID only have their type ID, even if their base has one.
+        assert_eq!(type_id_at("inner.slice", "Derived"), Some(("::Outer::Inner::Derived".to_owned(), None)));
+    }
+
+    #[test]
+    fn references_resolve_to_the_type_ids_of_the_types_they_refer_to() {
+        // The referenced class is defined in a different file (and module) than the reference, and its compact ID is
+        // found there.
+        let base = Some(("::Outer::Inner::Base".to_owned(), Some(7)));
+        assert_eq!(type_id_at("outer.slice", "Inner::Base"), base);
+        // Bases are references too (`Derived : Base`).
+        assert_eq!(type_id_at("inner.slice", "Base {}"), base);
+
+        let point = Some(("::Outer::Inner::Point".to_owned(), None));
+        assert_eq!(type_id_at("outer.slice", "Inner::Point"), point);
+    }
+
+    #[test]
+    fn symbols_without_type_ids_resolve_to_nothing() {
+        // Fields, enumerators, and built-in types don't have type IDs.
+        assert_eq!(type_id_at("outer.slice", "count"), None);
+        assert_eq!(type_id_at("outer.slice", "int32"), None);
+        assert_eq!(type_id_at("inner.slice", "Red"), None);
+        // Neither does the module declaration.
+        assert_eq!(type_id_at("inner.slice", "module"), None);
+    }
+}
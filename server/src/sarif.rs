@@ -0,0 +1,304 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::diagnostic_handler::diagnostics_for_file;
+use crate::position_encoding::PositionConverter;
+use crate::server_state::ServerState;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url};
+
+/// The version of the SARIF format that [`sarif_log`] produces.
+const SARIF_VERSION: &str = "2.1.0";
+
+/// The JSON schema of the SARIF format, which editors use to validate (and complete) SARIF files.
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Returns every diagnostic that the server currently holds as a SARIF log, for the `slice.exportSarif` command.
+///
+/// The log has a single run, with a result for each diagnostic, as it's published to the client (so diagnostics that
+/// are reported by multiple sets are only included once). Each diagnostic code becomes a rule, and notes become related
+/// locations. Columns are counted in characters (like `slicec` counts them), regardless of the negotiated encoding.
+pub fn sarif_log(server_state: &ServerState) -> Value {
+    let mut uris = server_state.files_with_diagnostics().into_iter().collect::<Vec<_>>();
+    uris.sort();
+
+    let mut rules = BTreeMap::new();
+    let mut results = Vec::new();
+    for uri in uris {
+        let text = compiled_text(server_state, &uri);
+        let converter = PositionConverter::new(text, server_state.server_config.position_encoding);
+        for diagnostic in diagnostics_for_file(server_state, &uri) {
+            let rule_id = rule_id(&diagnostic);
+            rules.entry(rule_id.clone()).or_insert_with(|| rule(&rule_id, &diagnostic));
+            results.push(result(&converter, &uri, rule_id, diagnostic));
+        }
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/zeroc-ice/vscode-slice",
+                    "rules": rules.into_values().collect::<Vec<_>>(),
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+/// Returns the text that a file was compiled from, which is needed to count its columns in characters. Files that
+/// aren't indexed (or were evicted) are treated as ASCII.
+fn compiled_text<'a>(server_state: &'a ServerState, uri: &Url) -> &'a str {
+    let Some(file_path) = server_state.server_config.paths.url_to_document_path(uri) else {
+        return "";
+    };
+    server_state
+        .configuration_sets
+        .iter()
+        .find_map(|set| set.compilation_data.files.get(&file_path))
+        .map_or("", |file| file.raw_text.as_str())
+}
+
+/// Returns the ID of the rule that a diagnostic is reported for: its code, or its source if it doesn't have one.
+fn rule_id(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code.clone(),
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        None => diagnostic.source.clone().unwrap_or_else(|| env!("CARGO_PKG_NAME").to_owned()),
+    }
+}
+
+/// Describes the rule with the provided ID, using the first diagnostic that was reported for it.
+fn rule(rule_id: &str, diagnostic: &Diagnostic) -> Value {
+    // Compiler diagnostics are labeled with the sets that reported them (like `slicec (set)`), which isn't part of the
+    // rule itself.
+    let source = match diagnostic.source.as_deref() {
+        Some(source) if source.starts_with("slicec") => "slicec",
+        Some(source) => source,
+        None => env!("CARGO_PKG_NAME"),
+    };
+    json!({
+        "id": rule_id,
+        "defaultConfiguration": { "level": level(diagnostic.severity) },
+        "properties": { "source": source },
+    })
+}
+
+fn result(converter: &PositionConverter, uri: &Url, rule_id: String, diagnostic: Diagnostic) -> Value {
+    let related_locations = diagnostic.related_information.into_iter().flatten().enumerate().map(|(id, related)| {
+        json!({
+            "id": id,
+            "message": { "text": related.message },
+            "physicalLocation": physical_location(converter, &related.location.uri, related.location.range),
+        })
+    });
+    json!({
+        "ruleId": rule_id,
+        "level": level(diagnostic.severity),
+        "message": { "text": diagnostic.message },
+        "locations": [{ "physicalLocation": physical_location(converter, uri, diagnostic.range) }],
+        "relatedLocations": related_locations.collect::<Vec<_>>(),
+    })
+}
+
+/// Converts a range of a file into a SARIF physical location. SARIF's lines and columns are 1-based.
+///
+/// Related locations are usually in the same file as their diagnostic. If they aren't, their columns are converted with
+/// the diagnostic's file, which is only off on lines with non-ASCII characters.
+fn physical_location(converter: &PositionConverter, uri: &Url, range: Range) -> Value {
+    let start = converter.position_to_location(range.start);
+    let end = converter.position_to_location(range.end);
+    json!({
+        "artifactLocation": { "uri": uri },
+        "region": {
+            "startLine": start.row,
+            "startColumn": start.col,
+            "endLine": end.row,
+            "endColumn": end.col,
+        },
+    })
+}
+
+fn level(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        _ => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration_set::ConfigurationSet;
+    use crate::diagnostic_handler::store_diagnostics_for_set;
+    use std::collections::{HashMap, HashSet};
+    use tower_lsp::lsp_types::{DiagnosticRelatedInformation, Location, Position};
+
+    /// Returns a server whose only set was compiled from the `relative` fixture (which has a single error in it), along
+    /// with a project file diagnostic that has notes and a numeric code.
+    fn server_state() -> ServerState {
+        let fixture_path = std::env::current_dir().unwrap().join("tests/fixtures/relative");
+        let mut set = ConfigurationSet::discovered_set("fixtures".to_owned(), vec![fixture_path.clone()], None);
+        set.slice_config.include_built_in_slice_files = false;
+
+        let mut server_state = ServerState::default();
+        let compilation = set.prepare_compilation(&server_state.server_config, &HashMap::new());
+        let (diagnostics, _) = set.install_compilation(&server_state.server_config, compilation.run()).unwrap();
+        store_diagnostics_for_set(diagnostics, &mut set, &server_state.server_config);
+        server_state.configuration_sets.push(set);
+
+        let uri = Url::from_file_path(fixture_path.join("slice.json")).unwrap();
+        let range = |line| Range::new(Position::new(line, 2), Position::new(line, 8));
+        let note = |line, message: &str| DiagnosticRelatedInformation {
+            location: Location { uri: uri.clone(), range: range(line) },
+            message: message.to_owned(),
+        };
+        let diagnostic = Diagnostic {
+            range: range(1),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::Number(7)),
+            source: Some("slice.json".to_owned()),
+            message: "a problem with notes".to_owned(),
+            related_information: Some(vec![note(3, "first note"), note(4, "second note")]),
+            ..Diagnostic::default()
+        };
+        server_state.project_file_diagnostics.insert(uri, vec![diagnostic]);
+        server_state
+    }
+
+    /// Exports the server's diagnostics, and parses the written log back, like the tools that read SARIF files do.
+    fn round_trip(server_state: &ServerState) -> Value {
+        let log = sarif_log(server_state);
+        let text = serde_json::to_string_pretty(&log).unwrap();
+        let parsed = serde_json::from_str::<Value>(&text).unwrap();
+        assert_eq!(parsed, log);
+        parsed
+    }
+
+    /// Checks a physical location against the SARIF 2.1.0 schema: its URI must be a valid URI, and its region's lines
+    /// and columns must be positive integers, with the end coming after the start.
+    fn assert_valid_physical_location(location: &Value) {
+        let uri = location["artifactLocation"]["uri"].as_str().unwrap();
+        assert!(Url::parse(uri).is_ok(), "{uri}");
+
+        let region = &location["region"];
+        let number = |key: &str| region[key].as_u64().filter(|value| *value >= 1).unwrap();
+        assert!((number("startLine"), number("startColumn")) <= (number("endLine"), number("endColumn")));
+    }
+
+    /// Checks a log against the parts of the SARIF 2.1.0 schema (and the spec's rules on top of it) which the server's
+    /// logs can violate.
+    fn assert_valid_sarif(log: &Value) {
+        assert_eq!(log["$schema"], SARIF_SCHEMA);
+        assert_eq!(log["version"], "2.1.0");
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 1);
+
+        let run = &runs[0];
+        let driver = &run["tool"]["driver"];
+        assert!(driver["name"].as_str().is_some_and(|name| !name.is_empty()));
+        assert!(Url::parse(driver["informationUri"].as_str().unwrap()).is_ok());
+        assert!(["unicodeCodePoints", "utf16CodeUnits"].contains(&run["columnKind"].as_str().unwrap()));
+
+        // Rules must have unique IDs, and their levels must be one of the schema's levels.
+        let levels = ["none", "note", "warning", "error"];
+        let rules = driver["rules"].as_array().unwrap();
+        let rule_ids = rules.iter().map(|rule| rule["id"].as_str().unwrap()).collect::<HashSet<_>>();
+        assert_eq!(rule_ids.len(), rules.len());
+        for rule in rules {
+            assert!(levels.contains(&rule["defaultConfiguration"]["level"].as_str().unwrap()));
+        }
+
+        for result in run["results"].as_array().unwrap() {
+            assert!(rule_ids.contains(result["ruleId"].as_str().unwrap()), "{result}");
+            assert!(levels.contains(&result["level"].as_str().unwrap()));
+            assert!(result["message"]["text"].is_string());
+
+            let locations = result["locations"].as_array().unwrap();
+            assert_eq!(locations.len(), 1);
+            assert_valid_physical_location(&locations[0]["physicalLocation"]);
+
+            // Related locations must have unique, non-negative IDs.
+            let related_locations = result["relatedLocations"].as_array().unwrap();
+            let ids = related_locations.iter().map(|related| related["id"].as_u64().unwrap());
+            assert_eq!(ids.collect::<HashSet<_>>().len(), related_locations.len());
+            for related in related_locations {
+                assert!(related["message"]["text"].is_string());
+                assert_valid_physical_location(&related["physicalLocation"]);
+            }
+        }
+    }
+
+    #[test]
+    fn exported_logs_are_valid_sarif() {
+        let log = round_trip(&server_state());
+        assert_valid_sarif(&log);
+
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        let rules = log["runs"][0]["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+    }
+
+    #[test]
+    fn compiler_diagnostics_are_exported_as_results() {
+        let log = round_trip(&server_state());
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        let result = results.iter().find(|result| result["level"] == "error").unwrap();
+
+        let location = &result["locations"][0]["physicalLocation"];
+        let uri = Url::parse(location["artifactLocation"]["uri"].as_str().unwrap()).unwrap();
+        assert_eq!(uri.to_file_path().unwrap().file_name().unwrap(), "sender.slice");
+        assert_eq!(location["region"]["startLine"], 5);
+        assert_eq!(location["region"]["startColumn"], 16);
+        assert!(result["message"]["text"].as_str().unwrap().contains("Missing"));
+        assert_eq!(result["relatedLocations"], json!([]));
+    }
+
+    #[test]
+    fn numeric_codes_and_notes_are_exported() {
+        let log = round_trip(&server_state());
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        let result = results.iter().find(|result| result["ruleId"] == "7").unwrap();
+        assert_eq!(result["level"], "warning");
+
+        let related_locations = result["relatedLocations"].as_array().unwrap();
+        let notes = related_locations
+            .iter()
+            .map(|related| (related["id"].as_u64().unwrap(), related["message"]["text"].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(notes, [(0, "first note"), (1, "second note")]);
+        assert_eq!(related_locations[1]["physicalLocation"]["region"]["startLine"], 5);
+        assert_eq!(related_locations[1]["physicalLocation"]["region"]["startColumn"], 3);
+
+        let rules = log["runs"][0]["tool"]["driver"]["rules"].as_array().unwrap();
+        let rule = rules.iter().find(|rule| rule["id"] == "7").unwrap();
+        assert_eq!(rule["properties"]["source"], "slice.json");
+    }
+
+    #[test]
+    fn servers_without_diagnostics_export_an_empty_run() {
+        let log = round_trip(&ServerState::default());
+        assert_valid_sarif(&log);
+        assert_eq!(log["runs"][0]["results"], json!([]));
+        assert_eq!(log["runs"][0]["tool"]["driver"]["rules"], json!([]));
+    }
+
+    #[test]
+    fn paths_are_exported_as_file_uris() {
+        let log = round_trip(&server_state());
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        for result in results {
+            let uri = result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str().unwrap();
+            let path = Url::parse(uri).unwrap().to_file_path().unwrap();
+            assert!(path.starts_with(std::env::current_dir().unwrap().join("tests/fixtures/relative")), "{uri}");
+        }
+    }
+}
@@ -1,9 +1,26 @@
 // Copyright (c) ZeroC, Inc.
 
+use crate::actions::PathProblem;
+use crate::client_capabilities::ClientCapabilitiesSummary;
 use crate::configuration_set::ConfigurationSet;
-use crate::configuration::ServerConfig;
-use crate::utils::{sanitize_path, url_to_sanitized_file_path};
-use tower_lsp::lsp_types::{DidChangeConfigurationParams, InitializeParams};
+use crate::configuration::{find_overlapping_paths, is_glob_pattern, resolve_path, resolve_path_in_roots};
+use crate::configuration::{CompilationTrigger, LogLevel, ServerConfig, SliceConfig, UnusedDefinitionsMode};
+use crate::discovery::discover_configuration_sets;
+use crate::duplicate_definitions::find_duplicate_definitions;
+use crate::features::Feature;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::project_file::{ProjectFile, PROJECT_FILE_NAME};
+use crate::slice_ignore::SliceIgnore;
+use crate::todo_comments::DEFAULT_TODO_COMMENT_MARKERS;
+use crate::utils::{hash_text, is_virtual_document_path, real_path, sanitize_path, PathResolver};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use slicec::slice_file::Location;
+use tower_lsp::lsp_types::{Diagnostic, DidChangeConfigurationParams, InitializeParams, Position, TraceValue, Url};
+use tracing::info;
 
 #[derive(Debug, Default)]
 pub struct ServerState {
@@ -11,68 +28,946 @@ pub struct ServerState {
     /// `SliceConfig` and `CompilationState`. The `SliceConfig` is used to determine which configuration set to use when
     /// publishing diagnostics. The `CompilationState` is used to retrieve the diagnostics for a given file.
     pub configuration_sets: Vec<ConfigurationSet>,
+    /// Set if some workspace folders don't have any configuration sets, and don't get a default set either. Opened
+    /// files in these folders are compiled in their own stand-alone sets, like when there isn't a workspace.
+    pub has_unconfigured_folders: bool,
+    /// Set once the user has been told how to configure the folders without any configuration sets, so they're only
+    /// told once for the lifetime of the server.
+    pub reported_unconfigured_folders: bool,
+    /// The configuration sets which the user turned off. These are kept aside, so they're never compiled, navigated, or
+    /// searched, but folders that only have disabled sets still don't get a default set.
+    pub disabled_sets: Vec<ConfigurationSet>,
     /// Configuration that affects the entire server.
     pub server_config: ServerConfig,
+    /// Set when the user asks to stop being notified about opened Slice files that aren't part of any configuration
+    /// set. This lasts for the lifetime of the server, which corresponds to the lifetime of the workspace.
+    pub suppress_unconfigured_file_notifications: bool,
+    /// The most recent 'slice' settings, which are re-applied whenever a project file changes.
+    pub settings: Option<serde_json::Value>,
+    /// The problems in each workspace folder's project file, which are published as diagnostics on the file.
+    pub project_file_diagnostics: HashMap<Url, Vec<Diagnostic>>,
+    /// Diagnostics from the checks that span multiple configuration sets, grouped by file.
+    /// These are published alongside the diagnostics of each set.
+    pub cross_set_diagnostics: HashMap<Url, Vec<Diagnostic>>,
+    /// What the client supports, as it told the server when initializing it.
+    pub client: ClientCapabilitiesSummary,
+    /// Set if the client supports pulling settings, but didn't send any in its initialization options. The settings are
+    /// pulled once the client is initialized instead.
+    pub pull_settings_on_initialized: bool,
+    /// The overlaps between configuration sets that the user was last warned about, so they're only warned once.
+    pub reported_set_overlaps: Vec<SetOverlap>,
+    /// The problems with the paths of configuration sets that the user was told about since the configuration last
+    /// changed, so they're only told once (instead of after every compilation).
+    pub reported_path_problems: HashSet<PathProblem>,
+    /// The names of the discovered configuration sets that the user was last told about, so they're only told once.
+    pub reported_discovered_sets: Vec<String>,
+    /// The problems that were found in the user's configuration sets, which haven't been reported to the user yet.
+    pub configuration_problems: Vec<String>,
+    /// Set once the server's file watchers have been registered with the client.
+    pub file_watchers_registered: bool,
+    /// The paths outside of the workspace which the client is currently watching for the server.
+    pub watched_external_paths: Vec<PathBuf>,
+    /// The features which are currently registered with the client.
+    pub registered_features: HashSet<Feature>,
+    /// The Slice files which were changed outside of the editor, and are waiting to be compiled as a single batch.
+    pub pending_file_changes: HashSet<PathBuf>,
+    /// The documents which are currently open in the client, keyed by their file paths.
+    pub open_documents: HashMap<PathBuf, OpenDocument>,
+    /// The documents which have been edited but not saved yet, keyed by their file paths.
+    /// These are compiled from their unsaved text, instead of their files' contents on disk.
+    pub unsaved_documents: HashMap<PathBuf, UnsavedDocument>,
+    /// The directory that the symbols of each configuration set are cached in, if the client provided one.
+    pub cache_directory: Option<PathBuf>,
+    /// The writes of symbol caches which may still be running (in the background). Shutting down waits for these, so
+    /// that caches aren't left half-written when the client exits the server.
+    pub symbol_cache_writes: Vec<JoinHandle<()>>,
+    /// Set once the client has asked the server to shut down, after which no more compilations are started.
+    pub is_shutting_down: bool,
+    /// How much the server traces its work to the client (with `$/logTrace`), which the client can change at any time
+    /// with `$/setTrace`.
+    pub trace: TraceValue,
+}
+
+/// Two configuration sets that include some of the same paths, which are compiled once for each set.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SetOverlap {
+    pub first_set: String,
+    pub second_set: String,
+    /// The paths that are included by both sets.
+    pub paths: Vec<PathBuf>,
+}
+
+/// A document that's open in the client, along with its latest text.
+#[derive(Debug)]
+pub struct OpenDocument {
+    /// The version of the document, which increases after each change.
+    pub version: i32,
+    /// The full text of the document, as it currently is in the client (which includes any unsaved changes).
+    pub text: String,
+}
+
+/// The unsaved contents of a document that's been edited in the client.
+#[derive(Debug)]
+pub struct UnsavedDocument {
+    /// The version of the document, which increases after each change.
+    pub version: i32,
+    /// The full text of the document.
+    pub text: String,
 }
 
 impl ServerState {
     // Update the properties of the server from `InitializeParams`
     pub fn update_from_initialize_params(&mut self, params: InitializeParams) {
+        self.client = ClientCapabilitiesSummary::new(&params);
         let initialization_options = params.initialization_options;
+        self.pull_settings_on_initialized = self.client.supports_configuration_pull
+            && !initialization_options.as_ref().is_some_and(serde_json::Value::is_object);
 
-        // Use the root_uri if it exists temporarily as we cannot access configuration until
-        // after initialization. Additionally, LSP may provide the windows path with escaping or a lowercase
-        // drive letter. To fix this, we convert the path to a URL and then back to a path.
-        let workspace_root_path = params
-            .root_uri
-            .and_then(|uri| url_to_sanitized_file_path(&uri))
-            .expect("`root_uri` was not sent by the client, or was malformed");
-
-        // This is the path to the built-in Slice files that are included with the extension. It should always
-        // be present.
-        let built_in_slice_path = initialization_options
-            .as_ref()
-            .and_then(|opts| opts.get("builtInSlicePath"))
-            .and_then(|value| value.as_str())
-            .map(sanitize_path)
-            .expect("builtInSlicePath not found in initialization options");
+        let position_encoding = PositionEncoding::negotiate(&params.capabilities);
+        self.trace = params.trace.unwrap_or_default();
+
+        // Use the root of each workspace folder to resolve relative paths. Clients that don't support workspace folders
+        // only send the root_uri, so we fall back to it. Additionally, LSP may provide the windows path with escaping or
+        // a lowercase drive letter. To fix this, we convert the path to a URL and then back to a path.
+        // Folders which were opened through a symlink are registered, so that their files are tracked under real paths.
+        let mut paths = PathResolver::default();
+        let mut workspace_root_paths = params
+            .workspace_folders
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|folder| paths.register_folder(&folder.uri))
+            .collect::<Vec<_>>();
+        // If there's no root either (like when a lone file is opened), then there's no workspace, and opened files are
+        // compiled on their own.
+        if workspace_root_paths.is_empty() {
+            workspace_root_paths.extend(params.root_uri.and_then(|uri| paths.register_folder(&uri)));
+        }
+
+        let slice_ignore = SliceIgnore::load(&workspace_root_paths);
+        self.server_config = ServerConfig {
+            slice_ignore,
+            workspace_root_paths,
+            position_encoding,
+            paths,
+            ..ServerConfig::default()
+        };
 
-        self.server_config = ServerConfig { workspace_root_path, built_in_slice_path };
+        // Load any server-wide settings. The initialization options use the same layout as the 'slice' settings.
+        if let Some(settings) = &initialization_options {
+            self.update_settings(settings);
+        }
+
+        // The cache directory isn't a setting, since it's provided by the client (VS Code gives each workspace one).
+        self.cache_directory = initialization_options
+            .as_ref()
+            .and_then(|v| v.get("cacheDirectory"))
+            .and_then(|v| v.as_str())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
 
-        // Load any user configuration from the 'slice.configurations' option.
-        let configuration_sets = initialization_options
+        // Load any user configuration from the 'slice.configurations' option (and its per-folder values).
+        let (mut configuration_sets, configuration_problems) = initialization_options
             .as_ref()
-            .and_then(|opts| opts.get("configurations"))
-            .and_then(|v| v.as_array())
-            .map(|arr| ConfigurationSet::parse_configuration_sets(arr))
+            .map(|settings| parse_configuration_sets(settings, &self.server_config.paths))
             .unwrap_or_default();
 
-        self.update_configurations(configuration_sets);
+        self.configuration_problems = configuration_problems;
+        self.configuration_problems.extend(self.find_missing_referenced_directories());
+        self.load_project_files(&mut configuration_sets);
+        self.update_configurations(configuration_sets, false);
+        self.settings = initialization_options;
     }
 
     // Update the configuration sets from the `DidChangeConfigurationParams` notification.
     pub fn update_configurations_from_params(&mut self, params: DidChangeConfigurationParams) {
-        // Parse the configurations from the notification
-        let configurations = params
-            .settings
-            .get("slice")
-            .and_then(|v| v.get("configurations"))
-            .and_then(|v| v.as_array())
-            .map(|arr| ConfigurationSet::parse_configuration_sets(arr))
+        self.update_from_settings(params.settings.get("slice"));
+    }
+
+    // Update the server-wide settings and the configuration sets from a JSON object holding the 'slice' settings.
+    // These are either pushed by the client, or pulled from it with `workspace/configuration`.
+    //
+    // Configuration sets which didn't change keep the results of their previous compilation, unless a server-wide
+    // setting that affects every set changed. Only the sets that haven't been compiled need to be compiled afterwards.
+    pub fn update_from_settings(&mut self, settings: Option<&serde_json::Value>) {
+        // Update the server-wide settings
+        let previous_compilation_settings = self.server_config.compilation_settings();
+        if let Some(settings) = settings {
+            self.update_settings(settings);
+        }
+        let keep_unchanged_sets = previous_compilation_settings == self.server_config.compilation_settings();
+
+        // Parse the configurations from the settings
+        let (mut configurations, configuration_problems) = settings
+            .map(|settings| parse_configuration_sets(settings, &self.server_config.paths))
             .unwrap_or_default();
 
         // Update the configuration sets
-        self.update_configurations(configurations);
+        self.configuration_problems = configuration_problems;
+        self.configuration_problems.extend(self.find_missing_referenced_directories());
+        self.load_project_files(&mut configurations);
+        self.update_configurations(configurations, keep_unchanged_sets);
+        self.settings = settings.cloned();
+    }
+
+    /// Re-loads the workspace's project files, by re-applying the most recent settings.
+    pub fn reload_project_files(&mut self) {
+        let settings = self.settings.take();
+        self.update_from_settings(settings.as_ref());
+    }
+
+    /// Returns the paths of the project files which can be at the root of each workspace folder.
+    pub fn project_file_paths(&self) -> Vec<PathBuf> {
+        let root_paths = &self.server_config.workspace_root_paths;
+        root_paths.iter().map(|root_path| root_path.join(PROJECT_FILE_NAME)).collect()
+    }
+
+    /// Returns the configuration of each set (including disabled sets, but not stand-alone sets) keyed by its name,
+    /// along with whether it's disabled. Comparing these before and after the configuration is reloaded shows which
+    /// sets changed, the same way that sets are matched to keep their previous results (see `update_configurations`).
+    pub fn configuration_snapshot(&self) -> BTreeMap<String, (SliceConfig, bool)> {
+        self.configuration_sets
+            .iter()
+            .chain(&self.disabled_sets)
+            .filter(|set| !set.is_stand_alone())
+            .map(|set| (set.name.clone(), (set.slice_config.clone(), set.is_disabled())))
+            .collect()
+    }
+
+    /// Returns every file which currently has diagnostics, from any configuration set, cross-set check or project file.
+    pub fn files_with_diagnostics(&self) -> HashSet<Url> {
+        self.configuration_sets
+            .iter()
+            .flat_map(|set| set.diagnostics.keys())
+            .chain(self.cross_set_diagnostics.keys())
+            .chain(self.project_file_diagnostics.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// Loads the configuration sets from the project file at the root of each workspace folder, and adds them to the
+    /// provided sets (which came from the user's settings).
+    ///
+    /// The user's settings take precedence over project files. So if any of the provided sets apply to a folder, its
+    /// project file is ignored, and the user is told about it.
+    fn load_project_files(&mut self, configurations: &mut Vec<ConfigurationSet>) {
+        let mut project_file_sets = Vec::new();
+        self.project_file_diagnostics.clear();
+        for root_path in &self.server_config.workspace_root_paths {
+            let Some(project_file) = ProjectFile::load(root_path) else {
+                continue;
+            };
+
+            let has_settings_sets = configurations.iter().any(|set| {
+                let folder_path = set.slice_config.folder_path.as_ref();
+                folder_path.is_none_or(|folder_path| folder_path == root_path)
+            });
+            if has_settings_sets {
+                self.configuration_problems.push(format!(
+                    "'{}' was ignored, since configuration sets for its folder are also specified in the settings \
+                     (which take precedence)",
+                    project_file.path.display(),
+                ));
+                continue;
+            }
+
+            if let Some(uri) = self.server_config.paths.convert_slice_path_to_uri(&project_file.path) {
+                if !project_file.diagnostics.is_empty() {
+                    self.project_file_diagnostics.insert(uri, project_file.diagnostics);
+                }
+            }
+            project_file_sets.extend(project_file.configuration_sets);
+        }
+        configurations.extend(project_file_sets);
+    }
+
+    /// Re-loads the workspace's `.sliceignore` files, and invalidates any options that were computed with the old ones.
+    pub fn reload_slice_ignore(&mut self) {
+        self.server_config.slice_ignore = SliceIgnore::load(&self.server_config.workspace_root_paths);
+        self.invalidate_cached_slice_options();
+    }
+
+    /// Adds and removes folders from the workspace.
+    ///
+    /// The configuration sets of removed folders are dropped, and default configuration sets are re-created for the
+    /// current folders, since which folders need them depends on which folders are in the workspace.
+    pub fn update_workspace_folders(&mut self, added_paths: Vec<PathBuf>, removed_paths: &[PathBuf]) {
+        let workspace_root_paths = &mut self.server_config.workspace_root_paths;
+        workspace_root_paths.retain(|root_path| !removed_paths.contains(root_path));
+        for added_path in added_paths {
+            if !workspace_root_paths.contains(&added_path) {
+                workspace_root_paths.push(added_path);
+            }
+        }
+
+        // Global sets are resolved against every folder, so their options must be re-computed too. This also has to
+        // happen before the sets are updated, since the sets that are discovered for folders depend on it.
+        self.reload_slice_ignore();
+
+        // Stand-alone sets are only kept if there's still no workspace (or they're for documents which aren't files),
+        // which is handled by `update_configurations`.
+        // Disabled sets are passed along with the others, so that they stay disabled (instead of being dropped).
+        let all_sets = std::mem::take(&mut self.configuration_sets)
+            .into_iter()
+            .chain(std::mem::take(&mut self.disabled_sets));
+        let (stand_alone_sets, configurations): (Vec<_>, Vec<_>) = all_sets.partition(ConfigurationSet::is_stand_alone);
+        self.configuration_sets = stand_alone_sets;
+
+        let configurations = configurations
+            .into_iter()
+            .filter(|set| !set.is_default() && !set.is_discovered())
+            .filter(|set| {
+                let folder_path = set.slice_config.folder_path.as_ref();
+                !folder_path.is_some_and(|folder_path| removed_paths.contains(folder_path))
+            })
+            .collect();
+        self.update_configurations(configurations, false);
+    }
+
+    /// Returns true if every configuration set containing the provided file has already compiled its current text (its
+    /// unsaved text if it has any, or its contents on disk otherwise), in which case compiling them again would be a
+    /// no-op. Files which aren't in any compiled set are never considered unchanged.
+    pub fn is_file_unchanged(&self, file_path: &Path) -> bool {
+        let Some(content_hash) = self.document_text(file_path).map(|text| hash_text(&text)) else {
+            return false;
+        };
+
+        let mut containing_sets = self
+            .configuration_sets
+            .iter()
+            .filter(|set| set.contains_file(file_path))
+            .peekable();
+        containing_sets.peek().is_some()
+            && containing_sets.all(|set| {
+                let compiled_file = set.compilation_data.file(file_path);
+                let is_current = compiled_file.is_some_and(|file| file.content_hash == content_hash);
+                set.is_compiled() && !set.is_evicted() && is_current
+            })
+    }
+
+    /// Returns the latest text of the file at the provided path: the client's text if the file is open, or its contents
+    /// on disk otherwise. Returns `None` if the file isn't open, and can't be read.
+    pub fn document_text(&self, file_path: &Path) -> Option<String> {
+        match self.open_documents.get(file_path) {
+            Some(document) => Some(document.text.clone()),
+            None => fs::read_to_string(file_path).ok(),
+        }
+    }
+
+    /// Converts a position that the client sent for a document into a location in the document, counting its column in
+    /// characters like `slicec` does. Positions are relative to the client's copy of the document, which can have
+    /// changes that weren't compiled yet, so the open document's text is used, falling back to `compiled_text`.
+    pub fn position_to_location(&self, file_path: &Path, compiled_text: &str, position: Position) -> Location {
+        let text = self
+            .open_documents
+            .get(file_path)
+            .map_or(compiled_text, |document| document.text.as_str());
+        PositionConverter::new(text, self.server_config.position_encoding).position_to_location(position)
+    }
+
+    /// Evicts the compilation data of each configuration set which hasn't been used for longer than the idle timeout
+    /// ('slice.compilation.idleTimeoutMinutes'), unless one of its files is open.
+    pub fn evict_idle_sets(&mut self) {
+        let Some(idle_set_timeout) = self.server_config.idle_set_timeout else {
+            return;
+        };
+        for set in &mut self.configuration_sets {
+            let has_open_documents = self.open_documents.keys().any(|path| set.contains_file(path));
+            if !has_open_documents && set.is_idle(idle_set_timeout) {
+                set.evict();
+            }
+        }
+    }
+
+    /// Adds a stand-alone configuration set for the provided file, unless there's a workspace (in which case the user's
+    /// configuration sets are used instead), or the file already has one. If some folders don't have any sets (and
+    /// don't get a default set), files which aren't in any set are also compiled on their own.
+    ///
+    /// Documents which aren't files (like untitled documents) can't be in any set, so they're always compiled on their
+    /// own.
+    pub fn add_stand_alone_set(&mut self, file_path: &Path) {
+        let has_workspace = !self.server_config.workspace_root_paths.is_empty();
+        let is_configurable = has_workspace && !is_virtual_document_path(file_path);
+        if (is_configurable && !self.has_unconfigured_folders)
+            || self.configuration_sets.iter().any(|set| set.is_stand_alone_set_for(file_path))
+            || (is_configurable && self.is_in_configuration_set(file_path))
+        {
+            return;
+        }
+        let mut stand_alone_set = ConfigurationSet::stand_alone_set(file_path);
+        stand_alone_set.update_membership(&self.server_config);
+        self.configuration_sets.push(stand_alone_set);
+    }
+
+    /// Returns true if the provided file is in one of the configuration sets, not counting stand-alone sets.
+    fn is_in_configuration_set(&self, file_path: &Path) -> bool {
+        self.configuration_sets
+            .iter()
+            .any(|set| !set.is_stand_alone() && set.contains_file(file_path))
+    }
+
+    /// Removes the stand-alone configuration set of the provided file, returning it if there was one.
+    pub fn remove_stand_alone_set(&mut self, file_path: &Path) -> Option<ConfigurationSet> {
+        let index = self
+            .configuration_sets
+            .iter()
+            .position(|set| set.is_stand_alone_set_for(file_path))?;
+        Some(self.configuration_sets.remove(index))
+    }
+
+    /// Invalidates the cached options of every configuration set, so they're re-computed for their next compilation,
+    /// and updates which files each set contains. This is necessary whenever files are created or deleted, since the
+    /// options can list each file individually.
+    pub fn invalidate_cached_slice_options(&mut self) {
+        for configuration_set in &mut self.configuration_sets {
+            configuration_set.invalidate_cached_slice_options();
+        }
+        self.update_set_membership();
+    }
+
+    /// Updates which files each configuration set (including the disabled ones) contains. This is called wherever the
+    /// configuration is applied, so that file events can check which sets contain a file without re-resolving paths.
+    fn update_set_membership(&mut self) {
+        for configuration_set in self.configuration_sets.iter_mut().chain(&mut self.disabled_sets) {
+            configuration_set.update_membership(&self.server_config);
+        }
+    }
+
+    /// Returns each pair of configuration sets that include some of the same paths. A path is included by both sets if
+    /// it's included by one set, and is equal to (or nested inside of) a path that's included by the other set.
+    /// Stand-alone sets are skipped, since they only hold files that aren't in a workspace.
+    pub fn find_set_overlaps(&self) -> Vec<SetOverlap> {
+        let sets_with_paths = self
+            .configuration_sets
+            .iter()
+            .filter(|set| !set.is_stand_alone())
+            .map(|set| (set, set.resolved_search_paths(&self.server_config)))
+            .collect::<Vec<_>>();
+
+        let mut overlaps = Vec::new();
+        for (index, (first_set, first_paths)) in sets_with_paths.iter().enumerate() {
+            for (second_set, second_paths) in &sets_with_paths[index + 1..] {
+                let paths = find_overlapping_paths(first_paths, second_paths);
+                if !paths.is_empty() {
+                    let first_set = first_set.name.clone();
+                    let second_set = second_set.name.clone();
+                    overlaps.push(SetOverlap { first_set, second_set, paths });
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// Returns the search paths of every configuration set which are outside of the workspace (including any directories
+    /// that are referenced by every set), without duplicates.
+    pub fn external_search_paths(&self) -> Vec<PathBuf> {
+        let workspace_root_paths = &self.server_config.workspace_root_paths;
+        let external_referenced_directories = self
+            .resolved_referenced_directories()
+            .into_iter()
+            .filter(|path| workspace_root_paths.iter().all(|root_path| !path.starts_with(root_path)));
+
+        let mut external_paths = self
+            .configuration_sets
+            .iter()
+            .flat_map(|set| set.external_search_paths(&self.server_config))
+            .chain(external_referenced_directories)
+            .collect::<Vec<_>>();
+        external_paths.sort();
+        external_paths.dedup();
+        external_paths
+    }
+
+    /// Returns the directories which are referenced by every configuration set, resolved against every workspace folder.
+    /// Only directories which exist are returned, and glob patterns aren't included.
+    fn resolved_referenced_directories(&self) -> Vec<PathBuf> {
+        let root_paths = self.server_config.workspace_root_paths.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+        self.server_config
+            .referenced_directories
+            .iter()
+            .filter(|path| !is_glob_pattern(path))
+            .flat_map(|path| resolve_path_in_roots(&root_paths, path))
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    /// Returns a description of each directory that's referenced by every configuration set, but doesn't exist (in any
+    /// workspace folder). These directories are skipped.
+    fn find_missing_referenced_directories(&self) -> Vec<String> {
+        let root_paths = self.server_config.workspace_root_paths.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+        self.server_config
+            .referenced_directories
+            .iter()
+            .filter(|path| !is_glob_pattern(path))
+            .filter(|path| !resolve_path_in_roots(&root_paths, path).iter().any(|resolved| resolved.is_dir()))
+            .map(|path| format!("the referenced directory '{}' does not exist, so it was skipped", path.display()))
+            .collect()
+    }
+
+    /// Re-runs the checks that span multiple configuration sets, which are affected by the compilation of any set.
+    /// Returns the files whose diagnostics could have changed because of this, and hence need to be re-published.
+    pub fn update_cross_set_diagnostics(&mut self) -> HashSet<Url> {
+        let cross_set_diagnostics = find_duplicate_definitions(&self.configuration_sets, &self.server_config);
+
+        // Both the files that had diagnostics and the files that have diagnostics now must be re-published, so that
+        // fixed issues are cleared.
+        let mut changed_files = self.cross_set_diagnostics.keys().cloned().collect::<HashSet<_>>();
+        changed_files.extend(cross_set_diagnostics.keys().cloned());
+
+        self.cross_set_diagnostics = cross_set_diagnostics;
+        changed_files
+    }
+
+    // Update the server-wide settings from a JSON object holding the 'slice' settings.
+    fn update_settings(&mut self, settings: &serde_json::Value) {
+        // This is the path to the built-in Slice files that are included with the extension. Our extension always sends
+        // it in the initialization options, but other clients can also provide it through the 'slice' settings.
+        if let Some(built_in_slice_path) = parse_built_in_slice_path(settings) {
+            self.server_config.built_in_slice_path = Some(built_in_slice_path);
+        }
+        self.server_config.unused_definitions = parse_unused_definitions(settings);
+        self.server_config.todo_comments = parse_todo_comments(settings);
+        self.server_config.todo_comment_markers = parse_todo_comment_markers(settings);
+        self.server_config.compilation_trigger = parse_compilation_trigger(settings);
+        self.server_config.lazy_compilation = parse_lazy_compilation(settings);
+        self.server_config.auto_discover_configurations = parse_auto_discover_configurations(settings);
+        self.server_config.skip_default_sets = !parse_compile_workspace_by_default(settings);
+        self.server_config.max_concurrent_compilations = parse_max_concurrent_compilations(settings);
+        self.server_config.max_file_size_kb = parse_max_file_size_kb(settings);
+        self.server_config.idle_set_timeout = parse_idle_set_timeout(settings);
+        self.server_config.referenced_directories = parse_referenced_directories(settings);
+        self.server_config.disabled_features = parse_disabled_features(settings);
+        self.server_config.new_file_header = parse_new_file_header(settings);
+        self.server_config.log_level = parse_log_level(settings);
+        self.server_config.log_file = parse_log_file(settings, &self.server_config.workspace_root_paths);
     }
 
-    // Update the configuration sets by replacing it with the new configurations. If there are no configuration sets
-    // after updating, insert the default configuration set.
-    fn update_configurations(&mut self, mut configurations: Vec<ConfigurationSet>) {
-        // Insert the default configuration set if needed
-        if configurations.is_empty() {
-            configurations.push(ConfigurationSet::default());
+    // Update the configuration sets by replacing it with the new configurations. Any workspace folders which aren't
+    // covered by a configuration set after updating get a default configuration set.
+    //
+    // If `keep_unchanged_sets` is set, new sets with the same configuration as an existing set take the results of its
+    // previous compilation. Sets are matched by their name and configuration, or just by their configuration if they
+    // were renamed (or moved).
+    fn update_configurations(&mut self, configurations: Vec<ConfigurationSet>, keep_unchanged_sets: bool) {
+        // The configuration changed, so any problems that are still in it are reported again.
+        self.reported_path_problems.clear();
+
+        // Without a workspace, there's nothing to resolve the configuration sets against, so they're skipped entirely.
+        // Opened files are compiled in their own stand-alone sets instead, which are kept until the files are closed.
+        if self.server_config.workspace_root_paths.is_empty() {
+            self.configuration_sets.retain(ConfigurationSet::is_stand_alone);
+            self.disabled_sets.clear();
+            return;
+        }
+
+        let (disabled_sets, mut configurations): (Vec<_>, Vec<_>) =
+            configurations.into_iter().partition(ConfigurationSet::is_disabled);
+        for set in &disabled_sets {
+            info!("Configuration set '{}' is disabled, so it isn't compiled", set.name);
+        }
+
+        // Global configuration sets cover every folder, so default sets are only needed if there aren't any.
+        // Disabled sets count too, since the user did configure the folder, they just don't want it compiled right now.
+        // If auto-discovery is enabled, folders get the sets discovered from their layout instead (if any are found).
+        // If the user turned off default sets, the folder is left without sets, and opened files are compiled alone.
+        self.has_unconfigured_folders = false;
+        let configured_folders = configurations
+            .iter()
+            .chain(&disabled_sets)
+            .map(|set| set.slice_config.folder_path.clone())
+            .collect::<Vec<_>>();
+        if configured_folders.iter().all(Option::is_some) {
+            let workspace_root_paths = &self.server_config.workspace_root_paths;
+            let unconfigured_folders = match workspace_root_paths.as_slice() {
+                [root_path] if configured_folders.is_empty() => vec![(root_path, None)],
+                _ => workspace_root_paths
+                    .iter()
+                    .filter(|root_path| !configured_folders.iter().any(|folder| folder.as_ref() == Some(root_path)))
+                    .map(|root_path| (root_path, Some(root_path.as_path())))
+                    .collect(),
+            };
+
+            let referenced_directories = self.resolved_referenced_directories();
+            for (root_path, folder_path) in unconfigured_folders {
+                let discovered_sets = match self.server_config.auto_discover_configurations {
+                    true => {
+                        let slice_ignore = &self.server_config.slice_ignore;
+                        discover_configuration_sets(root_path, folder_path, slice_ignore, &referenced_directories)
+                    }
+                    false => Vec::new(),
+                };
+                if !discovered_sets.is_empty() {
+                    configurations.extend(discovered_sets);
+                } else if self.server_config.skip_default_sets {
+                    let root_path = root_path.display();
+                    info!("'{root_path}' doesn't have any configuration sets, so only opened files are compiled");
+                    self.has_unconfigured_folders = true;
+                } else {
+                    configurations.push(ConfigurationSet::default_set(folder_path));
+                }
+            }
+        }
+
+        // Opened files which were compiled on their own keep their stand-alone sets, unless they're now in another set.
+        let (stand_alone_sets, previous_sets): (Vec<_>, Vec<_>) = std::mem::take(&mut self.configuration_sets)
+            .into_iter()
+            .partition(ConfigurationSet::is_stand_alone);
+        self.configuration_sets = previous_sets;
+        let stand_alone_sets = match self.has_unconfigured_folders {
+            true => stand_alone_sets,
+            false => stand_alone_sets.into_iter().filter(ConfigurationSet::is_virtual).collect(),
+        };
+
+        if keep_unchanged_sets {
+            let mut previous_sets = std::mem::take(&mut self.configuration_sets);
+            for set in &mut configurations {
+                let has_same_config = |previous_set: &ConfigurationSet| previous_set.slice_config == set.slice_config;
+                let matching_index = previous_sets
+                    .iter()
+                    .position(|previous_set| previous_set.name == set.name && has_same_config(previous_set))
+                    .or_else(|| previous_sets.iter().position(has_same_config));
+                if let Some(index) = matching_index {
+                    set.take_compilation_results(previous_sets.swap_remove(index));
+                }
+            }
         }
 
         self.configuration_sets = configurations;
+        self.disabled_sets = disabled_sets;
+        self.update_set_membership();
+        for set in stand_alone_sets {
+            let file_path = set.slice_config.slice_search_paths.first();
+            if !file_path.is_some_and(|file_path| self.is_in_configuration_set(file_path)) {
+                self.configuration_sets.push(set);
+            }
+        }
+    }
+}
+
+/// Parses the configuration sets from a JSON object holding the 'slice' settings, returning them alongside any problems
+/// that were found in them.
+///
+/// The global sets are stored in `configurations`. In multi-root workspaces, the sets that were configured for specific
+/// folders are stored in `folderConfigurations`, which is an array of objects holding a folder's URI, and its sets.
+fn parse_configuration_sets(
+    settings: &serde_json::Value,
+    paths: &PathResolver,
+) -> (Vec<ConfigurationSet>, Vec<String>) {
+    let (mut configuration_sets, mut problems) = match settings.get("configurations") {
+        Some(serde_json::Value::Array(arr)) => ConfigurationSet::parse_configuration_sets(arr, None),
+        None | Some(serde_json::Value::Null) => (Vec::new(), Vec::new()),
+        Some(_) => (Vec::new(), vec!["'slice.configurations' must be an array of configuration sets".to_owned()]),
+    };
+
+    let folder_configurations = settings
+        .get("folderConfigurations")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for folder_configuration in folder_configurations {
+        let folder_path = folder_configuration
+            .get("uri")
+            .and_then(|v| v.as_str())
+            .and_then(|uri| Url::parse(uri).ok())
+            .and_then(|uri| paths.url_to_sanitized_file_path(&uri));
+        let configurations = folder_configuration.get("configurations").and_then(|v| v.as_array());
+        if let (Some(folder_path), Some(configurations)) = (folder_path, configurations) {
+            let (folder_sets, folder_problems) =
+                ConfigurationSet::parse_configuration_sets(configurations, Some(&folder_path));
+            configuration_sets.extend(folder_sets);
+            problems.extend(folder_problems);
+        }
+    }
+    (configuration_sets, problems)
+}
+
+/// Parses the path to the built-in Slice files from a JSON value.
+fn parse_built_in_slice_path(value: &serde_json::Value) -> Option<String> {
+    value
+        .get("builtInSlicePath")
+        .and_then(|v| v.as_str())
+        .map(|path| real_path(Path::new(path)).display().to_string())
+}
+
+/// Parses which definitions should be reported if they're never referenced from a JSON value.
+fn parse_unused_definitions(value: &serde_json::Value) -> UnusedDefinitionsMode {
+    let mode = value
+        .get("diagnostics")
+        .and_then(|v| v.get("unusedDefinitions"))
+        .and_then(|v| v.as_str());
+
+    match mode {
+        Some("excludeInterfaces") => UnusedDefinitionsMode::ExcludeInterfaces,
+        Some("all") => UnusedDefinitionsMode::All,
+        _ => UnusedDefinitionsMode::Off,
+    }
+}
+
+/// Determines whether comments containing a TODO marker should be reported from a JSON value.
+fn parse_todo_comments(value: &serde_json::Value) -> bool {
+    value
+        .get("diagnostics")
+        .and_then(|v| v.get("todoComments"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Parses the markers which cause a comment to be reported from a JSON value.
+fn parse_todo_comment_markers(value: &serde_json::Value) -> Vec<String> {
+    value
+        .get("diagnostics")
+        .and_then(|v| v.get("todoCommentMarkers"))
+        .and_then(|v| v.as_array())
+        .map(|markers| {
+            markers
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_else(|| DEFAULT_TODO_COMMENT_MARKERS.map(str::to_owned).to_vec())
+}
+
+/// Parses whether configuration sets should only be compiled once one of their files is opened from a JSON value.
+fn parse_lazy_compilation(value: &serde_json::Value) -> bool {
+    value
+        .get("compilation")
+        .and_then(|v| v.get("lazy"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Parses whether folders without any configuration sets should get a default set that compiles the entire folder from
+/// a JSON value.
+fn parse_compile_workspace_by_default(value: &serde_json::Value) -> bool {
+    value
+        .get("compileWorkspaceByDefault")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
+}
+
+/// Parses whether configuration sets should be discovered for folders without any sets from a JSON value.
+fn parse_auto_discover_configurations(value: &serde_json::Value) -> bool {
+    value
+        .get("autoDiscoverConfigurations")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Parses the maximum number of configuration sets that are compiled at once from a JSON value.
+/// Returns `None` if it wasn't set (or wasn't a positive number), in which case a default based on the machine is used.
+fn parse_max_concurrent_compilations(value: &serde_json::Value) -> Option<usize> {
+    value
+        .get("compilation")
+        .and_then(|v| v.get("maxConcurrent"))
+        .and_then(|v| v.as_u64())
+        .filter(|max_concurrent| *max_concurrent > 0)
+        .and_then(|max_concurrent| usize::try_from(max_concurrent).ok())
+}
+
+/// Parses the size (in kilobytes) above which files are excluded from the per-request features from a JSON value.
+/// Returns `None` if the user turned the limit off by setting it to `0`, and defaults to 1024 if it wasn't set.
+fn parse_max_file_size_kb(value: &serde_json::Value) -> Option<u64> {
+    let max_file_size_kb = value.get("maxFileSizeKB").and_then(|v| v.as_u64()).unwrap_or(1024);
+    (max_file_size_kb > 0).then_some(max_file_size_kb)
+}
+
+/// Parses how long configuration sets can go unused before their compilation data is evicted from a JSON value.
+/// Returns `None` if it wasn't set (or was set to `0`), in which case sets are never evicted.
+fn parse_idle_set_timeout(value: &serde_json::Value) -> Option<Duration> {
+    value
+        .get("compilation")
+        .and_then(|v| v.get("idleTimeoutMinutes"))
+        .and_then(|v| v.as_u64())
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes * 60))
+}
+
+/// Parses which events should trigger a compilation from a JSON value.
+fn parse_compilation_trigger(value: &serde_json::Value) -> CompilationTrigger {
+    let trigger = value
+        .get("compilation")
+        .and_then(|v| v.get("trigger"))
+        .and_then(|v| v.as_str());
+
+    match trigger {
+        Some("onSave") => CompilationTrigger::OnSave,
+        _ => CompilationTrigger::OnType,
+    }
+}
+
+/// Parses the directories which are referenced by every configuration set from a JSON value.
+fn parse_referenced_directories(value: &serde_json::Value) -> Vec<PathBuf> {
+    value
+        .get("referencedDirectories")
+        .and_then(|v| v.as_array())
+        .map(|paths| {
+            paths
+                .iter()
+                .filter_map(|v| v.as_str())
+                .filter(|path| !path.trim().is_empty())
+                .map(|path| PathBuf::from(sanitize_path(path)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses the header that new Slice files are created with from a JSON value.
+fn parse_new_file_header(value: &serde_json::Value) -> String {
+    value
+        .get("newFile")
+        .and_then(|v| v.get("header"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Parses the level of the events which the server logs from a JSON value.
+fn parse_log_level(value: &serde_json::Value) -> LogLevel {
+    let log_level = value
+        .get("server")
+        .and_then(|v| v.get("logLevel"))
+        .and_then(|v| v.as_str());
+
+    match log_level {
+        Some("error") => LogLevel::Error,
+        Some("warn") => LogLevel::Warn,
+        Some("debug") => LogLevel::Debug,
+        Some("trace") => LogLevel::Trace,
+        _ => LogLevel::Info,
+    }
+}
+
+/// Parses the path of the file that the server's logs are written to from a JSON value. Like the paths of configuration
+/// sets, this can use variables, and relative paths are resolved against the first workspace folder.
+fn parse_log_file(value: &serde_json::Value, workspace_root_paths: &[PathBuf]) -> Option<PathBuf> {
+    let log_file = value
+        .get("server")
+        .and_then(|v| v.get("logFile"))
+        .and_then(|v| v.as_str())
+        .filter(|path| !path.is_empty())?;
+
+    let root_path = workspace_root_paths.first().map_or(Path::new(""), PathBuf::as_path);
+    resolve_path(root_path, Path::new(log_file))
+}
+
+/// Parses which language features were turned off from a JSON value. Features are enabled unless they're set to `false`.
+fn parse_disabled_features(value: &serde_json::Value) -> HashSet<Feature> {
+    let features = value.get("features");
+    Feature::ALL
+        .into_iter()
+        .filter(|feature| {
+            let is_enabled = features
+                .and_then(|v| v.get(feature.setting_name()))
+                .and_then(|v| v.as_bool());
+            is_enabled == Some(false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic_handler::{diagnostics_for_file, store_diagnostics_for_set};
+
+    /// Returns the 'slice' settings with a set for each of the provided names and `tests/fixtures` directories.
+    fn settings(sets: &[(&str, &str)]) -> serde_json::Value {
+        let fixtures_path = std::env::current_dir().unwrap().join("tests/fixtures");
+        let configurations = sets
+            .iter()
+            .map(|(name, directory)| {
+                let paths = [fixtures_path.join(directory)];
+                serde_json::json!({ "name": name, "paths": paths, "addWellKnownTypes": false })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({ "configurations": configurations })
+    }
+
+    /// Creates a server state for a workspace at `tests/fixtures`, with the provided settings, and compiles its sets.
+    fn compiled_server_state(settings: &serde_json::Value) -> ServerState {
+        let mut server_state = ServerState::default();
+        let fixtures_path = std::env::current_dir().unwrap().join("tests/fixtures");
+        server_state.server_config.workspace_root_paths = vec![fixtures_path];
+        server_state.update_from_settings(Some(settings));
+        compile_sets(&mut server_state);
+        server_state
+    }
+
+    /// Compiles the sets that haven't been compiled yet (like the server does after the configuration changes), and
+    /// returns their names.
+    fn compile_sets(server_state: &mut ServerState) -> Vec<String> {
+        let ServerState { configuration_sets, server_config, .. } = server_state;
+        let mut compiled_set_names = Vec::new();
+        for set in configuration_sets.iter_mut().filter(|set| !set.is_compiled()) {
+            let compilation = set.prepare_compilation(server_config, &HashMap::new());
+            let (diagnostics, _) = set.install_compilation(server_config, compilation.run()).unwrap();
+            store_diagnostics_for_set(diagnostics, set, server_config);
+            compiled_set_names.push(set.name.clone());
+        }
+        compiled_set_names
+    }
+
+    fn set_names(server_state: &ServerState) -> Vec<&str> {
+        server_state.configuration_sets.iter().map(|set| set.name.as_str()).collect()
+    }
+
+    #[test]
+    fn only_added_sets_are_compiled() {
+        let mut server_state = compiled_server_state(&settings(&[("a", "relative")]));
+
+        server_state.update_from_settings(Some(&settings(&[("a", "relative"), ("b", "type_ids")])));
+        assert_eq!(compile_sets(&mut server_state), ["b"]);
+        assert_eq!(set_names(&server_state), ["a", "b"]);
+    }
+
+    #[test]
+    fn reordered_sets_arent_compiled_again() {
+        let mut server_state = compiled_server_state(&settings(&[("a", "relative"), ("b", "type_ids")]));
+
+        server_state.update_from_settings(Some(&settings(&[("b", "type_ids"), ("a", "relative")])));
+        assert!(compile_sets(&mut server_state).is_empty());
+        assert_eq!(set_names(&server_state), ["b", "a"]);
+        assert!(server_state.configuration_sets.iter().all(|set| !set.compilation_data.files.is_empty()));
+    }
+
+    #[test]
+    fn only_sets_which_were_edited_in_place_are_compiled_again() {
+        let mut server_state = compiled_server_state(&settings(&[("a", "relative"), ("b", "type_ids")]));
+
+        server_state.update_from_settings(Some(&settings(&[("a", "relative"), ("b", "api_docs")])));
+        assert_eq!(compile_sets(&mut server_state), ["b"]);
+        let mut b_files = server_state.configuration_sets[1].compilation_data.files.keys();
+        assert!(b_files.all(|path| path.parent().unwrap().ends_with("api_docs")));
+    }
+
+    #[test]
+    fn editing_a_sets_paths_updates_which_files_it_contains() {
+        let mut server_state = compiled_server_state(&settings(&[("a", "relative")]));
+        let fixtures_path = std::env::current_dir().unwrap().join("tests/fixtures");
+        let greeter_path = fixtures_path.join("relative/greeter.slice");
+        let outer_path = fixtures_path.join("type_ids/outer.slice");
+        assert!(server_state.is_in_configuration_set(&greeter_path));
+        assert!(!server_state.is_in_configuration_set(&outer_path));
+
+        // Membership is updated as soon as the configuration changes, before the edited set is compiled again.
+        server_state.update_from_settings(Some(&settings(&[("a", "type_ids")])));
+        assert!(!server_state.is_in_configuration_set(&greeter_path));
+        assert!(server_state.is_in_configuration_set(&outer_path));
+        assert_eq!(compile_sets(&mut server_state), ["a"]);
+    }
+
+    #[test]
+    fn removed_sets_no_longer_report_diagnostics() {
+        let mut server_state = compiled_server_state(&settings(&[("a", "relative"), ("b", "type_ids")]));
+        let sender_path = std::env::current_dir().unwrap().join("tests/fixtures/relative/sender.slice");
+        let sender_uri = Url::from_file_path(sender_path).unwrap();
+        assert_eq!(diagnostics_for_file(&server_state, &sender_uri).len(), 1);
+
+        // The server re-publishes the files that had diagnostics before the change, which clears the removed set's.
+        let previous_uris = server_state.files_with_diagnostics();
+        server_state.update_from_settings(Some(&settings(&[("b", "type_ids")])));
+        assert!(compile_sets(&mut server_state).is_empty());
+        assert_eq!(set_names(&server_state), ["b"]);
+        assert!(previous_uris.contains(&sender_uri));
+        assert!(diagnostics_for_file(&server_state, &sender_uri).is_empty());
     }
 }
@@ -0,0 +1,131 @@
+// Copyright (c) ZeroC, Inc.
+
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the file that lists the paths which should be ignored by every configuration set.
+pub const SLICE_IGNORE_FILE_NAME: &str = ".sliceignore";
+
+/// The patterns from the `.sliceignore` files at the root of each workspace folder. These use the same syntax as
+/// `.gitignore` files:
+/// - Blank lines, and lines starting with `#` are ignored.
+/// - A leading `!` negates a pattern, re-including any paths that were matched by previous patterns.
+/// - A trailing `/` makes a pattern only match directories.
+/// - A pattern with a `/` at the beginning or in the middle is relative to the folder's root. Otherwise, it matches
+///   files and directories of that name at any depth.
+///
+/// Anything inside of an ignored directory is also ignored. Each file only applies to the paths inside of its folder.
+#[derive(Debug, Default)]
+pub struct SliceIgnore {
+    files: Vec<IgnoreFile>,
+}
+
+/// The rules of a single `.sliceignore` file, and the root path of the folder that it's in.
+#[derive(Debug)]
+struct IgnoreFile {
+    root_path: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug)]
+struct IgnoreRule {
+    pattern: Pattern,
+    is_negated: bool,
+    is_anchored: bool,
+    is_directory_only: bool,
+}
+
+impl SliceIgnore {
+    /// Loads the `.sliceignore` file at the root of each of the provided folders.
+    /// If a folder has no such file (or it can't be read), then nothing in that folder is ignored.
+    pub fn load(root_paths: &[PathBuf]) -> Self {
+        let files = root_paths
+            .iter()
+            .map(|root_path| {
+                let contents = fs::read_to_string(root_path.join(SLICE_IGNORE_FILE_NAME)).unwrap_or_default();
+                let rules = contents.lines().filter_map(IgnoreRule::parse).collect();
+                IgnoreFile { root_path: root_path.clone(), rules }
+            })
+            .collect();
+        SliceIgnore { files }
+    }
+
+    /// Returns true if this doesn't ignore anything.
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|file| file.rules.is_empty())
+    }
+
+    /// Returns true if the provided path is ignored, either directly, or because one of its ancestors is ignored.
+    ///
+    /// Only the ancestors which are inside of `base_path` are checked (and `base_path` itself isn't). This lets paths
+    /// that were explicitly listed by the user win over the ignore file.
+    pub fn is_ignored(&self, path: &Path, base_path: &Path) -> bool {
+        self.files.iter().any(|file| file.is_ignored(path, base_path))
+    }
+}
+
+impl IgnoreFile {
+    /// Returns true if this file ignores the provided path. See [`SliceIgnore::is_ignored`].
+    fn is_ignored(&self, path: &Path, base_path: &Path) -> bool {
+        let Ok(relative_path) = path.strip_prefix(&self.root_path) else {
+            return false;
+        };
+        let base_depth = base_path
+            .strip_prefix(&self.root_path)
+            .map_or(0, |relative_base| relative_base.components().count());
+
+        let components = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+
+        (base_depth + 1..=components.len()).any(|depth| {
+            let relative_prefix = components[..depth].join("/");
+            let is_directory = depth < components.len() || path.is_dir();
+            self.matches(&relative_prefix, &components[depth - 1], is_directory)
+        })
+    }
+
+    /// Returns true if a path (given relative to the folder's root, and by its name) is matched by the ignore rules.
+    /// Like with `.gitignore` files, the last rule that matches the path decides whether it's ignored.
+    fn matches(&self, relative_path: &str, name: &str, is_directory: bool) -> bool {
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::default() };
+
+        let mut is_ignored = false;
+        for rule in &self.rules {
+            if rule.is_directory_only && !is_directory {
+                continue;
+            }
+            let candidate = if rule.is_anchored { relative_path } else { name };
+            if rule.pattern.matches_with(candidate, options) {
+                is_ignored = !rule.is_negated;
+            }
+        }
+        is_ignored
+    }
+}
+
+impl IgnoreRule {
+    /// Parses a single line of a `.sliceignore` file, returning `None` if the line doesn't hold a (valid) rule.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (is_negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (is_directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let is_anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+
+        let pattern = Pattern::new(line).ok()?;
+        Some(IgnoreRule { pattern, is_negated, is_anchored, is_directory_only })
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::ServerConfig;
+use crate::configuration_set::{CompilationData, ConfigurationSet};
+use crate::duplicate_definitions::NamedDefinition;
+use crate::file_index::FileIndex;
+use crate::jump_definition::DefinitionLink;
+use crate::utils::hash_text;
+use serde_json::{json, Value};
+use slicec::slice_file::{Location, Span};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{Position, Range};
+use tracing::{debug, warn};
+
+/// The version of the cache's format. Caches written with a different version (or by a different version of the
+/// server, since the content hashes aren't stable across builds) are ignored, and rebuilt after the next compilation.
+const CACHE_FORMAT_VERSION: u64 = 1;
+
+/// The kinds of definitions which are stored in the cache. Kinds are stored as strings, and mapped back to these.
+const DEFINITION_KINDS: [&str; 7] = ["struct", "class", "exception", "interface", "enum", "custom type", "type alias"];
+
+/// Returns the path of the file that the symbols of the provided configuration set are cached in.
+///
+/// The file's name is derived from the set's name and configuration, so that sets which are configured differently
+/// (or are in different workspaces) never share a cache. The cached ranges are counted in the negotiated position
+/// encoding, so clients which negotiate different encodings don't share a cache either.
+pub fn cache_path(cache_directory: &Path, set: &ConfigurationSet, server_config: &ServerConfig) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    set.name.hash(&mut hasher);
+    format!("{:?}", set.slice_config).hash(&mut hasher);
+    server_config.workspace_root_paths.hash(&mut hasher);
+    server_config.position_encoding.hash(&mut hasher);
+    cache_directory.join(format!("symbols-{:016x}.json", hasher.finish()))
+}
+
+/// Converts the symbols of a compilation (the definitions of each file, and the spans which link to them) into the
+/// JSON value that's stored in the cache. Each file is stored along with a hash of the text it was compiled from.
+pub fn serialize(compilation_data: &CompilationData) -> Value {
+    let files = compilation_data
+        .files
+        .iter()
+        .map(|(path, file)| {
+            let definitions = file
+                .definitions
+                .iter()
+                .map(|definition| json!([definition.identifier, definition.kind, range_to_json(definition.range)]))
+                .collect::<Vec<_>>();
+            let links = file
+                .definition_links
+                .iter()
+                .map(|link| {
+                    let target = link.target.as_ref().map(|target| {
+                        let target_path = compilation_data.source_path(&target.file);
+                        json!([target_path.display().to_string(), span_to_json(target)])
+                    });
+                    json!([span_to_json(&link.span), target])
+                })
+                .collect::<Vec<_>>();
+            let value = json!({ "hash": file.content_hash, "definitions": definitions, "links": links });
+            (path.display().to_string(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    json!({ "version": CACHE_FORMAT_VERSION, "serverVersion": env!("CARGO_PKG_VERSION"), "files": files })
+}
+
+/// Writes the provided cache to a file. The cache is written to a temporary file first, and then moved into place, so
+/// that a server which is stopped halfway through writing it doesn't leave a truncated cache behind.
+pub fn save(path: &Path, cache: &Value) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path.with_extension("tmp"), cache.to_string()))
+        .and_then(|_| fs::rename(path.with_extension("tmp"), path));
+    if let Err(error) = result {
+        warn!("Failed to write the symbol cache '{}': {error}", path.display());
+    }
+}
+
+/// Loads the cache at the provided path, returning the symbols of each file that hasn't changed since it was cached.
+/// Files which changed (or were deleted) are dropped, since their symbols can't be trusted anymore.
+///
+/// Returns `None` if there's no cache, or if it can't be used (because it's corrupt, or has a different format). The
+/// cache is only a head start; it's replaced by the results of the set's first compilation either way.
+pub fn load(path: &Path) -> Option<CompilationData> {
+    let contents = fs::read_to_string(path).ok()?;
+    let Ok(cache) = serde_json::from_str::<Value>(&contents) else {
+        debug!("Ignoring the symbol cache '{}', since it's corrupt", path.display());
+        return None;
+    };
+    let version = cache.get("version").and_then(Value::as_u64);
+    let server_version = cache.get("serverVersion").and_then(Value::as_str);
+    if version != Some(CACHE_FORMAT_VERSION) || server_version != Some(env!("CARGO_PKG_VERSION")) {
+        debug!("Ignoring the symbol cache '{}', since it has a different format", path.display());
+        return None;
+    }
+
+    let mut files = HashMap::new();
+    for (file_path, value) in cache.get("files")?.as_object()? {
+        let file_path = PathBuf::from(file_path);
+        let Ok(raw_text) = fs::read_to_string(&file_path) else {
+            continue;
+        };
+        let content_hash = hash_text(&raw_text);
+        if value.get("hash").and_then(Value::as_u64) != Some(content_hash) {
+            continue;
+        }
+        let Some(file_index) = file_index_from_json(value, raw_text, content_hash) else {
+            debug!("Ignoring the symbol cache '{}', since it's corrupt", path.display());
+            return None;
+        };
+        files.insert(file_path, file_index);
+    }
+
+    debug!("Loaded the cached symbols of {} file(s) from '{}'", files.len(), path.display());
+    Some(CompilationData { files, ..CompilationData::default() })
+}
+
+fn file_index_from_json(value: &Value, raw_text: String, content_hash: u64) -> Option<FileIndex> {
+    let definitions = value
+        .get("definitions")?
+        .as_array()?
+        .iter()
+        .map(|definition| {
+            let identifier = definition.get(0)?.as_str()?.to_owned();
+            let kind = definition.get(1)?.as_str()?;
+            let kind = DEFINITION_KINDS.into_iter().find(|known_kind| *known_kind == kind)?;
+            let range = range_from_json(definition.get(2)?)?;
+            Some(NamedDefinition { identifier, kind, range })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let definition_links = value
+        .get("links")?
+        .as_array()?
+        .iter()
+        .map(|link| {
+            let span = span_from_json(link.get(0)?, String::new())?;
+            let target = match link.get(1)? {
+                Value::Null => None,
+                target => {
+                    let target_path = target.get(0)?.as_str()?.to_owned();
+                    Some(span_from_json(target.get(1)?, target_path)?)
+                }
+            };
+            Some(DefinitionLink { span, target })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(FileIndex { raw_text, content_hash, definitions, hover_entries: Vec::new(), definition_links })
+}
+
+fn range_to_json(range: Range) -> Value {
+    json!([range.start.line, range.start.character, range.end.line, range.end.character])
+}
+
+fn range_from_json(value: &Value) -> Option<Range> {
+    let [start_line, start_character, end_line, end_character] = numbers_from_json(value)?;
+    let start = Position::new(start_line.try_into().ok()?, start_character.try_into().ok()?);
+    let end = Position::new(end_line.try_into().ok()?, end_character.try_into().ok()?);
+    Some(Range::new(start, end))
+}
+
+fn span_to_json(span: &Span) -> Value {
+    json!([span.start.row, span.start.col, span.end.row, span.end.col])
+}
+
+fn span_from_json(value: &Value, file: String) -> Option<Span> {
+    let [start_row, start_col, end_row, end_col] = numbers_from_json(value)?;
+    let start = Location { row: start_row.try_into().ok()?, col: start_col.try_into().ok()? };
+    let end = Location { row: end_row.try_into().ok()?, col: end_col.try_into().ok()? };
+    Some(Span { start, end, file })
+}
+
+fn numbers_from_json(value: &Value) -> Option<[u64; 4]> {
+    let numbers = value.as_array()?.iter().map(Value::as_u64).collect::<Option<Vec<_>>>()?;
+    numbers.try_into().ok()
+}
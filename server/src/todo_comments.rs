@@ -0,0 +1,191 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration_set::CompilationData;
+use crate::position_encoding::PositionEncoding;
+use crate::utils::path_starts_with;
+use std::ops::Range;
+use std::path::Path;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Url};
+
+/// The markers that are reported when the user doesn't specify their own.
+pub const DEFAULT_TODO_COMMENT_MARKERS: [&str; 3] = ["TODO", "FIXME", "XXX"];
+
+/// Finds all the comments in a configuration set that contain one of the provided markers (`TODO`, `FIXME`, etc.),
+/// and returns a hint diagnostic for each of them. The message of each diagnostic is the comment's text, starting from
+/// the marker.
+///
+/// Markers are only matched as whole words, and markers inside code spans (`` `TODO` ``) of doc comments are ignored.
+/// Comments in the built-in Slice files are never reported, since users can't do anything about them.
+/// The columns of the diagnostics are counted in the provided encoding, since comments can contain non-ASCII text.
+pub fn find_todo_comments(
+    compilation_data: &CompilationData,
+    built_in_slice_path: Option<&str>,
+    markers: &[String],
+    encoding: PositionEncoding,
+) -> Vec<(Url, Diagnostic)> {
+    let mut diagnostics = Vec::new();
+    for (path, file) in &compilation_data.files {
+        if built_in_slice_path.is_some_and(|built_in_path| path_starts_with(path, Path::new(built_in_path))) {
+            continue;
+        }
+        let Some(uri) = compilation_data.paths.convert_slice_path_to_uri(path) else {
+            continue;
+        };
+
+        for segment in find_comment_segments(&file.raw_text, encoding) {
+            if let Some(diagnostic) = check_comment_segment(&segment, markers, encoding) {
+                diagnostics.push((uri.clone(), diagnostic));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The portion of a comment which lies on a single line.
+struct CommentSegment<'a> {
+    /// The (0-based) line that this segment is on.
+    line: u32,
+    /// The (0-based) column that this segment starts at, in the encoding that the comments were searched with.
+    column: u32,
+    /// The text of the comment on this line, excluding the comment's delimiters.
+    text: &'a str,
+    /// Whether this segment is part of a doc comment.
+    is_doc_comment: bool,
+}
+
+/// Returns a segment for each line of each comment in the provided Slice source code.
+///
+/// `slicec` only keeps the doc comments which are attached to definitions, so we have to find the other comments
+/// ourselves. This only tracks what's necessary to tell comments apart from the rest of the code: string literals
+/// (which can contain `//`) and comment delimiters.
+fn find_comment_segments(raw_text: &str, encoding: PositionEncoding) -> Vec<CommentSegment<'_>> {
+    let mut segments = Vec::new();
+
+    // Set while we're inside a block comment, and holds whether that comment is a doc comment.
+    let mut block_comment: Option<bool> = None;
+
+    for (line_number, line) in raw_text.lines().enumerate() {
+        let line_number = line_number as u32;
+        let mut push_segment = |byte_range: Range<usize>, is_doc_comment: bool| {
+            segments.push(CommentSegment {
+                line: line_number,
+                column: encoding.text_length(&line[..byte_range.start]),
+                text: &line[byte_range],
+                is_doc_comment,
+            });
+        };
+
+        // If we're continuing a block comment from a previous line, the segment starts at the beginning of the line.
+        let mut segment_start = block_comment.map(|is_doc_comment| (0, is_doc_comment));
+        let mut is_in_string = false;
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            // Inside of a block comment, the only thing we care about is where it ends.
+            if let Some((start, is_doc_comment)) = segment_start {
+                if c == '*' && matches!(chars.peek(), Some((_, '/'))) {
+                    push_segment(start..i, is_doc_comment);
+                    block_comment = None;
+                    segment_start = None;
+                    chars.next();
+                }
+                continue;
+            }
+
+            match c {
+                '"' => is_in_string = !is_in_string,
+                '\\' if is_in_string => {
+                    chars.next();
+                }
+                '/' if !is_in_string => match chars.peek() {
+                    // Line comments run until the end of the line. `///` starts a doc comment, but `////` doesn't.
+                    Some((_, '/')) => {
+                        let rest = &line[i..];
+                        let is_doc_comment = rest.starts_with("///") && !rest.starts_with("////");
+                        let delimiter_length = if is_doc_comment { 3 } else { 2 };
+                        push_segment(i + delimiter_length..line.len(), is_doc_comment);
+                        break;
+                    }
+                    // `/**` starts a doc comment, but `/**/` is just an empty comment.
+                    Some((_, '*')) => {
+                        let rest = &line[i..];
+                        let is_doc_comment = rest.starts_with("/**") && !rest.starts_with("/**/");
+                        block_comment = Some(is_doc_comment);
+                        segment_start = Some((i + 2, is_doc_comment));
+                        chars.next();
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        // If a block comment is still open at the end of the line, the rest of the line belongs to it.
+        if let Some((start, is_doc_comment)) = segment_start {
+            push_segment(start..line.len(), is_doc_comment);
+        }
+    }
+    segments
+}
+
+/// Checks whether the provided comment segment contains one of the provided markers, and if so, returns a diagnostic
+/// that spans from the first marker to the end of the segment's text.
+fn check_comment_segment(
+    segment: &CommentSegment,
+    markers: &[String],
+    encoding: PositionEncoding,
+) -> Option<Diagnostic> {
+    let code_spans = match segment.is_doc_comment {
+        true => find_code_spans(segment.text),
+        false => Vec::new(),
+    };
+
+    let marker_start = markers
+        .iter()
+        .filter(|marker| !marker.is_empty())
+        .filter_map(|marker| {
+            segment
+                .text
+                .match_indices(marker.as_str())
+                .map(|(index, _)| index)
+                .find(|&index| {
+                    is_whole_word(segment.text, index..index + marker.len())
+                        && !code_spans.iter().any(|span| span.contains(&index))
+                })
+        })
+        .min()?;
+
+    let text = segment.text[marker_start..].trim_end();
+    let start_column = segment.column + encoding.text_length(&segment.text[..marker_start]);
+    let end_column = start_column + encoding.text_length(text);
+
+    Some(Diagnostic {
+        range: tower_lsp::lsp_types::Range::new(
+            Position::new(segment.line, start_column),
+            Position::new(segment.line, end_column),
+        ),
+        severity: Some(DiagnosticSeverity::HINT),
+        code: Some(NumberOrString::String("todo-comment".to_owned())),
+        source: Some("slice-language-server".to_owned()),
+        message: text.to_owned(),
+        ..Diagnostic::default()
+    })
+}
+
+/// Returns the byte ranges of any code spans (text surrounded by backticks) in the provided text.
+/// An unterminated code span isn't a code span, so it isn't returned.
+fn find_code_spans(text: &str) -> Vec<Range<usize>> {
+    let backticks = text.match_indices('`').map(|(index, _)| index).collect::<Vec<_>>();
+    backticks
+        .chunks_exact(2)
+        .map(|pair| pair[0]..pair[1])
+        .collect()
+}
+
+/// Returns true if the provided range of text isn't directly preceded or followed by an identifier character.
+fn is_whole_word(text: &str, range: Range<usize>) -> bool {
+    let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_';
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char)
+}
@@ -0,0 +1,28 @@
+// Copyright (c) ZeroC, Inc.
+
+use std::path::Path;
+use std::time::Instant;
+use tower_lsp::lsp_types::notification::LogTrace;
+use tower_lsp::lsp_types::{LogTraceParams, TraceValue};
+use tower_lsp::Client;
+
+/// Sends a `$/logTrace` notification to the client, if it asked for tracing (with `$/setTrace`, or when initializing).
+///
+/// Unlike the server's logs, traces are only sent when the client asks for them, and are shown alongside the messages
+/// exchanged with the client. `verbose` is only called if the client asked for verbose tracing, so that details which
+/// are expensive to collect (like the resolved options of a set) are only collected when they're sent.
+pub async fn log_trace(client: &Client, trace: TraceValue, message: String, verbose: impl FnOnce() -> String) {
+    let verbose = match trace {
+        TraceValue::Off => return,
+        TraceValue::Messages => None,
+        TraceValue::Verbose => Some(verbose()),
+    };
+    client.send_notification::<LogTrace>(LogTraceParams { message, verbose }).await;
+}
+
+/// Traces how long the server took to handle a request for the provided document, which it started handling at
+/// `start_time`. The document is only included in verbose traces.
+pub async fn trace_request(client: &Client, trace: TraceValue, method: &str, start_time: Instant, file_path: &Path) {
+    let message = format!("Handled '{method}' in {:?}", start_time.elapsed());
+    log_trace(client, trace, message, || format!("Document: '{}'", file_path.display())).await;
+}
@@ -0,0 +1,75 @@
+// Copyright (c) ZeroC, Inc.
+
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The stream that the server reads the client's messages from.
+pub type Input = Box<dyn AsyncRead + Unpin + Send>;
+
+/// The stream that the server writes its messages to the client to.
+pub type Output = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// How the server communicates with its client. This is selected with the server's command-line arguments (see
+/// [`crate::arguments`]).
+///
+/// By default, the client spawns the server, and they communicate over the server's standard input and output. The
+/// other transports are for clients (and debugging setups) which can't spawn the server as a child process.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// The server's standard input and output (`--stdio`).
+    #[default]
+    Stdio,
+    /// A TCP connection that the server accepts on the provided port of the loopback interface (`--listen <port>`).
+    Listen(u16),
+    /// A TCP connection that the server makes to the provided port of the loopback interface (`--connect <port>`).
+    Connect(u16),
+    /// A named pipe (on Windows) or unix domain socket (elsewhere) that the server connects to (`--pipe <name>`).
+    Pipe(String),
+}
+
+impl Transport {
+    /// Opens the transport, returning the streams that the server reads its input from, and writes its output to.
+    /// For `--listen`, this waits until a client connects. Only the first client is accepted.
+    pub async fn open(&self) -> io::Result<(Input, Output)> {
+        match self {
+            Transport::Stdio => Ok((Box::new(tokio::io::stdin()), Box::new(tokio::io::stdout()))),
+            Transport::Listen(port) => {
+                let listener = TcpListener::bind(("127.0.0.1", *port)).await?;
+                let (stream, _) = listener.accept().await?;
+                let (input, output) = stream.into_split();
+                Ok((Box::new(input), Box::new(output)))
+            }
+            Transport::Connect(port) => {
+                let (input, output) = TcpStream::connect(("127.0.0.1", *port)).await?.into_split();
+                Ok((Box::new(input), Box::new(output)))
+            }
+            Transport::Pipe(name) => open_pipe(name).await,
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Stdio => write!(f, "standard input and output"),
+            Transport::Listen(port) => write!(f, "TCP port {port} (listening)"),
+            Transport::Connect(port) => write!(f, "TCP port {port}"),
+            Transport::Pipe(name) => write!(f, "pipe '{name}'"),
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn open_pipe(name: &str) -> io::Result<(Input, Output)> {
+    let (input, output) = tokio::net::UnixStream::connect(name).await?.into_split();
+    Ok((Box::new(input), Box::new(output)))
+}
+
+#[cfg(windows)]
+async fn open_pipe(name: &str) -> io::Result<(Input, Output)> {
+    let pipe = tokio::net::windows::named_pipe::ClientOptions::new().open(name)?;
+    let (input, output) = tokio::io::split(pipe);
+    Ok((Box::new(input), Box::new(output)))
+}
@@ -0,0 +1,121 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::UnusedDefinitionsMode;
+use crate::reference_index::ReferenceIndex;
+use crate::position_encoding::{PositionConverter, PositionEncoding};
+use crate::utils::{path_starts_with, PathResolver};
+use slicec::grammar::{Class, CustomType, Entity, Enum, Exception, Interface, Struct};
+use slicec::slice_file::SliceFile;
+use slicec::visitor::Visitor;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Url};
+
+/// Finds all the definitions in a configuration set that are never referenced, and returns a hint diagnostic for each.
+///
+/// A definition is referenced if it's used as a type, inherited from, thrown by an operation, or linked to from a doc
+/// comment, anywhere in the configuration set. Definitions in the built-in Slice files are never reported, since users
+/// can't do anything about them, but references from the built-in files are still counted.
+///
+/// Type aliases are never reported, since `slicec` resolves references to aliases into references to their underlying
+/// types, so we can't tell whether an alias was used.
+///
+/// This needs the set's `Ast`, so it's run right after the set is compiled, before the `Ast` is dropped. References are
+/// looked up in the set's [`ReferenceIndex`].
+pub fn find_unused_definitions(
+    files: &HashMap<PathBuf, SliceFile>,
+    references: &ReferenceIndex,
+    built_in_slice_path: Option<&str>,
+    mode: UnusedDefinitionsMode,
+    encoding: PositionEncoding,
+    paths: &PathResolver,
+) -> Vec<(Url, Diagnostic)> {
+    if mode == UnusedDefinitionsMode::Off {
+        return Vec::new();
+    }
+
+    // Check each definition in the user's files against the set's references.
+    let mut definition_visitor = DefinitionVisitor {
+        references,
+        positions: PositionConverter::new("", encoding),
+        include_interfaces: mode == UnusedDefinitionsMode::All,
+        unused_definitions: Vec::new(),
+    };
+
+    let mut diagnostics = Vec::new();
+    for (path, file) in files {
+        if built_in_slice_path.is_some_and(|built_in_path| path_starts_with(path, Path::new(built_in_path))) {
+            continue;
+        }
+        let Some(uri) = paths.convert_slice_path_to_uri(path) else {
+            continue;
+        };
+
+        definition_visitor.positions = PositionConverter::new(&file.raw_text, encoding);
+        file.visit_with(&mut definition_visitor);
+        diagnostics.extend(
+            definition_visitor
+                .unused_definitions
+                .drain(..)
+                .map(|diagnostic| (uri.clone(), diagnostic)),
+        );
+    }
+    diagnostics
+}
+
+/// Checks each visited definition against an index of references, and stores a diagnostic for each definition that
+/// isn't referenced.
+struct DefinitionVisitor<'a> {
+    references: &'a ReferenceIndex,
+    /// Converts the positions of the file that's currently being visited.
+    positions: PositionConverter<'a>,
+    include_interfaces: bool,
+    unused_definitions: Vec<Diagnostic>,
+}
+
+impl DefinitionVisitor<'_> {
+    fn check_definition(&mut self, entity: &dyn Entity) {
+        if self.references.is_referenced(&entity.parser_scoped_identifier()) {
+            return;
+        }
+
+        let message = format!("{} '{}' is never referenced", entity.kind(), entity.identifier());
+        self.unused_definitions.push(Diagnostic {
+            range: self.positions.span_to_range(&entity.raw_identifier().span),
+            severity: Some(DiagnosticSeverity::HINT),
+            code: Some(NumberOrString::String("unused-definition".to_owned())),
+            source: Some("slice-language-server".to_owned()),
+            message,
+            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+            ..Diagnostic::default()
+        });
+    }
+}
+
+impl Visitor for DefinitionVisitor<'_> {
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.check_definition(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.check_definition(class_def);
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.check_definition(exception_def);
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        if self.include_interfaces {
+            self.check_definition(interface_def);
+        }
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.check_definition(enum_def);
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.check_definition(custom_type_def);
+    }
+}
@@ -1,66 +1,1005 @@
 // Copyright (c) ZeroC, Inc.
 
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf, Prefix};
 
-use slicec::slice_file::{Location, Span};
-use tower_lsp::lsp_types::{Position, Range, Url};
+use crate::position_encoding::PositionEncoding;
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent, Url};
 
-// This helper function converts a Url from tower_lsp into a path that can be used to
-// retrieve a file from the compilation state from slicec.
-pub fn url_to_sanitized_file_path(url: &Url) -> Option<PathBuf> {
-    let path = url.to_file_path().ok()?;
-    let path_string = path.to_str()?;
-    Some(PathBuf::from(sanitize_path(path_string)))
+/// Converts between the URIs that the client knows files by and the paths that the server tracks them under.
+///
+/// Files are tracked under their canonical paths (see [`Self::canonical_path`]), which have their symlinks resolved, so
+/// that a file which the client reached through a symlink (like when a workspace folder is opened through one) is
+/// tracked under the same path that `slicec` reports it with. Resolving symlinks has to access the file system, so it
+/// only happens once for each workspace folder (when it's configured) and each document (when it's opened), which are
+/// then registered with the resolver (see [`Self::register`]). Every other path is resolved through the registered
+/// ones, which doesn't access the file system.
+///
+/// Each server has its own resolver, which only holds the workspace folders and open documents that were reached
+/// through a symlink, and forgets them once they're removed or closed.
+#[derive(Clone, Debug, Default)]
+pub struct PathResolver {
+    /// The paths that the client sent through a symlink, mapped to the paths they resolve to.
+    resolved_paths: BTreeMap<PathBuf, PathBuf>,
+    /// The inverse of `resolved_paths`.
+    ///
+    /// Files are tracked under their resolved paths, but the client only knows them by the paths it sent, so
+    /// diagnostics and locations must be sent back under those. Otherwise, the client would show them for a different
+    /// document than the one the user has open. See [`Self::client_path`].
+    client_paths: BTreeMap<PathBuf, PathBuf>,
+    /// The virtual paths of the open documents which aren't files, mapped to the documents' URIs. These paths are
+    /// derived from a hash of the URI (see [`Self::url_to_document_path`]), so the URI can't be recovered from them.
+    virtual_documents: BTreeMap<PathBuf, Url>,
 }
 
-pub fn convert_slice_path_to_uri(path: impl AsRef<Path>) -> Option<Url> {
-    Url::from_file_path(path).ok()
+/// The path of a file or folder that the client sent, along with the path that it resolves to once its symlinks are
+/// resolved. This is the part of registering a path which accesses the file system, so that it can happen before the
+/// server state is locked (see [`PathResolver::register`]).
+#[derive(Debug)]
+pub struct ResolvedPath {
+    client_path: PathBuf,
+    resolved_path: PathBuf,
 }
 
+impl ResolvedPath {
+    /// Resolves the symlinks in the path of a file URI. Returns `None` for URIs which aren't files.
+    pub fn new(url: &Url) -> Option<Self> {
+        let client_path = client_file_path(url)?;
+        let resolved_path = real_path(&client_path);
+        Some(ResolvedPath { client_path, resolved_path })
+    }
+
+    /// Returns the path as the client spelled it (apart from being normalized and sanitized).
+    pub fn client_path(&self) -> &Path {
+        &self.client_path
+    }
+}
+
+impl PathResolver {
+    /// Registers a file or folder that the client sent, so that it (and anything inside of it) is tracked under its
+    /// resolved path from now on. Returns true if it's reached through a symlink which wasn't already registered (by
+    /// one of its parent folders).
+    pub fn register(&mut self, resolved_path: ResolvedPath) -> bool {
+        let ResolvedPath { client_path, resolved_path } = resolved_path;
+        if self.canonical_path(&client_path) == resolved_path {
+            return false;
+        }
+        self.client_paths.insert(resolved_path.clone(), client_path.clone());
+        self.resolved_paths.insert(client_path, resolved_path);
+        true
+    }
+
+    /// Registers a workspace folder (see [`Self::register`]), and returns the path that it's tracked under.
+    pub fn register_folder(&mut self, url: &Url) -> Option<PathBuf> {
+        self.register(ResolvedPath::new(url)?);
+        self.url_to_sanitized_file_path(url)
+    }
+
+    /// Registers a document which isn't a file, so that paths in it can be converted back into its URI. Returns the
+    /// virtual path that the document is tracked under.
+    pub fn register_virtual_document(&mut self, url: &Url) -> PathBuf {
+        let path = virtual_document_path(url);
+        self.virtual_documents.insert(path.clone(), url.clone());
+        path
+    }
+
+    /// Forgets a file, folder, or virtual document that was registered, once it's no longer open in the client.
+    pub fn unregister(&mut self, url: &Url) {
+        let Some(client_path) = client_file_path(url) else {
+            self.virtual_documents.remove(&virtual_document_path(url));
+            return;
+        };
+        if let Some(resolved_path) = self.resolved_paths.remove(&client_path) {
+            if self.client_paths.get(&resolved_path) == Some(&client_path) {
+                self.client_paths.remove(&resolved_path);
+            }
+        }
+    }
+
+    // This helper function converts a Url from tower_lsp into a path that can be used to
+    // retrieve a file from the compilation state from slicec.
+    //
+    // This is the inverse of `convert_slice_path_to_uri`: both of them go through `canonical_path`, so a file's URI
+    // always converts back into the path it's tracked under, and vice versa.
+    pub fn url_to_sanitized_file_path(&self, url: &Url) -> Option<PathBuf> {
+        client_file_path(url).map(|path| self.canonical_path(&path))
+    }
+
+    /// Returns the path that the client knows the file at the provided canonical path by. Files inside of a directory
+    /// that the client sent through a symlink are spelled through the same symlink, even if the client never sent
+    /// their paths.
+    fn client_path<'a>(&self, canonical_path: &'a Path) -> Cow<'a, Path> {
+        match replace_ancestor(&self.client_paths, canonical_path) {
+            Some(client_path) => Cow::Owned(client_path),
+            None => Cow::Borrowed(canonical_path),
+        }
+    }
+
+    /// Converts the path of a Slice file into a URI. File URIs must be absolute, so relative paths are resolved against
+    /// the server's working directory first (which is what they're relative to, see [`Self::compiled_file_path`]).
+    /// Files which the client reached through a symlink are given a URI through the same symlink (see
+    /// [`Self::client_path`]).
+    pub fn convert_slice_path_to_uri(&self, path: impl AsRef<Path>) -> Option<Url> {
+        let path = path.as_ref();
+        if is_virtual_document_path(path) {
+            return self.virtual_documents.get(path).cloned();
+        }
+        // Sanitized paths keep the verbatim prefix of long paths, which URIs can't hold, so it's removed first.
+        // Converting the URI back into a path adds it again (see `url_to_sanitized_file_path`).
+        let canonical_path = match path.is_absolute() {
+            true => self.canonical_path(path),
+            false => self.compiled_file_path(&path.to_string_lossy()),
+        };
+        let absolute_path = self.client_path(&canonical_path);
+        match absolute_path.to_str() {
+            Some(absolute_path) => file_path_to_uri(Path::new(&strip_verbatim_prefix(absolute_path))),
+            None => file_path_to_uri(&absolute_path),
+        }
+    }
+
+    /// Returns the canonical form of an absolute path, which is the form that files are tracked under: keys of
+    /// `CompilationData::files`, paths of open documents, and paths looked up by requests are all canonical.
+    ///
+    /// The same file can be spelled in many ways (with `.` components, trailing separators, mixed separators on
+    /// Windows, or through a symlink), depending on whether its path came from the client (as a URI) or from `slicec`.
+    /// If these spellings were kept, a file could get diagnostics but no hover (or vice versa), so every path entering
+    /// the server is converted with this first. Symlinks are resolved through the registered paths (see
+    /// [`Self::register`]), so this never accesses the file system.
+    pub fn canonical_path(&self, path: &Path) -> PathBuf {
+        let normalized_path = sanitize_path_buf(normalize_path(path));
+        match replace_ancestor(&self.resolved_paths, &normalized_path) {
+            Some(resolved_path) => sanitize_path_buf(resolved_path),
+            None => normalized_path,
+        }
+    }
+
+    /// Converts the path of a compiled file, as `slicec` reports it (a file's `relative_path`, or a span's `file`),
+    /// into the path that the server tracks the file under, which is what documents are looked up with.
+    ///
+    /// `slicec` reports each file with the path it was found under, which is relative if the set's paths resolved to
+    /// a relative path (like when there isn't a workspace folder to resolve them against). `slicec` reads these
+    /// relative to the server's working directory, so they're resolved against it here. The resolved path is then made
+    /// canonical (see [`Self::canonical_path`]), so that it matches the path the client's URI for the file converts
+    /// into.
+    pub fn compiled_file_path(&self, reported_path: &str) -> PathBuf {
+        let path = Path::new(reported_path);
+        if path.is_absolute() {
+            return self.canonical_path(path);
+        }
+        match std::env::current_dir() {
+            Ok(working_directory) => self.canonical_path(&working_directory.join(path)),
+            Err(_) => PathBuf::from(sanitize_path(reported_path)),
+        }
+    }
+
+    /// Converts the URI of a document that's open in the client into the path that the document is tracked under.
+    /// Documents which aren't files (like untitled documents) don't have a path, so they get a virtual one instead,
+    /// which only converts back into their URI while they're registered (see [`Self::register_virtual_document`]).
+    pub fn url_to_document_path(&self, url: &Url) -> Option<PathBuf> {
+        match url.scheme() {
+            "file" => self.url_to_sanitized_file_path(url),
+            _ => Some(virtual_document_path(url)),
+        }
+    }
+}
+
+/// Returns the path of a file URI as the client spelled it, apart from being normalized and sanitized.
+fn client_file_path(url: &Url) -> Option<PathBuf> {
+    let path = url.to_file_path().ok().filter(|path| path.to_str().is_some())?;
+    Some(sanitize_path_buf(normalize_path(&path)))
+}
+
+/// Replaces the innermost ancestor of a path (which includes the path itself) that's a key of the provided map with the
+/// key's value. Returns `None` if none of the path's ancestors are keys of the map.
+fn replace_ancestor(paths: &BTreeMap<PathBuf, PathBuf>, path: &Path) -> Option<PathBuf> {
+    if paths.is_empty() {
+        return None;
+    }
+    path.ancestors().find_map(|ancestor| {
+        let replacement = paths.get(ancestor)?;
+        let relative_path = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+        Some(match relative_path.as_os_str().is_empty() {
+            true => replacement.clone(),
+            false => replacement.join(relative_path),
+        })
+    })
+}
+
+/// Converts an absolute path into a file URI, which is percent-encoded the same way that VS Code encodes the URIs it
+/// sends. Clients look documents up by their URIs, so if a file's diagnostics were published under a URI which was
+/// encoded differently than the one it was opened with (like `file:///C:/a+b` instead of `file:///c%3A/a%2Bb`),
+/// they'd never show up. Every character except unreserved ones (letters, digits, `-`, `.`, `_` and `~`) is encoded,
+/// and disk letters are lowercased. `Url::to_file_path` decodes any encoding, so the URI still converts back into the
+/// path.
+fn file_path_to_uri(path: &Path) -> Option<Url> {
+    let mut uri = Url::from_file_path(path).ok()?;
+    let mut encoded_path = String::new();
+    for component in path.components() {
+        let segment = match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+                    format!("{}%3A", letter.to_ascii_lowercase() as char)
+                }
+                // The server and share of network paths are the URI's host, and the first segment of its path.
+                Prefix::UNC(_, share) | Prefix::VerbatimUNC(_, share) => match share.to_str() {
+                    Some(share) => percent_encode(share),
+                    None => return Some(uri),
+                },
+                // Other prefixes can't be spelled in a file URI anyway, so they're left as `Url` encodes them.
+                _ => return Some(uri),
+            },
+            Component::Normal(name) => match name.to_str() {
+                Some(name) => percent_encode(name),
+                None => return Some(uri),
+            },
+            _ => continue,
+        };
+        encoded_path.push('/');
+        encoded_path.push_str(&segment);
+    }
+    if !path.components().any(|component| matches!(component, Component::Normal(_))) {
+        encoded_path.push('/');
+    }
+    // Percent signs are left as-is when setting a path, so the path isn't encoded twice.
+    uri.set_path(&encoded_path);
+    Some(uri)
+}
+
+/// Percent-encodes a segment of a URI's path: every byte of its UTF-8 encoding, except for unreserved characters.
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            true => encoded.push(byte as char),
+            false => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Resolves any symlinks in an absolute path, so that a file which can be reached through a symlink (like when a
+/// workspace folder is opened through one) is always spelled with its real path.
+///
+/// Only the part of the path which exists can be resolved, so the rest of it (like the name of a file which hasn't been
+/// saved yet) is appended to the resolved part as-is. Relative paths are returned as-is. On Windows, resolved paths are
+/// verbatim (`\\?\C:\...`), so the verbatim prefix is removed, since paths from the client never have it.
+///
+/// This accesses the file system, so it's only used when the configuration is loaded, and when a path is registered
+/// (see [`PathResolver::register`]), instead of whenever a path is converted.
+pub fn resolve_symlinks(path: &Path) -> PathBuf {
+    if !path.is_absolute() {
+        return path.to_owned();
+    }
+    let mut unresolved_components = Vec::new();
+    let mut existing_path = path;
+    let resolved_path = loop {
+        if let Ok(resolved_path) = fs::canonicalize(existing_path) {
+            break resolved_path;
+        }
+        match (existing_path.parent(), existing_path.file_name()) {
+            (Some(parent), Some(file_name)) => {
+                unresolved_components.push(file_name);
+                existing_path = parent;
+            }
+            _ => return path.to_owned(),
+        }
+    };
+
+    let mut resolved_path = match resolved_path.to_str() {
+        Some(path_string) => PathBuf::from(strip_verbatim_prefix(path_string)),
+        None => resolved_path,
+    };
+    resolved_path.extend(unresolved_components.iter().rev());
+    resolved_path
+}
+
+/// Returns the real path of an absolute path that the server was configured with (like the path to the built-in Slice
+/// files), which is normalized and has its symlinks resolved, like the paths of registered files (see
+/// [`ResolvedPath::new`]). This accesses the file system, so it's only used when the configuration is loaded.
+pub fn real_path(path: &Path) -> PathBuf {
+    sanitize_path_buf(resolve_symlinks(&normalize_path(path)))
+}
+
+/// The key that a path is compared with other paths by. Paths on Windows are case-insensitive, so they're compared in
+/// lowercase there. Canonical paths keep the casing they were spelled with (so that the client is sent back the same
+/// URIs it sent), so paths which could differ in casing must be compared by their keys.
+///
+/// Keys are only for comparing paths, and can't be turned back into paths, since they've lost their casing.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathKey(PathBuf);
+
+impl PathKey {
+    pub fn new(path: &Path) -> Self {
+        match cfg!(windows) {
+            true => PathKey(PathBuf::from(path.display().to_string().to_lowercase())),
+            false => PathKey(path.to_owned()),
+        }
+    }
+
+    /// Returns true if this key's path is the provided key's path, or is inside of it.
+    pub fn starts_with(&self, base_key: &PathKey) -> bool {
+        self.0.starts_with(&base_key.0)
+    }
+}
+
+/// Returns true if the provided paths refer to the same file (ignoring their casing on Windows).
+pub fn paths_match(path: &Path, other_path: &Path) -> bool {
+    path == other_path || (cfg!(windows) && PathKey::new(path) == PathKey::new(other_path))
+}
+
+/// Returns true if the provided path is the provided base path, or is inside of it (ignoring casing on Windows).
+pub fn path_starts_with(path: &Path, base_path: &Path) -> bool {
+    path.starts_with(base_path)
+        || (cfg!(windows) && PathKey::new(path).starts_with(&PathKey::new(base_path)))
+}
+
+/// Returns true if the provided path is the virtual path of a document which isn't a file (see
+/// [`PathResolver::url_to_document_path`]). Nothing exists at these paths, so these documents are always compiled from
+/// their text.
+pub fn is_virtual_document_path(path: &Path) -> bool {
+    path.parent() == Some(virtual_document_directory().as_path())
+}
+
+/// The directory which holds the virtual paths of documents that aren't files. Nothing is ever written to it.
+fn virtual_document_directory() -> PathBuf {
+    std::env::temp_dir().join("slice-language-server-documents")
+}
+
+/// Returns the virtual path of a document which isn't a file. The file name is a hash of the document's URI, since URIs
+/// can be longer than file names are allowed to be, and can contain characters that aren't allowed in them. Unsaved
+/// documents are written to an overlay under this file name (see [`crate::overlay::Overlay`]), so it must be valid.
+fn virtual_document_path(url: &Url) -> PathBuf {
+    virtual_document_directory().join(format!("{:016x}.slice", hash_text(url.as_str())))
+}
+
+/// Paths at least this long must be spelled with a verbatim (`\\?\`) prefix on Windows, or the file system rejects
+/// them (unless long paths were enabled system-wide, which the server can't rely on).
+#[cfg(target_os = "windows")]
+const MAX_PATH: usize = 260;
+
+/// Sanitizes a path on Windows, so that every spelling of a path ends up the same:
+/// - Forward-slashes are replaced with back-slashes.
+/// - Verbatim prefixes (`\\?\C:\` and `\\?\UNC\server\share\`) are removed, so that these paths match the paths that
+///   the client sends (which never have them), unless the path is too long to be used without one (see [`MAX_PATH`]).
+///   Long paths are always given a verbatim prefix instead, so they can still be read.
+/// - Disk prefixes are capitalized (`c:` becomes `C:`).
+///
+/// Network paths (`\\server\share\...`) are kept as they are, apart from the verbatim prefix.
 #[cfg(target_os = "windows")]
 pub fn sanitize_path(s: &str) -> String {
     use std::path::{Component, Prefix};
 
-    // Replace any forward-slashes with back-slashes.
-    let mut sanitized_path = s.replace('/', "\\");
+    // Replace any forward-slashes with back-slashes, and remove any verbatim prefix (it's added back below if needed).
+    let mut sanitized_path = strip_verbatim_prefix(&s.replace('/', "\\"));
+
+    let prefix = Path::new(&sanitized_path).components().next();
+    let is_disk = matches!(prefix, Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::Disk(_)));
+    let is_network = matches!(prefix, Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::UNC(..)));
 
     // Check if the path begins with a disk prefix (windows), and if so, make sure it's capitalized.
-    if let Some(Component::Prefix(prefix)) = Path::new(&sanitized_path).components().next() {
-        if matches!(prefix.kind(), Prefix::Disk(_) | Prefix::VerbatimDisk(_)) {
-            // disk prefixes are always of the form 'C:' or '\\?\C:'
-            let colon_index = sanitized_path.find(':').expect("no colon in disk prefix");
-            let disk_prefix = sanitized_path.split_at_mut(colon_index).0;
+    if is_disk {
+        // disk prefixes are always of the form 'C:'
+        let colon_index = sanitized_path.find(':').expect("no colon in disk prefix");
+        let disk_prefix = sanitized_path.split_at_mut(colon_index).0;
+
+        // Windows disk prefixes only use ascii characters.
+        assert!(disk_prefix.is_ascii());
+        disk_prefix.make_ascii_uppercase()
+    }
 
-            // Windows disk prefixes only use ascii characters.
-            assert!(disk_prefix.is_ascii());
-            disk_prefix.make_ascii_uppercase()
+    // Long paths can only be read with a verbatim prefix.
+    if sanitized_path.len() >= MAX_PATH {
+        if is_disk {
+            sanitized_path.insert_str(0, r"\\?\");
+        } else if is_network {
+            sanitized_path.replace_range(..r"\\".len(), r"\\?\UNC\");
         }
     }
 
     sanitized_path
 }
 
+/// Removes the verbatim (`\\?\`) prefix of a path on Windows, if it has one, since file URIs can't hold them. Verbatim
+/// network paths (`\\?\UNC\server\share\...`) are turned back into plain network paths (`\\server\share\...`). Other
+/// verbatim paths (like `\\?\Volume{...}\`) can't be spelled without their prefix, so they're kept as they are.
+#[cfg(target_os = "windows")]
+fn strip_verbatim_prefix(path: &str) -> String {
+    let is_disk_path = |path: &&str| path.get(1..2) == Some(":");
+    if let Some(network_path) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{network_path}")
+    } else if let Some(disk_path) = path.strip_prefix(r"\\?\").filter(is_disk_path) {
+        disk_path.to_owned()
+    } else {
+        path.to_owned()
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn strip_verbatim_prefix(path: &str) -> String {
+    path.to_owned()
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn sanitize_path(s: &str) -> String {
     s.to_owned()
 }
 
-/// Converts a [`slicec::slice_file::Span`] into a [`tower_lsp::lsp_types::Range`].
-pub fn span_to_range(span: Span) -> Range {
-    let start = Position::new(
-        (span.start.row - 1) as u32,
-        (span.start.col - 1) as u32,
-    );
-    let end = Position::new(
-        (span.end.row - 1) as u32,
-        (span.end.col - 1) as u32,
-    );
-    Range::new(start, end)
-}
-
-/// Converts a [`tower_lsp::lsp_types::Position`] into a [`slicec::slice_file::Location`].
-pub fn position_to_location(position: Position) -> Location {
-    let row = (position.line + 1) as usize;
-    let col = (position.character + 1) as usize;
-    Location { row, col }
+/// Sanitizes a path (see [`sanitize_path`]). Paths which aren't valid UTF-8 are kept as they are.
+fn sanitize_path_buf(path: PathBuf) -> PathBuf {
+    match path.to_str() {
+        Some(path_string) => PathBuf::from(sanitize_path(path_string)),
+        None => path,
+    }
+}
+
+/// Normalizes a path without accessing the file system, by removing any `.` components (and trailing separators), and
+/// collapsing any `..` components into their parent. Leading `..` components of relative paths are kept, since there's
+/// nothing to collapse them into. Note that this can differ from the file system's resolution if the path has symlinks.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // There's nothing above the root, so this is a no-op, just like it is on the file system.
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+
+    match normalized.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => normalized,
+    }
+}
+
+/// Hashes the text of a Slice file, so that it can be checked for changes without keeping a copy of its text around.
+pub fn hash_text(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Applies a change that the client sent for a document to the document's text. Changes without a range replace the
+/// document's entire text, whereas changes with a range only replace that part of it.
+pub fn apply_content_change(text: &mut String, change: TextDocumentContentChangeEvent, encoding: PositionEncoding) {
+    match change.range {
+        Some(range) => {
+            let start = position_to_offset(text, range.start, encoding);
+            let end = position_to_offset(text, range.end, encoding).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => *text = change.text,
+    }
+}
+
+/// Applies the changes that the client sent for a document to the document's text, in the order they were sent (each
+/// change's range is relative to the text after the previous change).
+///
+/// In debug builds, the changes are also applied to a shadow copy of the text, which finds the positions of the changes
+/// one character at a time instead (see [`apply_content_change_to_shadow`]). Returns false if the two copies end up
+/// different, which means that the text built up from the incremental changes has drifted from the client's copy of
+/// the document. Changes without a range replace both copies, so a full sync of the document never counts as drift.
+pub fn apply_content_changes(
+    text: &mut String,
+    changes: Vec<TextDocumentContentChangeEvent>,
+    encoding: PositionEncoding,
+) -> bool {
+    let mut shadow_text = cfg!(debug_assertions).then(|| text.clone());
+    for change in changes {
+        if let Some(shadow_text) = &mut shadow_text {
+            apply_content_change_to_shadow(shadow_text, &change, encoding);
+        }
+        apply_content_change(text, change, encoding);
+    }
+    shadow_text.is_none_or(|shadow_text| shadow_text == *text)
+}
+
+/// Applies a change to the shadow copy of a document's text (see [`apply_content_changes`]), like
+/// [`apply_content_change`] does, but without splitting the text into lines.
+fn apply_content_change_to_shadow(
+    text: &mut String,
+    change: &TextDocumentContentChangeEvent,
+    encoding: PositionEncoding,
+) {
+    let Some(range) = change.range else {
+        return text.clone_from(&change.text);
+    };
+    let offset_of = |position: Position| {
+        let (mut line, mut character) = (0, 0);
+        for (offset, c) in text.char_indices() {
+            let is_line_end = c == '\r' || c == '\n';
+            if line == position.line && (character >= position.character || is_line_end) {
+                return offset;
+            }
+            match c {
+                '\n' => (line, character) = (line + 1, 0),
+                _ => character += encoding.text_length(c.encode_utf8(&mut [0; 4])),
+            }
+        }
+        text.len()
+    };
+    let start = offset_of(range.start);
+    let end = offset_of(range.end).max(start);
+    text.replace_range(start..end, &change.text);
+}
+
+/// Converts a position in the provided text into a byte offset, counting the position's characters in the provided
+/// encoding. Positions past the end of a line are clamped to the end of that line, and positions past the last line
+/// are clamped to the end of the text.
+pub fn position_to_offset(text: &str, position: Position, encoding: PositionEncoding) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = &text[line_start..];
+    let line = &line[..line.find(['\r', '\n']).unwrap_or(line.len())];
+    let column = encoding.decode_column(line, position.character);
+    line_start + line.char_indices().nth(column).map_or(line.len(), |(index, _)| index)
+}
+
+/// Recursively searches a directory for Slice files (files with a `.slice` extension), the same way `slicec` does.
+pub fn find_slice_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+
+    // Sort the entries, so that files are always compiled in the same order.
+    let mut paths = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            find_slice_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "slice") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Range;
+
+    type LineColumn = (u32, u32);
+
+    fn apply(text: &str, range: Option<(LineColumn, LineColumn)>, insert: &str, encoding: PositionEncoding) -> String {
+        let mut text = text.to_owned();
+        let range = range.map(|(start, end)| Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)));
+        let change = TextDocumentContentChangeEvent { range, range_length: None, text: insert.to_owned() };
+        apply_content_change(&mut text, change, encoding);
+        text
+    }
+
+    #[test]
+    fn changes_without_a_range_replace_the_whole_text() {
+        assert_eq!(apply("module Foo\n", None, "module Bar\n", PositionEncoding::Utf16), "module Bar\n");
+    }
+
+    #[test]
+    fn changes_can_span_multiple_lines() {
+        let text = "module Foo\nstruct A {}\nstruct B {}\n";
+        let changed = apply(text, Some(((0, 7), (2, 8))), "Bar\nstruct C", PositionEncoding::Utf16);
+        assert_eq!(changed, "module Bar\nstruct C {}\n");
+
+        // Deleting a line terminator joins the lines.
+        let changed = apply(text, Some(((1, 11), (2, 0))), "", PositionEncoding::Utf16);
+        assert_eq!(changed, "module Foo\nstruct A {}struct B {}\n");
+    }
+
+    #[test]
+    fn changes_keep_crlf_line_terminators() {
+        let text = "module Foo\r\nstruct A {}\r\n";
+        assert_eq!(apply(text, Some(((1, 7), (1, 8))), "B", PositionEncoding::Utf16), "module Foo\r\nstruct B {}\r\n");
+
+        // Positions past the end of a line are clamped before its `\r\n`, rather than splitting it up.
+        let changed = apply(text, Some(((0, 50), (0, 60))), " // x", PositionEncoding::Utf16);
+        assert_eq!(changed, "module Foo // x\r\nstruct A {}\r\n");
+
+        // Replacing a range that covers a `\r\n` replaces the whole terminator.
+        let changed = apply(text, Some(((0, 10), (1, 0))), "\n", PositionEncoding::Utf16);
+        assert_eq!(changed, "module Foo\nstruct A {}\r\n");
+    }
+
+    #[test]
+    fn changes_past_the_end_are_clamped() {
+        let text = "module Foo\nstruct A {}";
+
+        // Past the end of a line.
+        assert_eq!(apply(text, Some(((0, 99), (0, 99))), "Bar", PositionEncoding::Utf16), "module FooBar\nstruct A {}");
+
+        // Past the last line.
+        let changed = apply(text, Some(((9, 0), (9, 5))), "\nstruct B {}", PositionEncoding::Utf16);
+        assert_eq!(changed, "module Foo\nstruct A {}\nstruct B {}");
+
+        // Ranges which end before they start don't remove anything.
+        assert_eq!(apply(text, Some(((1, 6), (0, 3))), "!", PositionEncoding::Utf16), "module Foo\nstruct! A {}");
+    }
+
+    #[test]
+    fn changes_count_columns_in_the_negotiated_encoding() {
+        // '日' is 3 bytes in UTF-8 (and 1 unit in UTF-16 and UTF-32), and '😀' is 4 bytes in UTF-8, 2 units in UTF-16
+        // (a surrogate pair), and 1 unit in UTF-32.
+        let text = "/// 日😀 x\nmodule Foo\n";
+        let expected = "/// 日😀 y\nmodule Foo\n";
+        assert_eq!(apply(text, Some(((0, 12), (0, 13))), "y", PositionEncoding::Utf8), expected);
+        assert_eq!(apply(text, Some(((0, 8), (0, 9))), "y", PositionEncoding::Utf16), expected);
+        assert_eq!(apply(text, Some(((0, 7), (0, 8))), "y", PositionEncoding::Utf32), expected);
+
+        // Replacing the emoji itself, and inserting non-ASCII text.
+        let expected = "/// 日本 x\nmodule Foo\n";
+        assert_eq!(apply(text, Some(((0, 5), (0, 7))), "本", PositionEncoding::Utf16), expected);
+        assert_eq!(apply(text, Some(((0, 7), (0, 11))), "本", PositionEncoding::Utf8), expected);
+
+        // A position inside of the surrogate pair is moved past it, so the pair is never split.
+        let changed = apply(text, Some(((0, 6), (0, 6))), "!", PositionEncoding::Utf16);
+        assert_eq!(changed, "/// 日😀! x\nmodule Foo\n");
+    }
+
+    #[test]
+    fn full_syncs_dont_count_as_drift() {
+        let mut text = "module Foo\n".to_owned();
+        let new_text = "module Bar\n".to_owned();
+        let change = TextDocumentContentChangeEvent { range: None, range_length: None, text: new_text };
+        assert!(apply_content_changes(&mut text, vec![change], PositionEncoding::Utf16));
+        assert_eq!(text, "module Bar\n");
+    }
+
+    #[test]
+    fn incremental_changes_match_their_shadow_copy() {
+        let change = |start: LineColumn, end: LineColumn, text: &str| TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))),
+            range_length: None,
+            text: text.to_owned(),
+        };
+
+        // Changes which are relative to the previous ones, with non-ASCII text, `\r\n`s, and positions to clamp.
+        for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32] {
+            let mut text = "module Foo\r\n/// 日😀 x\nstruct A {}".to_owned();
+            let changes = vec![
+                change((1, 4), (1, 5), "本"),
+                change((0, 50), (0, 60), " // y"),
+                change((1, 0), (2, 0), ""),
+                change((9, 0), (9, 0), "\nstruct B {}"),
+                change((0, 7), (0, 10), "Bar"),
+            ];
+            assert!(apply_content_changes(&mut text, changes, encoding), "{encoding:?}");
+        }
+    }
+
+    #[test]
+    fn positions_are_converted_into_offsets_in_each_encoding() {
+        let text = "module Foo\r\n/// a😀b 日本\nstruct Bar {}";
+        let expected_offsets = [
+            (PositionEncoding::Utf8, [4, 5, 9, 14]),
+            (PositionEncoding::Utf16, [4, 5, 7, 10]),
+            (PositionEncoding::Utf32, [4, 5, 6, 9]),
+        ];
+        for (encoding, characters) in expected_offsets {
+            // The `a`, `😀`, `b` and `本` of the 2nd line, which start at these byte offsets.
+            let offsets = characters.map(|character| position_to_offset(text, Position::new(1, character), encoding));
+            assert_eq!(offsets, [16, 17, 21, 26], "{encoding:?}");
+
+            // The start of the other lines, and positions past the end of a line and of the text.
+            assert_eq!(position_to_offset(text, Position::new(0, 0), encoding), 0);
+            assert_eq!(position_to_offset(text, Position::new(0, 20), encoding), 10);
+            assert_eq!(position_to_offset(text, Position::new(2, 0), encoding), 30);
+            assert_eq!(position_to_offset(text, Position::new(5, 0), encoding), text.len());
+        }
+    }
+
+    /// Creates an empty directory for a test to create files in, which is unique to the test.
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("slice-language-server-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_files_are_tracked_under_their_real_path_and_published_under_the_clients_path() {
+        let directory = test_directory("symlinks");
+        let real_directory = directory.join("real");
+        let linked_directory = directory.join("link");
+        fs::create_dir(&real_directory).unwrap();
+        fs::write(real_directory.join("a.slice"), "module A").unwrap();
+        fs::write(directory.join("other.slice"), "module Other").unwrap();
+        std::os::unix::fs::symlink(&real_directory, &linked_directory).unwrap();
+
+        // The client opens a folder through the symlink, and a file inside of it.
+        let mut paths = PathResolver::default();
+        let folder_uri = Url::from_directory_path(&linked_directory).unwrap();
+        let file_uri = Url::from_file_path(linked_directory.join("a.slice")).unwrap();
+        let folder_path = paths.register_folder(&folder_uri).unwrap();
+        assert!(!paths.register(ResolvedPath::new(&file_uri).unwrap()));
+        let file_path = paths.url_to_sanitized_file_path(&file_uri).unwrap();
+
+        // Both are tracked under their real paths, which is how `slicec` reports them if it was given the real path.
+        let real_directory = real_path(&real_directory);
+        assert_eq!(folder_path, real_directory);
+        assert_eq!(file_path, real_directory.join("a.slice"));
+        assert_eq!(paths.compiled_file_path(&real_directory.join("a.slice").to_string_lossy()), file_path);
+        assert_eq!(paths.canonical_path(&linked_directory.join("new.slice")), real_directory.join("new.slice"));
+
+        // But they're published under the URIs that the client knows them by, including files it never sent.
+        assert_eq!(paths.convert_slice_path_to_uri(&file_path), Some(file_uri));
+        let new_file_uri = Url::from_file_path(linked_directory.join("sub").join("b.slice")).unwrap();
+        assert_eq!(paths.convert_slice_path_to_uri(real_directory.join("sub").join("b.slice")), Some(new_file_uri));
+
+        // Files outside of the symlinked directory are unaffected.
+        let other_path = paths.canonical_path(&directory.join("other.slice"));
+        assert_eq!(paths.convert_slice_path_to_uri(&other_path), Url::from_file_path(&other_path).ok());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_documents_are_only_resolved_while_theyre_registered() {
+        let directory = test_directory("symlinked-documents");
+        fs::write(directory.join("real.slice"), "module A").unwrap();
+        std::os::unix::fs::symlink(directory.join("real.slice"), directory.join("link.slice")).unwrap();
+        let directory = real_path(&directory);
+        let (real_file_path, linked_file_path) = (directory.join("real.slice"), directory.join("link.slice"));
+        let linked_uri = Url::from_file_path(&linked_file_path).unwrap();
+
+        // Converting paths never accesses the file system, so the symlink is only resolved once it's registered.
+        let mut paths = PathResolver::default();
+        assert_eq!(paths.url_to_sanitized_file_path(&linked_uri), Some(linked_file_path.clone()));
+        assert!(paths.register(ResolvedPath::new(&linked_uri).unwrap()));
+        assert!(!paths.register(ResolvedPath::new(&linked_uri).unwrap()));
+        assert_eq!(paths.url_to_sanitized_file_path(&linked_uri), Some(real_file_path.clone()));
+        assert_eq!(paths.convert_slice_path_to_uri(&real_file_path), Some(linked_uri.clone()));
+
+        // Every server has its own resolver, so other servers don't know about the symlink.
+        assert_eq!(PathResolver::default().url_to_sanitized_file_path(&linked_uri), Some(linked_file_path.clone()));
+
+        // And once the document is unregistered, the resolver forgets it.
+        paths.unregister(&linked_uri);
+        assert_eq!(paths.url_to_sanitized_file_path(&linked_uri), Some(linked_file_path));
+        assert_eq!(paths.convert_slice_path_to_uri(&real_file_path), Url::from_file_path(&real_file_path).ok());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Returns an absolute path in a directory which doesn't exist, so that its symlinks (if any) can't be resolved.
+    fn absolute(path: &str) -> PathBuf {
+        match cfg!(windows) {
+            true => PathBuf::from(format!(r"C:\slice-language-server-test\{}", path.replace('/', "\\"))),
+            false => PathBuf::from(format!("/slice-language-server-test/{path}")),
+        }
+    }
+
+    /// Checks that a path converts into the same URI, and back into the same path, no matter which direction it's
+    /// converted in first, or how it was spelled.
+    fn assert_round_trips(path: &Path) -> (PathBuf, Url) {
+        let paths = PathResolver::default();
+        let key = paths.canonical_path(path);
+        let uri = paths.convert_slice_path_to_uri(path).unwrap();
+        assert_eq!(paths.url_to_sanitized_file_path(&uri).as_ref(), Some(&key), "{path:?}");
+        assert_eq!(paths.convert_slice_path_to_uri(&key).as_ref(), Some(&uri), "{path:?}");
+
+        // The URI that a client would send for the path converts into the same key, and back into the same URI.
+        let client_uri = Url::from_file_path(path).unwrap();
+        assert_eq!(paths.url_to_sanitized_file_path(&client_uri).as_ref(), Some(&key), "{path:?}");
+        let client_path = paths.url_to_sanitized_file_path(&client_uri).unwrap();
+        assert_eq!(paths.convert_slice_path_to_uri(client_path), Some(uri.clone()));
+        (key, uri)
+    }
+
+    #[test]
+    fn awkward_paths_round_trip_through_uris() {
+        let spellings = [
+            ["dir/file.slice", "dir/./file.slice", "dir//file.slice", "dir/sub/../file.slice"],
+            ["dir", "dir/", "dir/.", "./dir/sub/.."],
+            ["with space/a b.slice", "with space/./a b.slice", "with space//a b.slice", "with space/x/../a b.slice"],
+            ["%20/100%.slice", "%20/./100%.slice", "%20//100%.slice", "%20/%2F/../100%.slice"],
+        ];
+        for spellings in spellings {
+            let (key, uri) = assert_round_trips(&absolute(spellings[0]));
+            for spelling in &spellings[1..] {
+                assert_eq!(assert_round_trips(&absolute(spelling)), (key.clone(), uri.clone()), "{spelling}");
+            }
+        }
+
+        // Names with characters that have a meaning in URIs, or are encoded differently by different encoders.
+        let names = ["a+b", "a&b=c", "[a]", "a;b", "a,b", "a@b", "a$b", "a!b", "a'b", "a(b)", "~a", "a.b.c", "é", "😀"];
+        for name in names {
+            assert_round_trips(&absolute(&format!("{name}/{name}.slice")));
+        }
+    }
+
+    #[test]
+    fn uris_are_encoded_like_vscode_encodes_them() {
+        let resolver = PathResolver::default();
+        let root_uri = match cfg!(windows) {
+            true => "file:///c%3A/slice-language-server-test",
+            false => "file:///slice-language-server-test",
+        };
+        let paths = [
+            ("My Project/greeter.slice", "/My%20Project/greeter.slice"),
+            ("C#/a#b.slice", "/C%23/a%23b.slice"),
+            ("c++/a+b.slice", "/c%2B%2B/a%2Bb.slice"),
+            ("100%/a.slice", "/100%25/a.slice"),
+            ("über/日本語/😀.slice", "/%C3%BCber/%E6%97%A5%E6%9C%AC%E8%AA%9E/%F0%9F%98%80.slice"),
+            ("a b/c#d/é+f/x.slice", "/a%20b/c%23d/%C3%A9%2Bf/x.slice"),
+        ];
+        for (path, encoded_path) in paths {
+            let path = absolute(path);
+            let expected_uri = Url::parse(&format!("{root_uri}{encoded_path}")).unwrap();
+            assert_eq!(resolver.convert_slice_path_to_uri(&path).as_ref(), Some(&expected_uri), "{path:?}");
+            let expected_path = resolver.canonical_path(&path);
+            assert_eq!(resolver.url_to_sanitized_file_path(&expected_uri), Some(expected_path), "{path:?}");
+            assert_round_trips(&path);
+        }
+    }
+
+    #[test]
+    fn uris_with_other_encodings_convert_into_the_same_path() {
+        let paths = PathResolver::default();
+        // Clients can encode the same path in different ways, which must all be tracked under the same path.
+        let root_uri = match cfg!(windows) {
+            true => "file:///C:/slice-language-server-test",
+            false => "file:///slice-language-server-test",
+        };
+        let path = paths.canonical_path(&absolute("a b/c+d/日本.slice"));
+        let encoded_paths = [
+            "/a%20b/c%2Bd/%E6%97%A5%E6%9C%AC.slice",
+            "/a b/c+d/日本.slice",
+            "/a%20b/c+d/%e6%97%a5%e6%9c%ac.slice",
+        ];
+        for encoded_path in encoded_paths {
+            let uri = Url::parse(&format!("{root_uri}{encoded_path}")).unwrap();
+            assert_eq!(paths.url_to_sanitized_file_path(&uri).as_ref(), Some(&path), "{uri}");
+        }
+    }
+
+    #[test]
+    fn documents_which_arent_files_are_tracked_under_short_virtual_paths() {
+        let mut paths = PathResolver::default();
+        let long_uri = Url::parse(&format!("untitled:{}", "Untitled-1/".repeat(100))).unwrap();
+        let short_uri = Url::parse("untitled:Untitled-1").unwrap();
+        for uri in [&long_uri, &short_uri] {
+            // However long the URI is, its path must be short enough to write the document's text to.
+            let path = paths.url_to_document_path(uri).unwrap();
+            assert!(is_virtual_document_path(&path), "{path:?}");
+            assert!(path.file_name().unwrap().len() < 255, "{path:?}");
+
+            // The path only converts back into the URI while the document is registered.
+            assert_eq!(paths.convert_slice_path_to_uri(&path), None);
+            assert_eq!(paths.register_virtual_document(uri), path);
+            assert_eq!(paths.convert_slice_path_to_uri(&path).as_ref(), Some(uri));
+        }
+        assert_ne!(paths.url_to_document_path(&long_uri), paths.url_to_document_path(&short_uri));
+
+        paths.unregister(&long_uri);
+        assert_eq!(paths.convert_slice_path_to_uri(paths.url_to_document_path(&long_uri).unwrap()), None);
+        let short_path = paths.url_to_document_path(&short_uri).unwrap();
+        assert_eq!(paths.convert_slice_path_to_uri(short_path), Some(short_uri));
+    }
+
+    #[test]
+    fn mixed_separators_are_encoded_as_on_the_platform() {
+        let paths = PathResolver::default();
+        let path = absolute("a b").join(r"c\d/e.slice");
+        let uri = paths.convert_slice_path_to_uri(&path).unwrap();
+        match cfg!(windows) {
+            // Both are separators on Windows.
+            true => assert!(uri.as_str().ends_with("/a%20b/c/d/e.slice"), "{uri}"),
+            // Backslashes are allowed in file names on other platforms, so they're encoded.
+            false => assert!(uri.as_str().ends_with("/a%20b/c%5Cd/e.slice"), "{uri}"),
+        }
+        assert_round_trips(&path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn paths_are_matched_case_insensitively_on_windows() {
+        let paths = PathResolver::default();
+        // The set was configured with a lowercase path, but the user opened the file with its real casing.
+        let configured_path = Path::new(r"c:\slice-language-server-test\proto");
+        let opened_path = Path::new(r"C:\Slice-Language-Server-Test\Proto\greeter.slice");
+        assert!(path_starts_with(opened_path, configured_path));
+        assert!(path_starts_with(opened_path, &paths.canonical_path(configured_path)));
+        assert!(!path_starts_with(Path::new(r"C:\Slice-Language-Server-Test\Protos"), configured_path));
+
+        assert!(paths_match(opened_path, Path::new(r"c:\SLICE-language-server-test\proto\Greeter.slice")));
+        assert!(!paths_match(opened_path, Path::new(r"C:\Slice-Language-Server-Test\Proto\other.slice")));
+        assert_eq!(PathKey::new(opened_path), PathKey::new(&paths.canonical_path(opened_path)));
+
+        // Disk letters are always capitalized, but the rest of the path keeps its casing, so it's sent back as-is.
+        assert_eq!(sanitize_path(r"c:\Work\Proto"), r"C:\Work\Proto");
+        assert_eq!(sanitize_path("c:/Work/Proto"), r"C:\Work\Proto");
+        assert_eq!(sanitize_path(r"C:\work\proto"), r"C:\work\proto");
+
+        // URIs with differently cased disk letters and directories convert into paths which match each other.
+        let lowercase_uri = Url::parse("file:///c%3A/slice-language-server-test/proto/greeter.slice").unwrap();
+        let uppercase_uri = Url::parse("file:///C:/Slice-Language-Server-Test/Proto/greeter.slice").unwrap();
+        let lowercase_path = paths.url_to_sanitized_file_path(&lowercase_uri).unwrap();
+        let uppercase_path = paths.url_to_sanitized_file_path(&uppercase_uri).unwrap();
+        assert!(lowercase_path.starts_with("C:\\"));
+        assert!(paths_match(&lowercase_path, &uppercase_path));
+        assert!(paths_match(&lowercase_path, opened_path));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn paths_are_matched_case_sensitively_on_other_platforms() {
+        let path = absolute("Proto/greeter.slice");
+        assert!(paths_match(&path, &absolute("Proto/greeter.slice")));
+        assert!(!paths_match(&path, &absolute("proto/greeter.slice")));
+        assert!(path_starts_with(&path, &absolute("Proto")));
+        assert!(!path_starts_with(&path, &absolute("proto")));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_and_network_paths_are_sanitized_on_windows() {
+        // Verbatim prefixes are removed, since paths from the client never have them.
+        assert_eq!(sanitize_path(r"\\?\C:\Work\a.slice"), r"C:\Work\a.slice");
+        assert_eq!(sanitize_path(r"\\?\c:\Work\a.slice"), r"C:\Work\a.slice");
+        assert_eq!(sanitize_path(r"\\?\UNC\server\share\dir\a.slice"), r"\\server\share\dir\a.slice");
+
+        // Network paths are kept as they are, apart from their separators.
+        assert_eq!(sanitize_path(r"\\server\share\dir\a.slice"), r"\\server\share\dir\a.slice");
+        assert_eq!(sanitize_path("//server/share/dir/a.slice"), r"\\server\share\dir\a.slice");
+
+        // Verbatim paths which can't be spelled without their prefix keep it.
+        let volume_path = r"\\?\Volume{01234567-89ab-cdef-0123-456789abcdef}\a.slice";
+        assert_eq!(sanitize_path(volume_path), volume_path);
+
+        // Long paths always get a verbatim prefix, however they were spelled.
+        let long_name = "a".repeat(MAX_PATH);
+        let long_disk_path = format!(r"\\?\C:\{long_name}\a.slice");
+        assert_eq!(sanitize_path(&format!(r"C:\{long_name}\a.slice")), long_disk_path);
+        assert_eq!(sanitize_path(&format!(r"c:/{long_name}/a.slice")), long_disk_path);
+        assert_eq!(sanitize_path(&long_disk_path), long_disk_path);
+        let long_network_path = format!(r"\\?\UNC\server\share\{long_name}\a.slice");
+        assert_eq!(sanitize_path(&format!(r"\\server\share\{long_name}\a.slice")), long_network_path);
+        assert_eq!(sanitize_path(&long_network_path), long_network_path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_and_network_paths_round_trip_through_uris_on_windows() {
+        let paths = PathResolver::default();
+        // A share which doesn't exist on the local machine, so that resolving its symlinks fails quickly.
+        let network_path = Path::new(r"\\127.0.0.1\slice-language-server-test\my dir\a#b.slice");
+        let network_uri = Url::parse("file://127.0.0.1/slice-language-server-test/my%20dir/a%23b.slice").unwrap();
+        assert_eq!(paths.convert_slice_path_to_uri(network_path).as_ref(), Some(&network_uri));
+        assert_eq!(paths.url_to_sanitized_file_path(&network_uri).as_deref(), Some(network_path));
+        let verbatim_network_path = Path::new(r"\\?\UNC\127.0.0.1\slice-language-server-test\my dir\a#b.slice");
+        assert_eq!(paths.convert_slice_path_to_uri(verbatim_network_path).as_ref(), Some(&network_uri));
+        assert_round_trips(network_path);
+
+        let disk_uri = Url::parse("file:///c%3A/slice-language-server-test/a.slice").unwrap();
+        let verbatim_disk_path = Path::new(r"\\?\C:\slice-language-server-test\a.slice");
+        assert_eq!(paths.convert_slice_path_to_uri(verbatim_disk_path).as_ref(), Some(&disk_uri));
+        let disk_path = paths.url_to_sanitized_file_path(&disk_uri).unwrap();
+        assert_eq!(disk_path, Path::new(r"C:\slice-language-server-test\a.slice"));
+        assert_round_trips(verbatim_disk_path);
+
+        // URIs can't hold verbatim prefixes, so long paths are converted without one, and get it back afterwards.
+        let long_path = absolute(&format!("{}/a.slice", "a".repeat(MAX_PATH)));
+        let long_uri = paths.convert_slice_path_to_uri(&long_path).unwrap();
+        assert!(!long_uri.as_str().contains('?'), "{long_uri}");
+        let tracked_path = paths.url_to_sanitized_file_path(&long_uri).unwrap();
+        assert_eq!(tracked_path, PathBuf::from(format!(r"\\?\{}", long_path.display())));
+        assert_eq!(paths.convert_slice_path_to_uri(&tracked_path), Some(long_uri));
+    }
 }
@@ -0,0 +1,114 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::configuration::ServerConfig;
+use crate::configuration_set::{ConfigurationSet, PendingCompilation};
+use crate::diagnostic_handler::process_diagnostics;
+use crate::position_encoding::PositionEncoding;
+use crate::server_state::UnsavedDocument;
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+use tracing::debug;
+
+/// The scheme of the URIs that validated text is compiled under. Diagnostics which relate to the validated text (in
+/// their related information) refer to it by one of these URIs.
+pub const VALIDATION_URI_SCHEME: &str = "slice-validate";
+
+/// A snapshot of everything that's needed to validate a piece of Slice text, for the `slice/validateFile` request.
+///
+/// The text is compiled on its own (with the built-in Slice files), in a configuration set that only exists for this
+/// compilation, so validating text never affects the user's configuration sets. Like unsaved documents, the text is
+/// compiled from an overlay, under a virtual path derived from `uri`.
+pub struct PendingValidation {
+    uri: Url,
+    compilation: PendingCompilation,
+    position_encoding: PositionEncoding,
+}
+
+impl PendingValidation {
+    /// Prepares the compilation of the provided text. The file name is only used to name the text in diagnostics.
+    pub fn new(
+        server_config: &ServerConfig,
+        file_name: &str,
+        text: String,
+        defined_symbols: Vec<String>,
+    ) -> Option<Self> {
+        let uri = Url::parse(&format!("{VALIDATION_URI_SCHEME}:{file_name}")).ok()?;
+        let file_path = server_config.paths.url_to_document_path(&uri)?;
+
+        let mut configuration_set = ConfigurationSet::stand_alone_set(&file_path);
+        configuration_set.slice_config.defined_symbols = defined_symbols;
+        let unsaved_documents = HashMap::from([(file_path, UnsavedDocument { version: 0, text })]);
+        let mut compilation = configuration_set.prepare_inspection(server_config, &unsaved_documents);
+        compilation.register_virtual_document(&uri);
+        Some(PendingValidation { uri, compilation, position_encoding: server_config.position_encoding })
+    }
+
+    /// Compiles the text, and returns the diagnostics that were reported in it. Diagnostics in the built-in Slice files
+    /// (and diagnostics without a span) aren't about the text, so they're left out.
+    pub fn run(self) -> Vec<Diagnostic> {
+        let (compilation_data, diagnostics) = self.compilation.run().into_results();
+        let mut diagnostics_by_uri = HashMap::new();
+        process_diagnostics(diagnostics, &compilation_data, self.position_encoding, &mut diagnostics_by_uri);
+        let diagnostics = diagnostics_by_uri.remove(&self.uri).unwrap_or_default();
+        debug!("Validated '{}', with {} diagnostic(s)", self.uri, diagnostics.len());
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{DiagnosticSeverity, Position};
+
+    fn validate(text: &str, defined_symbols: &[&str]) -> Vec<Diagnostic> {
+        let defined_symbols = defined_symbols.iter().map(|symbol| symbol.to_string()).collect();
+        let server_config = ServerConfig::default();
+        let validation = PendingValidation::new(&server_config, "snippet.slice", text.to_owned(), defined_symbols);
+        validation.unwrap().run()
+    }
+
+    #[test]
+    fn valid_text_has_no_diagnostics() {
+        let text = "module Test\n\nstruct Point {\n    x: int32\n    y: int32\n}\n";
+        assert!(validate(text, &[]).is_empty());
+    }
+
+    #[test]
+    fn invalid_text_has_diagnostics_in_it() {
+        let text = "module Test\n\nstruct Point {\n    x: int32\n    y: Missing\n}\n";
+        let diagnostics = validate(text, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].range.start, Position::new(4, 7));
+        assert!(diagnostics[0].message.contains("Missing"), "{}", diagnostics[0].message);
+
+        // Syntax errors are reported too.
+        assert!(!validate("module Test\n\nstruct {\n", &[]).is_empty());
+    }
+
+    #[test]
+    fn text_is_compiled_with_the_provided_defines() {
+        let text = "module Test\n\n#if USE_POINT\nstruct Point {}\n#else\nstruct Broken { x: Missing }\n#endif\n";
+        assert!(validate(text, &["USE_POINT"]).is_empty());
+        assert_eq!(validate(text, &[]).len(), 1);
+        assert_eq!(validate(text, &["OTHER"]).len(), 1);
+    }
+
+    #[test]
+    fn validating_the_same_text_twice_gives_the_same_diagnostics() {
+        let text = "module Test\nstruct Point { x: Missing }\n";
+        assert_eq!(validate(text, &[]), validate(text, &[]));
+    }
+
+    #[test]
+    fn text_with_a_long_file_name_is_validated() {
+        // The text is written to an overlay under its virtual path, which must be short enough for a file name, however
+        // long the file name (and so the URI) that the text is validated under is.
+        let file_name = format!("{}.slice", "snippet".repeat(100));
+        let text = "module Test\nstruct Point { x: Missing }\n".to_owned();
+        let validation = PendingValidation::new(&ServerConfig::default(), &file_name, text, Vec::new());
+        let diagnostics = validation.unwrap().run();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 18));
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) ZeroC, Inc.
+
+use crate::utils::path_starts_with;
+use serde_json::{json, Value};
+use slicec::grammar::{
+    Attributable, Class, CustomType, Enum, Exception, Interface, Module, NamedSymbol, Operation, Struct, TypeAlias,
+};
+use slicec::slice_file::SliceFile;
+use slicec::visitor::Visitor;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// What a single Slice file defines, counted by [`count_file`].
+#[derive(Debug, Default)]
+pub struct FileStats {
+    /// The compilation mode of the file (like `Slice2`).
+    mode: String,
+    /// The fully-qualified names of the modules that the file contributes to.
+    modules: BTreeSet<String>,
+    interfaces: usize,
+    operations: usize,
+    structs: usize,
+    classes: usize,
+    exceptions: usize,
+    enums: usize,
+    custom_types: usize,
+    type_aliases: usize,
+    /// How many of the file's definitions (including operations) are marked with the `deprecated` attribute.
+    deprecated: usize,
+}
+
+/// Counts what each of the provided files defines, for the `slice.workspaceStats` command. The built-in Slice files
+/// are skipped, unless `include_built_in` is set.
+pub fn count_files(
+    files: &[(PathBuf, &SliceFile)],
+    built_in_slice_path: Option<&str>,
+    include_built_in: bool,
+) -> Vec<(PathBuf, FileStats)> {
+    let is_built_in =
+        |path: &Path| built_in_slice_path.is_some_and(|built_in_path| path_starts_with(path, Path::new(built_in_path)));
+    files
+        .iter()
+        .filter(|(path, _)| include_built_in || !is_built_in(path))
+        .map(|(path, file)| (path.clone(), count_file(file)))
+        .collect()
+}
+
+fn count_file(file: &SliceFile) -> FileStats {
+    let mut stats = FileStats { mode: format!("{:?}", file.compilation_mode()), ..FileStats::default() };
+    file.visit_with(&mut stats);
+    stats
+}
+
+/// Renders the statistics of each configuration set as JSON, along with the totals across every set. Files which are
+/// in multiple sets are counted once for each set, but only once in the totals.
+pub fn workspace_stats(sets: Vec<(String, Vec<(PathBuf, FileStats)>)>) -> Value {
+    let mut all_files = BTreeMap::new();
+    let configuration_sets = sets
+        .into_iter()
+        .map(|(name, files)| {
+            let mut stats = stats_json(files.iter().map(|(_, stats)| stats));
+            stats["name"] = json!(name);
+            all_files.extend(files);
+            stats
+        })
+        .collect::<Vec<_>>();
+
+    let mut totals = stats_json(all_files.values());
+    totals["configurationSets"] = json!(configuration_sets);
+    totals
+}
+
+/// Adds up the statistics of the provided files. Modules are counted by name, so a module which is reopened in
+/// multiple files is only counted once.
+fn stats_json<'a>(files: impl Iterator<Item = &'a FileStats>) -> Value {
+    let mut total = FileStats::default();
+    let mut modes = BTreeMap::<&str, usize>::new();
+    let mut file_count = 0;
+    for stats in files {
+        file_count += 1;
+        *modes.entry(&stats.mode).or_default() += 1;
+        total.modules.extend(stats.modules.iter().cloned());
+        total.interfaces += stats.interfaces;
+        total.operations += stats.operations;
+        total.structs += stats.structs;
+        total.classes += stats.classes;
+        total.exceptions += stats.exceptions;
+        total.enums += stats.enums;
+        total.custom_types += stats.custom_types;
+        total.type_aliases += stats.type_aliases;
+        total.deprecated += stats.deprecated;
+    }
+    json!({
+        "files": file_count,
+        "modules": total.modules.len(),
+        "interfaces": total.interfaces,
+        "operations": total.operations,
+        "structs": total.structs,
+        "classes": total.classes,
+        "exceptions": total.exceptions,
+        "enums": total.enums,
+        "customTypes": total.custom_types,
+        "typeAliases": total.type_aliases,
+        "deprecated": total.deprecated,
+        "filesPerMode": modes,
+    })
+}
+
+impl FileStats {
+    fn count_deprecated(&mut self, attributable: &dyn Attributable) {
+        let attributes = attributable.attributes();
+        if attributes.iter().any(|attribute| attribute.kind.directive() == "deprecated") {
+            self.deprecated += 1;
+        }
+    }
+}
+
+impl Visitor for FileStats {
+    fn visit_module(&mut self, module_def: &Module) {
+        self.modules.insert(module_def.parser_scoped_identifier());
+    }
+
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        self.structs += 1;
+        self.count_deprecated(struct_def);
+    }
+
+    fn visit_class(&mut self, class_def: &Class) {
+        self.classes += 1;
+        self.count_deprecated(class_def);
+    }
+
+    fn visit_exception(&mut self, exception_def: &Exception) {
+        self.exceptions += 1;
+        self.count_deprecated(exception_def);
+    }
+
+    fn visit_interface(&mut self, interface_def: &Interface) {
+        self.interfaces += 1;
+        self.count_deprecated(interface_def);
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        self.enums += 1;
+        self.count_deprecated(enum_def);
+    }
+
+    fn visit_operation(&mut self, operation_def: &Operation) {
+        self.operations += 1;
+        self.count_deprecated(operation_def);
+    }
+
+    fn visit_custom_type(&mut self, custom_type_def: &CustomType) {
+        self.custom_types += 1;
+        self.count_deprecated(custom_type_def);
+    }
+
+    fn visit_type_alias(&mut self, type_alias_def: &TypeAlias) {
+        self.type_aliases += 1;
+        self.count_deprecated(type_alias_def);
+    }
+}
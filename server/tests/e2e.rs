@@ -0,0 +1,48 @@
+// Copyright (c) ZeroC, Inc.
+
+//! Tests the server end to end, by running it in the same process as a client (see `support/client.rs`), which talks
+//! to it like an editor would.
+
+// The client is shared with the library's own tests, which use the helpers that these tests don't.
+#[allow(dead_code)]
+#[path = "support/client.rs"]
+mod client;
+
+use client::TestClient;
+use serde_json::{json, Value};
+use slice_language_server::serve;
+use std::fs;
+use std::path::PathBuf;
+use tower_lsp::lsp_types::Url;
+
+fn fixture_path(name: &str) -> PathBuf {
+    std::env::current_dir().unwrap().join("tests/fixtures").join(name)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn opened_workspaces_are_compiled_and_their_documents_can_be_hovered() {
+    let fixture_path = fixture_path("relative");
+    let mut client = TestClient::start();
+    let result = client.initialize(&fixture_path, Value::Null).await;
+    assert!(result["capabilities"]["hoverProvider"].as_bool().unwrap_or(false));
+
+    // The workspace's default configuration set is compiled once the server is initialized.
+    let diagnostics = client.published_diagnostics(&fixture_path.join("sender.slice")).await;
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(diagnostics[0]["severity"], json!(1));
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 4, "character": 15 }));
+    let diagnostics = client.published_diagnostics(&fixture_path.join("greeter.slice")).await;
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    // Open a document, and hover over the (primitive) type of one of its fields.
+    let greeter_path = fixture_path.join("greeter.slice");
+    let uri = Url::from_file_path(&greeter_path).unwrap();
+    let text = fs::read_to_string(&greeter_path).unwrap();
+    let text_document = json!({ "uri": uri, "languageId": "slice", "version": 1, "text": text });
+    client.notify("textDocument/didOpen", json!({ "textDocument": text_document }));
+    let params = json!({ "textDocument": { "uri": uri }, "position": { "line": 3, "character": 12 } });
+    let hover = client.request("textDocument/hover", params).await;
+    assert_eq!(hover["contents"]["value"], json!("A UTF-8 string."), "{hover}");
+
+    client.request("shutdown", Value::Null).await;
+}
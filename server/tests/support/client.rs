@@ -0,0 +1,180 @@
+// Copyright (c) ZeroC, Inc.
+
+//! An in-process client for testing the server end to end. It runs the server on an in-memory stream (from
+//! `tokio::io::duplex`), and speaks JSON-RPC to it like an editor would.
+
+use super::serve;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tower_lsp::lsp_types::Url;
+
+/// How long to wait for a message from the server before failing the test.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct TestClient {
+    outgoing: UnboundedSender<Value>,
+    incoming: UnboundedReceiver<Value>,
+    /// The messages that were received while waiting for a different one, in the order they were received.
+    received: Vec<Value>,
+    next_id: u64,
+}
+
+impl TestClient {
+    /// Starts a server, and connects a client to it.
+    pub fn start() -> Self {
+        let (client_stream, server_stream) = tokio::io::duplex(1 << 16);
+        let (server_input, server_output) = tokio::io::split(server_stream);
+        tokio::spawn(serve(server_input, server_output));
+
+        let (input, mut output) = tokio::io::split(client_stream);
+        let (outgoing, mut outgoing_receiver) = unbounded_channel::<Value>();
+        tokio::spawn(async move {
+            while let Some(message) = outgoing_receiver.recv().await {
+                write_message(&mut output, &message).await;
+            }
+        });
+
+        let (incoming_sender, incoming) = unbounded_channel();
+        let replies = outgoing.clone();
+        tokio::spawn(async move {
+            let mut input = BufReader::new(input);
+            while let Some(message) = read_message(&mut input).await {
+                // Answer the server's own requests (like `window/showMessageRequest`) like a minimal client would.
+                if let (Some(id), Some(_)) = (message.get("id"), message.get("method")) {
+                    let _ = replies.send(json!({ "jsonrpc": "2.0", "id": id, "result": null }));
+                }
+                if incoming_sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        TestClient { outgoing, incoming, received: Vec::new(), next_id: 1 }
+    }
+
+    /// Initializes the server with a workspace at `root`, and the provided initialization options (which use the same
+    /// layout as the 'slice' settings).
+    pub async fn initialize(&mut self, root: &Path, initialization_options: Value) -> Value {
+        let root_uri = Url::from_directory_path(root).unwrap();
+        let params = json!({
+            "processId": null,
+            "rootUri": root_uri,
+            "capabilities": {},
+            "initializationOptions": initialization_options,
+        });
+        let result = self.request("initialize", params).await;
+        self.notify("initialized", json!({}));
+        result
+    }
+
+    /// Sends a request to the server, without waiting for its response. Returns the request's ID.
+    /// Requests without parameters (like `shutdown`) are sent with `Value::Null`.
+    pub fn send_request(&mut self, method: &str, params: Value) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut request = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        let _ = self.outgoing.send(request);
+        id
+    }
+
+    /// Waits for the response to the request with the provided ID, and returns its result (or its error).
+    pub async fn response(&mut self, id: u64) -> Result<Value, Value> {
+        let is_response = |message: &Value| message.get("method").is_none() && message["id"] == id;
+        let mut response = self.receive(is_response).await;
+        match response.get("error") {
+            Some(error) => Err(error.clone()),
+            None => Ok(response["result"].take()),
+        }
+    }
+
+    /// Sends a request to the server, and returns its result. Fails the test if the request fails.
+    pub async fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.send_request(method, params);
+        self.response(id).await.unwrap_or_else(|error| panic!("'{method}' failed: {error}"))
+    }
+
+    /// Sends a notification to the server. Notifications without parameters (like `exit`) are sent with `Value::Null`.
+    pub fn notify(&self, method: &str, params: Value) {
+        let mut notification = json!({ "jsonrpc": "2.0", "method": method });
+        if !params.is_null() {
+            notification["params"] = params;
+        }
+        let _ = self.outgoing.send(notification);
+    }
+
+    /// Waits for a notification with the provided method whose parameters match `predicate`, and returns them.
+    pub async fn notification(&mut self, method: &str, predicate: impl Fn(&Value) -> bool) -> Value {
+        let is_match = |message: &Value| message["method"] == method && predicate(&message["params"]);
+        self.receive(is_match).await["params"].take()
+    }
+
+    /// Waits for the server to publish the diagnostics of the provided file, and returns them.
+    pub async fn published_diagnostics(&mut self, path: &Path) -> Vec<Value> {
+        let uri = Url::from_file_path(path).unwrap();
+        let is_file = |params: &Value| params["uri"] == uri.as_str();
+        let params = self.notification("textDocument/publishDiagnostics", is_file).await;
+        params["diagnostics"].as_array().cloned().unwrap_or_default()
+    }
+
+    /// Returns the parameters of every notification with the provided method that the server sent so far, which
+    /// haven't been returned yet.
+    pub fn take_notifications(&mut self, method: &str) -> Vec<Value> {
+        while let Ok(message) = self.incoming.try_recv() {
+            self.received.push(message);
+        }
+        let (mut matches, received) = std::mem::take(&mut self.received)
+            .into_iter()
+            .partition::<Vec<_>, _>(|message| message["method"] == method && message.get("id").is_none());
+        self.received = received;
+        matches.iter_mut().map(|message| message["params"].take()).collect()
+    }
+
+    /// Returns the first message (either already received, or received next) which matches `predicate`.
+    async fn receive(&mut self, predicate: impl Fn(&Value) -> bool) -> Value {
+        if let Some(index) = self.received.iter().position(&predicate) {
+            return self.received.remove(index);
+        }
+        loop {
+            let message = tokio::time::timeout(TIMEOUT, self.incoming.recv())
+                .await
+                .expect("timed out waiting for a message from the server")
+                .expect("the server closed the connection");
+            if predicate(&message) {
+                return message;
+            }
+            self.received.push(message);
+        }
+    }
+}
+
+async fn write_message(output: &mut (impl AsyncWrite + Unpin), message: &Value) {
+    let content = message.to_string();
+    let frame = format!("Content-Length: {}\r\n\r\n{content}", content.len());
+    let _ = output.write_all(frame.as_bytes()).await;
+}
+
+async fn read_message(input: &mut BufReader<impl AsyncRead + Unpin>) -> Option<Value> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length: ") {
+            content_length = length.parse().ok();
+        }
+    }
+    let mut content = vec![0; content_length?];
+    input.read_exact(&mut content).await.ok()?;
+    serde_json::from_slice(&content).ok()
+}